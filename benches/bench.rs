@@ -138,6 +138,38 @@ macro_rules! bench {
     };
 }
 
+macro_rules! bench_from_bytes {
+    ($name:ident, $data:ident, $counter:ident, $result:expr) => {
+        #[bench]
+        fn $name(b: &mut Bencher) {
+            let data = $data.as_bytes();
+            b.bytes = data.len() as u64;
+            b.iter(|| {
+                let mut rdr =
+                    ReaderBuilder::new().has_headers(false).from_bytes(data);
+                assert_eq!($counter(&mut rdr), $result);
+            })
+        }
+    };
+}
+
+macro_rules! bench_wide {
+    ($name:ident, $fields_hint:expr) => {
+        #[bench]
+        fn $name(b: &mut Bencher) {
+            let data = wide_data();
+            b.bytes = data.len() as u64;
+            b.iter(|| {
+                let mut rdr = ReaderBuilder::new()
+                    .has_headers(false)
+                    .expected_fields_hint($fields_hint)
+                    .from_reader(data.as_bytes());
+                assert_eq!(count_iter_bytes(&mut rdr), 100 * 10000);
+            })
+        }
+    };
+}
+
 macro_rules! bench_trimmed {
     ($name:ident, $data:ident, $counter:ident, $result:expr) => {
         #[bench]
@@ -313,6 +345,15 @@ bench!(count_nfl_iter_str, NFL, count_iter_str, 130000);
 bench_trimmed!(count_nfl_iter_str_trimmed, NFL, count_iter_str, 130000);
 bench!(count_nfl_read_bytes, NFL, count_read_bytes, 130000);
 bench!(count_nfl_read_str, NFL, count_read_str, 130000);
+bench!(count_nfl_read_fields, NFL, count_read_fields, 130000);
+bench_from_bytes!(
+    count_nfl_read_bytes_from_bytes,
+    NFL,
+    count_read_bytes,
+    130000
+);
+bench_wide!(count_wide_iter_bytes, 0);
+bench_wide!(count_wide_iter_bytes_fields_hint, 100);
 bench_serde!(
     no_headers,
     count_game_deserialize_owned_bytes,
@@ -349,6 +390,13 @@ bench!(count_game_iter_bytes, GAME, count_iter_bytes, 600000);
 bench!(count_game_iter_str, GAME, count_iter_str, 600000);
 bench!(count_game_read_bytes, GAME, count_read_bytes, 600000);
 bench!(count_game_read_str, GAME, count_read_str, 600000);
+bench!(count_game_read_fields, GAME, count_read_fields, 600000);
+bench_from_bytes!(
+    count_game_read_bytes_from_bytes,
+    GAME,
+    count_read_bytes,
+    600000
+);
 bench_serde!(
     count_pop_deserialize_owned_bytes,
     count_pop_serialize_owned_bytes,
@@ -383,6 +431,7 @@ bench!(count_pop_iter_bytes, POP, count_iter_bytes, 140007);
 bench!(count_pop_iter_str, POP, count_iter_str, 140007);
 bench!(count_pop_read_bytes, POP, count_read_bytes, 140007);
 bench!(count_pop_read_str, POP, count_read_str, 140007);
+bench!(count_pop_read_fields, POP, count_read_fields, 140007);
 bench_serde!(
     count_mbta_deserialize_owned_bytes,
     count_mbta_serialize_owned_bytes,
@@ -417,6 +466,7 @@ bench!(count_mbta_iter_bytes, MBTA, count_iter_bytes, 90000);
 bench!(count_mbta_iter_str, MBTA, count_iter_str, 90000);
 bench!(count_mbta_read_bytes, MBTA, count_read_bytes, 90000);
 bench!(count_mbta_read_str, MBTA, count_read_str, 90000);
+bench!(count_mbta_read_fields, MBTA, count_read_fields, 90000);
 
 macro_rules! bench_write {
     ($name:ident, $data:ident) => {
@@ -459,6 +509,41 @@ macro_rules! bench_write_bytes {
 bench_write!(write_nfl_record, NFL);
 bench_write_bytes!(write_nfl_bytes, NFL);
 
+// These benchmarks write 10 million tiny records, where per-record overhead
+// dominates. A much larger buffer does not make writing substantially faster,
+// since the writer only drains its buffer once it is full, and not per field
+// or per record. Instead, most of the cost is in the per-field bookkeeping of
+// the core writer, which the fast path in `write_byte_record` skips.
+const TINY_RECORDS: usize = 10_000_000;
+
+macro_rules! bench_write_tiny {
+    ($name:ident, $capacity:expr, $write:ident) => {
+        #[bench]
+        fn $name(b: &mut Bencher) {
+            let record = ByteRecord::from(vec!["a", "1"]);
+            b.bytes = (TINY_RECORDS * 4) as u64;
+            b.iter(|| {
+                let mut counter = ByteCounter::default();
+                {
+                    let mut wtr = WriterBuilder::new()
+                        .buffer_capacity($capacity)
+                        .from_writer(&mut counter);
+                    for _ in 0..TINY_RECORDS {
+                        wtr.$write(&record).unwrap();
+                    }
+                    assert!(wtr.flush().is_ok());
+                }
+                assert_eq!(counter.count, TINY_RECORDS * 4);
+            })
+        }
+    };
+}
+
+bench_write_tiny!(write_tiny_record, 8 * (1 << 10), write_record);
+bench_write_tiny!(write_tiny_record_1mb, 1 << 20, write_record);
+bench_write_tiny!(write_tiny_bytes, 8 * (1 << 10), write_byte_record);
+bench_write_tiny!(write_tiny_bytes_1mb, 1 << 20, write_byte_record);
+
 fn count_deserialize_owned_bytes<R, D>(rdr: &mut Reader<R>) -> u64
 where
     R: io::Read,
@@ -511,6 +596,14 @@ fn count_read_bytes<R: io::Read>(rdr: &mut Reader<R>) -> u64 {
     count
 }
 
+fn count_read_fields<R: io::Read>(rdr: &mut Reader<R>) -> u64 {
+    let mut count = 0;
+    while let Some(fields) = rdr.read_fields().unwrap() {
+        count += fields.len() as u64;
+    }
+    count
+}
+
 fn count_read_str<R: io::Read>(rdr: &mut Reader<R>) -> u64 {
     let mut count = 0;
     let mut rec = StringRecord::new();
@@ -520,6 +613,21 @@ fn count_read_str<R: io::Read>(rdr: &mut Reader<R>) -> u64 {
     count
 }
 
+/// Returns CSV data with 10,000 records of 100 short fields each.
+fn wide_data() -> String {
+    let mut data = String::new();
+    for i in 0..10000 {
+        for j in 0..100 {
+            if j > 0 {
+                data.push(',');
+            }
+            data.push_str(&(i + j).to_string());
+        }
+        data.push('\n');
+    }
+    data
+}
+
 fn collect_records(data: &[u8]) -> Vec<ByteRecord> {
     let mut rdr = ReaderBuilder::new().has_headers(false).from_reader(data);
     rdr.byte_records().collect::<Result<Vec<_>, _>>().unwrap()
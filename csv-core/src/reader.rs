@@ -102,6 +102,9 @@ pub struct Reader {
     nfa_state: NfaState,
     /// The delimiter that separates fields.
     delimiter: u8,
+    /// If enabled, the delimiter is not treated specially, so that every
+    /// record has a single field.
+    single_field: bool,
     /// The terminator that separates records.
     term: Terminator,
     /// The quotation byte.
@@ -110,11 +113,23 @@ pub struct Reader {
     escape: Option<u8>,
     /// Whether to recognized doubled quotes.
     double_quote: bool,
+    /// Whether to recognize escapes in unquoted fields.
+    escape_unquoted: bool,
     /// If enabled, lines beginning with this byte are ignored.
     comment: Option<u8>,
     /// If enabled (the default), then quotes are respected. When disabled,
     /// quotes are not treated specially.
     quoting: bool,
+    /// If enabled, quotes that appear inside an unquoted field are recorded
+    /// in `unescaped_quote`.
+    strict_quotes: bool,
+    /// The index of the first field in the current record that contained a
+    /// quote inside of an unquoted field. Only set when `strict_quotes` is
+    /// enabled.
+    unescaped_quote: Option<u64>,
+    /// The number of fields parsed so far in the current record, not
+    /// including those found in the current call to `read_record`.
+    record_fields: u64,
     /// Whether to use the NFA for parsing.
     ///
     /// Generally this is for debugging. There's otherwise no good reason
@@ -135,12 +150,17 @@ impl Default for Reader {
             dfa_state: DfaState::start(),
             nfa_state: NfaState::StartRecord,
             delimiter: b',',
+            single_field: false,
             term: Terminator::default(),
             quote: b'"',
             escape: None,
             double_quote: true,
+            escape_unquoted: false,
             comment: None,
             quoting: true,
+            strict_quotes: false,
+            unescaped_quote: None,
+            record_fields: 0,
             use_nfa: false,
             line: 1,
             has_read: false,
@@ -154,7 +174,7 @@ impl Default for Reader {
 /// This builder can be used to tweak the field delimiter, record terminator
 /// and more for parsing CSV. Once a CSV `Reader` is built, its configuration
 /// cannot be changed.
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct ReaderBuilder {
     rdr: Reader,
 }
@@ -203,6 +223,12 @@ impl ReaderBuilder {
     /// In some variants of CSV, quotes are escaped using a special escape
     /// character like `\` (instead of escaping quotes by doubling them).
     ///
+    /// Inside a quoted field, the escape character makes the byte following
+    /// it literal, whatever that byte is. So with `\` as the escape
+    /// character, `"abc\\"` is the field `abc\`, closed by its last quote,
+    /// while `"abc\""` is the field `abc"`. In `"abc\"`, the second quote is
+    /// escaped, so the field doesn't end there.
+    ///
     /// By default, recognizing these idiosyncratic escapes is disabled.
     pub fn escape(&mut self, escape: Option<u8>) -> &mut ReaderBuilder {
         self.rdr.escape = escape;
@@ -218,6 +244,18 @@ impl ReaderBuilder {
         self
     }
 
+    /// Enable escapes in unquoted fields.
+    ///
+    /// This is disabled by default. When enabled, the escape character set
+    /// with `escape` is also recognized outside of quotes: the byte following
+    /// it is copied to the field as is, even if it's a delimiter, record
+    /// terminator or quote, and the escape character itself is dropped. This
+    /// has no effect if no escape character is set.
+    pub fn escape_unquoted(&mut self, yes: bool) -> &mut ReaderBuilder {
+        self.rdr.escape_unquoted = yes;
+        self
+    }
+
     /// Enable or disable quoting.
     ///
     /// This is enabled by default, but it may be disabled. When disabled,
@@ -227,6 +265,17 @@ impl ReaderBuilder {
         self
     }
 
+    /// Enable or disable splitting records into fields.
+    ///
+    /// This is disabled by default. When enabled, the delimiter is not
+    /// treated specially, so that every record consists of a single field
+    /// that spans the whole line. Quotes are still respected if quoting is
+    /// enabled.
+    pub fn single_field(&mut self, yes: bool) -> &mut ReaderBuilder {
+        self.rdr.single_field = yes;
+        self
+    }
+
     /// The comment character to use when parsing CSV.
     ///
     /// If the start of a record begins with the byte given here, then that
@@ -238,6 +287,19 @@ impl ReaderBuilder {
         self
     }
 
+    /// Enable or disable detection of unescaped quotes.
+    ///
+    /// When enabled, a quote that appears inside an unquoted field (e.g.,
+    /// `a"b`) is still parsed as a literal byte, but the index of the field
+    /// it occurs in is reported by `Reader::take_unescaped_quote`. This only
+    /// applies to `read_record`, and has no effect when quoting is disabled.
+    ///
+    /// This is disabled by default.
+    pub fn strict_quotes(&mut self, yes: bool) -> &mut ReaderBuilder {
+        self.rdr.strict_quotes = yes;
+        self
+    }
+
     /// A convenience method for specifying a configuration to read ASCII
     /// delimited text.
     ///
@@ -429,13 +491,14 @@ enum NfaState {
     InEscapedQuote = 4,
     InDoubleEscapedQuote = 5,
     InComment = 6,
+    InEscapedField = 7,
     // All states below are "final field" states.
     // Namely, they indicate that a field has been parsed.
-    EndFieldDelim = 7,
+    EndFieldDelim = 8,
     // All states below are "final record" states.
     // Namely, they indicate that a record has been parsed.
-    EndRecord = 8,
-    CRLF = 9,
+    EndRecord = 9,
+    CRLF = 10,
 }
 
 /// A list of NFA states that have an explicit representation in the DFA.
@@ -448,6 +511,7 @@ const NFA_STATES: &'static [NfaState] = &[
     NfaState::InEscapedQuote,
     NfaState::InDoubleEscapedQuote,
     NfaState::InComment,
+    NfaState::InEscapedField,
     NfaState::EndRecord,
     NfaState::CRLF,
 ];
@@ -487,6 +551,18 @@ impl Reader {
         self.nfa_state = NfaState::StartRecord;
         self.line = 1;
         self.has_read = false;
+        self.unescaped_quote = None;
+        self.record_fields = 0;
+    }
+
+    /// Return the index of the first field containing an unescaped quote in
+    /// the most recently parsed record, and clear it.
+    ///
+    /// This always returns `None` unless `strict_quotes` is enabled. Callers
+    /// should call this after every record returned by `read_record`, since
+    /// it is otherwise only cleared by `reset`.
+    pub fn take_unescaped_quote(&mut self) -> Option<u64> {
+        self.unescaped_quote.take()
     }
 
     /// Return the current line number as measured by the number of occurrences
@@ -505,6 +581,73 @@ impl Reader {
         self.line = line;
     }
 
+    /// Returns true if the parser is inside a quoted field whose closing
+    /// quote hasn't been seen yet.
+    ///
+    /// When this is true once all input has been given to the parser, the
+    /// CSV data ends with an unterminated quoted field. The parser still
+    /// returns that field as part of the final record.
+    pub fn is_in_quoted_field(&self) -> bool {
+        if self.use_nfa {
+            matches!(
+                self.nfa_state,
+                NfaState::InQuotedField | NfaState::InEscapedQuote
+            )
+        } else {
+            self.dfa_state == self.dfa.in_quoted
+                || self.dfa_state
+                    == self.dfa.new_state(NfaState::InEscapedQuote)
+        }
+    }
+
+    /// Returns true if escapes are recognized in unquoted fields.
+    #[inline]
+    pub fn get_escape_unquoted(&self) -> bool {
+        self.escape_unquoted
+    }
+
+    /// Return the delimiter used for this reader.
+    #[inline]
+    pub fn get_delimiter(&self) -> u8 {
+        self.delimiter
+    }
+
+    /// Return the terminator used for this reader.
+    #[inline]
+    pub fn get_terminator(&self) -> Terminator {
+        self.term
+    }
+
+    /// Return the quote character used for this reader.
+    #[inline]
+    pub fn get_quote(&self) -> u8 {
+        self.quote
+    }
+
+    /// Return the escape character used for this reader, if any.
+    #[inline]
+    pub fn get_escape(&self) -> Option<u8> {
+        self.escape
+    }
+
+    /// Return whether this reader recognizes doubled quotes.
+    #[inline]
+    pub fn get_double_quote(&self) -> bool {
+        self.double_quote
+    }
+
+    /// Return the comment character used for this reader, if any.
+    #[inline]
+    pub fn get_comment(&self) -> Option<u8> {
+        self.comment
+    }
+
+    /// Return whether this reader treats quotes specially.
+    #[inline]
+    pub fn get_quoting(&self) -> bool {
+        self.quoting
+    }
+
     /// Parse a single CSV field in `input` and copy field data to `output`.
     ///
     /// This routine requires a caller provided buffer of CSV data as the
@@ -597,11 +740,19 @@ impl Reader {
         ends: &mut [usize],
     ) -> (ReadRecordResult, usize, usize, usize) {
         let (input, bom_nin) = self.strip_utf8_bom(input);
-        let (res, nin, nout, nend) = if self.use_nfa {
-            self.read_record_nfa(input, output, ends)
-        } else {
-            self.read_record_dfa(input, output, ends)
+        // The parsing loops are inlined into each of these calls, so that
+        // checking for unescaped quotes costs nothing unless it's enabled.
+        let (res, nin, nout, nend) = match (self.use_nfa, self.strict_quotes) {
+            (true, false) => self.read_record_nfa(input, output, ends, false),
+            (true, true) => self.read_record_nfa(input, output, ends, true),
+            (false, false) => self.read_record_dfa(input, output, ends, false),
+            (false, true) => self.read_record_dfa(input, output, ends, true),
         };
+        if res == ReadRecordResult::Record {
+            self.record_fields = 0;
+        } else {
+            self.record_fields += nend as u64;
+        }
         self.has_read = true;
         (res, nin + bom_nin, nout, nend)
     }
@@ -628,6 +779,7 @@ impl Reader {
         input: &[u8],
         output: &mut [u8],
         ends: &mut [usize],
+        strict_quotes: bool,
     ) -> (ReadRecordResult, usize, usize, usize) {
         if input.is_empty() {
             let s = self.transition_final_dfa(self.dfa_state);
@@ -665,6 +817,9 @@ impl Reader {
         let (mut nin, mut nout, mut nend) = (0, 0, 0);
         let mut state = self.dfa_state;
         while nin < input.len() && nout < output.len() && nend < ends.len() {
+            if strict_quotes && state == self.dfa.in_field {
+                self.check_unescaped_quote(input[nin], nend);
+            }
             let (s, has_out) = self.dfa.get_output(state, input[nin]);
             self.line += (input[nin] == b'\n') as u64;
             state = s;
@@ -747,6 +902,16 @@ impl Reader {
         (res, nin, nout)
     }
 
+    /// Record an unescaped quote if `c` is a quote found inside an unquoted
+    /// field, where `nend` is the number of fields parsed so far by the
+    /// current call to `read_record`.
+    #[inline(always)]
+    fn check_unescaped_quote(&mut self, c: u8, nend: usize) {
+        if self.quoting && c == self.quote && self.unescaped_quote.is_none() {
+            self.unescaped_quote = Some(self.record_fields + nend as u64);
+        }
+    }
+
     /// Perform the final state transition, i.e., when the caller indicates
     /// that the input has been exhausted.
     fn transition_final_dfa(&self, state: DfaState) -> DfaState {
@@ -802,9 +967,13 @@ impl Reader {
         // Even though this requires an extra bit of indirection when computing
         // the next transition, microbenchmarks say that it doesn't make much
         // of a difference. Perhaps because everything fits into the L1 cache.
-        self.dfa.classes.add(self.delimiter);
+        if !self.single_field {
+            self.dfa.classes.add(self.delimiter);
+        }
         if self.quoting {
             self.dfa.classes.add(self.quote);
+        }
+        if self.quoting || self.escape_unquoted {
             if let Some(escape) = self.escape {
                 self.dfa.classes.add(escape);
             }
@@ -856,6 +1025,7 @@ impl Reader {
         input: &[u8],
         output: &mut [u8],
         ends: &mut [usize],
+        strict_quotes: bool,
     ) -> (ReadRecordResult, usize, usize, usize) {
         if input.is_empty() {
             let s = self.transition_final_nfa(self.nfa_state);
@@ -885,6 +1055,9 @@ impl Reader {
         let (mut nin, mut nout, mut nend) = (0, self.output_pos, 0);
         let mut state = self.nfa_state;
         while nin < input.len() && nout < output.len() && nend < ends.len() {
+            if strict_quotes && state == NfaState::InField {
+                self.check_unescaped_quote(input[nin], nend);
+            }
             let (s, io) = self.transition_nfa(state, input[nin]);
             match io {
                 NfaInputAction::CopyToOutput => {
@@ -971,10 +1144,23 @@ impl Reader {
             End | StartRecord | EndRecord | InComment | CRLF => End,
             StartField | EndFieldDelim | EndFieldTerm | InField
             | InQuotedField | InEscapedQuote | InDoubleEscapedQuote
-            | InRecordTerm => EndRecord,
+            | InEscapedField | InRecordTerm => EndRecord,
         }
     }
 
+    /// Returns true if the given byte escapes the next one in an unquoted
+    /// field.
+    #[inline(always)]
+    fn is_unquoted_escape(&self, c: u8) -> bool {
+        self.escape_unquoted && self.escape == Some(c)
+    }
+
+    /// Returns true if the given byte separates fields.
+    #[inline(always)]
+    fn is_delimiter(&self, c: u8) -> bool {
+        !self.single_field && self.delimiter == c
+    }
+
     /// Compute the next NFA state given the current NFA state and the current
     /// input byte.
     ///
@@ -1003,7 +1189,9 @@ impl Reader {
             StartField => {
                 if self.quoting && self.quote == c {
                     (InQuotedField, NfaInputAction::Discard)
-                } else if self.delimiter == c {
+                } else if self.is_unquoted_escape(c) {
+                    (InEscapedField, NfaInputAction::Discard)
+                } else if self.is_delimiter(c) {
                     (EndFieldDelim, NfaInputAction::Discard)
                 } else if self.term.equals(c) {
                     (EndFieldTerm, NfaInputAction::Epsilon)
@@ -1014,7 +1202,9 @@ impl Reader {
             EndFieldDelim => (StartField, NfaInputAction::Epsilon),
             EndFieldTerm => (InRecordTerm, NfaInputAction::Epsilon),
             InField => {
-                if self.delimiter == c {
+                if self.is_unquoted_escape(c) {
+                    (InEscapedField, NfaInputAction::Discard)
+                } else if self.is_delimiter(c) {
                     (EndFieldDelim, NfaInputAction::Discard)
                 } else if self.term.equals(c) {
                     (EndFieldTerm, NfaInputAction::Epsilon)
@@ -1032,10 +1222,13 @@ impl Reader {
                 }
             }
             InEscapedQuote => (InQuotedField, NfaInputAction::CopyToOutput),
+            InEscapedField => (InField, NfaInputAction::CopyToOutput),
             InDoubleEscapedQuote => {
                 if self.quoting && self.double_quote && self.quote == c {
                     (InQuotedField, NfaInputAction::CopyToOutput)
-                } else if self.delimiter == c {
+                } else if self.is_unquoted_escape(c) {
+                    (InEscapedField, NfaInputAction::Discard)
+                } else if self.is_delimiter(c) {
                     (EndFieldDelim, NfaInputAction::Discard)
                 } else if self.term.equals(c) {
                     (EndFieldTerm, NfaInputAction::Epsilon)
@@ -1072,7 +1265,7 @@ impl Reader {
 ///
 /// This number is computed by multiplying the maximum number of transition
 /// classes (7) by the total number of NFA states that are used in the DFA
-/// (10).
+/// (11).
 ///
 /// The number of transition classes is determined by an equivalence class of
 /// bytes, where every byte in the same equivalence classes is
@@ -1082,12 +1275,12 @@ impl Reader {
 /// effectively be treated as identical. This reduces storage space
 /// substantially.
 ///
-/// The total number of NFA states (13) is greater than the total number of
+/// The total number of NFA states (14) is greater than the total number of
 /// NFA states that are in the DFA. In particular, any NFA state that can only
 /// be reached by epsilon transitions will never have explicit usage in the
 /// DFA.
 const TRANS_CLASSES: usize = 7;
-const DFA_STATES: usize = 10;
+const DFA_STATES: usize = 11;
 const TRANS_SIZE: usize = TRANS_CLASSES * DFA_STATES;
 
 /// The number of possible transition classes. (See the comment on `TRANS_SIZE`
@@ -1525,6 +1718,14 @@ mod tests {
         csv![["a", "b", ""], ["x", "y", ""]]
     );
     parses_to!(many_rows_one_field_cr, "a\rb\r", csv![["a"], ["b"]]);
+    parses_to!(many_rows_lone_cr, "a\rb", csv![["a"], ["b"]]);
+    parses_to!(many_rows_lone_crlf, "a\r\nb", csv![["a"], ["b"]]);
+    parses_to!(many_rows_lf_cr, "a\n\rb", csv![["a"], ["b"]]);
+    parses_to!(
+        many_rows_many_fields_lone_cr,
+        "a,b\rc",
+        csv![["a", "b"], ["c"]]
+    );
     parses_to!(
         many_rows_many_fields_cr,
         "a,b,c\rx,y,z\r",
@@ -1647,6 +1848,31 @@ mod tests {
         }
     );
 
+    parses_to!(
+        quote_escapes_escape_before_close,
+        r#""abc\\",x"#,
+        csv![[r#"abc\"#, "x"]],
+        |b: &mut ReaderBuilder| {
+            b.escape(Some(b'\\'));
+        }
+    );
+    parses_to!(
+        quote_escapes_quote_before_close,
+        r#""abc\"",x"#,
+        csv![[r#"abc""#, "x"]],
+        |b: &mut ReaderBuilder| {
+            b.escape(Some(b'\\'));
+        }
+    );
+    parses_to!(
+        quote_escapes_quote_unterminated,
+        r#""abc\",x"#,
+        csv![[r#"abc",x"#]],
+        |b: &mut ReaderBuilder| {
+            b.escape(Some(b'\\'));
+        }
+    );
+
     parses_to!(
         quote_escapes_with_comma,
         r#""\"A,B\"""#,
@@ -1656,6 +1882,23 @@ mod tests {
         }
     );
 
+    parses_to!(
+        escape_unquoted,
+        "a\\,b,c\\\nd\n\"x\\\"y\"\\,z\n",
+        csv![["a,b", "c\nd"], ["x\"y,z"]],
+        |b: &mut ReaderBuilder| {
+            b.escape(Some(b'\\')).escape_unquoted(true);
+        }
+    );
+    parses_to!(
+        escape_unquoted_disabled,
+        "a\\,b",
+        csv![["a\\", "b"]],
+        |b: &mut ReaderBuilder| {
+            b.escape(Some(b'\\'));
+        }
+    );
+
     parses_to!(
         quoting_disabled,
         r#""abc,foo""#,
@@ -1681,6 +1924,14 @@ mod tests {
             b.delimiter(b'z');
         }
     );
+    parses_to!(
+        single_field,
+        "a,b\n\"c,\nd\",e\n",
+        csv![["a,b"], ["c,\nd,e"]],
+        |b: &mut ReaderBuilder| {
+            b.single_field(true);
+        }
+    );
 
     parses_to!(extra_record_crlf_1, "foo\n1\n", csv![["foo"], ["1"]]);
     parses_to!(extra_record_crlf_2, "foo\r\n1\r\n", csv![["foo"], ["1"]]);
@@ -2002,4 +2253,62 @@ mod tests {
 
         assert_read_record!(rdr, &inp, out, ends, 0, 0, 0, End);
     }
+
+    #[test]
+    fn strict_quotes() {
+        use crate::ReadRecordResult::*;
+
+        for &nfa in &[false, true] {
+            let out = &mut [0; 1024];
+            let ends = &mut [0; 10];
+            let mut rdr =
+                ReaderBuilder::new().strict_quotes(true).nfa(nfa).build();
+
+            assert_read_record!(
+                rdr,
+                b("x,\"y\"\n"),
+                out,
+                ends,
+                6,
+                2,
+                2,
+                Record
+            );
+            assert_eq!(rdr.take_unescaped_quote(), None);
+
+            // Split the record across two calls to make sure the field index
+            // accounts for fields parsed by previous calls.
+            let (res, _, _, _) = rdr.read_record(b("x,y,"), out, ends);
+            assert_eq!(res, InputEmpty);
+            let (res, _, _, _) = rdr.read_record(b("a\"b\n"), out, ends);
+            assert_eq!(res, Record);
+            assert_eq!(rdr.take_unescaped_quote(), Some(2));
+            assert_eq!(rdr.take_unescaped_quote(), None);
+
+            let mut rdr = ReaderBuilder::new().nfa(nfa).build();
+            assert_read_record!(rdr, b("a\"b\n"), out, ends, 4, 3, 1, Record);
+            assert_eq!(rdr.take_unescaped_quote(), None);
+        }
+    }
+
+    #[test]
+    fn in_quoted_field() {
+        for &nfa in &[false, true] {
+            let out = &mut [0; 1024];
+            let ends = &mut [0; 10];
+            let mut rdr = ReaderBuilder::new().nfa(nfa).build();
+            assert!(!rdr.is_in_quoted_field());
+            rdr.read_record(b("a,\"b"), out, ends);
+            assert!(rdr.is_in_quoted_field());
+            rdr.read_record(b("\"\""), out, ends);
+            assert!(rdr.is_in_quoted_field());
+            rdr.read_record(b("\""), out, ends);
+            assert!(!rdr.is_in_quoted_field());
+
+            let mut rdr =
+                ReaderBuilder::new().escape(Some(b'\\')).nfa(nfa).build();
+            rdr.read_record(b("\"a\\"), out, ends);
+            assert!(rdr.is_in_quoted_field());
+        }
+    }
 }
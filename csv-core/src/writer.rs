@@ -1,7 +1,7 @@
 use core::fmt;
 use core::str;
 
-use memchr::memchr;
+use memchr::{memchr, memchr2};
 
 use crate::{QuoteStyle, Terminator};
 
@@ -26,6 +26,8 @@ impl WriterBuilder {
             quote: b'"',
             escape: b'\\',
             double_quote: true,
+            quote_empty: true,
+            quoting: true,
         };
         WriterBuilder { wtr: wtr }
     }
@@ -35,6 +37,10 @@ impl WriterBuilder {
         use crate::Terminator::*;
 
         let mut wtr = self.wtr.clone();
+        if !self.wtr.quoting {
+            wtr.style = QuoteStyle::Never;
+            wtr.quote_empty = false;
+        }
         wtr.requires_quotes[self.wtr.delimiter as usize] = true;
         wtr.requires_quotes[self.wtr.quote as usize] = true;
         if !self.wtr.double_quote {
@@ -114,11 +120,36 @@ impl WriterBuilder {
     /// them. e.g., `"` escapes to `""`.
     ///
     /// When disabled, quotes are escaped with the escape character (which
-    /// is `\\` by default).
+    /// is `\\` by default). The escape character is then escaped as well,
+    /// so that e.g., `\\` escapes to `\\\\`. This matches how a reader
+    /// with the same escape character unescapes quoted fields.
     pub fn double_quote(&mut self, yes: bool) -> &mut WriterBuilder {
         self.wtr.double_quote = yes;
         self
     }
+
+    /// Whether to write a pair of quotes for an empty record.
+    ///
+    /// A record with a single empty field is otherwise indistinguishable from
+    /// an empty line, so when enabled (which is the default), such a record
+    /// is written as `""`. When disabled, nothing is written for it but the
+    /// record terminator.
+    pub fn quote_empty(&mut self, yes: bool) -> &mut WriterBuilder {
+        self.wtr.quote_empty = yes;
+        self
+    }
+
+    /// Enable or disable quoting.
+    ///
+    /// This is enabled by default, but it may be disabled. When disabled,
+    /// fields are never quoted or escaped, regardless of the quoting style,
+    /// so the writer built uses `QuoteStyle::Never`. A record with a single
+    /// empty field is then written as an empty line, as if `quote_empty`
+    /// were disabled.
+    pub fn quoting(&mut self, yes: bool) -> &mut WriterBuilder {
+        self.wtr.quoting = yes;
+        self
+    }
 }
 
 impl Default for WriterBuilder {
@@ -166,6 +197,8 @@ pub struct Writer {
     quote: u8,
     escape: u8,
     double_quote: bool,
+    quote_empty: bool,
+    quoting: bool,
 }
 
 impl Clone for Writer {
@@ -183,6 +216,8 @@ impl Clone for Writer {
             quote: self.quote,
             escape: self.escape,
             double_quote: self.double_quote,
+            quote_empty: self.quote_empty,
+            quoting: self.quoting,
         }
     }
 }
@@ -197,6 +232,8 @@ impl fmt::Debug for Writer {
             .field("quote", &self.quote)
             .field("escape", &self.escape)
             .field("double_quote", &self.double_quote)
+            .field("quote_empty", &self.quote_empty)
+            .field("quoting", &self.quoting)
             .finish()
     }
 }
@@ -214,7 +251,8 @@ struct WriterState {
     /// The number of total bytes written for the current record.
     ///
     /// If the writer is finished or a terminator is written when this is `0`,
-    /// then an empty field is added as a pair of adjacent quotes.
+    /// then an empty field is added as a pair of adjacent quotes, unless
+    /// `quote_empty` is disabled.
     record_bytes: u64,
 }
 
@@ -228,9 +266,15 @@ impl Writer {
     ///
     /// This must be called when one is done writing CSV data to `output`.
     /// In particular, it will write closing quotes if necessary.
+    ///
+    /// Once this returns `WriteResult::InputEmpty`, the current record is
+    /// complete and the next field written begins a new record.
     pub fn finish(&mut self, mut output: &mut [u8]) -> (WriteResult, usize) {
         let mut nout = 0;
-        if self.state.record_bytes == 0 && self.state.in_field {
+        if self.state.record_bytes == 0
+            && self.state.in_field
+            && self.quote_empty
+        {
             assert!(!self.state.quoting);
             let (res, o) = self.write(&[self.quote, self.quote], output);
             if o == 0 {
@@ -241,6 +285,8 @@ impl Writer {
             self.state.record_bytes += o as u64;
         }
         if !self.state.quoting {
+            self.state.record_bytes = 0;
+            self.state.in_field = false;
             return (WriteResult::InputEmpty, nout);
         }
         let (res, o) = self.write(&[self.quote], output);
@@ -254,6 +300,15 @@ impl Writer {
         (res, nout)
     }
 
+    /// Forget about the record currently being written, if any.
+    ///
+    /// The next field written begins a new record. Nothing is written for
+    /// the abandoned record, so in particular, no closing quote is written
+    /// if it ended in the middle of a quoted field.
+    pub fn reset(&mut self) {
+        self.state = WriterState::default();
+    }
+
     /// Write a single CSV field from `input` to `output` while employing this
     /// writer's quoting style.
     ///
@@ -352,7 +407,7 @@ impl Writer {
         mut output: &mut [u8],
     ) -> (WriteResult, usize) {
         let mut nout = 0;
-        if self.state.record_bytes == 0 {
+        if self.state.record_bytes == 0 && self.quote_empty {
             assert!(!self.state.quoting);
             let (res, o) = self.write(&[self.quote, self.quote], output);
             if o == 0 {
@@ -466,6 +521,12 @@ impl Writer {
         self.double_quote
     }
 
+    /// Return whether quoting is enabled for this writer.
+    #[inline]
+    pub fn get_quoting(&self) -> bool {
+        self.quoting
+    }
+
     fn write(&self, data: &[u8], output: &mut [u8]) -> (WriteResult, usize) {
         if data.len() > output.len() {
             (WriteResult::OutputFull, 0)
@@ -520,7 +581,8 @@ pub fn is_non_numeric(input: &[u8]) -> bool {
 ///
 /// `quote` is the quote byte and `escape` is the escape byte. If
 /// `double_quote` is true, then quotes are escaped by doubling them,
-/// otherwise, quotes are escaped with the `escape` byte.
+/// otherwise, quotes and the `escape` byte itself are escaped with the
+/// `escape` byte.
 ///
 /// N.B. This function is provided for low level usage. It is called
 /// automatically if you're using a `Writer`.
@@ -533,7 +595,12 @@ pub fn quote(
 ) -> (WriteResult, usize, usize) {
     let (mut nin, mut nout) = (0, 0);
     loop {
-        match memchr(quote, input) {
+        let next = if double_quote {
+            memchr(quote, input)
+        } else {
+            memchr2(quote, escape, input)
+        };
+        match next {
             None => {
                 let (res, i, o) = write_optimistic(input, output);
                 nin += i;
@@ -558,7 +625,8 @@ pub fn quote(
                     nout += o;
                     output = &mut moving(output)[o..];
                 } else {
-                    let (res, o) = write_pessimistic(&[escape, quote], output);
+                    let (res, o) =
+                        write_pessimistic(&[escape, input[0]], output);
                     if let WriteResult::OutputFull = res {
                         return (res, nin, nout);
                     }
@@ -698,6 +766,53 @@ mod tests {
         assert_write!(wtr, finish, &mut out[..], 2, InputEmpty, "\"\"");
     }
 
+    #[test]
+    fn writer_finish_starts_new_record() {
+        let mut wtr = Writer::new();
+        let out = &mut [0; 1024];
+
+        assert_field!(wtr, b("a"), &mut out[..], 1, 1, InputEmpty, "a");
+        assert_write!(wtr, finish, &mut out[..], 0, InputEmpty, "");
+        assert_field!(wtr, b("b,c"), &mut out[..], 3, 4, InputEmpty, "\"b,c");
+        assert_write!(wtr, finish, &mut out[..], 1, InputEmpty, "\"");
+    }
+
+    #[test]
+    fn writer_no_quote_empty() {
+        let mut wtr = WriterBuilder::new().quote_empty(false).build();
+        let out = &mut [0; 1024];
+
+        assert_field!(wtr, b(""), &mut out[..], 0, 0, InputEmpty, "");
+        assert_write!(wtr, terminator, &mut out[..], 1, InputEmpty, "\n");
+        assert_write!(wtr, terminator, &mut out[..], 1, InputEmpty, "\n");
+        assert_field!(wtr, b(""), &mut out[..], 0, 0, InputEmpty, "");
+        assert_write!(wtr, finish, &mut out[..], 0, InputEmpty, "");
+    }
+
+    #[test]
+    fn writer_no_quoting() {
+        let mut wtr = WriterBuilder::new()
+            .quote_style(QuoteStyle::Always)
+            .quoting(false)
+            .build();
+        assert!(!wtr.get_quoting());
+        assert!(matches!(wtr.get_quote_style(), QuoteStyle::Never));
+        let out = &mut [0; 1024];
+
+        assert_field!(
+            wtr,
+            b("a,\"b\""),
+            &mut out[..],
+            5,
+            5,
+            InputEmpty,
+            "a,\"b\""
+        );
+        assert_write!(wtr, terminator, &mut out[..], 1, InputEmpty, "\n");
+        assert_field!(wtr, b(""), &mut out[..], 0, 0, InputEmpty, "");
+        assert_write!(wtr, finish, &mut out[..], 0, InputEmpty, "");
+    }
+
     #[test]
     fn writer_many_one_empty_field_terminator() {
         let mut wtr = Writer::new();
@@ -1000,6 +1115,14 @@ mod tests {
         assert_quote!(inp, out, 2, 4, InputEmpty, r#"\"\""#, false);
     }
 
+    #[test]
+    fn quote_escaped_escape() {
+        let inp = b(r#"a\b"c\\"#);
+        let out = &mut [0; 1024];
+
+        assert_quote!(inp, out, 7, 11, InputEmpty, r#"a\\b\"c\\\\"#, false);
+    }
+
     #[test]
     fn quote_misc() {
         let inp = b(r#"foo "bar" baz "quux"?"#);
@@ -2,6 +2,7 @@ use std::io;
 
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use csv;
+use serde::de::DeserializeOwned;
 
 /// A simple index for random access to CSV records.
 ///
@@ -225,6 +226,130 @@ impl<R: io::Read + io::Seek> RandomAccessSimple<R> {
         Ok(pos)
     }
 
+    /// Seek the given CSV reader to the data record at index `i` and read it.
+    ///
+    /// If `rdr` is configured to read headers, then the header record is not
+    /// counted, so index `0` refers to the first record after the header row.
+    /// Otherwise, this uses the same indices as `get`. If `i` is out of
+    /// bounds, then `None` is returned.
+    ///
+    /// The CSV reader given should read the same CSV data that was used to
+    /// create this index. Like `get`, this will cause the CSV reader to report
+    /// incorrect line numbers for subsequent records.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use std::io;
+    /// use csv_index::RandomAccessSimple;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,pop
+    /// Boston,United States,4628910
+    /// Concord,United States,42695
+    /// ";
+    ///     let mut rdr = csv::Reader::from_reader(io::Cursor::new(data));
+    ///     let mut wtr = io::Cursor::new(vec![]);
+    ///     RandomAccessSimple::create(&mut rdr, &mut wtr)?;
+    ///
+    ///     let mut idx = RandomAccessSimple::open(wtr)?;
+    ///     let record = idx.record(&mut rdr, 1)?.unwrap();
+    ///     assert_eq!(record, vec!["Concord", "United States", "42695"]);
+    ///     assert!(idx.record(&mut rdr, 2)?.is_none());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn record<S: io::Read + io::Seek>(
+        &mut self,
+        rdr: &mut csv::Reader<S>,
+        i: u64,
+    ) -> csv::Result<Option<csv::StringRecord>> {
+        if !self.seek_record(rdr, i)? {
+            return Ok(None);
+        }
+        let mut record = csv::StringRecord::new();
+        if rdr.read_record(&mut record)? {
+            Ok(Some(record))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Seek the given CSV reader to the data record at index `i` and
+    /// deserialize it.
+    ///
+    /// This is the typed counterpart of `record`, and uses the same indices.
+    /// The record is deserialized as by `csv::Reader::deserialize`, so if
+    /// `rdr` is configured to read headers, then they're used to match
+    /// fields to the fields of structs. If `i` is out of bounds, then `None`
+    /// is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use std::io;
+    /// use csv_index::RandomAccessSimple;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq)]
+    /// struct Row {
+    ///     pop: u64,
+    ///     city: String,
+    /// }
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,pop
+    /// Boston,United States,4628910
+    /// Concord,United States,42695
+    /// ";
+    ///     let mut rdr = csv::Reader::from_reader(io::Cursor::new(data));
+    ///     let mut wtr = io::Cursor::new(vec![]);
+    ///     RandomAccessSimple::create(&mut rdr, &mut wtr)?;
+    ///
+    ///     let mut idx = RandomAccessSimple::open(wtr)?;
+    ///     let row: Row = idx.deserialize_record(&mut rdr, 1)?.unwrap();
+    ///     assert_eq!(row, Row { pop: 42695, city: "Concord".to_string() });
+    ///     assert!(idx.deserialize_record::<_, Row>(&mut rdr, 2)?.is_none());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn deserialize_record<S, D>(
+        &mut self,
+        rdr: &mut csv::Reader<S>,
+        i: u64,
+    ) -> csv::Result<Option<D>>
+    where
+        S: io::Read + io::Seek,
+        D: DeserializeOwned,
+    {
+        if !self.seek_record(rdr, i)? {
+            return Ok(None);
+        }
+        rdr.deserialize().next().transpose()
+    }
+
+    /// Seek the given CSV reader to the data record at index `i`, as counted
+    /// by `record`. Returns false if `i` is out of bounds.
+    fn seek_record<S: io::Read + io::Seek>(
+        &mut self,
+        rdr: &mut csv::Reader<S>,
+        i: u64,
+    ) -> csv::Result<bool> {
+        let i = match i.checked_add(rdr.has_headers() as u64) {
+            Some(i) if i < self.len => i,
+            _ => return Ok(false),
+        };
+        let pos = self.get(i)?;
+        rdr.seek(pos)?;
+        Ok(true)
+    }
+
     /// Return the number of records (including the header record) in this
     /// index.
     pub fn len(&self) -> u64 {
@@ -242,6 +367,7 @@ mod tests {
     use std::io;
 
     use csv;
+    use serde::Deserialize;
 
     use super::RandomAccessSimple;
 
@@ -368,4 +494,56 @@ c
         assert_eq!(idx.read_at(2), vec!["b"]);
         assert_eq!(idx.read_at(3), vec!["c"]);
     }
+
+    #[test]
+    fn record_random_access() {
+        let data = "h1,h2\na,1\nb,2\nc,3\n";
+        let mut idx = Indexed::new(true, data);
+        assert_eq!(
+            idx.idx.record(&mut idx.csv, 2).unwrap().unwrap(),
+            vec!["c", "3"]
+        );
+        assert_eq!(
+            idx.idx.record(&mut idx.csv, 0).unwrap().unwrap(),
+            vec!["a", "1"]
+        );
+        assert_eq!(
+            idx.idx.record(&mut idx.csv, 1).unwrap().unwrap(),
+            vec!["b", "2"]
+        );
+        assert!(idx.idx.record(&mut idx.csv, 3).unwrap().is_none());
+        assert!(idx.idx.record(&mut idx.csv, u64::MAX).unwrap().is_none());
+
+        let mut idx = Indexed::new(false, data);
+        assert_eq!(
+            idx.idx.record(&mut idx.csv, 0).unwrap().unwrap(),
+            vec!["h1", "h2"]
+        );
+        assert_eq!(
+            idx.idx.record(&mut idx.csv, 3).unwrap().unwrap(),
+            vec!["c", "3"]
+        );
+        assert!(idx.idx.record(&mut idx.csv, 4).unwrap().is_none());
+    }
+
+    #[test]
+    fn deserialize_random_access() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Row {
+            n: u32,
+            name: String,
+        }
+
+        let data = "name,n\na,1\nb,2\nc,3\n";
+        let mut idx = Indexed::new(true, data);
+        let row: Row =
+            idx.idx.deserialize_record(&mut idx.csv, 2).unwrap().unwrap();
+        assert_eq!(row, Row { n: 3, name: "c".to_string() });
+        let row: Row =
+            idx.idx.deserialize_record(&mut idx.csv, 0).unwrap().unwrap();
+        assert_eq!(row, Row { n: 1, name: "a".to_string() });
+        let row: Option<Row> =
+            idx.idx.deserialize_record(&mut idx.csv, 3).unwrap();
+        assert!(row.is_none());
+    }
 }
@@ -0,0 +1,24 @@
+use std::error::Error;
+use std::process;
+
+use csv::{ReaderBuilder, WriterBuilder};
+
+fn example() -> Result<(), Box<dyn Error>> {
+    // Lock stdin and stdout once, and copy every record (including the
+    // header) from one to the other.
+    let mut rdr = ReaderBuilder::new().has_headers(false).from_stdin();
+    let mut wtr = WriterBuilder::new().from_stdout();
+    for result in rdr.byte_records() {
+        let record = result?;
+        wtr.write_byte_record(&record)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = example() {
+        println!("error running example: {}", err);
+        process::exit(1);
+    }
+}
@@ -10,6 +10,11 @@ use serde::de::Deserialize;
 use crate::deserializer::deserialize_byte_record;
 use crate::error::{new_utf8_error, Result, Utf8Error};
 use crate::string_record::StringRecord;
+use crate::GrowthPolicy;
+
+/// A field that differs between two records, as returned by
+/// `ByteRecord::diff`: its index and its value in each record.
+type FieldDiff<'a> = (usize, Option<&'a [u8]>, Option<&'a [u8]>);
 
 /// A single CSV record stored as raw bytes.
 ///
@@ -252,6 +257,35 @@ impl ByteRecord {
         self.into_iter()
     }
 
+    /// Returns an iterator over all fields in this record, along with the
+    /// range of each field within the record's buffer.
+    ///
+    /// The buffer is the slice returned by `as_slice`. Since fields are stored
+    /// back to back, the ranges are contiguous: each range starts where the
+    /// previous one ends, and together they cover the entire buffer.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use csv::ByteRecord;
+    ///
+    /// let record = ByteRecord::from(vec!["a", "bc", ""]);
+    /// let fields: Vec<_> = record.iter_with_ranges().collect();
+    /// assert_eq!(fields, vec![
+    ///     (0..1, &b"a"[..]),
+    ///     (1..3, &b"bc"[..]),
+    ///     (3..3, &b""[..]),
+    /// ]);
+    /// ```
+    pub fn iter_with_ranges(
+        &self,
+    ) -> impl Iterator<Item = (Range<usize>, &[u8])> + '_ {
+        (0..self.len()).map(move |i| {
+            let range = self.0.bounds.get(i).unwrap();
+            (range.clone(), &self.0.fields[range])
+        })
+    }
+
     /// Return the field at index `i`.
     ///
     /// If no field at index `i` exists, then this returns `None`.
@@ -270,6 +304,31 @@ impl ByteRecord {
         self.0.bounds.get(i).map(|range| &self.0.fields[range])
     }
 
+    /// Return a mutable reference to the field at index `i`.
+    ///
+    /// This permits editing a field in place, without reallocating the
+    /// record, e.g., to change its case. The length of the field can't be
+    /// changed this way. To replace a field with one of a different length,
+    /// build a new record instead.
+    ///
+    /// If no field at index `i` exists, then this returns `None`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use csv::ByteRecord;
+    ///
+    /// let mut record = ByteRecord::from(vec!["a", "boston", "c"]);
+    /// record.get_mut(1).unwrap().make_ascii_uppercase();
+    /// assert_eq!(record, vec!["a", "BOSTON", "c"]);
+    /// assert_eq!(record.get_mut(3), None);
+    /// ```
+    #[inline]
+    pub fn get_mut(&mut self, i: usize) -> Option<&mut [u8]> {
+        let range = self.0.bounds.get(i)?;
+        Some(&mut self.0.fields[range])
+    }
+
     /// Returns true if and only if this record is empty.
     ///
     /// # Example
@@ -322,6 +381,38 @@ impl ByteRecord {
         }
     }
 
+    /// Remove the field at index `i` from this record.
+    ///
+    /// All fields after `i` are shifted down by one, and the field data that
+    /// follows the removed field is moved to fill its space.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `i` is not less than the number of fields in this
+    /// record.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use csv::ByteRecord;
+    ///
+    /// let mut record = ByteRecord::from(vec!["a", "b", "c"]);
+    /// record.remove_field(1);
+    /// assert_eq!(record, vec!["a", "c"]);
+    /// ```
+    pub fn remove_field(&mut self, i: usize) {
+        let len = self.len();
+        assert!(
+            i < len,
+            "field index {} out of bounds for record with {} fields",
+            i,
+            len
+        );
+        let end = self.0.bounds.end();
+        let range = self.0.bounds.remove(i);
+        self.0.fields.copy_within(range.end..end, range.start);
+    }
+
     /// Clear this record so that it has zero fields.
     ///
     /// This is equivalent to calling `truncate(0)`.
@@ -391,12 +482,66 @@ impl ByteRecord {
     pub fn push_field(&mut self, field: &[u8]) {
         let (s, e) = (self.0.bounds.end(), self.0.bounds.end() + field.len());
         while e > self.0.fields.len() {
-            self.expand_fields();
+            self.expand_fields(GrowthPolicy::Double);
         }
         self.0.fields[s..e].copy_from_slice(field);
         self.0.bounds.add(e);
     }
 
+    /// Add all of the fields of `other` to the end of this record.
+    ///
+    /// The data of `other` is copied into this record's buffer in one go,
+    /// which is faster than pushing each of its fields individually.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use csv::ByteRecord;
+    ///
+    /// let mut record = ByteRecord::from(vec!["a", "b"]);
+    /// record.extend_from_record(&ByteRecord::from(vec!["c", "d", "e"]));
+    /// assert_eq!(record, vec!["a", "b", "c", "d", "e"]);
+    /// ```
+    pub fn extend_from_record(&mut self, other: &ByteRecord) {
+        let offset = self.0.bounds.end();
+        let data = other.as_slice();
+        while offset + data.len() > self.0.fields.len() {
+            self.expand_fields(GrowthPolicy::Double);
+        }
+        self.0.fields[offset..offset + data.len()].copy_from_slice(data);
+        self.0.bounds.reserve(self.len() + other.len());
+        for &end in other.0.bounds.ends() {
+            self.0.bounds.add(offset + end);
+        }
+    }
+
+    /// Compare this record with `other` field by field.
+    ///
+    /// This returns the index of every field that differs, along with the
+    /// field of this record and the field of `other` at that index. A field
+    /// is `None` when its record is too short to have it. If the records
+    /// have equal fields, then an empty vector is returned. Positions are
+    /// ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use csv::ByteRecord;
+    ///
+    /// let a = ByteRecord::from(vec!["a", "b", "c"]);
+    /// let b = ByteRecord::from(vec!["a", "x"]);
+    /// assert_eq!(a.diff(&b), vec![
+    ///     (1, Some(&b"b"[..]), Some(&b"x"[..])),
+    ///     (2, Some(&b"c"[..]), None),
+    /// ]);
+    /// ```
+    pub fn diff<'a>(&'a self, other: &'a ByteRecord) -> Vec<FieldDiff<'a>> {
+        (0..self.len().max(other.len()))
+            .map(|i| (i, self.get(i), other.get(i)))
+            .filter(|&(_, a, b)| a != b)
+            .collect()
+    }
+
     /// Return the position of this record, if available.
     ///
     /// # Example
@@ -497,6 +642,24 @@ impl ByteRecord {
         &self.0.fields[..self.0.bounds.end()]
     }
 
+    /// Return the total number of bytes in all fields of this record.
+    ///
+    /// This doesn't include delimiters or quotes, and is equivalent to the
+    /// length of `as_slice`. It is computed in constant time.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use csv::ByteRecord;
+    ///
+    /// let record = ByteRecord::from(vec!["foo", "quux", "z"]);
+    /// assert_eq!(record.byte_len(), 8);
+    /// ```
+    #[inline]
+    pub fn byte_len(&self) -> usize {
+        self.0.bounds.end()
+    }
+
     /// Retrieve the underlying parts of a byte record.
     #[inline]
     pub(crate) fn as_parts(&mut self) -> (&mut Vec<u8>, &mut Vec<usize>) {
@@ -512,9 +675,15 @@ impl ByteRecord {
 
     /// Expand the capacity for storing fields.
     #[inline]
-    pub(crate) fn expand_fields(&mut self) {
-        let new_len = self.0.fields.len().checked_mul(2).unwrap();
-        self.0.fields.resize(cmp::max(4, new_len), 0);
+    pub(crate) fn expand_fields(&mut self, growth: GrowthPolicy) {
+        let new_len = growth.grow(self.0.fields.len());
+        self.0.fields.resize(new_len, 0);
+    }
+
+    /// Return the capacity for storing fields.
+    #[cfg(test)]
+    pub(crate) fn fields_capacity(&self) -> usize {
+        self.0.fields.len()
     }
 
     /// Expand the capacity for storing field ending positions.
@@ -523,6 +692,13 @@ impl ByteRecord {
         self.0.bounds.expand();
     }
 
+    /// Ensure there is capacity for storing at least `fields` field ending
+    /// positions.
+    #[inline]
+    pub(crate) fn reserve_ends(&mut self, fields: usize) {
+        self.0.bounds.reserve(fields);
+    }
+
     /// Validate the given record as UTF-8.
     ///
     /// If it's not UTF-8, return an error.
@@ -699,6 +875,29 @@ impl Bounds {
         self.ends.resize(cmp::max(4, new_len), 0);
     }
 
+    /// Ensure there is capacity for storing at least `fields` field ending
+    /// positions.
+    #[inline]
+    fn reserve(&mut self, fields: usize) {
+        if self.ends.len() < fields {
+            self.ends.resize(fields, 0);
+        }
+    }
+
+    /// Remove the field at index `i`, which must be in bounds, and return
+    /// the range it occupied. The ends of all subsequent fields are shifted
+    /// down by the length of the removed field.
+    #[inline]
+    fn remove(&mut self, i: usize) -> Range<usize> {
+        let range = self.get(i).unwrap();
+        let width = range.end - range.start;
+        for j in i + 1..self.len {
+            self.ends[j - 1] = self.ends[j] - width;
+        }
+        self.len -= 1;
+        range
+    }
+
     /// Add a new field with the given ending position.
     #[inline]
     fn add(&mut self, pos: usize) {
@@ -1146,4 +1345,108 @@ mod tests {
         let test2 = ByteRecord::from(vec!["12", "34"]);
         assert_ne!(test1, test2);
     }
+
+    #[test]
+    fn remove_field_middle() {
+        let mut rec = ByteRecord::from(vec!["foo", "quux", "ba"]);
+        rec.remove_field(1);
+        assert_eq!(rec.len(), 2);
+        assert_eq!(rec.get(0), Some(b("foo")));
+        assert_eq!(rec.get(1), Some(b("ba")));
+        assert_eq!(rec.get(2), None);
+        assert_eq!(rec.as_slice(), b("fooba"));
+
+        rec.push_field(b("z"));
+        assert_eq!(rec, vec!["foo", "ba", "z"]);
+    }
+
+    #[test]
+    fn remove_field_ends() {
+        let mut rec = ByteRecord::from(vec!["a", "bc", "", "d"]);
+        rec.remove_field(0);
+        assert_eq!(rec, vec!["bc", "", "d"]);
+        rec.remove_field(2);
+        assert_eq!(rec, vec!["bc", ""]);
+        rec.remove_field(1);
+        rec.remove_field(0);
+        assert!(rec.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn remove_field_out_of_bounds() {
+        let mut rec = ByteRecord::from(vec!["a"]);
+        rec.remove_field(1);
+    }
+
+    #[test]
+    fn iter_with_ranges() {
+        let rec = ByteRecord::from(vec!["foo", "", "quux", "b"]);
+        let mut end = 0;
+        for ((range, field), expected) in rec.iter_with_ranges().zip(&rec) {
+            assert_eq!(range.start, end);
+            assert_eq!(&rec.as_slice()[range.clone()], field);
+            assert_eq!(field, expected);
+            end = range.end;
+        }
+        assert_eq!(end, rec.as_slice().len());
+        assert_eq!(rec.iter_with_ranges().count(), rec.len());
+
+        assert_eq!(ByteRecord::new().iter_with_ranges().count(), 0);
+    }
+
+    #[test]
+    fn byte_len() {
+        let mut rec = ByteRecord::from(vec!["abc", "", "de", "f"]);
+        assert_eq!(rec.byte_len(), 6);
+        rec.truncate(3);
+        assert_eq!(rec.byte_len(), 5);
+        rec.remove_field(0);
+        assert_eq!(rec.byte_len(), 2);
+        rec.clear();
+        assert_eq!(rec.byte_len(), 0);
+        assert_eq!(ByteRecord::new().byte_len(), 0);
+    }
+
+    #[test]
+    fn diff() {
+        let a = ByteRecord::from(vec!["a", "b", "c"]);
+        assert!(a.diff(&a.clone()).is_empty());
+        assert!(ByteRecord::new().diff(&ByteRecord::new()).is_empty());
+
+        let b = ByteRecord::from(vec!["a", "x", "c"]);
+        assert_eq!(a.diff(&b), vec![(1, Some(&b"b"[..]), Some(&b"x"[..]))]);
+
+        let b = ByteRecord::from(vec!["a", "b", "c", "", "e"]);
+        assert_eq!(
+            a.diff(&b),
+            vec![(3, None, Some(&b""[..])), (4, None, Some(&b"e"[..]))]
+        );
+    }
+
+    #[test]
+    fn extend_from_record() {
+        let mut rec = ByteRecord::from(vec!["ab", ""]);
+        rec.extend_from_record(&ByteRecord::from(vec!["cde", "", "f"]));
+        assert_eq!(rec.len(), 5);
+        assert_eq!(rec, vec!["ab", "", "cde", "", "f"]);
+        assert_eq!(rec.as_slice(), b"abcdef");
+
+        let mut rec = ByteRecord::new();
+        rec.extend_from_record(&ByteRecord::new());
+        assert!(rec.is_empty());
+        rec.extend_from_record(&ByteRecord::from(vec!["x"]));
+        rec.push_field(b"y");
+        assert_eq!(rec, vec!["x", "y"]);
+    }
+
+    #[test]
+    fn get_mut() {
+        let mut rec = ByteRecord::from(vec!["abc", "xyz", "", "123"]);
+        rec.get_mut(1).unwrap().make_ascii_uppercase();
+        rec.get_mut(2).unwrap().make_ascii_uppercase();
+        assert_eq!(rec, vec!["abc", "XYZ", "", "123"]);
+        assert_eq!(rec.as_slice(), b"abcXYZ123");
+        assert!(rec.get_mut(4).is_none());
+    }
 }
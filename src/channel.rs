@@ -0,0 +1,99 @@
+use std::io;
+use std::sync::mpsc::Receiver;
+
+use crate::byte_record::ByteRecord;
+use crate::error::Result;
+use crate::writer::Writer;
+
+/// Write every record received from a channel.
+///
+/// Records are written to `wtr` in the order they are received, until every
+/// sender of the channel has been dropped. The writer is then flushed, and
+/// the number of records written is returned. This is useful when records
+/// are produced by other threads, which can each send records through a
+/// clone of the channel's `Sender`.
+///
+/// If writing a record fails, then the error is returned immediately and
+/// records still in the channel are not written.
+///
+/// # Example
+///
+/// ```
+/// use std::error::Error;
+/// use std::sync::mpsc;
+/// use std::thread;
+/// use csv::{ByteRecord, Writer};
+///
+/// # fn main() { example().unwrap(); }
+/// fn example() -> Result<(), Box<dyn Error>> {
+///     let (tx, rx) = mpsc::channel();
+///     let producer = thread::spawn(move || {
+///         for i in 0..3 {
+///             let record = ByteRecord::from(vec![i.to_string()]);
+///             tx.send(record).unwrap();
+///         }
+///     });
+///
+///     let mut wtr = Writer::from_writer(vec![]);
+///     let count = csv::write_from_receiver(&mut wtr, rx)?;
+///     producer.join().unwrap();
+///
+///     assert_eq!(count, 3);
+///     let data = String::from_utf8(wtr.into_inner()?)?;
+///     assert_eq!(data, "0\n1\n2\n");
+///     Ok(())
+/// }
+/// ```
+pub fn write_from_receiver<W: io::Write>(
+    wtr: &mut Writer<W>,
+    rx: Receiver<ByteRecord>,
+) -> Result<u64> {
+    let mut count = 0;
+    for record in rx {
+        wtr.write_byte_record(&record)?;
+        count += 1;
+    }
+    wtr.flush()?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::thread;
+
+    use crate::byte_record::ByteRecord;
+    use crate::writer::Writer;
+
+    use super::write_from_receiver;
+
+    #[test]
+    fn several_producers() {
+        let (tx, rx) = mpsc::channel();
+        let producers: Vec<_> = (0..4)
+            .map(|i| {
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    for j in 0..5 {
+                        let fields = vec![i.to_string(), j.to_string()];
+                        tx.send(ByteRecord::from(fields)).unwrap();
+                    }
+                })
+            })
+            .collect();
+        drop(tx);
+
+        let mut wtr = Writer::from_writer(vec![]);
+        assert_eq!(write_from_receiver(&mut wtr, rx).unwrap(), 20);
+        for producer in producers {
+            producer.join().unwrap();
+        }
+
+        let data = String::from_utf8(wtr.into_inner().unwrap()).unwrap();
+        let mut lines: Vec<&str> = data.lines().collect();
+        assert_eq!(lines.len(), 20);
+        lines.sort();
+        assert_eq!(&lines[..3], &["0,0", "0,1", "0,2"]);
+        assert_eq!(lines[19], "3,4");
+    }
+}
@@ -20,6 +20,10 @@ For **writing** CSV:
 5. [Basic](#writing-basic)
 6. [With Serde](#writing-with-serde)
 
+For **filtering** CSV:
+
+7. [Passing stdin through to stdout](#filtering-passing-stdin-through-to-stdout)
+
 Please
 [submit a pull request](https://github.com/BurntSushi/rust-csv/pulls)
 if you're interested in adding an example to this list!
@@ -291,4 +295,46 @@ $ git clone git://github.com/BurntSushi/rust-csv
 $ cd rust-csv
 $ cargo run --example cookbook-write-serde > /tmp/simplepop.csv
 ```
+
+# Filtering: passing stdin through to stdout
+
+This example shows how to copy CSV data from stdin to stdout, which is the
+skeleton of most CSV filtering tools. The standard streams are locked once
+using `ReaderBuilder::from_stdin` and `WriterBuilder::from_stdout`.
+
+```no_run
+# //cookbook-passthrough.rs
+use std::error::Error;
+use std::process;
+
+use csv::{ReaderBuilder, WriterBuilder};
+
+fn example() -> Result<(), Box<dyn Error>> {
+    // Lock stdin and stdout once, and copy every record (including the
+    // header) from one to the other.
+    let mut rdr = ReaderBuilder::new().has_headers(false).from_stdin();
+    let mut wtr = WriterBuilder::new().from_stdout();
+    for result in rdr.byte_records() {
+        let record = result?;
+        wtr.write_byte_record(&record)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = example() {
+        println!("error running example: {}", err);
+        process::exit(1);
+    }
+}
+```
+
+The above example can be run like so:
+
+```ignore
+$ git clone git://github.com/BurntSushi/rust-csv
+$ cd rust-csv
+$ cargo run --example cookbook-passthrough < examples/data/smallpop.csv
+```
 */
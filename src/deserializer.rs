@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::error::Error as StdError;
 use std::fmt;
 use std::iter;
@@ -15,19 +16,53 @@ use serde::serde_if_integer128;
 use crate::byte_record::{ByteRecord, ByteRecordIter};
 use crate::error::{Error, ErrorKind};
 use crate::string_record::{StringRecord, StringRecordIter};
+use crate::NumberFormat;
 
 use self::DeserializeErrorKind as DEK;
 
+/// Settings that alter how individual fields are deserialized.
+///
+/// These are configured on a `ReaderBuilder` and passed along by the
+/// reader's deserializing iterators.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct DeserializeConfig {
+    /// The strings to interpret as `true` and `false`, respectively. When
+    /// absent, `bool` values are parsed with `str::parse`.
+    pub(crate) bool_format: Option<(String, String)>,
+    /// Whether header names are lowercased before being matched to field
+    /// names.
+    pub(crate) case_insensitive_headers: bool,
+    /// The format of numbers. When absent, numbers are parsed with
+    /// `str::parse`.
+    pub(crate) number_format: Option<NumberFormat>,
+}
+
+/// The configuration used when deserializing a record on its own.
+static DEFAULT_CONFIG: DeserializeConfig = DeserializeConfig {
+    bool_format: None,
+    case_insensitive_headers: false,
+    number_format: None,
+};
+
 pub fn deserialize_string_record<'de, D: Deserialize<'de>>(
     record: &'de StringRecord,
     headers: Option<&'de StringRecord>,
+) -> Result<D, Error> {
+    deserialize_string_record_with(record, headers, &DEFAULT_CONFIG)
+}
+
+pub(crate) fn deserialize_string_record_with<'de, D: Deserialize<'de>>(
+    record: &'de StringRecord,
+    headers: Option<&'de StringRecord>,
+    config: &'de DeserializeConfig,
 ) -> Result<D, Error> {
     let mut deser = DeRecordWrap(DeStringRecord {
         it: record.iter().peekable(),
         headers: headers.map(|r| r.iter()),
         field: 0,
+        config,
     });
-    D::deserialize(&mut deser).map_err(|err| {
+    D::deserialize(DeTopLevel(&mut deser)).map_err(|err| {
         Error::new(ErrorKind::Deserialize {
             pos: record.position().map(Clone::clone),
             err: err,
@@ -43,8 +78,9 @@ pub fn deserialize_byte_record<'de, D: Deserialize<'de>>(
         it: record.iter().peekable(),
         headers: headers.map(|r| r.iter()),
         field: 0,
+        config: &DEFAULT_CONFIG,
     });
-    D::deserialize(&mut deser).map_err(|err| {
+    D::deserialize(DeTopLevel(&mut deser)).map_err(|err| {
         Error::new(ErrorKind::Deserialize {
             pos: record.position().map(Clone::clone),
             err: err,
@@ -72,6 +108,9 @@ trait DeRecord<'r> {
     /// Returns true if and only if this deserialize has access to headers.
     fn has_headers(&self) -> bool;
 
+    /// Returns the configuration that applies to this record.
+    fn config(&self) -> &'r DeserializeConfig;
+
     /// Extracts the next string header value from the underlying record.
     fn next_header(&mut self) -> Result<Option<&'r str>, DeserializeError>;
 
@@ -101,12 +140,29 @@ trait DeRecord<'r> {
 
 struct DeRecordWrap<T>(T);
 
+impl<'r, T: DeRecord<'r>> DeRecordWrap<T> {
+    /// Extracts the next field as a number in the format understood by
+    /// `str::parse`, according to the configured number format.
+    fn next_number(&mut self) -> Result<Cow<'r, str>, DeserializeError> {
+        let field = self.next_field()?;
+        Ok(match self.config().number_format {
+            None => Cow::Borrowed(field),
+            Some(ref format) => format.normalize(field),
+        })
+    }
+}
+
 impl<'r, T: DeRecord<'r>> DeRecord<'r> for DeRecordWrap<T> {
     #[inline]
     fn has_headers(&self) -> bool {
         self.0.has_headers()
     }
 
+    #[inline]
+    fn config(&self) -> &'r DeserializeConfig {
+        self.0.config()
+    }
+
     #[inline]
     fn next_header(&mut self) -> Result<Option<&'r str>, DeserializeError> {
         self.0.next_header()
@@ -152,6 +208,7 @@ struct DeStringRecord<'r> {
     it: iter::Peekable<StringRecordIter<'r>>,
     headers: Option<StringRecordIter<'r>>,
     field: u64,
+    config: &'r DeserializeConfig,
 }
 
 impl<'r> DeRecord<'r> for DeStringRecord<'r> {
@@ -160,6 +217,11 @@ impl<'r> DeRecord<'r> for DeStringRecord<'r> {
         self.headers.is_some()
     }
 
+    #[inline]
+    fn config(&self) -> &'r DeserializeConfig {
+        self.config
+    }
+
     #[inline]
     fn next_header(&mut self) -> Result<Option<&'r str>, DeserializeError> {
         Ok(self.headers.as_mut().and_then(|it| it.next()))
@@ -236,6 +298,7 @@ struct DeByteRecord<'r> {
     it: iter::Peekable<ByteRecordIter<'r>>,
     headers: Option<ByteRecordIter<'r>>,
     field: u64,
+    config: &'r DeserializeConfig,
 }
 
 impl<'r> DeRecord<'r> for DeByteRecord<'r> {
@@ -244,6 +307,11 @@ impl<'r> DeRecord<'r> for DeByteRecord<'r> {
         self.headers.is_some()
     }
 
+    #[inline]
+    fn config(&self) -> &'r DeserializeConfig {
+        self.config
+    }
+
     #[inline]
     fn next_header(&mut self) -> Result<Option<&'r str>, DeserializeError> {
         match self.next_header_bytes() {
@@ -334,7 +402,7 @@ macro_rules! deserialize_int {
             self,
             visitor: V,
         ) -> Result<V::Value, Self::Error> {
-            let field = self.next_field()?;
+            let field = self.next_number()?;
             let num =
                 if field.starts_with("0x") {
                     <$inttype>::from_str_radix(&field[2..], 16)
@@ -362,10 +430,21 @@ impl<'a, 'de: 'a, T: DeRecord<'de>> Deserializer<'de>
         self,
         visitor: V,
     ) -> Result<V::Value, Self::Error> {
+        let field = self.next_field()?;
+        if let Some((ref yes, ref no)) = self.config().bool_format {
+            return if field == yes {
+                visitor.visit_bool(true)
+            } else if field == no {
+                visitor.visit_bool(false)
+            } else {
+                Err(self.error(DEK::Message(format!(
+                    "expected '{}' or '{}' but got '{}'",
+                    yes, no, field
+                ))))
+            };
+        }
         visitor.visit_bool(
-            self.next_field()?
-                .parse()
-                .map_err(|err| self.error(DEK::ParseBool(err)))?,
+            field.parse().map_err(|err| self.error(DEK::ParseBool(err)))?,
         )
     }
 
@@ -389,7 +468,7 @@ impl<'a, 'de: 'a, T: DeRecord<'de>> Deserializer<'de>
         visitor: V,
     ) -> Result<V::Value, Self::Error> {
         visitor.visit_f32(
-            self.next_field()?
+            self.next_number()?
                 .parse()
                 .map_err(|err| self.error(DEK::ParseFloat(err)))?,
         )
@@ -400,7 +479,7 @@ impl<'a, 'de: 'a, T: DeRecord<'de>> Deserializer<'de>
         visitor: V,
     ) -> Result<V::Value, Self::Error> {
         visitor.visit_f64(
-            self.next_field()?
+            self.next_number()?
                 .parse()
                 .map_err(|err| self.error(DEK::ParseFloat(err)))?,
         )
@@ -537,9 +616,11 @@ impl<'a, 'de: 'a, T: DeRecord<'de>> Deserializer<'de>
 
     fn deserialize_identifier<V: Visitor<'de>>(
         self,
-        _visitor: V,
+        visitor: V,
     ) -> Result<V::Value, Self::Error> {
-        Err(self.error(DEK::Unsupported("deserialize_identifier".into())))
+        // Identifiers are read from fields when a discriminator column
+        // selects the variant of an internally tagged enum.
+        self.deserialize_str(visitor)
     }
 
     fn deserialize_enum<V: Visitor<'de>>(
@@ -563,6 +644,87 @@ impl<'a, 'de: 'a, T: DeRecord<'de>> Deserializer<'de>
     }
 }
 
+/// The deserializer for a record as a whole.
+///
+/// This behaves exactly like the `DeRecordWrap` it wraps, except that
+/// `deserialize_any` visits the record as a map when headers are available,
+/// instead of inferring the type of its first field. This is what makes it
+/// possible to deserialize a record into an internally tagged enum, where
+/// Serde buffers the record's entries to find the discriminator column
+/// before choosing a variant.
+struct DeTopLevel<'a, T>(&'a mut DeRecordWrap<T>);
+
+macro_rules! forward_to_record {
+    ($($method:ident($($arg:ident: $ty:ty),*)),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(
+                self,
+                $($arg: $ty,)*
+                visitor: V,
+            ) -> Result<V::Value, Self::Error> {
+                self.0.$method($($arg,)* visitor)
+            }
+        )*
+    };
+}
+
+impl<'a, 'de: 'a, T: DeRecord<'de>> Deserializer<'de> for DeTopLevel<'a, T> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        if self.0.has_headers() {
+            visitor.visit_map(self.0)
+        } else {
+            self.0.deserialize_any(visitor)
+        }
+    }
+
+    forward_to_record! {
+        deserialize_bool(),
+        deserialize_u8(),
+        deserialize_u16(),
+        deserialize_u32(),
+        deserialize_u64(),
+        deserialize_i8(),
+        deserialize_i16(),
+        deserialize_i32(),
+        deserialize_i64(),
+        deserialize_f32(),
+        deserialize_f64(),
+        deserialize_char(),
+        deserialize_str(),
+        deserialize_string(),
+        deserialize_bytes(),
+        deserialize_byte_buf(),
+        deserialize_option(),
+        deserialize_unit(),
+        deserialize_unit_struct(name: &'static str),
+        deserialize_newtype_struct(name: &'static str),
+        deserialize_seq(),
+        deserialize_tuple(len: usize),
+        deserialize_tuple_struct(name: &'static str, len: usize),
+        deserialize_map(),
+        deserialize_struct(
+            name: &'static str,
+            fields: &'static [&'static str]
+        ),
+        deserialize_identifier(),
+        deserialize_enum(
+            name: &'static str,
+            variants: &'static [&'static str]
+        ),
+        deserialize_ignored_any(),
+    }
+
+    forward_to_record! {
+        deserialize_u128(),
+        deserialize_i128(),
+    }
+}
+
 impl<'a, 'de: 'a, T: DeRecord<'de>> EnumAccess<'de>
     for &'a mut DeRecordWrap<T>
 {
@@ -645,6 +807,10 @@ impl<'a, 'de: 'a, T: DeRecord<'de>> MapAccess<'de>
             None => return Ok(None),
             Some(field) => field,
         };
+        if self.config().case_insensitive_headers {
+            let field = String::from_utf8_lossy(field).to_lowercase();
+            return seed.deserialize(field.into_deserializer()).map(Some);
+        }
         seed.deserialize(BorrowedBytesDeserializer::new(field)).map(Some)
     }
 
@@ -842,6 +1008,52 @@ mod tests {
         assert_eq!(got, Foo { x: "hi".into(), y: 42, z: 1.3 });
     }
 
+    #[test]
+    fn with_header_rename() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Foo {
+            #[serde(rename = "Full Name")]
+            name: String,
+            #[serde(rename = "name")]
+            nick: String,
+            #[serde(rename = "Age", default)]
+            age: Option<u32>,
+            #[serde(default)]
+            city: String,
+        }
+
+        let got: Foo = de_headers(
+            &["name", "Age", "Full Name", "city"],
+            &["jd", "42", "Jane Doe", "Boston"],
+        )
+        .unwrap();
+        assert_eq!(
+            got,
+            Foo {
+                name: "Jane Doe".into(),
+                nick: "jd".into(),
+                age: Some(42),
+                city: "Boston".into(),
+            }
+        );
+
+        // Renamed and defaulted fields may be missing.
+        let got: Foo =
+            de_headers(&["Full Name", "name"], &["Jane Doe", "jd"]).unwrap();
+        assert_eq!(
+            got,
+            Foo {
+                name: "Jane Doe".into(),
+                nick: "jd".into(),
+                age: None,
+                city: "".into(),
+            }
+        );
+
+        // The Rust field name doesn't match a renamed field.
+        assert!(de_headers::<Foo>(&["nick", "name"], &["jd", "x"]).is_err());
+    }
+
     #[test]
     fn with_header_unknown() {
         #[derive(Deserialize, Debug, PartialEq)]
@@ -1051,6 +1263,55 @@ mod tests {
         assert_eq!(got["c"], 10);
     }
 
+    #[test]
+    fn map_headers_in_order() {
+        use std::fmt;
+
+        use serde::de::{Deserializer, MapAccess, Visitor};
+
+        // A map that keeps its entries in insertion order, like `IndexMap`.
+        #[derive(Debug, PartialEq)]
+        struct OrderedMap(Vec<(String, String)>);
+
+        impl<'de> Deserialize<'de> for OrderedMap {
+            fn deserialize<D: Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<OrderedMap, D::Error> {
+                struct OrderedMapVisitor;
+
+                impl<'de> Visitor<'de> for OrderedMapVisitor {
+                    type Value = OrderedMap;
+
+                    fn expecting(
+                        &self,
+                        f: &mut fmt::Formatter,
+                    ) -> fmt::Result {
+                        f.write_str("a map")
+                    }
+
+                    fn visit_map<A: MapAccess<'de>>(
+                        self,
+                        mut map: A,
+                    ) -> Result<OrderedMap, A::Error> {
+                        let mut entries = vec![];
+                        while let Some(entry) = map.next_entry()? {
+                            entries.push(entry);
+                        }
+                        Ok(OrderedMap(entries))
+                    }
+                }
+
+                deserializer.deserialize_map(OrderedMapVisitor)
+            }
+        }
+
+        let got: OrderedMap =
+            de_headers(&["zeta", "alpha", "mid"], &["1", "2", "3"]).unwrap();
+        let keys: Vec<&str> = got.0.iter().map(|(k, _)| &**k).collect();
+        assert_eq!(keys, vec!["zeta", "alpha", "mid"]);
+        assert_eq!(got.0[1], ("alpha".to_string(), "2".to_string()));
+    }
+
     #[test]
     fn map_no_headers() {
         let got = de::<HashMap<String, i32>>(&["1", "5", "10"]);
@@ -1251,4 +1512,39 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn internally_tagged_enum() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        #[serde(tag = "kind")]
+        enum Event {
+            Click { x: u32, y: u32 },
+            Key { code: String },
+        }
+
+        let headers = &["kind", "x", "y", "code"];
+        let got: Event =
+            de_headers(headers, &["Click", "3", "4", ""]).unwrap();
+        assert_eq!(got, Event::Click { x: 3, y: 4 });
+        let got: Event =
+            de_headers(headers, &["Key", "", "", "Enter"]).unwrap();
+        assert_eq!(got, Event::Key { code: "Enter".into() });
+        assert!(de_headers::<Event>(headers, &["Scroll", "", "", ""]).is_err());
+    }
+
+    #[test]
+    fn adjacently_tagged_enum() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        #[serde(tag = "kind", content = "value")]
+        enum Reading {
+            Temp(f64),
+            Label(String),
+        }
+
+        let headers = &["kind", "value"];
+        let got: Reading = de_headers(headers, &["Temp", "21.5"]).unwrap();
+        assert_eq!(got, Reading::Temp(21.5));
+        let got: Reading = de_headers(headers, &["Label", "42"]).unwrap();
+        assert_eq!(got, Reading::Label("42".into()));
+    }
 }
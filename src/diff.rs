@@ -0,0 +1,211 @@
+use std::io;
+
+use crate::byte_record::ByteRecord;
+use crate::error::Result;
+use crate::reader::Reader;
+
+/// A single difference between two sets of CSV data, as reported by `diff`.
+///
+/// Record indices start at `0` with the first record that isn't a header
+/// row. Field values are `None` when one of the records has too few fields
+/// to include the column.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DiffEntry {
+    /// The header rows differ in the given column.
+    Header {
+        /// The index of the column that differs.
+        column: usize,
+        /// The header name in the first CSV data.
+        a: Option<Vec<u8>>,
+        /// The header name in the second CSV data.
+        b: Option<Vec<u8>>,
+    },
+    /// The records at the same index differ in the given column.
+    Field {
+        /// The index of the record that differs.
+        record: u64,
+        /// The index of the column that differs.
+        column: usize,
+        /// The field in the first CSV data.
+        a: Option<Vec<u8>>,
+        /// The field in the second CSV data.
+        b: Option<Vec<u8>>,
+    },
+    /// The first CSV data has a record beyond the end of the second.
+    ExtraInA {
+        /// The index of the extra record.
+        record: u64,
+        /// The extra record.
+        fields: ByteRecord,
+    },
+    /// The second CSV data has a record beyond the end of the first.
+    ExtraInB {
+        /// The index of the extra record.
+        record: u64,
+        /// The extra record.
+        fields: ByteRecord,
+    },
+    /// Hints that destructuring should not be exhaustive.
+    ///
+    /// This enum may grow additional variants, so this makes sure clients
+    /// don't count on exhaustive matching. (Otherwise, adding a new variant
+    /// could break existing code.)
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+/// Compare two sets of CSV data record by record.
+///
+/// Records are compared in order: the `i`th record of `a` is compared with
+/// the `i`th record of `b`, and a `DiffEntry::Field` is reported for every
+/// column in which they differ. Once one reader is exhausted, every
+/// remaining record of the other is reported as extra. If both readers are
+/// configured to read headers, then the header rows are compared first and
+/// differences are reported as `DiffEntry::Header`.
+///
+/// Both readers are read to the end. If the data is identical, then an
+/// empty vector is returned.
+///
+/// # Example
+///
+/// ```
+/// use std::error::Error;
+/// use csv::{DiffEntry, Reader};
+///
+/// # fn main() { example().unwrap(); }
+/// fn example() -> Result<(), Box<dyn Error>> {
+///     let old = "city,pop\nBoston,4628910\n";
+///     let new = "city,pop\nBoston,4628911\n";
+///     let mut a = Reader::from_reader(old.as_bytes());
+///     let mut b = Reader::from_reader(new.as_bytes());
+///
+///     let entries = csv::diff(&mut a, &mut b)?;
+///     assert_eq!(entries, vec![DiffEntry::Field {
+///         record: 0,
+///         column: 1,
+///         a: Some(b"4628910".to_vec()),
+///         b: Some(b"4628911".to_vec()),
+///     }]);
+///     Ok(())
+/// }
+/// ```
+pub fn diff<R1: io::Read, R2: io::Read>(
+    a: &mut Reader<R1>,
+    b: &mut Reader<R2>,
+) -> Result<Vec<DiffEntry>> {
+    let mut entries = vec![];
+    if a.has_headers() && b.has_headers() {
+        let (ha, hb) = (a.byte_headers()?, b.byte_headers()?);
+        for (column, a, b) in ha.diff(hb) {
+            let (a, b) = (a.map(<[u8]>::to_vec), b.map(<[u8]>::to_vec));
+            entries.push(DiffEntry::Header { column, a, b });
+        }
+    }
+
+    let (mut ra, mut rb) = (ByteRecord::new(), ByteRecord::new());
+    let mut record = 0;
+    loop {
+        match (a.read_byte_record(&mut ra)?, b.read_byte_record(&mut rb)?) {
+            (true, true) => {
+                for (column, a, b) in ra.diff(&rb) {
+                    let (a, b) =
+                        (a.map(<[u8]>::to_vec), b.map(<[u8]>::to_vec));
+                    entries.push(DiffEntry::Field { record, column, a, b });
+                }
+            }
+            (true, false) => {
+                entries
+                    .push(DiffEntry::ExtraInA { record, fields: ra.clone() });
+            }
+            (false, true) => {
+                entries
+                    .push(DiffEntry::ExtraInB { record, fields: rb.clone() });
+            }
+            (false, false) => return Ok(entries),
+        }
+        record += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::byte_record::ByteRecord;
+    use crate::reader::ReaderBuilder;
+
+    use super::{diff, DiffEntry};
+
+    fn diff_str(a: &str, b: &str) -> Vec<DiffEntry> {
+        let mut a =
+            ReaderBuilder::new().flexible(true).from_reader(a.as_bytes());
+        let mut b =
+            ReaderBuilder::new().flexible(true).from_reader(b.as_bytes());
+        diff(&mut a, &mut b).unwrap()
+    }
+
+    fn s(field: &str) -> Option<Vec<u8>> {
+        Some(field.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn identical() {
+        assert_eq!(diff_str("a,b\n1,2\n", "a,b\n1,2\n"), vec![]);
+        assert_eq!(diff_str("", ""), vec![]);
+    }
+
+    #[test]
+    fn one_cell() {
+        assert_eq!(
+            diff_str("a,b\n1,2\n3,4\n", "a,b\n1,2\n3,5\n"),
+            vec![DiffEntry::Field {
+                record: 1,
+                column: 1,
+                a: s("4"),
+                b: s("5")
+            }]
+        );
+        assert_eq!(
+            diff_str("a,b\n1,2\n", "a,b\n1,2,3\n"),
+            vec![DiffEntry::Field {
+                record: 0,
+                column: 2,
+                a: None,
+                b: s("3")
+            }]
+        );
+    }
+
+    #[test]
+    fn row_count() {
+        assert_eq!(
+            diff_str("a\n1\n2\n3\n", "a\n1\n"),
+            vec![
+                DiffEntry::ExtraInA {
+                    record: 1,
+                    fields: ByteRecord::from(vec!["2"]),
+                },
+                DiffEntry::ExtraInA {
+                    record: 2,
+                    fields: ByteRecord::from(vec!["3"]),
+                },
+            ]
+        );
+        assert_eq!(
+            diff_str("a\n", "a\n1\n"),
+            vec![DiffEntry::ExtraInB {
+                record: 0,
+                fields: ByteRecord::from(vec!["1"]),
+            }]
+        );
+    }
+
+    #[test]
+    fn headers() {
+        assert_eq!(
+            diff_str("a,b\n1,2\n", "a,c,d\n1,2\n"),
+            vec![
+                DiffEntry::Header { column: 1, a: s("b"), b: s("c") },
+                DiffEntry::Header { column: 2, a: None, b: s("d") },
+            ]
+        );
+    }
+}
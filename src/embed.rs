@@ -0,0 +1,80 @@
+use crate::error::Result;
+use crate::writer::WriterBuilder;
+
+/// Write rows as CSV data that can be used as a single field of another
+/// record.
+///
+/// The rows are written with a writer built from `dialect`, so the embedded
+/// data can use its own delimiter, quoting and record terminator. The
+/// returned bytes, including the terminator of the last row, can then be
+/// given to an outer writer as one field, which quotes and escapes it as
+/// needed. Reading that field back and parsing it with a reader configured
+/// like `dialect` recovers the rows.
+///
+/// # Example
+///
+/// ```
+/// use std::error::Error;
+/// use csv::{Writer, WriterBuilder};
+///
+/// # fn main() { example().unwrap(); }
+/// fn example() -> Result<(), Box<dyn Error>> {
+///     let rows: &[&[&str]] = &[&["x", "1"], &["y", "2"]];
+///     let points = csv::embed_csv(rows, &WriterBuilder::new())?;
+///
+///     let mut wtr = Writer::from_writer(vec![]);
+///     wtr.write_record(&[&b"line"[..], &points])?;
+///
+///     let data = String::from_utf8(wtr.into_inner()?)?;
+///     assert_eq!(data, "line,\"x,1\ny,2\n\"\n");
+///     Ok(())
+/// }
+/// ```
+pub fn embed_csv(
+    rows: &[&[&str]],
+    dialect: &WriterBuilder,
+) -> Result<Vec<u8>> {
+    let mut data = vec![];
+    {
+        let mut wtr = dialect.from_writer(&mut data);
+        for row in rows {
+            wtr.write_record(*row)?;
+        }
+        wtr.flush()?;
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::reader::ReaderBuilder;
+    use crate::writer::{Writer, WriterBuilder};
+
+    use super::embed_csv;
+
+    #[test]
+    fn round_trip() {
+        let rows: &[&[&str]] = &[&["a", "b;c"], &["\"q\"", ""]];
+        let mut dialect = WriterBuilder::new();
+        dialect.delimiter(b';');
+        let inner = embed_csv(rows, &dialect).unwrap();
+        assert_eq!(inner, b"a;\"b;c\"\n\"\"\"q\"\"\";\n");
+
+        let mut wtr = Writer::from_writer(vec![]);
+        wtr.write_record(&[&b"id"[..], &inner, b"end"]).unwrap();
+        let outer = wtr.into_inner().unwrap();
+
+        let mut rdr =
+            ReaderBuilder::new().has_headers(false).from_reader(&*outer);
+        let record = rdr.byte_records().next().unwrap().unwrap();
+        assert_eq!(record.len(), 3);
+        assert_eq!(&record[1], &inner[..]);
+
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .delimiter(b';')
+            .from_reader(&record[1]);
+        let got: Vec<_> = rdr.records().map(|r| r.unwrap()).collect();
+        assert_eq!(got, vec![vec!["a", "b;c"], vec!["\"q\"", ""]]);
+    }
+}
@@ -0,0 +1,108 @@
+use std::str;
+
+use crate::byte_record::ByteRecord;
+use crate::error::{new_utf8_error, Error, ErrorKind, Result};
+
+/// The characters that the bytes `0x80` through `0x9F` decode to in
+/// Windows-1252.
+///
+/// The five bytes that Windows-1252 leaves undefined decode to the C1 control
+/// character with the same value, as specified by the WHATWG Encoding
+/// Standard.
+const HIGH: [char; 32] = [
+    '\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}',
+    '\u{2020}', '\u{2021}', '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}',
+    '\u{0152}', '\u{008D}', '\u{017D}', '\u{008F}', '\u{0090}', '\u{2018}',
+    '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{009D}',
+    '\u{017E}', '\u{0178}',
+];
+
+/// Transcode the given UTF-8 field to Windows-1252.
+///
+/// `field_index` is the index of the field in its record, and is used to
+/// report errors. An error is returned if the field isn't valid UTF-8, or if
+/// it contains a character that Windows-1252 can't represent.
+pub(crate) fn utf8_to_latin1(
+    field: &[u8],
+    field_index: u64,
+) -> Result<Vec<u8>> {
+    let s = str::from_utf8(field).map_err(|err| {
+        Error::new(ErrorKind::Utf8 {
+            pos: None,
+            err: new_utf8_error(field_index as usize, err.valid_up_to()),
+        })
+    })?;
+    let mut encoded = Vec::with_capacity(s.len());
+    for ch in s.chars() {
+        encoded.push(match ch as u32 {
+            0x00..=0x7F | 0xA0..=0xFF => ch as u8,
+            _ => match HIGH.iter().position(|&c| c == ch) {
+                Some(i) => 0x80 + i as u8,
+                None => {
+                    return Err(Error::new(ErrorKind::Unencodable {
+                        field_index,
+                        ch,
+                    }))
+                }
+            },
+        });
+    }
+    Ok(encoded)
+}
+
+/// Transcode every field of the given record from Windows-1252 to UTF-8.
+///
+/// Every byte sequence is valid Windows-1252, so this cannot fail.
+pub(crate) fn latin1_to_utf8(record: &mut ByteRecord) {
+    if record.as_slice().is_ascii() {
+        return;
+    }
+    let raw = record.clone();
+    record.clear();
+    let mut field = vec![];
+    for raw_field in raw.iter() {
+        field.clear();
+        for &b in raw_field {
+            let ch = match b {
+                0x80..=0x9F => HIGH[(b - 0x80) as usize],
+                _ => b as char,
+            };
+            field.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes());
+        }
+        record.push_field(&field);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::byte_record::ByteRecord;
+    use crate::error::ErrorKind;
+
+    use super::{latin1_to_utf8, utf8_to_latin1};
+
+    #[test]
+    fn decode() {
+        let mut record =
+            ByteRecord::from(vec![&b"caf\xE9"[..], b"\x80\x81", b"plain"]);
+        latin1_to_utf8(&mut record);
+        assert_eq!(
+            record,
+            ByteRecord::from(vec!["café", "\u{20AC}\u{81}", "plain"])
+        );
+    }
+
+    #[test]
+    fn encode() {
+        let encoded =
+            utf8_to_latin1("café ñ €5 \u{81}".as_bytes(), 0).unwrap();
+        assert_eq!(encoded, b"caf\xE9 \xF1 \x805 \x81");
+
+        let err = utf8_to_latin1("a→b".as_bytes(), 3).unwrap_err();
+        match *err.kind() {
+            ErrorKind::Unencodable { field_index: 3, ch: '→' } => {}
+            ref x => panic!("expected Unencodable, got {:?}", x),
+        }
+        assert!(utf8_to_latin1(b"\xFF", 0).is_err());
+    }
+}
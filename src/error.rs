@@ -1,6 +1,7 @@
 use std::error::Error as StdError;
 use std::fmt;
 use std::io;
+use std::path::PathBuf;
 use std::result;
 
 use crate::byte_record::{ByteRecord, Position};
@@ -84,12 +85,99 @@ pub enum ErrorKind {
         /// The number of fields in the bad record.
         len: u64,
     },
+    /// This error occurs when a quote is found inside an unquoted field. This
+    /// error only occurs when the `strict_quotes` option in a CSV reader is
+    /// enabled.
+    UnescapedQuote {
+        /// The position of the record containing the quote, if available.
+        pos: Option<Position>,
+        /// The index of the field containing the quote.
+        field: u64,
+    },
+    /// This error occurs when the CSV data ends inside a quoted field, i.e.,
+    /// the closing quote of the last field is missing. The record containing
+    /// the field is still counted, and reading may continue, at which point
+    /// the end of the data is reached.
+    ///
+    /// This error occurs by default, unless quoting is disabled. Earlier
+    /// versions of this crate didn't report it, and silently returned the
+    /// rest of the data as the contents of the unterminated field.
+    UnterminatedQuote {
+        /// The position of the record containing the field, if available.
+        pos: Option<Position>,
+        /// The index of the field missing its closing quote.
+        field: u64,
+    },
+    /// This error occurs when a `Projection` selects a column that is absent
+    /// from the source record, and filling missing columns is disabled. It
+    /// also occurs when `Reader::column` reads a record that lacks the
+    /// column.
+    MissingColumn {
+        /// The position of the source record, if available.
+        pos: Option<Position>,
+        /// The name of the missing column.
+        name: String,
+    },
     /// This error occurs when either the `byte_headers` or `headers` methods
     /// are called on a CSV reader that was asked to `seek` before it parsed
     /// the first record.
     Seek,
     /// An error of this kind occurs only when using the Serde serializer.
     Serialize(String),
+    /// This error occurs when a CSV writer that never quotes is asked to
+    /// write a field that requires quotes. This error only occurs when the
+    /// `never_quote_strict` option is enabled.
+    FieldNeedsQuoting {
+        /// The index of the offending field in its record.
+        field_index: u64,
+    },
+    /// This error occurs when a CSV reader finds, or a CSV writer is asked
+    /// to write, more records than the limit set by the `max_records`
+    /// option.
+    TooManyRecords {
+        /// The maximum number of records permitted.
+        limit: u64,
+    },
+    /// This error occurs when `Writer::set_header` is called on a CSV writer
+    /// that has already written a row.
+    HeaderAfterRecords,
+    /// This error occurs when a CSV writer is asked to write a character
+    /// that its configured encoding cannot represent. This error only occurs
+    /// when the `encoding` feature is enabled.
+    Unencodable {
+        /// The index of the offending field in its record.
+        field_index: u64,
+        /// The character that cannot be represented.
+        ch: char,
+    },
+    /// This error occurs when a CSV writer is asked to write a field that
+    /// is longer than the limit set by the `max_field_len` option.
+    FieldTooLong {
+        /// The index of the offending field in its record.
+        field_index: u64,
+        /// The length of the field, in bytes.
+        len: u64,
+        /// The maximum length of a field, in bytes.
+        limit: u64,
+    },
+    /// This error occurs when a CSV reader finds a trailer row at the end of
+    /// the data whose record count doesn't match the number of records
+    /// read. This error only occurs when the `verify_trailer` option in a
+    /// CSV reader is enabled.
+    TrailerMismatch {
+        /// The position of the trailer row, if available.
+        pos: Option<Position>,
+        /// The number of records that the trailer claims were written.
+        expected: u64,
+        /// The number of records that were actually read.
+        actual: u64,
+    },
+    /// This error occurs when a reader over several files finds a file
+    /// whose header row differs from that of the first file.
+    HeaderMismatch {
+        /// The path of the file whose header row differs.
+        path: PathBuf,
+    },
     /// An error of this kind occurs only when performing automatic
     /// deserialization with serde.
     Deserialize {
@@ -116,6 +204,10 @@ impl ErrorKind {
         match *self {
             ErrorKind::Utf8 { ref pos, .. } => pos.as_ref(),
             ErrorKind::UnequalLengths { ref pos, .. } => pos.as_ref(),
+            ErrorKind::UnescapedQuote { ref pos, .. } => pos.as_ref(),
+            ErrorKind::UnterminatedQuote { ref pos, .. } => pos.as_ref(),
+            ErrorKind::MissingColumn { ref pos, .. } => pos.as_ref(),
+            ErrorKind::TrailerMismatch { ref pos, .. } => pos.as_ref(),
             ErrorKind::Deserialize { ref pos, .. } => pos.as_ref(),
             _ => None,
         }
@@ -140,8 +232,18 @@ impl StdError for Error {
             ErrorKind::Io(ref err) => Some(err),
             ErrorKind::Utf8 { ref err, .. } => Some(err),
             ErrorKind::UnequalLengths { .. } => None,
+            ErrorKind::UnescapedQuote { .. } => None,
+            ErrorKind::UnterminatedQuote { .. } => None,
+            ErrorKind::MissingColumn { .. } => None,
             ErrorKind::Seek => None,
             ErrorKind::Serialize(_) => None,
+            ErrorKind::FieldNeedsQuoting { .. } => None,
+            ErrorKind::TooManyRecords { .. } => None,
+            ErrorKind::HeaderAfterRecords => None,
+            ErrorKind::Unencodable { .. } => None,
+            ErrorKind::FieldTooLong { .. } => None,
+            ErrorKind::TrailerMismatch { .. } => None,
+            ErrorKind::HeaderMismatch { .. } => None,
             ErrorKind::Deserialize { ref err, .. } => Some(err),
             _ => unreachable!(),
         }
@@ -189,6 +291,51 @@ impl fmt::Display for Error {
                 len,
                 expected_len
             ),
+            ErrorKind::UnescapedQuote { pos: None, field } => write!(
+                f,
+                "CSV parse error: field {}: found quote in unquoted field",
+                field
+            ),
+            ErrorKind::UnescapedQuote { pos: Some(ref pos), field } => write!(
+                f,
+                "CSV parse error: record {} (line {}, field: {}, byte: {}): \
+                 found quote in unquoted field",
+                pos.record(),
+                pos.line(),
+                field,
+                pos.byte()
+            ),
+            ErrorKind::UnterminatedQuote { pos: None, field } => write!(
+                f,
+                "CSV parse error: field {}: found end of data in quoted field",
+                field
+            ),
+            ErrorKind::UnterminatedQuote { pos: Some(ref pos), field } => {
+                write!(
+                    f,
+                    "CSV parse error: record {} \
+                     (line {}, field: {}, byte: {}): \
+                     found end of data in quoted field",
+                    pos.record(),
+                    pos.line(),
+                    field,
+                    pos.byte()
+                )
+            }
+            ErrorKind::MissingColumn { pos: None, ref name } => {
+                write!(f, "CSV error: missing column '{}'", name)
+            }
+            ErrorKind::MissingColumn { pos: Some(ref pos), ref name } => {
+                write!(
+                    f,
+                    "CSV error: record {} (line: {}, byte: {}): \
+                 missing column '{}'",
+                    pos.record(),
+                    pos.line(),
+                    pos.byte(),
+                    name
+                )
+            }
             ErrorKind::Seek => write!(
                 f,
                 "CSV error: cannot access headers of CSV data \
@@ -198,6 +345,62 @@ impl fmt::Display for Error {
             ErrorKind::Serialize(ref err) => {
                 write!(f, "CSV write error: {}", err)
             }
+            ErrorKind::FieldNeedsQuoting { field_index } => write!(
+                f,
+                "CSV write error: field {} requires quotes, but quoting \
+                 is disabled",
+                field_index
+            ),
+            ErrorKind::TooManyRecords { limit } => write!(
+                f,
+                "CSV error: found more than the limit of {} records",
+                limit
+            ),
+            ErrorKind::HeaderAfterRecords => write!(
+                f,
+                "CSV write error: cannot set the header after a row \
+                 has been written"
+            ),
+            ErrorKind::Unencodable { field_index, ch } => write!(
+                f,
+                "CSV write error: field {}: {:?} cannot be represented in \
+                 the target encoding",
+                field_index, ch
+            ),
+            ErrorKind::FieldTooLong { field_index, len, limit } => write!(
+                f,
+                "CSV write error: field {}: length of {} bytes exceeds the \
+                 limit of {} bytes",
+                field_index, len, limit
+            ),
+            ErrorKind::TrailerMismatch { pos: None, expected, actual } => {
+                write!(
+                    f,
+                    "CSV error: trailer counts {} records, but {} records \
+                     were read",
+                    expected, actual
+                )
+            }
+            ErrorKind::TrailerMismatch {
+                pos: Some(ref pos),
+                expected,
+                actual,
+            } => write!(
+                f,
+                "CSV error: record {} (line: {}, byte: {}): \
+                 trailer counts {} records, but {} records were read",
+                pos.record(),
+                pos.line(),
+                pos.byte(),
+                expected,
+                actual
+            ),
+            ErrorKind::HeaderMismatch { ref path } => write!(
+                f,
+                "CSV error: {}: header row differs from that of the first \
+                 file",
+                path.display()
+            ),
             ErrorKind::Deserialize { pos: None, ref err } => {
                 write!(f, "CSV deserialize error: {}", err)
             }
@@ -332,6 +535,12 @@ impl<W> IntoInnerError<W> {
     pub fn into_inner(self) -> W {
         self.wtr
     }
+
+    /// Consumes this error, returning the error which caused the call to
+    /// `into_inner` to fail.
+    pub(crate) fn into_error(self) -> io::Error {
+        self.err
+    }
 }
 
 impl<W: std::any::Any> StdError for IntoInnerError<W> {
@@ -0,0 +1,449 @@
+use std::mem;
+
+use csv_core::{ReadRecordResult, Reader as CoreReader, Terminator};
+
+use crate::byte_record::ByteRecord;
+
+/// A CSV parser for configurations that the core parser doesn't support.
+///
+/// This supports delimiters that are a sequence of bytes, like `||`, rather
+/// than a single byte, as well as lenient quotes, where a quote inside a
+/// quoted field only ends the quoted part of the field when it is followed
+/// by a delimiter or terminator. It is considerably slower than the core
+/// parser, since it handles input one byte at a time.
+///
+/// Apart from the delimiter, it follows the configuration of the core
+/// parser that it is built from: its quote, escape and comment characters,
+/// whether quotes are doubled, and its record terminator. Like the core
+/// parser, it skips empty lines.
+#[derive(Clone, Debug)]
+pub(crate) struct FallbackReader {
+    delimiter: Vec<u8>,
+    lenient_quotes: bool,
+    term: Terminator,
+    quote: u8,
+    escape: Option<u8>,
+    escape_unquoted: bool,
+    double_quote: bool,
+    comment: Option<u8>,
+    quoting: bool,
+    state: State,
+    /// The data of the field currently being parsed.
+    field: Vec<u8>,
+    /// The position in `field` at which its current run of unquoted data
+    /// starts. The delimiter is only recognized within such a run.
+    unquoted_from: usize,
+    /// The current line number.
+    line: u64,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum State {
+    StartRecord,
+    StartField,
+    InField,
+    EscapeInField,
+    InQuotedField,
+    QuoteInQuotedField,
+    EscapeInQuotedField,
+    InComment,
+}
+
+impl FallbackReader {
+    /// Create a parser for the given delimiter that otherwise follows the
+    /// configuration of `core`.
+    ///
+    /// An empty delimiter is never recognized, so that every record has a
+    /// single field.
+    pub(crate) fn new(
+        delimiter: &[u8],
+        lenient_quotes: bool,
+        core: &CoreReader,
+    ) -> FallbackReader {
+        FallbackReader {
+            delimiter: delimiter.to_vec(),
+            lenient_quotes,
+            term: core.get_terminator(),
+            quote: core.get_quote(),
+            escape: core.get_escape(),
+            escape_unquoted: core.get_escape_unquoted(),
+            double_quote: core.get_double_quote(),
+            comment: core.get_comment(),
+            quoting: core.get_quoting(),
+            state: State::StartRecord,
+            field: vec![],
+            unquoted_from: 0,
+            line: 1,
+        }
+    }
+
+    /// Reset the parser such that it behaves as if it had never been used.
+    pub(crate) fn reset(&mut self) {
+        self.state = State::StartRecord;
+        self.field.clear();
+        self.unquoted_from = 0;
+        self.line = 1;
+    }
+
+    /// Returns true if the parser is inside a quoted field whose closing
+    /// quote hasn't been seen yet.
+    pub(crate) fn is_in_quoted_field(&self) -> bool {
+        matches!(self.state, State::InQuotedField | State::EscapeInQuotedField)
+    }
+
+    /// Return the current line number as measured by the number of
+    /// occurrences of `\n`.
+    pub(crate) fn line(&self) -> u64 {
+        self.line
+    }
+
+    /// Set the line number.
+    pub(crate) fn set_line(&mut self, line: u64) {
+        self.line = line;
+    }
+
+    /// Parse CSV data in `input` and append the fields of the record it
+    /// contains to `record`.
+    ///
+    /// This returns the result of parsing along with the number of bytes of
+    /// `input` that were consumed. The result is `InputEmpty` when all of
+    /// `input` was consumed without completing a record, in which case this
+    /// should be called again with more input and the same record. An empty
+    /// `input` indicates the end of the data. This never returns
+    /// `OutputFull` or `OutputEndsFull`.
+    pub(crate) fn read_record(
+        &mut self,
+        input: &[u8],
+        record: &mut ByteRecord,
+    ) -> (ReadRecordResult, usize) {
+        if input.is_empty() {
+            return match self.state {
+                State::StartRecord | State::InComment => {
+                    self.state = State::StartRecord;
+                    (ReadRecordResult::End, 0)
+                }
+                _ => {
+                    self.end_field(record);
+                    self.state = State::StartRecord;
+                    (ReadRecordResult::Record, 0)
+                }
+            };
+        }
+        for (i, &b) in input.iter().enumerate() {
+            self.line += (b == b'\n') as u64;
+            if self.step(b, record) {
+                return (ReadRecordResult::Record, i + 1);
+            }
+        }
+        (ReadRecordResult::InputEmpty, input.len())
+    }
+
+    /// Find the byte ranges of the fields of the last record in `raw`.
+    ///
+    /// `raw` must start at a record boundary. Each range in `spans` covers
+    /// the data of a field, excluding its enclosing quotes, but including
+    /// any quotes or escapes inside of it. This resets the parser.
+    pub(crate) fn field_spans(
+        &mut self,
+        raw: &[u8],
+        spans: &mut Vec<(usize, usize)>,
+    ) {
+        self.reset();
+        spans.clear();
+        let mut record = ByteRecord::new();
+        let mut current = vec![];
+        // The start of the current field, once its first byte is seen.
+        let mut start = None;
+        // The position of the last quote that may close the current field.
+        let mut closing_quote = None;
+        let mut quoted = false;
+        for (i, &b) in raw.iter().enumerate() {
+            let before = self.state;
+            let fields = record.len();
+            let ended_record = self.step(b, &mut record);
+            if ended_record || record.len() > fields {
+                let mut end = if ended_record {
+                    i
+                } else {
+                    i + 1 - self.delimiter.len()
+                };
+                if quoted && closing_quote == Some(end.wrapping_sub(1)) {
+                    end -= 1;
+                }
+                current.push((start.take().unwrap_or(end), end));
+                quoted = false;
+                closing_quote = None;
+                if ended_record {
+                    mem::swap(spans, &mut current);
+                    current.clear();
+                    record.clear();
+                }
+                continue;
+            }
+            match self.state {
+                State::StartRecord | State::StartField | State::InComment => {}
+                State::InQuotedField if start.is_none() => {
+                    start = Some(i + 1);
+                    quoted = true;
+                }
+                State::QuoteInQuotedField => closing_quote = Some(i),
+                State::InField if before == State::InQuotedField => {
+                    closing_quote = Some(i)
+                }
+                _ if start.is_none() => start = Some(i),
+                _ => {}
+            }
+        }
+        if !matches!(self.state, State::StartRecord | State::InComment) {
+            let mut end = raw.len();
+            if quoted && closing_quote == Some(end.wrapping_sub(1)) {
+                end -= 1;
+            }
+            current.push((start.unwrap_or(end), end));
+            mem::swap(spans, &mut current);
+        }
+        self.reset();
+    }
+
+    /// Handle a single byte of input. Returns true if it ended a record.
+    fn step(&mut self, b: u8, record: &mut ByteRecord) -> bool {
+        loop {
+            match self.state {
+                State::StartRecord => {
+                    if self.is_terminator(b) {
+                        // Empty lines are skipped.
+                    } else if self.comment == Some(b) {
+                        self.state = State::InComment;
+                    } else {
+                        self.state = State::StartField;
+                        continue;
+                    }
+                }
+                State::InComment => {
+                    if self.is_terminator(b) {
+                        self.state = State::StartRecord;
+                    }
+                }
+                State::StartField => {
+                    self.field.clear();
+                    self.unquoted_from = 0;
+                    if self.quoting && b == self.quote {
+                        self.state = State::InQuotedField;
+                    } else {
+                        self.state = State::InField;
+                        continue;
+                    }
+                }
+                State::InField => {
+                    if self.is_terminator(b) {
+                        self.end_field(record);
+                        self.state = State::StartRecord;
+                        return true;
+                    }
+                    if self.escape_unquoted && self.escape == Some(b) {
+                        self.state = State::EscapeInField;
+                        return false;
+                    }
+                    self.field.push(b);
+                    if !self.delimiter.is_empty()
+                        && self.field[self.unquoted_from..]
+                            .ends_with(&self.delimiter)
+                    {
+                        let len = self.field.len() - self.delimiter.len();
+                        self.field.truncate(len);
+                        self.end_field(record);
+                        self.state = State::StartField;
+                    }
+                }
+                State::EscapeInField => {
+                    // The escaped byte never starts or ends a delimiter.
+                    self.field.push(b);
+                    self.unquoted_from = self.field.len();
+                    self.state = State::InField;
+                }
+                State::InQuotedField => {
+                    if b == self.quote {
+                        if self.double_quote || self.lenient_quotes {
+                            self.state = State::QuoteInQuotedField;
+                        } else {
+                            self.start_unquoted();
+                        }
+                    } else if self.escape == Some(b) {
+                        self.state = State::EscapeInQuotedField;
+                    } else {
+                        self.field.push(b);
+                    }
+                }
+                State::QuoteInQuotedField => {
+                    if self.double_quote && b == self.quote {
+                        self.field.push(b);
+                        self.state = State::InQuotedField;
+                    } else if self.lenient_quotes
+                        && self.delimiter.first() != Some(&b)
+                        && !self.is_terminator(b)
+                    {
+                        // The previous quote is a literal one.
+                        self.field.push(self.quote);
+                        self.state = State::InQuotedField;
+                        continue;
+                    } else {
+                        self.start_unquoted();
+                        continue;
+                    }
+                }
+                State::EscapeInQuotedField => {
+                    self.field.push(b);
+                    self.state = State::InQuotedField;
+                }
+            }
+            return false;
+        }
+    }
+
+    /// Continue the current field with unquoted data, e.g., after its
+    /// closing quote.
+    fn start_unquoted(&mut self) {
+        self.unquoted_from = self.field.len();
+        self.state = State::InField;
+    }
+
+    /// Add the current field to `record`.
+    fn end_field(&mut self, record: &mut ByteRecord) {
+        record.push_field(&self.field);
+        self.field.clear();
+        self.unquoted_from = 0;
+    }
+
+    fn is_terminator(&self, b: u8) -> bool {
+        match self.term {
+            Terminator::CRLF => b == b'\r' || b == b'\n',
+            Terminator::Any(t) => b == t,
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use csv_core::{ReadRecordResult, ReaderBuilder as CoreReaderBuilder};
+
+    use crate::byte_record::ByteRecord;
+
+    use super::FallbackReader;
+
+    /// Parse all records in `data`, feeding it to the parser `chunk` bytes
+    /// at a time.
+    fn parse(
+        delimiter: &str,
+        lenient: bool,
+        core: &mut CoreReaderBuilder,
+        data: &str,
+        chunk: usize,
+    ) -> Vec<ByteRecord> {
+        let mut rdr =
+            FallbackReader::new(delimiter.as_bytes(), lenient, &core.build());
+        let mut input = data.as_bytes();
+        let mut records = vec![];
+        let mut record = ByteRecord::new();
+        loop {
+            let n = input.len().min(chunk);
+            let (res, nin) = rdr.read_record(&input[..n], &mut record);
+            input = &input[nin..];
+            match res {
+                ReadRecordResult::InputEmpty => {}
+                ReadRecordResult::Record => {
+                    records.push(record.clone());
+                    record.clear();
+                }
+                ReadRecordResult::End => return records,
+                res => panic!("unexpected result {:?}", res),
+            }
+        }
+    }
+
+    fn records(rows: Vec<Vec<&str>>) -> Vec<ByteRecord> {
+        rows.into_iter().map(ByteRecord::from).collect()
+    }
+
+    #[test]
+    fn fields() {
+        let data = "a||b|c||\n\n\"x||y\"||\"q\"\"\"z\r\n|||||\n||";
+        let expected = records(vec![
+            vec!["a", "b|c", ""],
+            vec!["x||y", "q\"z"],
+            vec!["", "", "|"],
+            vec!["", ""],
+        ]);
+        for chunk in 1..data.len() + 1 {
+            let got =
+                parse("||", false, &mut CoreReaderBuilder::new(), data, chunk);
+            assert_eq!(got, expected, "chunk size {}", chunk);
+        }
+    }
+
+    #[test]
+    fn overlapping_delimiter() {
+        let got = parse(
+            "|~|",
+            false,
+            &mut CoreReaderBuilder::new(),
+            "a|~|~|b|~||~|c",
+            1,
+        );
+        assert_eq!(got, records(vec![vec!["a", "~|b", "", "c"]]));
+    }
+
+    #[test]
+    fn core_configuration() {
+        let mut core = CoreReaderBuilder::new();
+        core.quote(b'\'')
+            .double_quote(false)
+            .escape(Some(b'\\'))
+            .comment(Some(b'#'))
+            .terminator(csv_core::Terminator::Any(b';'));
+        let got = parse("::", false, &mut core, "#x::y;'a\\'::b'::\"c\";", 3);
+        assert_eq!(got, records(vec![vec!["a'::b", "\"c\""]]));
+    }
+
+    #[test]
+    fn lenient_quotes() {
+        let core = &mut CoreReaderBuilder::new();
+        let data =
+            "\"foo \"bar\" baz\",\"a\"\"b\"\n\"x\"y\"\n\"\"\"\",\"c\"\n";
+        let expected = records(vec![
+            vec!["foo \"bar\" baz", "a\"b"],
+            vec!["x\"y"],
+            vec!["\"", "c"],
+        ]);
+        assert_eq!(parse(",", true, core, data, 1), expected);
+
+        let data = "\"foo \"bar\", baz\"\n";
+        let expected = records(vec![vec!["foo \"bar", " baz\""]]);
+        assert_eq!(parse(",", true, core, data, 1), expected);
+
+        let core = &mut CoreReaderBuilder::new();
+        core.double_quote(false);
+        let expected = records(vec![vec!["a\"\"b", "c"]]);
+        assert_eq!(parse(",", true, core, "\"a\"\"b\",c", 1), expected);
+    }
+
+    #[test]
+    fn field_spans() {
+        let data = "h\n'a''b',\\,c,'d'e\nx,'y'''\n";
+        let mut core = CoreReaderBuilder::new();
+        core.quote(b'\'').escape(Some(b'\\')).escape_unquoted(true);
+        let mut rdr = FallbackReader::new(b",", false, &core.build());
+        let mut spans = vec![];
+        rdr.field_spans(&data.as_bytes()[..18], &mut spans);
+        let fields: Vec<&str> =
+            spans.iter().map(|&(s, e)| &data[s..e]).collect();
+        assert_eq!(fields, vec!["a''b", "\\,c", "d'e"]);
+
+        rdr.field_spans(&data.as_bytes()[18..], &mut spans);
+        assert_eq!(spans, vec![(0, 1), (3, 6)]);
+        rdr.field_spans(b"", &mut spans);
+        assert!(spans.is_empty());
+        rdr.field_spans(b"a,", &mut spans);
+        assert_eq!(spans, vec![(0, 1), (2, 2)]);
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+
+use crate::byte_record::ByteRecord;
+use crate::error::Result;
+use crate::reader::Reader;
+use crate::writer::Writer;
+
+/// How `merge_join` names the columns of its header row when the two inputs
+/// have columns with the same name.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DuplicateNames {
+    /// Write every name as is, even if it occurs more than once.
+    Keep,
+    /// Suffix a name that occurs more than once with the number of its
+    /// occurrence starting from the second, e.g., `id`, `id_2`. A number is
+    /// skipped if the suffixed name is already used by another column.
+    Number,
+    /// Hints that destructuring should not be exhaustive.
+    ///
+    /// This enum may grow additional variants, so this makes sure clients
+    /// don't count on exhaustive matching. (Otherwise, adding a new variant
+    /// could break existing code.)
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+/// Join two CSV inputs that are sorted by their key columns.
+///
+/// This performs an inner join: for every pair of a record read from `left`
+/// and a record read from `right` whose fields at `left_key` and
+/// `right_key`, respectively, are equal, a record made of the fields of the
+/// left record followed by the fields of the right record is written to
+/// `wtr`. Records that lack the key column are treated as if it were empty.
+///
+/// Both inputs must be sorted by their key columns, comparing fields byte
+/// by byte, e.g., with `sort_by_column` and `SortKey::Lexical`. The inputs
+/// are read in a single pass, and only the right records sharing the
+/// current key are held in memory at once, so memory use is bounded when
+/// the keys of `right` are unique. If an input isn't sorted, then matches
+/// are missed.
+///
+/// If both readers are configured to read headers, then a header row made
+/// of the left headers followed by the right headers is written first, with
+/// duplicate names treated according to `names`.
+///
+/// # Example
+///
+/// ```
+/// use std::error::Error;
+/// use csv::{DuplicateNames, Reader, Writer};
+///
+/// # fn main() { example().unwrap(); }
+/// fn example() -> Result<(), Box<dyn Error>> {
+///     let cities = "\
+/// id,city
+/// 1,Boston
+/// 2,Concord
+/// 3,Springfield
+/// ";
+///     let pops = "\
+/// id,pop
+/// 1,4628910
+/// 3,153060
+/// ";
+///     let mut left = Reader::from_reader(cities.as_bytes());
+///     let mut right = Reader::from_reader(pops.as_bytes());
+///     let mut wtr = Writer::from_writer(vec![]);
+///     csv::merge_join(
+///         &mut left,
+///         &mut right,
+///         0,
+///         0,
+///         &mut wtr,
+///         DuplicateNames::Number,
+///     )?;
+///
+///     let data = String::from_utf8(wtr.into_inner()?)?;
+///     assert_eq!(data, "\
+/// id,city,id_2,pop
+/// 1,Boston,1,4628910
+/// 3,Springfield,3,153060
+/// ");
+///     Ok(())
+/// }
+/// ```
+pub fn merge_join<R1: io::Read, R2: io::Read, W: io::Write>(
+    left: &mut Reader<R1>,
+    right: &mut Reader<R2>,
+    left_key: usize,
+    right_key: usize,
+    wtr: &mut Writer<W>,
+    names: DuplicateNames,
+) -> Result<()> {
+    if left.has_headers() && right.has_headers() {
+        let mut headers = left.byte_headers()?.clone();
+        headers.extend_from_record(right.byte_headers()?);
+        if let DuplicateNames::Number = names {
+            headers = number_duplicates(&headers);
+        }
+        if !headers.is_empty() {
+            wtr.write_byte_record(&headers)?;
+        }
+    }
+
+    let mut lrec = ByteRecord::new();
+    let mut rrec = ByteRecord::new();
+    let mut has_left = left.read_byte_record(&mut lrec)?;
+    let mut has_right = right.read_byte_record(&mut rrec)?;
+    let mut group = vec![];
+    let mut joined = ByteRecord::new();
+    while has_left && has_right {
+        let key = lrec.get(left_key).unwrap_or(b"");
+        if key < rrec.get(right_key).unwrap_or(b"") {
+            has_left = left.read_byte_record(&mut lrec)?;
+            continue;
+        }
+        if key > rrec.get(right_key).unwrap_or(b"") {
+            has_right = right.read_byte_record(&mut rrec)?;
+            continue;
+        }
+        let key = key.to_vec();
+        group.clear();
+        while has_right && rrec.get(right_key).unwrap_or(b"") == &*key {
+            group.push(rrec.clone());
+            has_right = right.read_byte_record(&mut rrec)?;
+        }
+        while has_left && lrec.get(left_key).unwrap_or(b"") == &*key {
+            for rrec in &group {
+                joined.clear();
+                joined.extend_from_record(&lrec);
+                joined.extend_from_record(rrec);
+                wtr.write_byte_record(&joined)?;
+            }
+            has_left = left.read_byte_record(&mut lrec)?;
+        }
+    }
+    Ok(())
+}
+
+/// Suffix every name in `headers` that occurred before with the number of
+/// its occurrence, skipping numbers that would produce a name already in use.
+fn number_duplicates(headers: &ByteRecord) -> ByteRecord {
+    let mut used: HashSet<Vec<u8>> =
+        headers.iter().map(<[u8]>::to_vec).collect();
+    let mut seen: HashMap<&[u8], u64> = HashMap::new();
+    let mut numbered = ByteRecord::with_capacity(0, headers.len());
+    for name in headers {
+        let count = seen.entry(name).or_insert(0);
+        if *count == 0 {
+            *count = 1;
+            numbered.push_field(name);
+            continue;
+        }
+        let numbered_name = loop {
+            *count += 1;
+            let mut numbered_name = name.to_vec();
+            numbered_name.extend_from_slice(format!("_{}", count).as_bytes());
+            if !used.contains(&numbered_name) {
+                break numbered_name;
+            }
+        };
+        numbered.push_field(&numbered_name);
+        used.insert(numbered_name);
+    }
+    numbered
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::reader::ReaderBuilder;
+    use crate::writer::Writer;
+
+    use super::{merge_join, DuplicateNames};
+
+    fn join(
+        left: &str,
+        right: &str,
+        left_key: usize,
+        right_key: usize,
+        names: DuplicateNames,
+    ) -> String {
+        let mut left = ReaderBuilder::new().from_reader(left.as_bytes());
+        let mut right = ReaderBuilder::new().from_reader(right.as_bytes());
+        let mut wtr = Writer::from_writer(vec![]);
+        merge_join(
+            &mut left, &mut right, left_key, right_key, &mut wtr, names,
+        )
+        .unwrap();
+        String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn inner_join() {
+        let orders = "order,customer\n10,a\n11,b\n12,b\n13,d\n14,e\n";
+        let customers = "id,name\nb,Bob\nc,Carol\nd,Dan\ne,Eve\ne,Eve2\n";
+        assert_eq!(
+            join(orders, customers, 1, 0, DuplicateNames::Keep),
+            "order,customer,id,name\n11,b,b,Bob\n12,b,b,Bob\n13,d,d,Dan\n\
+             14,e,e,Eve\n14,e,e,Eve2\n"
+        );
+    }
+
+    #[test]
+    fn duplicate_names() {
+        let left = "k,v,v\n1,a,b\n";
+        let right = "k,v\n1,c\n";
+        assert_eq!(
+            join(left, right, 0, 0, DuplicateNames::Keep),
+            "k,v,v,k,v\n1,a,b,1,c\n"
+        );
+        assert_eq!(
+            join(left, right, 0, 0, DuplicateNames::Number),
+            "k,v,v_2,k_2,v_3\n1,a,b,1,c\n"
+        );
+        assert_eq!(
+            join("k,v,v_2\n1,a,b\n", right, 0, 0, DuplicateNames::Number),
+            "k,v,v_2,k_2,v_3\n1,a,b,1,c\n"
+        );
+        assert_eq!(
+            join(
+                "k,v\n1,a\n",
+                "k,v,v_2\n1,b,c\n",
+                0,
+                0,
+                DuplicateNames::Number
+            ),
+            "k,v,k_2,v_3,v_2\n1,a,1,b,c\n"
+        );
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+
+use crate::error::Result;
+use crate::string_record::StringRecord;
+
+/// Write a single record as a line of JSON.
+///
+/// The record is written to `out` as one JSON object whose keys are taken
+/// from `headers` and whose values are the record's fields, as strings,
+/// followed by a `\n`. Writing one object per record produces the JSON Lines
+/// format.
+///
+/// Since JSON objects shouldn't contain duplicate keys, a header name that
+/// occurs more than once is suffixed with the number of its occurrence
+/// starting from the second, e.g., `name`, `name_2`, `name_3`. A number is
+/// skipped if the suffixed name is already used by another header. If the
+/// record has fewer fields than `headers`, then the values of the missing
+/// fields are `null`. If it has more fields than `headers`, then the extra
+/// fields are keyed by their position, e.g., `field3` for the field at index
+/// `3`.
+///
+/// # Example
+///
+/// ```
+/// use std::error::Error;
+/// use csv::Reader;
+///
+/// # fn main() { example().unwrap(); }
+/// fn example() -> Result<(), Box<dyn Error>> {
+///     let data = "\
+/// city,country,pop
+/// Boston,United States,4628910
+/// ";
+///     let mut rdr = Reader::from_reader(data.as_bytes());
+///     let headers = rdr.headers()?.clone();
+///     let mut out = vec![];
+///     for result in rdr.records() {
+///         csv::record_to_json(&headers, &result?, &mut out)?;
+///     }
+///
+///     let json = String::from_utf8(out)?;
+///     assert_eq!(
+///         json,
+///         "{\"city\":\"Boston\",\"country\":\"United States\",\"pop\":\"4628910\"}\n",
+///     );
+///     Ok(())
+/// }
+/// ```
+pub fn record_to_json<W: io::Write>(
+    headers: &StringRecord,
+    record: &StringRecord,
+    out: &mut W,
+) -> Result<()> {
+    let mut line = vec![];
+    write_object(&mut line, headers, record);
+    line.push(b'\n');
+    out.write_all(&line)?;
+    Ok(())
+}
+
+/// Writes records as a JSON array of objects.
+///
+/// Each record is written as a JSON object on a line of its own, with keys
+/// named after the headers given when the writer is created, exactly as by
+/// `record_to_json`. The objects are enclosed in `[` and `]` and separated
+/// by commas, so the output is a single JSON document, which makes this a
+/// drop-in replacement for a CSV `Writer` when JSON output is wanted.
+///
+/// The closing `]` is written by `finish`, or when the writer is dropped.
+/// Errors that occur when dropping the writer are ignored, so `finish`
+/// should be called to handle them.
+///
+/// This is only available when the `serde_json` feature is enabled.
+///
+/// # Example
+///
+/// ```
+/// use std::error::Error;
+/// use csv::{JsonArrayWriter, Reader};
+///
+/// # fn main() { example().unwrap(); }
+/// fn example() -> Result<(), Box<dyn Error>> {
+///     let data = "\
+/// city,pop
+/// Boston,4628910
+/// Concord,42695
+/// ";
+///     let mut rdr = Reader::from_reader(data.as_bytes());
+///     let mut wtr = JsonArrayWriter::new(vec![], rdr.headers()?.clone());
+///     for result in rdr.records() {
+///         wtr.write_record(&result?)?;
+///     }
+///
+///     let json = String::from_utf8(wtr.finish()?)?;
+///     assert_eq!(json, "\
+/// [
+///   {\"city\":\"Boston\",\"pop\":\"4628910\"},
+///   {\"city\":\"Concord\",\"pop\":\"42695\"}
+/// ]
+/// ");
+///     Ok(())
+/// }
+/// ```
+#[cfg(feature = "serde_json")]
+#[derive(Debug)]
+pub struct JsonArrayWriter<W: io::Write> {
+    wtr: Option<W>,
+    headers: StringRecord,
+    line: Vec<u8>,
+    count: u64,
+}
+
+#[cfg(feature = "serde_json")]
+impl<W: io::Write> JsonArrayWriter<W> {
+    /// Create a new writer that writes JSON objects keyed by `headers` to
+    /// `wtr`.
+    pub fn new(wtr: W, headers: StringRecord) -> JsonArrayWriter<W> {
+        JsonArrayWriter { wtr: Some(wtr), headers, line: vec![], count: 0 }
+    }
+
+    /// Write a single record as a JSON object.
+    pub fn write_record(&mut self, record: &StringRecord) -> Result<()> {
+        self.line.clear();
+        self.line.extend_from_slice(if self.count == 0 {
+            b"[\n  "
+        } else {
+            b",\n  "
+        });
+        write_object(&mut self.line, &self.headers, record);
+        self.wtr.as_mut().unwrap().write_all(&self.line)?;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Return the number of records written so far.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Close the JSON array, flush the underlying writer and return it.
+    ///
+    /// If no records were written, then an empty array is written.
+    pub fn finish(mut self) -> Result<W> {
+        self.close()?;
+        Ok(self.wtr.take().unwrap())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        let end: &[u8] = if self.count == 0 { b"[]\n" } else { b"\n]\n" };
+        let wtr = self.wtr.as_mut().unwrap();
+        wtr.write_all(end)?;
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde_json")]
+impl<W: io::Write> Drop for JsonArrayWriter<W> {
+    fn drop(&mut self) {
+        if self.wtr.is_some() {
+            let _ = self.close();
+        }
+    }
+}
+
+/// Append `record` to `line` as a JSON object keyed by `headers`, as
+/// described by `record_to_json`.
+fn write_object(
+    line: &mut Vec<u8>,
+    headers: &StringRecord,
+    record: &StringRecord,
+) {
+    let keys = unique_keys(headers);
+    line.push(b'{');
+    for i in 0..headers.len().max(record.len()) {
+        if i > 0 {
+            line.push(b',');
+        }
+        match keys.get(i) {
+            Some(key) => write_string(line, key),
+            None => write_string(line, &format!("field{}", i)),
+        }
+        line.push(b':');
+        match record.get(i) {
+            Some(field) => write_string(line, field),
+            None => line.extend_from_slice(b"null"),
+        }
+    }
+    line.push(b'}');
+}
+
+/// Return the names in `headers`, with every name that occurred before
+/// suffixed with the number of its occurrence. Numbers that would produce a
+/// name already in use are skipped, so that every key is unique.
+fn unique_keys(headers: &StringRecord) -> Vec<String> {
+    let mut used: HashSet<String> = headers.iter().map(String::from).collect();
+    let mut seen: HashMap<&str, u64> = HashMap::new();
+    let mut keys = Vec::with_capacity(headers.len());
+    for name in headers {
+        let count = seen.entry(name).or_insert(0);
+        if *count == 0 {
+            *count = 1;
+            keys.push(name.to_string());
+            continue;
+        }
+        let key = loop {
+            *count += 1;
+            let key = format!("{}_{}", name, count);
+            if !used.contains(&key) {
+                break key;
+            }
+        };
+        used.insert(key.clone());
+        keys.push(key);
+    }
+    keys
+}
+
+/// Append `s` to `out` as a JSON string, including its enclosing quotes.
+fn write_string(out: &mut Vec<u8>, s: &str) {
+    out.push(b'"');
+    for &b in s.as_bytes() {
+        match b {
+            b'"' => out.extend_from_slice(b"\\\""),
+            b'\\' => out.extend_from_slice(b"\\\\"),
+            b'\n' => out.extend_from_slice(b"\\n"),
+            b'\r' => out.extend_from_slice(b"\\r"),
+            b'\t' => out.extend_from_slice(b"\\t"),
+            0x00..=0x1F => {
+                out.extend_from_slice(format!("\\u{:04x}", b).as_bytes())
+            }
+            b => out.push(b),
+        }
+    }
+    out.push(b'"');
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::string_record::StringRecord;
+
+    use super::record_to_json;
+    #[cfg(feature = "serde_json")]
+    use super::JsonArrayWriter;
+
+    fn json(headers: Vec<&str>, record: Vec<&str>) -> String {
+        let headers = StringRecord::from(headers);
+        let record = StringRecord::from(record);
+        let mut out = vec![];
+        record_to_json(&headers, &record, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn three_columns() {
+        assert_eq!(
+            json(vec!["a", "b", "c"], vec!["1", "x y", ""]),
+            "{\"a\":\"1\",\"b\":\"x y\",\"c\":\"\"}\n"
+        );
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            json(vec!["q\""], vec!["a\\b\n\u{1}é"]),
+            "{\"q\\\"\":\"a\\\\b\\n\\u0001é\"}\n"
+        );
+    }
+
+    #[test]
+    fn duplicate_and_missing() {
+        assert_eq!(
+            json(vec!["a", "b", "a", "a"], vec!["1", "2", "3"]),
+            "{\"a\":\"1\",\"b\":\"2\",\"a_2\":\"3\",\"a_3\":null}\n"
+        );
+        assert_eq!(
+            json(vec!["a"], vec!["1", "2"]),
+            "{\"a\":\"1\",\"field1\":\"2\"}\n"
+        );
+        assert_eq!(
+            json(vec!["a", "a_2", "a", "a"], vec!["1", "2", "3", "4"]),
+            "{\"a\":\"1\",\"a_2\":\"2\",\"a_3\":\"3\",\"a_4\":\"4\"}\n"
+        );
+        assert_eq!(
+            json(vec!["a", "a", "a_2"], vec!["1", "2", "3"]),
+            "{\"a\":\"1\",\"a_3\":\"2\",\"a_2\":\"3\"}\n"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde_json")]
+    fn json_array() {
+        let headers = StringRecord::from(vec!["id", "name"]);
+        let mut wtr = JsonArrayWriter::new(vec![], headers.clone());
+        wtr.write_record(&StringRecord::from(vec!["1", "ann"])).unwrap();
+        wtr.write_record(&StringRecord::from(vec!["2", "bob"])).unwrap();
+        assert_eq!(wtr.count(), 2);
+        assert_eq!(
+            String::from_utf8(wtr.finish().unwrap()).unwrap(),
+            "[\n  {\"id\":\"1\",\"name\":\"ann\"},\n  \
+             {\"id\":\"2\",\"name\":\"bob\"}\n]\n"
+        );
+
+        let wtr = JsonArrayWriter::new(vec![], headers.clone());
+        assert_eq!(wtr.finish().unwrap(), b"[]\n");
+
+        let mut out = vec![];
+        {
+            let mut wtr = JsonArrayWriter::new(&mut out, headers);
+            wtr.write_record(&StringRecord::from(vec!["3"])).unwrap();
+        }
+        assert_eq!(out, b"[\n  {\"id\":\"3\",\"name\":null}\n]\n");
+    }
+}
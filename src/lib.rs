@@ -149,31 +149,70 @@ $ cargo run --example cookbook-read-serde < examples/data/smallpop.csv
 
 #![deny(missing_docs)]
 
+use std::borrow::Cow;
+use std::cmp;
 use std::result;
 
 use serde::{Deserialize, Deserializer};
 
 pub use crate::byte_record::{ByteRecord, ByteRecordIter, Position};
+pub use crate::channel::write_from_receiver;
 pub use crate::deserializer::{DeserializeError, DeserializeErrorKind};
+pub use crate::diff::{diff, DiffEntry};
+pub use crate::embed::embed_csv;
 pub use crate::error::{
     Error, ErrorKind, FromUtf8Error, IntoInnerError, Result, Utf8Error,
 };
+pub use crate::join::{merge_join, DuplicateNames};
+pub use crate::json::record_to_json;
+#[cfg(feature = "serde_json")]
+pub use crate::json::JsonArrayWriter;
+#[cfg(feature = "glob")]
+pub use crate::multi::{MultiReader, MultiRecordsIter};
+pub use crate::projection::{select_columns, Projection};
 pub use crate::reader::{
-    ByteRecordsIntoIter, ByteRecordsIter, DeserializeRecordsIntoIter,
-    DeserializeRecordsIter, Reader, ReaderBuilder, StringRecordsIntoIter,
-    StringRecordsIter,
+    Bom, ByteRecordsIntoIter, ByteRecordsIter, DeserializeRecordsIntoIter,
+    DeserializeRecordsIter, EnumerateRecordsIter, FieldEvent, Fields,
+    FieldsIter, Reader, ReaderBuilder, StringRecordsIntoIter,
+    StringRecordsIter, WindowsIter,
 };
+pub use crate::schema::{FieldType, InferredType};
+pub use crate::shard::ShardingWriter;
+pub use crate::sort::{sort_by_column, SortKey, SortingWriter};
 pub use crate::string_record::{StringRecord, StringRecordIter};
+pub use crate::transform::transform_with;
+pub use crate::transpose::transpose;
+pub use crate::validate::{ValidationReport, WidthReport};
+pub use crate::write_records::write_records;
 pub use crate::writer::{Writer, WriterBuilder};
 
 mod byte_record;
+mod channel;
 pub mod cookbook;
 mod deserializer;
+mod diff;
+mod embed;
+#[cfg(feature = "encoding")]
+mod encoding;
 mod error;
+mod fallback_reader;
+mod join;
+mod json;
+#[cfg(feature = "glob")]
+mod multi;
+mod projection;
 mod reader;
+mod schema;
 mod serializer;
+mod shard;
+mod sort;
 mod string_record;
+mod trailer;
+mod transform;
+mod transpose;
 pub mod tutorial;
+mod validate;
+mod write_records;
 mod writer;
 
 /// The quoting style to use when writing CSV data.
@@ -222,6 +261,35 @@ impl Default for QuoteStyle {
     }
 }
 
+/// A preset for the format of written CSV data.
+///
+/// Use this with `WriterBuilder::dialect` to set the delimiter, quote,
+/// escape, quoting style and record terminator in one go, and with
+/// `ReaderBuilder::dialect` to read the data back. Every dialect
+/// here uses `,` as the delimiter and `"` as the quote, and escapes quotes
+/// by doubling them.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Dialect {
+    /// The format specified by RFC 4180, which terminates records with
+    /// `\r\n` and quotes fields only when necessary.
+    Rfc4180,
+    /// The format written by Microsoft Excel, which is the same as RFC 4180.
+    Excel,
+    /// The format commonly used on Unix systems, which terminates records
+    /// with `\n` and quotes every field.
+    Unix,
+    /// The CSV format of PostgreSQL's `COPY` command, which terminates
+    /// records with `\n` and quotes fields only when necessary.
+    PostgresCopy,
+    /// Hints that destructuring should not be exhaustive.
+    ///
+    /// This enum may grow additional variants, so this makes sure clients
+    /// don't count on exhaustive matching. (Otherwise, adding a new variant
+    /// could break existing code.)
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
 /// A record terminator.
 ///
 /// Use this to specify the record terminator while parsing CSV. The default is
@@ -294,6 +362,221 @@ impl Default for Trim {
     }
 }
 
+/// The header row written when serializing tuples.
+///
+/// Tuples and tuple structs have no field names, so there is no natural
+/// header row for them. This controls what the Serde serializer writes
+/// instead, when headers are enabled.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TupleHeaderStyle {
+    /// Write no header row. This is the default.
+    #[default]
+    None,
+    /// Write a header row of positional names: `field0`, `field1` and so on,
+    /// where each number is the index of the column.
+    ///
+    /// Columns that come from a struct nested in the tuple are still named
+    /// after the struct's fields.
+    Positional,
+    /// Hints that destructuring should not be exhaustive.
+    ///
+    /// This enum may grow additional variants, so this makes sure clients
+    /// don't count on exhaustive matching. (Otherwise, adding a new variant
+    /// could break existing code.)
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+/// How a reader grows the buffer that a record's fields are parsed into.
+///
+/// When a record doesn't fit in the space already allocated for its fields,
+/// the reader grows that space and tries again. Growing geometrically keeps
+/// the number of reallocations low, but for very large fields it can
+/// allocate up to twice as much memory as the field needs.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum GrowthPolicy {
+    /// Double the capacity each time. This is the default.
+    #[default]
+    Double,
+    /// Grow the capacity by the given number of bytes each time.
+    Linear(usize),
+    /// Call the given function with the current capacity to get the new one.
+    ///
+    /// If the function returns a capacity that isn't larger than the current
+    /// one, then the capacity is grown by one byte instead.
+    Custom(fn(usize) -> usize),
+    /// Hints that destructuring should not be exhaustive.
+    ///
+    /// This enum may grow additional variants, so this makes sure clients
+    /// don't count on exhaustive matching. (Otherwise, adding a new variant
+    /// could break existing code.)
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+impl GrowthPolicy {
+    /// Return the capacity to grow to from the given capacity.
+    fn grow(self, len: usize) -> usize {
+        let new_len = match self {
+            GrowthPolicy::Double => len.checked_mul(2).unwrap(),
+            GrowthPolicy::Linear(n) => len.checked_add(n).unwrap(),
+            GrowthPolicy::Custom(f) => f(len),
+            _ => unreachable!(),
+        };
+        cmp::max(4, cmp::max(new_len, len.checked_add(1).unwrap()))
+    }
+}
+
+/// The trailer row that a writer appends after the last record.
+///
+/// A trailer lets consumers check that they received all of the data. Its
+/// first field names the kind of trailer, and its second field holds the
+/// value to check.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum TrailerKind {
+    /// Write no trailer. This is the default.
+    #[default]
+    None,
+    /// Write `#COUNT,<n>`, where `n` is the number of records written, not
+    /// counting a header row.
+    Count,
+    /// Write `#CRC32,<crc>`, where `crc` is the CRC-32 of all of the bytes
+    /// written before the trailer, as eight lowercase hexadecimal digits.
+    Crc32,
+    /// Hints that destructuring should not be exhaustive.
+    ///
+    /// This enum may grow additional variants, so this makes sure clients
+    /// don't count on exhaustive matching. (Otherwise, adding a new variant
+    /// could break existing code.)
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+/// The character encoding of CSV data.
+///
+/// This is only available when the `encoding` feature is enabled.
+#[cfg(feature = "encoding")]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Encoding {
+    /// UTF-8, which is written as is. This is the default.
+    #[default]
+    Utf8,
+    /// Windows-1252, the superset of ISO-8859-1 (Latin-1) commonly used by
+    /// legacy software on Windows.
+    Latin1,
+    /// Hints that destructuring should not be exhaustive.
+    ///
+    /// This enum may grow additional variants, so this makes sure clients
+    /// don't count on exhaustive matching. (Otherwise, adding a new variant
+    /// could break existing code.)
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+/// The format of numbers in CSV data.
+///
+/// Use this with `ReaderBuilder::number_format` to deserialize numbers
+/// written with a thousands separator, or with a decimal point other than
+/// `.`, such as `1,234.56` or `1.234,56`. The default format has no
+/// thousands separator and uses `.` as the decimal point.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct NumberFormat {
+    thousands_separator: Option<char>,
+    decimal_point: char,
+}
+
+impl NumberFormat {
+    /// Create a number format with the given thousands separator and
+    /// decimal point.
+    ///
+    /// When deserializing a number, every occurrence of the thousands
+    /// separator is removed, and the decimal point is interpreted as `.`.
+    pub fn new(
+        thousands_separator: Option<char>,
+        decimal_point: char,
+    ) -> NumberFormat {
+        NumberFormat { thousands_separator, decimal_point }
+    }
+
+    /// Rewrite the given number into the format understood by `str::parse`.
+    fn normalize<'a>(&self, field: &'a str) -> Cow<'a, str> {
+        let sep = self.thousands_separator;
+        let point = self.decimal_point;
+        let needs_rewrite =
+            |c: char| Some(c) == sep || (c == point && point != '.');
+        if !field.contains(needs_rewrite) {
+            return Cow::Borrowed(field);
+        }
+        let mut normalized = String::with_capacity(field.len());
+        for c in field.chars() {
+            if Some(c) == sep {
+                continue;
+            }
+            normalized.push(if c == point { '.' } else { c });
+        }
+        Cow::Owned(normalized)
+    }
+}
+
+impl Default for NumberFormat {
+    fn default() -> NumberFormat {
+        NumberFormat::new(None, '.')
+    }
+}
+
+/// The format of the numbers in a column written by the Serde serializer.
+///
+/// Use this with `WriterBuilder::column_formats` to format every number in a
+/// column the same way, such as with exactly two decimal places.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ColumnFormat {
+    /// Write numbers as they are serialized. This is the default.
+    #[default]
+    Default,
+    /// Write numbers with exactly the given number of decimal places,
+    /// rounding floating point numbers as needed. Integers are written with
+    /// zeros after the decimal point.
+    Decimals(usize),
+    /// Pad integers with leading zeros to at least the given number of
+    /// digits, not counting a minus sign. Floating point numbers are written
+    /// as they are serialized.
+    ZeroPad(usize),
+    /// Hints that destructuring should not be exhaustive.
+    ///
+    /// This enum may grow additional variants, so this makes sure clients
+    /// don't count on exhaustive matching. (Otherwise, adding a new variant
+    /// could break existing code.)
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+impl ColumnFormat {
+    /// Rewrite the given serialized number into this format.
+    fn apply<'a>(&self, number: &'a str) -> Cow<'a, str> {
+        let digits = number.strip_prefix('-').unwrap_or(number);
+        let integer =
+            !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit());
+        match *self {
+            ColumnFormat::Decimals(0) if integer => Cow::Borrowed(number),
+            ColumnFormat::Decimals(places) if integer => {
+                Cow::Owned(format!("{}.{}", number, "0".repeat(places)))
+            }
+            ColumnFormat::Decimals(places) => match number.parse::<f64>() {
+                Ok(v) => Cow::Owned(format!("{:.*}", places, v)),
+                Err(_) => Cow::Borrowed(number),
+            },
+            ColumnFormat::ZeroPad(width)
+                if integer && digits.len() < width =>
+            {
+                let sign = &number[..number.len() - digits.len()];
+                let zeros = "0".repeat(width - digits.len());
+                Cow::Owned(format!("{}{}{}", sign, zeros, digits))
+            }
+            _ => Cow::Borrowed(number),
+        }
+    }
+}
+
 /// A custom Serde deserializer for possibly invalid `Option<T>` fields.
 ///
 /// When deserializing CSV data, it is sometimes desirable to simply ignore
@@ -0,0 +1,420 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::result;
+
+use crate::byte_record::ByteRecord;
+use crate::error::{Error, ErrorKind, Result, Utf8Error};
+use crate::reader::{Reader, ReaderBuilder};
+use crate::string_record::StringRecord;
+
+impl Reader<File> {
+    /// Create a reader over every file matching a glob pattern.
+    ///
+    /// The file name in `pattern` may contain the wildcards `*`, which
+    /// matches any sequence of characters, and `?`, which matches any single
+    /// character. The directory part of the pattern is taken literally, so
+    /// `data/*.csv` matches every file in `data` whose name ends with `.csv`.
+    /// The matching files are opened in sorted order with a reader built
+    /// from `builder`, and are read as one continuous stream of records.
+    ///
+    /// If `has_headers` is enabled, then every file must start with the same
+    /// header row, which is only reported once by the returned reader. A
+    /// `HeaderMismatch` error is returned when reaching a file whose header
+    /// row differs from that of the first file. Reading may continue after
+    /// such an error, in which case the records of that file are read.
+    ///
+    /// This is only available when the `glob` feature is enabled.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::error::Error;
+    /// use csv::{Reader, ReaderBuilder};
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut rdr = Reader::from_glob("data/*.csv", &ReaderBuilder::new())?;
+    ///     println!("{:?}", rdr.headers()?);
+    ///     for result in rdr.records() {
+    ///         let record = result?;
+    ///         println!("{:?}", record);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn from_glob(
+        pattern: &str,
+        builder: &ReaderBuilder,
+    ) -> Result<MultiReader> {
+        let pattern = Path::new(pattern);
+        let dir = match pattern.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let name = match pattern.file_name().and_then(|name| name.to_str()) {
+            Some(name) => name,
+            None => return Ok(MultiReader::new(vec![], builder.clone())),
+        };
+        let mut paths = vec![];
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let matches = match entry.file_name().to_str() {
+                Some(file) => glob_match(name.as_bytes(), file.as_bytes()),
+                None => false,
+            };
+            if matches && entry.file_type()?.is_file() {
+                paths.push(dir.join(entry.file_name()));
+            }
+        }
+        paths.sort();
+        Ok(MultiReader::new(paths, builder.clone()))
+    }
+}
+
+/// A CSV reader that reads several files as a single stream of records.
+///
+/// This is created by `Reader::from_glob`. It reads the records of each of
+/// its files in turn, and otherwise behaves like a `Reader`. Only one file
+/// is open at a time: each file is opened when the stream of records
+/// reaches it. An error opening a file is returned at that point, after
+/// which reading continues with the next file.
+#[derive(Debug)]
+pub struct MultiReader {
+    paths: Vec<PathBuf>,
+    /// The configuration of the reader of each file.
+    builder: ReaderBuilder,
+    /// The reader of the file that records are read from, once it's open.
+    rdr: Option<Reader<File>>,
+    /// The index of the file that records are read from.
+    current: usize,
+    /// The header row of the first file read, once it has been read.
+    headers: Option<Headers>,
+    /// The headers reported when there are no files.
+    empty: StringRecord,
+}
+
+/// The header row of the first file read by a `MultiReader`.
+#[derive(Debug)]
+struct Headers {
+    byte_record: ByteRecord,
+    string_record: result::Result<StringRecord, Utf8Error>,
+}
+
+impl MultiReader {
+    fn new(paths: Vec<PathBuf>, builder: ReaderBuilder) -> MultiReader {
+        MultiReader {
+            paths,
+            builder,
+            rdr: None,
+            current: 0,
+            headers: None,
+            empty: StringRecord::new(),
+        }
+    }
+
+    /// Returns the paths of the files read, in the order they are read.
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Returns a reference to the header row of the first file.
+    ///
+    /// If there are no files, then the header row is empty.
+    pub fn headers(&mut self) -> Result<&StringRecord> {
+        if self.headers.is_none() {
+            self.check_current()?;
+        }
+        match self.headers {
+            None => Ok(&self.empty),
+            Some(ref headers) => match headers.string_record {
+                Ok(ref record) => Ok(record),
+                Err(ref err) => Err(Error::new(ErrorKind::Utf8 {
+                    pos: headers.byte_record.position().cloned(),
+                    err: err.clone(),
+                })),
+            },
+        }
+    }
+
+    /// Returns a reference to the header row of the first file as raw bytes.
+    ///
+    /// If there are no files, then the header row is empty.
+    pub fn byte_headers(&mut self) -> Result<&ByteRecord> {
+        if self.headers.is_none() {
+            self.check_current()?;
+        }
+        match self.headers {
+            None => Ok(self.empty.as_byte_record()),
+            Some(ref headers) => Ok(&headers.byte_record),
+        }
+    }
+
+    /// Read a single row into the given record. Returns false when no more
+    /// records could be read from any of the files.
+    pub fn read_record(&mut self, record: &mut StringRecord) -> Result<bool> {
+        while self.check_current()? {
+            if self.rdr.as_mut().unwrap().read_record(record)? {
+                return Ok(true);
+            }
+            self.next_file();
+        }
+        Ok(false)
+    }
+
+    /// Read a single row into the given byte record. Returns false when no
+    /// more records could be read from any of the files.
+    pub fn read_byte_record(
+        &mut self,
+        record: &mut ByteRecord,
+    ) -> Result<bool> {
+        while self.check_current()? {
+            if self.rdr.as_mut().unwrap().read_byte_record(record)? {
+                return Ok(true);
+            }
+            self.next_file();
+        }
+        Ok(false)
+    }
+
+    /// Returns a borrowed iterator over all records as strings.
+    pub fn records(&mut self) -> MultiRecordsIter<'_> {
+        MultiRecordsIter { rdr: self, rec: StringRecord::new() }
+    }
+
+    /// Close the current file and move on to the next one.
+    fn next_file(&mut self) {
+        self.rdr = None;
+        self.current += 1;
+    }
+
+    /// Open the current file and check its header row, if that hasn't been
+    /// done yet. Returns false if every file has been read.
+    fn check_current(&mut self) -> Result<bool> {
+        if self.rdr.is_some() {
+            return Ok(true);
+        }
+        if self.current >= self.paths.len() {
+            return Ok(false);
+        }
+        let mut rdr = match self.builder.from_path(&self.paths[self.current]) {
+            Ok(rdr) => rdr,
+            Err(err) => {
+                self.current += 1;
+                return Err(err);
+            }
+        };
+        // A file whose header row can't be read is skipped, so that reading
+        // doesn't fail on the same file again.
+        let byte_record = match rdr.byte_headers() {
+            Ok(byte_record) => byte_record.clone(),
+            Err(err) => {
+                self.current += 1;
+                return Err(err);
+            }
+        };
+        // The reader is kept even if its header row is rejected, so that its
+        // records can still be read afterwards.
+        let result = self.check_headers(&rdr, byte_record);
+        self.rdr = Some(rdr);
+        result.map(|()| true)
+    }
+
+    /// Remember `byte_record`, the header row of `rdr`, if it's the first
+    /// header row read, and otherwise compare it to the first header row.
+    fn check_headers(
+        &mut self,
+        rdr: &Reader<File>,
+        byte_record: ByteRecord,
+    ) -> Result<()> {
+        match self.headers {
+            None => {
+                let string_record =
+                    StringRecord::from_byte_record(byte_record.clone())
+                        .map_err(|err| err.utf8_error().clone());
+                self.headers = Some(Headers { byte_record, string_record });
+            }
+            Some(ref headers) => {
+                if rdr.has_headers() && headers.byte_record != byte_record {
+                    return Err(Error::new(ErrorKind::HeaderMismatch {
+                        path: self.paths[self.current].clone(),
+                    }));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A borrowed iterator over the records of a `MultiReader` as strings.
+///
+/// The lifetime parameter `'r` refers to the lifetime of the underlying
+/// `MultiReader`.
+#[derive(Debug)]
+pub struct MultiRecordsIter<'r> {
+    rdr: &'r mut MultiReader,
+    rec: StringRecord,
+}
+
+impl<'r> Iterator for MultiRecordsIter<'r> {
+    type Item = Result<StringRecord>;
+
+    fn next(&mut self) -> Option<Result<StringRecord>> {
+        match self.rdr.read_record(&mut self.rec) {
+            Err(err) => Some(Err(err)),
+            Ok(true) => Some(Ok(self.rec.clone())),
+            Ok(false) => None,
+        }
+    }
+}
+
+/// Returns true if `name` matches `pattern`, in which `*` matches any
+/// sequence of bytes and `?` matches any single character.
+fn glob_match(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => {
+            (0..=name.len()).any(|i| glob_match(rest, &name[i..]))
+        }
+        Some((b'?', rest)) => match name.first() {
+            None => false,
+            Some(&b) => {
+                // Skip the continuation bytes of a multi-byte character.
+                let len = match b {
+                    0xF0..=0xFF => 4,
+                    0xE0..=0xEF => 3,
+                    0xC0..=0xDF => 2,
+                    _ => 1,
+                };
+                len <= name.len() && glob_match(rest, &name[len..])
+            }
+        },
+        Some((&b, rest)) => {
+            name.first() == Some(&b) && glob_match(rest, &name[1..])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    use crate::error::ErrorKind;
+    use crate::reader::{Reader, ReaderBuilder};
+
+    use super::glob_match;
+
+    #[test]
+    fn patterns() {
+        assert!(glob_match(b"*.csv", b"a.csv"));
+        assert!(glob_match(b"*.csv", b".csv"));
+        assert!(!glob_match(b"*.csv", b"a.csv.gz"));
+        assert!(glob_match(b"part-?.csv", b"part-1.csv"));
+        assert!(glob_match("?.csv".as_bytes(), "é.csv".as_bytes()));
+        assert!(!glob_match(b"part-?.csv", b"part-10.csv"));
+        assert!(glob_match(b"a*b*c", b"abxbc"));
+        assert!(glob_match(b"*", b""));
+    }
+
+    #[test]
+    fn from_glob() {
+        let dir = env::temp_dir().join(format!("csv-glob-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("b.csv"), "id,name\n3,c\n").unwrap();
+        fs::write(dir.join("a.csv"), "id,name\n1,a\n2,b\n").unwrap();
+        fs::write(dir.join("notes.txt"), "not,csv\n").unwrap();
+
+        let pattern = dir.join("*.csv");
+        let mut rdr = Reader::from_glob(
+            pattern.to_str().unwrap(),
+            &ReaderBuilder::new(),
+        )
+        .unwrap();
+        assert_eq!(rdr.paths(), &[dir.join("a.csv"), dir.join("b.csv")]);
+        assert_eq!(rdr.headers().unwrap(), vec!["id", "name"]);
+        let records: Vec<_> = rdr.records().map(|r| r.unwrap()).collect();
+        assert_eq!(
+            records,
+            vec![vec!["1", "a"], vec!["2", "b"], vec!["3", "c"]]
+        );
+
+        fs::write(dir.join("c.csv"), "id,title\n4,d\n").unwrap();
+        let mut rdr = Reader::from_glob(
+            pattern.to_str().unwrap(),
+            &ReaderBuilder::new(),
+        )
+        .unwrap();
+        let results: Vec<_> = rdr.records().collect();
+        assert_eq!(results.len(), 5);
+        assert_eq!(results[4].as_ref().unwrap(), &vec!["4", "d"]);
+        match *results[3].as_ref().unwrap_err().kind() {
+            ErrorKind::HeaderMismatch { ref path } => {
+                assert_eq!(path, &dir.join("c.csv"))
+            }
+            ref x => panic!("expected HeaderMismatch, got {:?}", x),
+        }
+
+        let pattern = dir.join("*.tsv");
+        let mut rdr = Reader::from_glob(
+            pattern.to_str().unwrap(),
+            &ReaderBuilder::new(),
+        )
+        .unwrap();
+        assert!(rdr.headers().unwrap().is_empty());
+        assert_eq!(rdr.records().count(), 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn from_glob_opens_files_lazily() {
+        let dir =
+            env::temp_dir().join(format!("csv-glob-lazy-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.csv"), "id\n1\n").unwrap();
+        fs::write(dir.join("b.csv"), "id\n2\n").unwrap();
+        fs::write(dir.join("c.csv"), "id\n3\n").unwrap();
+
+        let pattern = dir.join("*.csv");
+        let mut rdr = Reader::from_glob(
+            pattern.to_str().unwrap(),
+            &ReaderBuilder::new(),
+        )
+        .unwrap();
+        fs::remove_file(dir.join("b.csv")).unwrap();
+        let results: Vec<_> = rdr.records().collect();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap(), &vec!["1"]);
+        match *results[1].as_ref().unwrap_err().kind() {
+            ErrorKind::Io(_) => {}
+            ref x => panic!("expected Io, got {:?}", x),
+        }
+        assert_eq!(results[2].as_ref().unwrap(), &vec!["3"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn from_glob_skips_unreadable_headers() {
+        let dir =
+            env::temp_dir().join(format!("csv-glob-bad-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.csv"), "\"id\n1\n").unwrap();
+        fs::write(dir.join("b.csv"), "id\n2\n").unwrap();
+
+        let pattern = dir.join("*.csv");
+        let mut rdr = Reader::from_glob(
+            pattern.to_str().unwrap(),
+            &ReaderBuilder::new(),
+        )
+        .unwrap();
+        let results: Vec<_> = rdr.records().collect();
+        assert_eq!(results.len(), 2);
+        match *results[0].as_ref().unwrap_err().kind() {
+            ErrorKind::UnterminatedQuote { .. } => {}
+            ref x => panic!("expected UnterminatedQuote, got {:?}", x),
+        }
+        assert_eq!(results[1].as_ref().unwrap(), &vec!["2"]);
+        assert_eq!(rdr.headers().unwrap(), vec!["id"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,264 @@
+use std::io;
+
+use crate::byte_record::ByteRecord;
+use crate::error::{Error, ErrorKind, Result};
+use crate::reader::Reader;
+use crate::writer::Writer;
+
+/// Selects and reorders the columns of records by header name.
+///
+/// A projection is built from a list of target column names and the header
+/// row of the source data. It can then copy the selected columns of each
+/// source record, in the target order, into a destination record. This is
+/// useful for writing a subset of columns of CSV data, possibly renamed or
+/// in a different order, without rebuilding each record by hand.
+///
+/// By default, projecting a record that lacks one of the target columns
+/// (either because the column is absent from the source headers, or because
+/// the record has too few fields) returns an `ErrorKind::MissingColumn`
+/// error. Use `fill_missing` to write empty fields instead.
+///
+/// # Example
+///
+/// ```
+/// use std::error::Error;
+/// use csv::{ByteRecord, Projection, Reader, Writer};
+///
+/// # fn main() { example().unwrap(); }
+/// fn example() -> Result<(), Box<dyn Error>> {
+///     let data = "\
+/// city,country,pop
+/// Boston,United States,4628910
+/// ";
+///     let mut rdr = Reader::from_reader(data.as_bytes());
+///     let mut wtr = Writer::from_writer(vec![]);
+///
+///     let proj = Projection::new(&["pop", "city"], rdr.byte_headers()?);
+///     wtr.write_byte_record(proj.headers())?;
+///
+///     let mut projected = ByteRecord::new();
+///     for result in rdr.byte_records() {
+///         proj.project(&result?, &mut projected)?;
+///         wtr.write_byte_record(&projected)?;
+///     }
+///
+///     let data = String::from_utf8(wtr.into_inner()?)?;
+///     assert_eq!(data, "pop,city\n4628910,Boston\n");
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Projection {
+    /// The names of the target columns, in order.
+    headers: ByteRecord,
+    /// For each target column, the index of the corresponding source column,
+    /// if it exists.
+    indices: Vec<Option<usize>>,
+    /// Whether missing columns are filled with empty fields.
+    fill_missing: bool,
+}
+
+impl Projection {
+    /// Create a projection that selects the given columns from records with
+    /// the given header row.
+    ///
+    /// If a header name is repeated in `headers`, then its first occurrence
+    /// is selected.
+    pub fn new<I, T>(columns: I, headers: &ByteRecord) -> Projection
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        let mut target = ByteRecord::new();
+        let mut indices = vec![];
+        for column in columns {
+            let column = column.as_ref();
+            target.push_field(column);
+            indices.push(headers.iter().position(|h| h == column));
+        }
+        Projection { headers: target, indices, fill_missing: false }
+    }
+
+    /// Whether to fill missing columns with empty fields instead of
+    /// returning an error.
+    ///
+    /// This is disabled by default.
+    pub fn fill_missing(&mut self, yes: bool) -> &mut Projection {
+        self.fill_missing = yes;
+        self
+    }
+
+    /// Returns the names of the target columns, in order.
+    ///
+    /// This is suitable for writing as the header row of projected records.
+    pub fn headers(&self) -> &ByteRecord {
+        &self.headers
+    }
+
+    /// Copy the selected columns of `src`, in the target order, into `dst`.
+    ///
+    /// Any existing fields in `dst` are cleared first, and `dst` is given
+    /// the position of `src`.
+    ///
+    /// If a target column is missing from `src` and `fill_missing` is
+    /// disabled, then this returns an error and the contents of `dst` are
+    /// unspecified.
+    pub fn project(
+        &self,
+        src: &ByteRecord,
+        dst: &mut ByteRecord,
+    ) -> Result<()> {
+        dst.clear();
+        dst.set_position(src.position().cloned());
+        for (i, index) in self.indices.iter().enumerate() {
+            match index.and_then(|j| src.get(j)) {
+                Some(field) => dst.push_field(field),
+                None if self.fill_missing => dst.push_field(b""),
+                None => {
+                    let name = &self.headers[i];
+                    return Err(Error::new(ErrorKind::MissingColumn {
+                        pos: src.position().cloned(),
+                        name: String::from_utf8_lossy(name).into_owned(),
+                    }));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Copy CSV data, keeping only the columns with the given header names.
+///
+/// The header row of `rdr` is read, and the columns named by `keep` are
+/// written to `wtr` in the order of `keep`, starting with a header row of
+/// just those columns. This returns the number of records written, not
+/// including the header row.
+///
+/// If one of the names in `keep` isn't in the header row, then an
+/// `ErrorKind::MissingColumn` error with that name is returned before
+/// anything is written. A record that is too short to have one of the
+/// columns also results in a `MissingColumn` error.
+///
+/// # Example
+///
+/// ```
+/// use std::error::Error;
+/// use csv::{Reader, Writer};
+///
+/// # fn main() { example().unwrap(); }
+/// fn example() -> Result<(), Box<dyn Error>> {
+///     let data = "\
+/// city,country,pop
+/// Boston,United States,4628910
+/// ";
+///     let mut rdr = Reader::from_reader(data.as_bytes());
+///     let mut wtr = Writer::from_writer(vec![]);
+///     let count = csv::select_columns(&mut rdr, &mut wtr, &["pop", "city"])?;
+///
+///     assert_eq!(count, 1);
+///     let data = String::from_utf8(wtr.into_inner()?)?;
+///     assert_eq!(data, "pop,city\n4628910,Boston\n");
+///     Ok(())
+/// }
+/// ```
+pub fn select_columns<R, W, T>(
+    rdr: &mut Reader<R>,
+    wtr: &mut Writer<W>,
+    keep: &[T],
+) -> Result<u64>
+where
+    R: io::Read,
+    W: io::Write,
+    T: AsRef<[u8]>,
+{
+    let proj = Projection::new(keep, rdr.byte_headers()?);
+    if let Some(i) = proj.indices.iter().position(Option::is_none) {
+        return Err(Error::new(ErrorKind::MissingColumn {
+            pos: None,
+            name: String::from_utf8_lossy(&proj.headers[i]).into_owned(),
+        }));
+    }
+    wtr.write_byte_record(proj.headers())?;
+
+    let mut count = 0;
+    let mut record = ByteRecord::new();
+    let mut projected = ByteRecord::new();
+    while rdr.read_byte_record(&mut record)? {
+        proj.project(&record, &mut projected)?;
+        wtr.write_byte_record(&projected)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::byte_record::ByteRecord;
+    use crate::error::ErrorKind;
+    use crate::reader::Reader;
+    use crate::writer::Writer;
+
+    use super::{select_columns, Projection};
+
+    #[test]
+    fn project_reorder() {
+        let headers = ByteRecord::from(vec!["a", "b", "c"]);
+        let proj = Projection::new(&["c", "a"], &headers);
+        assert_eq!(proj.headers(), &ByteRecord::from(vec!["c", "a"]));
+
+        let mut dst = ByteRecord::from(vec!["stale"]);
+        proj.project(&ByteRecord::from(vec!["1", "2", "3"]), &mut dst)
+            .unwrap();
+        assert_eq!(dst, vec!["3", "1"]);
+    }
+
+    #[test]
+    fn project_missing_error() {
+        let headers = ByteRecord::from(vec!["a", "b", "c"]);
+        let mut dst = ByteRecord::new();
+
+        let proj = Projection::new(&["a", "z"], &headers);
+        let err = proj
+            .project(&ByteRecord::from(vec!["1", "2", "3"]), &mut dst)
+            .unwrap_err();
+        match *err.kind() {
+            ErrorKind::MissingColumn { ref name, .. } => assert_eq!(name, "z"),
+            ref err => panic!("unexpected error: {:?}", err),
+        }
+
+        // Short records are missing columns too.
+        let proj = Projection::new(&["c"], &headers);
+        assert!(proj.project(&ByteRecord::from(vec!["1"]), &mut dst).is_err());
+    }
+
+    #[test]
+    fn project_fill_missing() {
+        let headers = ByteRecord::from(vec!["a", "b", "c"]);
+        let mut proj = Projection::new(&["z", "c", "a"], &headers);
+        proj.fill_missing(true);
+
+        let mut dst = ByteRecord::new();
+        proj.project(&ByteRecord::from(vec!["1", "2"]), &mut dst).unwrap();
+        assert_eq!(dst, vec!["", "", "1"]);
+    }
+
+    #[test]
+    fn select_columns_reorder() {
+        let data = "a,b,c,d\n1,2,3,4\n5,6,7,8\n";
+        let mut rdr = Reader::from_reader(data.as_bytes());
+        let mut wtr = Writer::from_writer(vec![]);
+        let keep = vec!["d".to_string(), "b".to_string()];
+        assert_eq!(select_columns(&mut rdr, &mut wtr, &keep).unwrap(), 2);
+        let got = String::from_utf8(wtr.into_inner().unwrap()).unwrap();
+        assert_eq!(got, "d,b\n4,2\n8,6\n");
+
+        let mut rdr = Reader::from_reader(data.as_bytes());
+        let mut wtr = Writer::from_writer(vec![]);
+        let err = select_columns(&mut rdr, &mut wtr, &["a", "z"]).unwrap_err();
+        match *err.kind() {
+            ErrorKind::MissingColumn { ref name, .. } => assert_eq!(name, "z"),
+            ref err => panic!("unexpected error: {:?}", err),
+        }
+        assert!(wtr.into_inner().unwrap().is_empty());
+    }
+}
@@ -1,28 +1,63 @@
+use std::cmp;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
 use std::fs::File;
 use std::io::{self, BufRead, Seek};
 use std::marker::PhantomData;
+use std::mem;
 use std::path::Path;
 use std::result;
+use std::str;
+use std::sync::Arc;
 
 use csv_core::{Reader as CoreReader, ReaderBuilder as CoreReaderBuilder};
 use serde::de::DeserializeOwned;
 
-use crate::byte_record::{ByteRecord, Position};
+use crate::byte_record::{ByteRecord, ByteRecordIter, Position};
+use crate::deserializer::{deserialize_string_record_with, DeserializeConfig};
+#[cfg(feature = "encoding")]
+use crate::encoding;
 use crate::error::{Error, ErrorKind, Result, Utf8Error};
+use crate::fallback_reader::FallbackReader;
+use crate::schema::{self, InferredType};
 use crate::string_record::StringRecord;
-use crate::{Terminator, Trim};
+use crate::trailer::COUNT_LABEL;
+use crate::validate::{ValidationReport, WidthReport};
+#[cfg(feature = "encoding")]
+use crate::Encoding;
+use crate::{Dialect, GrowthPolicy, NumberFormat, Terminator, Trim};
 
 /// Builds a CSV reader with various configuration knobs.
 ///
 /// This builder can be used to tweak the field delimiter, record terminator
 /// and more. Once a CSV `Reader` is built, its configuration cannot be
 /// changed.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ReaderBuilder {
     capacity: usize,
+    growth: GrowthPolicy,
+    fields_hint: usize,
+    #[cfg(feature = "encoding")]
+    encoding: Encoding,
     flexible: bool,
+    expect_fields: Option<usize>,
+    ignore_trailing_delimiter: bool,
+    skip_blank_records: bool,
+    stop_at_blank: bool,
+    verify_trailer: bool,
+    cache_positions: bool,
+    max_records: Option<u64>,
+    max_fields: Option<usize>,
+    record_boundary: Option<RecordBoundary>,
     has_headers: bool,
     trim: Trim,
+    /// The delimiter used instead of the core parser's single byte
+    /// delimiter, or empty if there is none.
+    multi_delimiter: Vec<u8>,
+    lenient_quotes: bool,
+    single_field: bool,
+    /// Settings for the Serde deserializer.
+    deserialize: DeserializeConfig,
     /// The underlying CSV parser builder.
     ///
     /// We explicitly put this on the heap because CoreReaderBuilder embeds an
@@ -31,13 +66,44 @@ pub struct ReaderBuilder {
     builder: Box<CoreReaderBuilder>,
 }
 
+/// A predicate that tells whether a physical record starts a new logical
+/// record.
+type StartsRecord = dyn Fn(&[u8]) -> bool + Send + Sync;
+
+/// The predicate set by `ReaderBuilder::record_boundary`.
+#[derive(Clone)]
+struct RecordBoundary(Arc<StartsRecord>);
+
+impl fmt::Debug for RecordBoundary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "RecordBoundary(..)")
+    }
+}
+
 impl Default for ReaderBuilder {
     fn default() -> ReaderBuilder {
         ReaderBuilder {
             capacity: 8 * (1 << 10),
+            growth: GrowthPolicy::default(),
+            fields_hint: 0,
+            #[cfg(feature = "encoding")]
+            encoding: Encoding::default(),
             flexible: false,
+            expect_fields: None,
+            ignore_trailing_delimiter: false,
+            skip_blank_records: false,
+            stop_at_blank: false,
+            verify_trailer: false,
+            cache_positions: false,
+            max_records: None,
+            max_fields: None,
+            record_boundary: None,
             has_headers: true,
             trim: Trim::default(),
+            multi_delimiter: vec![],
+            lenient_quotes: false,
+            single_field: false,
+            deserialize: DeserializeConfig::default(),
             builder: Box::new(CoreReaderBuilder::default()),
         }
     }
@@ -134,6 +200,72 @@ impl ReaderBuilder {
         Reader::new(self, rdr)
     }
 
+    /// Build a CSV parser from this configuration that reads data from
+    /// standard input.
+    ///
+    /// This locks stdin for the lifetime of the returned reader, which avoids
+    /// re-acquiring the lock on every read. Only one lock on stdin can be
+    /// held at a time, so any other attempt to read from stdin (including
+    /// a second call to `from_stdin`) blocks until this reader is dropped.
+    ///
+    /// # Example
+    ///
+    /// This copies CSV data from stdin to stdout, much like `cat`:
+    ///
+    /// ```no_run
+    /// use std::error::Error;
+    /// use csv::{ReaderBuilder, WriterBuilder};
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut rdr = ReaderBuilder::new().from_stdin();
+    ///     let mut wtr = WriterBuilder::new().from_stdout();
+    ///
+    ///     wtr.write_byte_record(rdr.byte_headers()?)?;
+    ///     for result in rdr.byte_records() {
+    ///         wtr.write_byte_record(&result?)?;
+    ///     }
+    ///     wtr.flush()?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn from_stdin(&self) -> Reader<io::StdinLock<'static>> {
+        Reader::new(self, io::stdin().lock())
+    }
+
+    /// Build a CSV parser from this configuration that reads data from an
+    /// in-memory byte slice.
+    ///
+    /// The slice is wrapped in an `io::Cursor`, so the reader returned
+    /// supports `seek`. Since the data is already in memory, the internal
+    /// buffer is never made larger than the data itself, which avoids
+    /// allocating a full sized buffer for small inputs.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::ReaderBuilder;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut rdr = ReaderBuilder::new()
+    ///         .from_bytes(b"city,pop\nBoston,4628910\n");
+    ///     for result in rdr.records() {
+    ///         let record = result?;
+    ///         assert_eq!(record, vec!["Boston", "4628910"]);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn from_bytes<'a>(
+        &self,
+        bytes: &'a [u8],
+    ) -> Reader<io::Cursor<&'a [u8]>> {
+        let capacity = cmp::max(1, cmp::min(self.capacity, bytes.len()));
+        Reader::with_capacity(self, capacity, io::Cursor::new(bytes))
+    }
+
     /// The field delimiter to use when parsing CSV.
     ///
     /// The default is `b','`.
@@ -165,6 +297,58 @@ impl ReaderBuilder {
     /// ```
     pub fn delimiter(&mut self, delimiter: u8) -> &mut ReaderBuilder {
         self.builder.delimiter(delimiter);
+        self.multi_delimiter.clear();
+        self
+    }
+
+    /// The field delimiter to use when parsing CSV, as a sequence of bytes.
+    ///
+    /// This is like `delimiter`, but permits delimiters that are longer than
+    /// a single byte, such as `||` or `|~|`. The delimiter only separates
+    /// fields where it appears outside of quotes, and a field containing
+    /// only part of it, e.g., a single `|` when the delimiter is `||`, is
+    /// read as is. Quoting, comments and record terminators work as they do
+    /// with a single byte delimiter. The `strict_quotes` setting is ignored.
+    ///
+    /// Parsing with a delimiter longer than a single byte is slower than
+    /// parsing with a single byte delimiter. Whichever of `delimiter` and
+    /// `multi_delimiter` is called last takes effect.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `delimiter` is empty.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::ReaderBuilder;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city||country||pop
+    /// Boston||United States|USA||4628910
+    /// ";
+    ///     let mut rdr = ReaderBuilder::new()
+    ///         .multi_delimiter(b"||")
+    ///         .from_reader(data.as_bytes());
+    ///
+    ///     if let Some(result) = rdr.records().next() {
+    ///         let record = result?;
+    ///         assert_eq!(record, vec!["Boston", "United States|USA", "4628910"]);
+    ///         Ok(())
+    ///     } else {
+    ///         Err(From::from("expected at least one record but got none"))
+    ///     }
+    /// }
+    /// ```
+    pub fn multi_delimiter(&mut self, delimiter: &[u8]) -> &mut ReaderBuilder {
+        assert!(!delimiter.is_empty(), "delimiter must not be empty");
+        if delimiter.len() == 1 {
+            return self.delimiter(delimiter[0]);
+        }
+        self.multi_delimiter = delimiter.to_vec();
         self
     }
 
@@ -305,95 +489,117 @@ impl ReaderBuilder {
         self
     }
 
-    /// Whether fields are trimmed of leading and trailing whitespace or not.
-    ///
-    /// By default, no trimming is performed. This method permits one to
-    /// override that behavior and choose one of the following options:
-    ///
-    /// 1. `Trim::Headers` trims only header values.
-    /// 2. `Trim::Fields` trims only non-header or "field" values.
-    /// 3. `Trim::All` trims both header and non-header values.
+    /// Require every record to have exactly the given number of fields.
     ///
-    /// A value is only interpreted as a header value if this CSV reader is
-    /// configured to read a header record (which is the default).
+    /// When set, every record read, including the header row, is checked
+    /// against `len`, and any record with a different number of fields
+    /// results in an `UnequalLengths` error whose `expected_len` is `len`.
+    /// Unlike the default behavior, the expected length is not inferred from
+    /// the first record, and the check is applied even if `flexible` is
+    /// enabled.
     ///
-    /// When reading string records, characters meeting the definition of
-    /// Unicode whitespace are trimmed. When reading byte records, characters
-    /// meeting the definition of ASCII whitespace are trimmed. ASCII
-    /// whitespace characters correspond to the set `[\t\n\v\f\r ]`.
+    /// This is disabled by default.
     ///
     /// # Example
     ///
-    /// This example shows what happens when all values are trimmed.
-    ///
     /// ```
     /// use std::error::Error;
-    /// use csv::{ReaderBuilder, StringRecord, Trim};
+    /// use csv::{ErrorKind, ReaderBuilder};
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
     ///     let data = "\
-    /// city ,   country ,  pop
-    /// Boston,\"
-    ///    United States\",4628910
-    /// Concord,   United States   ,42695
+    /// city,country
+    /// Boston,United States
     /// ";
     ///     let mut rdr = ReaderBuilder::new()
-    ///         .trim(Trim::All)
+    ///         .expect_fields(Some(3))
     ///         .from_reader(data.as_bytes());
-    ///     let records = rdr
-    ///         .records()
-    ///         .collect::<Result<Vec<StringRecord>, csv::Error>>()?;
-    ///     assert_eq!(records, vec![
-    ///         vec!["Boston", "United States", "4628910"],
-    ///         vec!["Concord", "United States", "42695"],
-    ///     ]);
-    ///     Ok(())
+    ///
+    ///     match *rdr.headers().unwrap_err().kind() {
+    ///         ErrorKind::UnequalLengths { expected_len, len, .. } => {
+    ///             assert_eq!(expected_len, 3);
+    ///             assert_eq!(len, 2);
+    ///             Ok(())
+    ///         }
+    ///         ref wrong => Err(From::from(format!(
+    ///             "expected UnequalLengths error but got {:?}",
+    ///             wrong
+    ///         ))),
+    ///     }
     /// }
     /// ```
-    pub fn trim(&mut self, trim: Trim) -> &mut ReaderBuilder {
-        self.trim = trim;
+    pub fn expect_fields(&mut self, len: Option<usize>) -> &mut ReaderBuilder {
+        self.expect_fields = len;
         self
     }
 
-    /// The record terminator to use when parsing CSV.
+    /// Whether to ignore a single delimiter at the end of a record.
     ///
-    /// A record terminator can be any single byte. The default is a special
-    /// value, `Terminator::CRLF`, which treats any occurrence of `\r`, `\n`
-    /// or `\r\n` as a single record terminator.
+    /// A record like `a,b,` has three fields, the last of which is empty.
+    /// Some tools emit a delimiter after every field, in which case that
+    /// last empty field is an artifact. When this is enabled, a record with
+    /// more than one field whose last field is empty has that field
+    /// removed, so `a,b,` has just the fields `a` and `b`. This applies to
+    /// the header row too.
     ///
-    /// # Example: `$` as a record terminator
+    /// Note that a trailing empty field is removed even if it's quoted, as
+    /// in `a,b,""`. Only one field is removed, so `a,b,,` still has three
+    /// fields.
+    ///
+    /// This is disabled by default.
+    ///
+    /// # Example
     ///
     /// ```
     /// use std::error::Error;
-    /// use csv::{ReaderBuilder, Terminator};
+    /// use csv::ReaderBuilder;
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
-    ///     let data = "city,country,pop$Boston,United States,4628910";
+    ///     let data = "\
+    /// city,country,
+    /// Boston,United States,
+    /// ";
     ///     let mut rdr = ReaderBuilder::new()
-    ///         .terminator(Terminator::Any(b'$'))
+    ///         .ignore_trailing_delimiter(true)
     ///         .from_reader(data.as_bytes());
     ///
+    ///     assert_eq!(rdr.headers()?, vec!["city", "country"]);
     ///     if let Some(result) = rdr.records().next() {
     ///         let record = result?;
-    ///         assert_eq!(record, vec!["Boston", "United States", "4628910"]);
+    ///         assert_eq!(record, vec!["Boston", "United States"]);
     ///         Ok(())
     ///     } else {
     ///         Err(From::from("expected at least one record but got none"))
     ///     }
     /// }
     /// ```
-    pub fn terminator(&mut self, term: Terminator) -> &mut ReaderBuilder {
-        self.builder.terminator(term.to_core());
+    pub fn ignore_trailing_delimiter(
+        &mut self,
+        yes: bool,
+    ) -> &mut ReaderBuilder {
+        self.ignore_trailing_delimiter = yes;
         self
     }
 
-    /// The quote character to use when parsing CSV.
+    /// Whether to skip records that are blank.
     ///
-    /// The default is `b'"'`.
+    /// A record is blank if it has no fields, or if every one of its fields
+    /// is empty or consists only of ASCII whitespace, e.g., a line containing
+    /// just spaces, or `" ", ,` when the delimiter is `,`. When this is
+    /// enabled, blank records are not returned by any method that reads
+    /// records, including the header row. Skipped records still count toward
+    /// the record number reported by `Position::record`, and they are
+    /// skipped before their length is checked, so they never cause an
+    /// `UnequalLengths` error.
     ///
-    /// # Example: single quotes instead of double quotes
+    /// Note that lines that are completely empty are always skipped,
+    /// regardless of this setting.
+    ///
+    /// This is disabled by default.
+    ///
+    /// # Example
     ///
     /// ```
     /// use std::error::Error;
@@ -401,34 +607,44 @@ impl ReaderBuilder {
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
-    ///     let data = "\
-    /// city,country,pop
-    /// Boston,'United States',4628910
-    /// ";
+    ///     let data = "city,country\n   \nBoston,United States\n";
     ///     let mut rdr = ReaderBuilder::new()
-    ///         .quote(b'\'')
+    ///         .skip_blank_records(true)
     ///         .from_reader(data.as_bytes());
     ///
     ///     if let Some(result) = rdr.records().next() {
     ///         let record = result?;
-    ///         assert_eq!(record, vec!["Boston", "United States", "4628910"]);
+    ///         assert_eq!(record, vec!["Boston", "United States"]);
     ///         Ok(())
     ///     } else {
     ///         Err(From::from("expected at least one record but got none"))
     ///     }
     /// }
     /// ```
-    pub fn quote(&mut self, quote: u8) -> &mut ReaderBuilder {
-        self.builder.quote(quote);
+    pub fn skip_blank_records(&mut self, yes: bool) -> &mut ReaderBuilder {
+        self.skip_blank_records = yes;
         self
     }
 
-    /// The escape character to use when parsing CSV.
+    /// Whether to stop reading records at a blank line.
     ///
-    /// In some variants of CSV, quotes are escaped using a special escape
-    /// character like `\` (instead of escaping quotes by doubling them).
+    /// Some files hold several tables, called sections, that are separated
+    /// by blank lines. When this is enabled, reading a record fails to find
+    /// one when a blank line follows the previous record, as if the end of
+    /// the data had been reached, so that iterators like `records` end
+    /// there. The next read then continues with the first record of the
+    /// following section, which makes it possible to read the data section
+    /// by section. Several blank lines in a row count as a single section
+    /// break, and blank lines at the start of the data are ignored. If
+    /// `skip_blank_records` is also enabled, then a record that is blank
+    /// ends a section too. Use `Reader::is_done` to tell the end of a
+    /// section from the end of the data.
     ///
-    /// By default, recognizing these idiosyncratic escapes is disabled.
+    /// Each section may have a different number of fields, but the header
+    /// row is only read from the first section. The first record of every
+    /// following section can be read as its header row, if it has one.
+    ///
+    /// This is disabled by default, which skips blank lines.
     ///
     /// # Example
     ///
@@ -439,109 +655,182 @@ impl ReaderBuilder {
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
     ///     let data = "\
-    /// city,country,pop
-    /// Boston,\"The \\\"United\\\" States\",4628910
+    /// city,pop
+    /// Boston,4628910
+    ///
+    /// state,abbrev
+    /// Massachusetts,MA
     /// ";
     ///     let mut rdr = ReaderBuilder::new()
-    ///         .escape(Some(b'\\'))
+    ///         .stop_at_blank(true)
     ///         .from_reader(data.as_bytes());
+    ///     assert_eq!(rdr.records().count(), 1);
+    ///     assert!(!rdr.is_done());
     ///
-    ///     if let Some(result) = rdr.records().next() {
-    ///         let record = result?;
-    ///         assert_eq!(record, vec![
-    ///             "Boston", "The \"United\" States", "4628910",
-    ///         ]);
-    ///         Ok(())
-    ///     } else {
-    ///         Err(From::from("expected at least one record but got none"))
-    ///     }
+    ///     let mut records = rdr.records();
+    ///     assert_eq!(records.next().unwrap()?, vec!["state", "abbrev"]);
+    ///     assert_eq!(records.next().unwrap()?, vec!["Massachusetts", "MA"]);
+    ///     assert!(records.next().is_none());
+    ///     assert!(rdr.is_done());
+    ///     Ok(())
     /// }
     /// ```
-    pub fn escape(&mut self, escape: Option<u8>) -> &mut ReaderBuilder {
-        self.builder.escape(escape);
+    pub fn stop_at_blank(&mut self, yes: bool) -> &mut ReaderBuilder {
+        self.stop_at_blank = yes;
         self
     }
 
-    /// Enable double quote escapes.
+    /// Whether to verify a trailer row at the end of the data.
     ///
-    /// This is enabled by default, but it may be disabled. When disabled,
-    /// doubled quotes are not interpreted as escapes.
+    /// When enabled, a record of exactly two fields whose first field is
+    /// `#COUNT` and whose second field is a number is recognized as a
+    /// trailer if it is the last record in the data, as written by
+    /// `WriterBuilder::trailer` with `TrailerKind::Count`. The trailer is
+    /// never returned as a record. Instead, its count is compared with the
+    /// number of records read before it, not counting a header row, and a
+    /// `TrailerMismatch` error is returned if they differ. A record that
+    /// looks like a trailer but isn't last is returned as a record.
+    ///
+    /// The number of records read is unknown after a call to `seek` or
+    /// `seek_raw`, so a trailer is then skipped without being verified.
+    ///
+    /// This is disabled by default.
     ///
     /// # Example
     ///
     /// ```
     /// use std::error::Error;
-    /// use csv::ReaderBuilder;
+    /// use csv::{ErrorKind, ReaderBuilder};
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
     ///     let data = "\
-    /// city,country,pop
-    /// Boston,\"The \"\"United\"\" States\",4628910
+    /// city,pop
+    /// Boston,4628910
+    /// Concord,42695
+    /// #COUNT,3
     /// ";
     ///     let mut rdr = ReaderBuilder::new()
-    ///         .double_quote(false)
+    ///         .verify_trailer(true)
     ///         .from_reader(data.as_bytes());
-    ///
-    ///     if let Some(result) = rdr.records().next() {
-    ///         let record = result?;
-    ///         assert_eq!(record, vec![
-    ///             "Boston", "The \"United\"\" States\"", "4628910",
-    ///         ]);
-    ///         Ok(())
-    ///     } else {
-    ///         Err(From::from("expected at least one record but got none"))
+    ///     let results: Vec<_> = rdr.records().collect();
+    ///     assert_eq!(results.len(), 3);
+    ///     assert!(results[1].is_ok());
+    ///     match *results[2].as_ref().unwrap_err().kind() {
+    ///         ErrorKind::TrailerMismatch { expected: 3, actual: 2, .. } => {}
+    ///         ref wrong => panic!("unexpected error: {:?}", wrong),
     ///     }
+    ///     Ok(())
     /// }
     /// ```
-    pub fn double_quote(&mut self, yes: bool) -> &mut ReaderBuilder {
-        self.builder.double_quote(yes);
+    pub fn verify_trailer(&mut self, yes: bool) -> &mut ReaderBuilder {
+        self.verify_trailer = yes;
         self
     }
 
-    /// Enable or disable quoting.
+    /// Whether to remember the position of every record read.
     ///
-    /// This is enabled by default, but it may be disabled. When disabled,
-    /// quotes are not treated specially.
+    /// When enabled, the position of each record is recorded the first time
+    /// it is read, including the header row. After a forward pass, any
+    /// record that has already been visited can then be returned to cheaply
+    /// with `Reader::seek_cached`, without building an index up front. The
+    /// positions recorded so far are available from
+    /// `Reader::position_cache`.
+    ///
+    /// This is disabled by default, since it requires memory proportional
+    /// to the number of records read.
     ///
     /// # Example
     ///
     /// ```
     /// use std::error::Error;
+    /// use std::io;
     /// use csv::ReaderBuilder;
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
     ///     let data = "\
     /// city,country,pop
-    /// Boston,\"The United States,4628910
+    /// Boston,United States,4628910
+    /// Concord,United States,42695
     /// ";
     ///     let mut rdr = ReaderBuilder::new()
-    ///         .quoting(false)
-    ///         .from_reader(data.as_bytes());
+    ///         .cache_positions(true)
+    ///         .from_reader(io::Cursor::new(data));
+    ///     let records = rdr.records().collect::<Result<Vec<_>, _>>()?;
+    ///     assert_eq!(records.len(), 2);
+    ///     assert_eq!(rdr.position_cache().len(), 3);
+    ///
+    ///     // The header row is record 0, so the first data record is 1.
+    ///     rdr.seek_cached(1)?;
+    ///     let record = rdr.records().next().unwrap()?;
+    ///     assert_eq!(record, vec!["Boston", "United States", "4628910"]);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn cache_positions(&mut self, yes: bool) -> &mut ReaderBuilder {
+        self.cache_positions = yes;
+        self
+    }
+
+    /// The maximum number of records that may be read.
     ///
-    ///     if let Some(result) = rdr.records().next() {
-    ///         let record = result?;
-    ///         assert_eq!(record, vec![
-    ///             "Boston", "\"The United States", "4628910",
-    ///         ]);
-    ///         Ok(())
-    ///     } else {
-    ///         Err(From::from("expected at least one record but got none"))
+    /// When set to `Some(n)`, reading the record after the first `n` records
+    /// returns a `TooManyRecords` error instead of the record, which guards
+    /// against processing arbitrarily large untrusted input. The header row
+    /// doesn't count toward the limit. Data with exactly `n` records is read
+    /// without error.
+    ///
+    /// By default, there is no limit.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::{ErrorKind, ReaderBuilder};
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,pop
+    /// Boston,4628910
+    /// Concord,42695
+    /// Springfield,153060
+    /// ";
+    ///     let mut rdr = ReaderBuilder::new()
+    ///         .max_records(Some(2))
+    ///         .from_reader(data.as_bytes());
+    ///     let mut records = rdr.records();
+    ///
+    ///     assert!(records.next().unwrap().is_ok());
+    ///     assert!(records.next().unwrap().is_ok());
+    ///     let err = records.next().unwrap().unwrap_err();
+    ///     match *err.kind() {
+    ///         ErrorKind::TooManyRecords { limit: 2 } => Ok(()),
+    ///         ref kind => Err(From::from(format!("unexpected {:?}", kind))),
     ///     }
     /// }
     /// ```
-    pub fn quoting(&mut self, yes: bool) -> &mut ReaderBuilder {
-        self.builder.quoting(yes);
+    pub fn max_records(&mut self, limit: Option<u64>) -> &mut ReaderBuilder {
+        self.max_records = limit;
         self
     }
 
-    /// The comment character to use when parsing CSV.
+    /// The maximum number of fields to keep from each record.
     ///
-    /// If the start of a record begins with the byte given here, then that
-    /// line is ignored by the CSV parser.
+    /// When set to `Some(n)`, only the first `n` fields of every record are
+    /// kept, including those of the header row. The rest of each record is
+    /// still parsed, so that reading continues at the next record, but its
+    /// fields are not copied into the record. This makes reading just the
+    /// first few columns of very wide data faster. Since every record is cut
+    /// to the same number of fields, records with more fields than the
+    /// first one don't cause an `UnequalLengths` error.
     ///
-    /// This is disabled by default.
+    /// By default, all fields are kept.
+    ///
+    /// # Panics
+    ///
+    /// This panics if the limit given is `Some(0)`.
     ///
     /// # Example
     ///
@@ -552,33 +841,47 @@ impl ReaderBuilder {
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
     ///     let data = "\
-    /// city,country,pop
-    /// #Concord,United States,42695
-    /// Boston,United States,4628910
+    /// city,country,pop,area,founded
+    /// Boston,United States,4628910,232.1,1630
     /// ";
     ///     let mut rdr = ReaderBuilder::new()
-    ///         .comment(Some(b'#'))
+    ///         .max_fields(Some(2))
     ///         .from_reader(data.as_bytes());
     ///
+    ///     assert_eq!(rdr.headers()?, vec!["city", "country"]);
     ///     if let Some(result) = rdr.records().next() {
     ///         let record = result?;
-    ///         assert_eq!(record, vec!["Boston", "United States", "4628910"]);
+    ///         assert_eq!(record, vec!["Boston", "United States"]);
     ///         Ok(())
     ///     } else {
     ///         Err(From::from("expected at least one record but got none"))
     ///     }
     /// }
     /// ```
-    pub fn comment(&mut self, comment: Option<u8>) -> &mut ReaderBuilder {
-        self.builder.comment(comment);
+    pub fn max_fields(&mut self, max: Option<usize>) -> &mut ReaderBuilder {
+        assert!(max != Some(0), "max_fields must be at least 1");
+        self.max_fields = max;
         self
     }
 
-    /// A convenience method for specifying a configuration to read ASCII
-    /// delimited text.
+    /// Group physical records into logical records with a predicate.
     ///
-    /// This sets the delimiter and record terminator to the ASCII unit
-    /// separator (`\x1F`) and record separator (`\x1E`), respectively.
+    /// When set, a logical record spans consecutive physical records, and
+    /// consists of all of their fields, in order. A physical record is
+    /// usually a single line, unless a quoted field spans more than one.
+    /// `starts_record` is called with the raw bytes of every physical record
+    /// after the first, without any leading or trailing record terminators.
+    /// If it returns true, then that physical record starts a new logical
+    /// record. Otherwise, its fields are appended to the current logical
+    /// record. The header row is grouped the same way.
+    ///
+    /// Checks on the number of fields of a record, like those enabled by
+    /// `flexible` and `expect_fields`, apply to logical records. Reading a
+    /// logical record requires reading the physical record that follows
+    /// it, so the position of this reader (as reported by `position`)
+    /// reflects the end of that physical record.
+    ///
+    /// By default, every physical record is a logical record.
     ///
     /// # Example
     ///
@@ -589,949 +892,750 @@ impl ReaderBuilder {
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
     ///     let data = "\
-    /// city\x1Fcountry\x1Fpop\x1EBoston\x1FUnited States\x1F4628910";
+    /// ---,Boston
+    /// United States,4628910
+    /// ---,Paris
+    /// France,2161000
+    /// ";
     ///     let mut rdr = ReaderBuilder::new()
-    ///         .ascii()
+    ///         .has_headers(false)
+    ///         .record_boundary(Box::new(|line| line.starts_with(b"---")))
     ///         .from_reader(data.as_bytes());
     ///
     ///     if let Some(result) = rdr.records().next() {
     ///         let record = result?;
-    ///         assert_eq!(record, vec!["Boston", "United States", "4628910"]);
+    ///         assert_eq!(record, vec!["---", "Boston", "United States", "4628910"]);
     ///         Ok(())
     ///     } else {
     ///         Err(From::from("expected at least one record but got none"))
     ///     }
     /// }
     /// ```
-    pub fn ascii(&mut self) -> &mut ReaderBuilder {
-        self.builder.ascii();
-        self
-    }
-
-    /// Set the capacity (in bytes) of the buffer used in the CSV reader.
-    /// This defaults to a reasonable setting.
-    pub fn buffer_capacity(&mut self, capacity: usize) -> &mut ReaderBuilder {
-        self.capacity = capacity;
-        self
-    }
-
-    /// Enable or disable the NFA for parsing CSV.
-    ///
-    /// This is intended to be a debug option. The NFA is always slower than
-    /// the DFA.
-    #[doc(hidden)]
-    pub fn nfa(&mut self, yes: bool) -> &mut ReaderBuilder {
-        self.builder.nfa(yes);
+    pub fn record_boundary(
+        &mut self,
+        starts_record: Box<StartsRecord>,
+    ) -> &mut ReaderBuilder {
+        self.record_boundary = Some(RecordBoundary(Arc::from(starts_record)));
         self
     }
-}
 
-/// A already configured CSV reader.
-///
-/// A CSV reader takes as input CSV data and transforms that into standard Rust
-/// values. The most flexible way to read CSV data is as a sequence of records,
-/// where a record is a sequence of fields and each field is a string. However,
-/// a reader can also deserialize CSV data into Rust types like `i64` or
-/// `(String, f64, f64, f64)` or even a custom struct automatically using
-/// Serde.
-///
-/// # Configuration
-///
-/// A CSV reader has a couple convenient constructor methods like `from_path`
-/// and `from_reader`. However, if you want to configure the CSV reader to use
-/// a different delimiter or quote character (among many other things), then
-/// you should use a [`ReaderBuilder`](struct.ReaderBuilder.html) to construct
-/// a `Reader`. For example, to change the field delimiter:
-///
-/// ```
-/// use std::error::Error;
-/// use csv::ReaderBuilder;
-///
-/// # fn main() { example().unwrap(); }
-/// fn example() -> Result<(), Box<dyn Error>> {
-///     let data = "\
-/// city;country;pop
-/// Boston;United States;4628910
-/// ";
-///     let mut rdr = ReaderBuilder::new()
-///         .delimiter(b';')
-///         .from_reader(data.as_bytes());
-///
-///     if let Some(result) = rdr.records().next() {
-///         let record = result?;
-///         assert_eq!(record, vec!["Boston", "United States", "4628910"]);
-///         Ok(())
-///     } else {
-///         Err(From::from("expected at least one record but got none"))
-///     }
-/// }
-/// ```
-///
-/// # Error handling
-///
-/// In general, CSV *parsing* does not ever return an error. That is, there is
-/// no such thing as malformed CSV data. Instead, this reader will prioritize
-/// finding a parse over rejecting CSV data that it does not understand. This
-/// choice was inspired by other popular CSV parsers, but also because it is
-/// pragmatic. CSV data varies wildly, so even if the CSV data is malformed,
-/// it might still be possible to work with the data. In the land of CSV, there
-/// is no "right" or "wrong," only "right" and "less right."
-///
-/// With that said, a number of errors can occur while reading CSV data:
-///
-/// * By default, all records in CSV data must have the same number of fields.
-///   If a record is found with a different number of fields than a prior
-///   record, then an error is returned. This behavior can be disabled by
-///   enabling flexible parsing via the `flexible` method on
-///   [`ReaderBuilder`](struct.ReaderBuilder.html).
-/// * When reading CSV data from a resource (like a file), it is possible for
-///   reading from the underlying resource to fail. This will return an error.
-/// * When reading CSV data into `String` or `&str` fields (e.g., via a
-///   [`StringRecord`](struct.StringRecord.html)), UTF-8 is strictly
-///   enforced. If CSV data is invalid UTF-8, then an error is returned. If
-///   you want to read invalid UTF-8, then you should use the byte oriented
-///   APIs such as [`ByteRecord`](struct.ByteRecord.html). If you need explicit
-///   support for another encoding entirely, then you'll need to use another
-///   crate to transcode your CSV data to UTF-8 before parsing it.
-/// * When using Serde to deserialize CSV data into Rust types, it is possible
-///   for a number of additional errors to occur. For example, deserializing
-///   a field `xyz` into an `i32` field will result in an error.
-///
-/// For more details on the precise semantics of errors, see the
-/// [`Error`](enum.Error.html) type.
-#[derive(Debug)]
-pub struct Reader<R> {
-    /// The underlying CSV parser.
-    ///
-    /// We explicitly put this on the heap because CoreReader embeds an entire
-    /// DFA transition table, which along with other things, tallies up to
-    /// almost 500 bytes on the stack.
-    core: Box<CoreReader>,
-    /// The underlying reader.
-    rdr: io::BufReader<R>,
-    /// Various state tracking.
+    /// Whether fields are trimmed of leading and trailing whitespace or not.
     ///
-    /// There is more state embedded in the `CoreReader`.
-    state: ReaderState,
-}
-
-#[derive(Debug)]
-struct ReaderState {
-    /// When set, this contains the first row of any parsed CSV data.
+    /// By default, no trimming is performed. This method permits one to
+    /// override that behavior and choose one of the following options:
     ///
-    /// This is always populated, regardless of whether `has_headers` is set.
-    headers: Option<Headers>,
-    /// When set, the first row of parsed CSV data is excluded from things
-    /// that read records, like iterators and `read_record`.
-    has_headers: bool,
-    /// When set, there is no restriction on the length of records. When not
-    /// set, every record must have the same number of fields, or else an error
-    /// is reported.
-    flexible: bool,
-    trim: Trim,
-    /// The number of fields in the first record parsed.
-    first_field_count: Option<u64>,
-    /// The current position of the parser.
+    /// 1. `Trim::Headers` trims only header values.
+    /// 2. `Trim::Fields` trims only non-header or "field" values.
+    /// 3. `Trim::All` trims both header and non-header values.
     ///
-    /// Note that this position is only observable by callers at the start
-    /// of a record. More granular positions are not supported.
-    cur_pos: Position,
-    /// Whether the first record has been read or not.
-    first: bool,
-    /// Whether the reader has been seeked or not.
-    seeked: bool,
-    /// Whether EOF of the underlying reader has been reached or not.
-    eof: bool,
-}
-
-/// Headers encapsulates any data associated with the headers of CSV data.
-///
-/// The headers always correspond to the first row.
-#[derive(Debug)]
-struct Headers {
-    /// The header, as raw bytes.
-    byte_record: ByteRecord,
-    /// The header, as valid UTF-8 (or a UTF-8 error).
-    string_record: result::Result<StringRecord, Utf8Error>,
-}
-
-impl Reader<Reader<File>> {
-    /// Create a new CSV parser with a default configuration for the given
-    /// file path.
+    /// A value is only interpreted as a header value if this CSV reader is
+    /// configured to read a header record (which is the default).
     ///
-    /// To customize CSV parsing, use a `ReaderBuilder`.
+    /// When reading string records, characters meeting the definition of
+    /// Unicode whitespace are trimmed. When reading byte records, characters
+    /// meeting the definition of ASCII whitespace are trimmed. ASCII
+    /// whitespace characters correspond to the set `[\t\n\v\f\r ]`.
     ///
     /// # Example
     ///
-    /// ```no_run
+    /// This example shows what happens when all values are trimmed.
+    ///
+    /// ```
     /// use std::error::Error;
-    /// use csv::Reader;
+    /// use csv::{ReaderBuilder, StringRecord, Trim};
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
-    ///     let mut rdr = Reader::from_path("foo.csv")?;
-    ///     for result in rdr.records() {
-    ///         let record = result?;
-    ///         println!("{:?}", record);
-    ///     }
+    ///     let data = "\
+    /// city ,   country ,  pop
+    /// Boston,\"
+    ///    United States\",4628910
+    /// Concord,   United States   ,42695
+    /// ";
+    ///     let mut rdr = ReaderBuilder::new()
+    ///         .trim(Trim::All)
+    ///         .from_reader(data.as_bytes());
+    ///     let records = rdr
+    ///         .records()
+    ///         .collect::<Result<Vec<StringRecord>, csv::Error>>()?;
+    ///     assert_eq!(records, vec![
+    ///         vec!["Boston", "United States", "4628910"],
+    ///         vec!["Concord", "United States", "42695"],
+    ///     ]);
     ///     Ok(())
     /// }
     /// ```
-    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Reader<File>> {
-        ReaderBuilder::new().from_path(path)
-    }
-}
-
-impl<R: io::Read> Reader<R> {
-    /// Create a new CSV reader given a builder and a source of underlying
-    /// bytes.
-    fn new(builder: &ReaderBuilder, rdr: R) -> Reader<R> {
-        Reader {
-            core: Box::new(builder.builder.build()),
-            rdr: io::BufReader::with_capacity(builder.capacity, rdr),
-            state: ReaderState {
-                headers: None,
-                has_headers: builder.has_headers,
-                flexible: builder.flexible,
-                trim: builder.trim,
-                first_field_count: None,
-                cur_pos: Position::new(),
-                first: false,
-                seeked: false,
-                eof: false,
-            },
-        }
+    pub fn trim(&mut self, trim: Trim) -> &mut ReaderBuilder {
+        self.trim = trim;
+        self
     }
 
-    /// Create a new CSV parser with a default configuration for the given
-    /// reader.
+    /// The record terminator to use when parsing CSV.
     ///
-    /// To customize CSV parsing, use a `ReaderBuilder`.
+    /// A record terminator can be any single byte. The default is a special
+    /// value, `Terminator::CRLF`, which treats any occurrence of `\r`, `\n`
+    /// or `\r\n` as a single record terminator.
     ///
-    /// # Example
+    /// # Example: `$` as a record terminator
     ///
     /// ```
     /// use std::error::Error;
-    /// use csv::Reader;
+    /// use csv::{ReaderBuilder, Terminator};
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
-    ///     let data = "\
-    /// city,country,pop
-    /// Boston,United States,4628910
-    /// Concord,United States,42695
-    /// ";
-    ///     let mut rdr = Reader::from_reader(data.as_bytes());
-    ///     for result in rdr.records() {
+    ///     let data = "city,country,pop$Boston,United States,4628910";
+    ///     let mut rdr = ReaderBuilder::new()
+    ///         .terminator(Terminator::Any(b'$'))
+    ///         .from_reader(data.as_bytes());
+    ///
+    ///     if let Some(result) = rdr.records().next() {
     ///         let record = result?;
-    ///         println!("{:?}", record);
+    ///         assert_eq!(record, vec!["Boston", "United States", "4628910"]);
+    ///         Ok(())
+    ///     } else {
+    ///         Err(From::from("expected at least one record but got none"))
     ///     }
-    ///     Ok(())
     /// }
     /// ```
-    pub fn from_reader(rdr: R) -> Reader<R> {
-        ReaderBuilder::new().from_reader(rdr)
+    pub fn terminator(&mut self, term: Terminator) -> &mut ReaderBuilder {
+        self.builder.terminator(term.to_core());
+        self
     }
 
-    /// Returns a borrowed iterator over deserialized records.
-    ///
-    /// Each item yielded by this iterator is a `Result<D, Error>`.
-    /// Therefore, in order to access the record, callers must handle the
-    /// possibility of error (typically with `try!` or `?`).
+    /// The quote character to use when parsing CSV.
     ///
-    /// If `has_headers` was enabled via a `ReaderBuilder` (which is the
-    /// default), then this does not include the first record. Additionally,
-    /// if `has_headers` is enabled, then deserializing into a struct will
-    /// automatically align the values in each row to the fields of a struct
-    /// based on the header row.
+    /// The default is `b'"'`.
     ///
-    /// # Example
-    ///
-    /// This shows how to deserialize CSV data into normal Rust structs. The
-    /// fields of the header row are used to match up the values in each row
-    /// to the fields of the struct.
+    /// # Example: single quotes instead of double quotes
     ///
     /// ```
     /// use std::error::Error;
-    ///
-    /// use csv::Reader;
-    /// use serde::Deserialize;
-    ///
-    /// #[derive(Debug, Deserialize, Eq, PartialEq)]
-    /// struct Row {
-    ///     city: String,
-    ///     country: String,
-    ///     #[serde(rename = "popcount")]
-    ///     population: u64,
-    /// }
+    /// use csv::ReaderBuilder;
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
     ///     let data = "\
-    /// city,country,popcount
-    /// Boston,United States,4628910
+    /// city,country,pop
+    /// Boston,'United States',4628910
     /// ";
-    ///     let mut rdr = Reader::from_reader(data.as_bytes());
-    ///     let mut iter = rdr.deserialize();
+    ///     let mut rdr = ReaderBuilder::new()
+    ///         .quote(b'\'')
+    ///         .from_reader(data.as_bytes());
     ///
-    ///     if let Some(result) = iter.next() {
-    ///         let record: Row = result?;
-    ///         assert_eq!(record, Row {
-    ///             city: "Boston".to_string(),
-    ///             country: "United States".to_string(),
-    ///             population: 4628910,
-    ///         });
+    ///     if let Some(result) = rdr.records().next() {
+    ///         let record = result?;
+    ///         assert_eq!(record, vec!["Boston", "United States", "4628910"]);
     ///         Ok(())
     ///     } else {
     ///         Err(From::from("expected at least one record but got none"))
     ///     }
     /// }
     /// ```
+    pub fn quote(&mut self, quote: u8) -> &mut ReaderBuilder {
+        self.builder.quote(quote);
+        self
+    }
+
+    /// The escape character to use when parsing CSV.
     ///
-    /// # Rules
+    /// In some variants of CSV, quotes are escaped using a special escape
+    /// character like `\` (instead of escaping quotes by doubling them).
     ///
-    /// For the most part, any Rust type that maps straight-forwardly to a CSV
-    /// record is supported. This includes maps, structs, tuples and tuple
-    /// structs. Other Rust types, such as `Vec`s, arrays, and enums have
-    /// a more complicated story. In general, when working with CSV data, one
-    /// should avoid *nested sequences* as much as possible.
+    /// Inside a quoted field, the escape character makes the byte following
+    /// it literal, whatever that byte is, as in most implementations. So with
+    /// `\` as the escape character, `"abc\\"` is the field `abc\`, closed by
+    /// its last quote, while `"abc\""` is the field `abc"`. In `"abc\"`, the
+    /// second quote is escaped, so the field doesn't end there, and if the
+    /// data ends before another closing quote, an `UnterminatedQuote` error
+    /// is returned.
     ///
-    /// Maps, structs, tuples and tuple structs map to CSV records in a simple
-    /// way. Tuples and tuple structs decode their fields in the order that
-    /// they are defined. Structs will do the same only if `has_headers` has
-    /// been disabled using [`ReaderBuilder`](struct.ReaderBuilder.html),
-    /// otherwise, structs and maps are deserialized based on the fields
-    /// defined in the header row. (If there is no header row, then
-    /// deserializing into a map will result in an error.)
+    /// By default, recognizing these idiosyncratic escapes is disabled.
     ///
-    /// Nested sequences are supported in a limited capacity. Namely, they
-    /// are flattened. As a result, it's often useful to use a `Vec` to capture
-    /// a "tail" of fields in a record:
+    /// # Example
     ///
     /// ```
     /// use std::error::Error;
-    ///
     /// use csv::ReaderBuilder;
-    /// use serde::Deserialize;
-    ///
-    /// #[derive(Debug, Deserialize, Eq, PartialEq)]
-    /// struct Row {
-    ///     label: String,
-    ///     values: Vec<i32>,
-    /// }
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
-    ///     let data = "foo,1,2,3";
+    ///     let data = "\
+    /// city,country,pop
+    /// Boston,\"The \\\"United\\\" States\",4628910
+    /// ";
     ///     let mut rdr = ReaderBuilder::new()
-    ///         .has_headers(false)
+    ///         .escape(Some(b'\\'))
     ///         .from_reader(data.as_bytes());
-    ///     let mut iter = rdr.deserialize();
     ///
-    ///     if let Some(result) = iter.next() {
-    ///         let record: Row = result?;
-    ///         assert_eq!(record, Row {
-    ///             label: "foo".to_string(),
-    ///             values: vec![1, 2, 3],
-    ///         });
+    ///     if let Some(result) = rdr.records().next() {
+    ///         let record = result?;
+    ///         assert_eq!(record, vec![
+    ///             "Boston", "The \"United\" States", "4628910",
+    ///         ]);
     ///         Ok(())
     ///     } else {
     ///         Err(From::from("expected at least one record but got none"))
     ///     }
     /// }
     /// ```
+    pub fn escape(&mut self, escape: Option<u8>) -> &mut ReaderBuilder {
+        self.builder.escape(escape);
+        self
+    }
+
+    /// Recognize the escape character in unquoted fields.
     ///
-    /// In the above example, adding another field to the `Row` struct after
-    /// the `values` field will result in a deserialization error. This is
-    /// because the deserializer doesn't know when to stop reading fields
-    /// into the `values` vector, so it will consume the rest of the fields in
-    /// the record leaving none left over for the additional field.
+    /// When enabled, the escape character set with `escape` may also appear
+    /// outside of quotes. The byte following it is then taken literally,
+    /// even if it is a delimiter, a quote or a record terminator, and the
+    /// escape character itself is dropped. This has no effect unless an
+    /// escape character is set.
     ///
-    /// Finally, simple enums in Rust can be deserialized as well. Namely,
-    /// enums must either be variants with no arguments or variants with a
-    /// single argument. Variants with no arguments are deserialized based on
-    /// which variant name the field matches. Variants with a single argument
-    /// are deserialized based on which variant can store the data. The latter
-    /// is only supported when using "untagged" enum deserialization. The
-    /// following example shows both forms in action:
+    /// This is disabled by default, since RFC 4180 doesn't permit escapes
+    /// outside of quoted fields.
+    ///
+    /// # Example
     ///
     /// ```
     /// use std::error::Error;
-    ///
-    /// use csv::Reader;
-    /// use serde::Deserialize;
-    ///
-    /// #[derive(Debug, Deserialize, PartialEq)]
-    /// struct Row {
-    ///     label: Label,
-    ///     value: Number,
-    /// }
-    ///
-    /// #[derive(Debug, Deserialize, PartialEq)]
-    /// #[serde(rename_all = "lowercase")]
-    /// enum Label {
-    ///     Celsius,
-    ///     Fahrenheit,
-    /// }
-    ///
-    /// #[derive(Debug, Deserialize, PartialEq)]
-    /// #[serde(untagged)]
-    /// enum Number {
-    ///     Integer(i64),
-    ///     Float(f64),
-    /// }
+    /// use csv::ReaderBuilder;
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
-    ///     let data = "\
-    /// label,value
-    /// celsius,22.2222
-    /// fahrenheit,72
-    /// ";
-    ///     let mut rdr = Reader::from_reader(data.as_bytes());
-    ///     let mut iter = rdr.deserialize();
-    ///
-    ///     // Read the first record.
-    ///     if let Some(result) = iter.next() {
-    ///         let record: Row = result?;
-    ///         assert_eq!(record, Row {
-    ///             label: Label::Celsius,
-    ///             value: Number::Float(22.2222),
-    ///         });
-    ///     } else {
-    ///         return Err(From::from(
-    ///             "expected at least two records but got none"));
-    ///     }
+    ///     let data = "a\\,b,c\n";
+    ///     let mut rdr = ReaderBuilder::new()
+    ///         .has_headers(false)
+    ///         .escape(Some(b'\\'))
+    ///         .escape_unquoted(true)
+    ///         .from_reader(data.as_bytes());
     ///
-    ///     // Read the second record.
-    ///     if let Some(result) = iter.next() {
-    ///         let record: Row = result?;
-    ///         assert_eq!(record, Row {
-    ///             label: Label::Fahrenheit,
-    ///             value: Number::Integer(72),
-    ///         });
+    ///     if let Some(result) = rdr.records().next() {
+    ///         let record = result?;
+    ///         assert_eq!(record, vec!["a,b", "c"]);
     ///         Ok(())
     ///     } else {
-    ///         Err(From::from(
-    ///             "expected at least two records but got only one"))
+    ///         Err(From::from("expected at least one record but got none"))
     ///     }
     /// }
     /// ```
-    pub fn deserialize<D>(&mut self) -> DeserializeRecordsIter<R, D>
-    where
-        D: DeserializeOwned,
-    {
-        DeserializeRecordsIter::new(self)
+    pub fn escape_unquoted(&mut self, yes: bool) -> &mut ReaderBuilder {
+        self.builder.escape_unquoted(yes);
+        self
     }
 
-    /// Returns an owned iterator over deserialized records.
-    ///
-    /// Each item yielded by this iterator is a `Result<D, Error>`.
-    /// Therefore, in order to access the record, callers must handle the
-    /// possibility of error (typically with `try!` or `?`).
-    ///
-    /// This is mostly useful when you want to return a CSV iterator or store
-    /// it somewhere.
-    ///
-    /// If `has_headers` was enabled via a `ReaderBuilder` (which is the
-    /// default), then this does not include the first record. Additionally,
-    /// if `has_headers` is enabled, then deserializing into a struct will
-    /// automatically align the values in each row to the fields of a struct
-    /// based on the header row.
+    /// Enable double quote escapes.
     ///
-    /// For more detailed deserialization rules, see the documentation on the
-    /// `deserialize` method.
+    /// This is enabled by default, but it may be disabled. When disabled,
+    /// doubled quotes are not interpreted as escapes.
     ///
     /// # Example
     ///
     /// ```
     /// use std::error::Error;
-    ///
-    /// use csv::Reader;
-    /// use serde::Deserialize;
-    ///
-    /// #[derive(Debug, Deserialize, Eq, PartialEq)]
-    /// struct Row {
-    ///     city: String,
-    ///     country: String,
-    ///     #[serde(rename = "popcount")]
-    ///     population: u64,
-    /// }
+    /// use csv::ReaderBuilder;
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
     ///     let data = "\
-    /// city,country,popcount
-    /// Boston,United States,4628910
+    /// city,country,pop
+    /// Boston,\"The \"\"United\"\" States\",4628910
     /// ";
-    ///     let rdr = Reader::from_reader(data.as_bytes());
-    ///     let mut iter = rdr.into_deserialize();
+    ///     let mut rdr = ReaderBuilder::new()
+    ///         .double_quote(false)
+    ///         .from_reader(data.as_bytes());
     ///
-    ///     if let Some(result) = iter.next() {
-    ///         let record: Row = result?;
-    ///         assert_eq!(record, Row {
-    ///             city: "Boston".to_string(),
-    ///             country: "United States".to_string(),
-    ///             population: 4628910,
-    ///         });
+    ///     if let Some(result) = rdr.records().next() {
+    ///         let record = result?;
+    ///         assert_eq!(record, vec![
+    ///             "Boston", "The \"United\"\" States\"", "4628910",
+    ///         ]);
     ///         Ok(())
     ///     } else {
     ///         Err(From::from("expected at least one record but got none"))
     ///     }
     /// }
     /// ```
-    pub fn into_deserialize<D>(self) -> DeserializeRecordsIntoIter<R, D>
-    where
-        D: DeserializeOwned,
-    {
-        DeserializeRecordsIntoIter::new(self)
+    pub fn double_quote(&mut self, yes: bool) -> &mut ReaderBuilder {
+        self.builder.double_quote(yes);
+        self
     }
 
-    /// Returns a borrowed iterator over all records as strings.
-    ///
-    /// Each item yielded by this iterator is a `Result<StringRecord, Error>`.
-    /// Therefore, in order to access the record, callers must handle the
-    /// possibility of error (typically with `try!` or `?`).
+    /// Enable or disable quoting.
     ///
-    /// If `has_headers` was enabled via a `ReaderBuilder` (which is the
-    /// default), then this does not include the first record.
+    /// This is enabled by default, but it may be disabled. When disabled,
+    /// quotes are not treated specially: fields are split only on delimiters
+    /// and record terminators, and any quote characters are kept as part of
+    /// the field. This suits formats that never quote fields, such as many
+    /// TSV files.
     ///
     /// # Example
     ///
     /// ```
     /// use std::error::Error;
-    /// use csv::Reader;
+    /// use csv::ReaderBuilder;
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
     ///     let data = "\
     /// city,country,pop
-    /// Boston,United States,4628910
+    /// Boston,\"The United States,4628910
     /// ";
-    ///     let mut rdr = Reader::from_reader(data.as_bytes());
-    ///     let mut iter = rdr.records();
+    ///     let mut rdr = ReaderBuilder::new()
+    ///         .quoting(false)
+    ///         .from_reader(data.as_bytes());
     ///
-    ///     if let Some(result) = iter.next() {
+    ///     if let Some(result) = rdr.records().next() {
     ///         let record = result?;
-    ///         assert_eq!(record, vec!["Boston", "United States", "4628910"]);
+    ///         assert_eq!(record, vec![
+    ///             "Boston", "\"The United States", "4628910",
+    ///         ]);
     ///         Ok(())
     ///     } else {
     ///         Err(From::from("expected at least one record but got none"))
     ///     }
     /// }
     /// ```
-    pub fn records(&mut self) -> StringRecordsIter<R> {
-        StringRecordsIter::new(self)
+    pub fn quoting(&mut self, yes: bool) -> &mut ReaderBuilder {
+        self.builder.quoting(yes);
+        self
     }
 
-    /// Returns an owned iterator over all records as strings.
-    ///
-    /// Each item yielded by this iterator is a `Result<StringRecord, Error>`.
-    /// Therefore, in order to access the record, callers must handle the
-    /// possibility of error (typically with `try!` or `?`).
+    /// Read every line as a record with a single field.
     ///
-    /// This is mostly useful when you want to return a CSV iterator or store
-    /// it somewhere.
+    /// When enabled, the delimiter (including one set by `multi_delimiter`)
+    /// is not treated specially, so every record consists of one field that
+    /// spans the whole line. This is useful for reading plain lines, such as
+    /// log lines, while keeping the position tracking and other machinery of
+    /// a CSV reader. Quotes are still respected unless `quoting` is
+    /// disabled, in which case a field ends at the first record terminator.
     ///
-    /// If `has_headers` was enabled via a `ReaderBuilder` (which is the
-    /// default), then this does not include the first record.
+    /// This is disabled by default.
     ///
     /// # Example
     ///
     /// ```
     /// use std::error::Error;
-    /// use csv::Reader;
+    /// use csv::ReaderBuilder;
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
     ///     let data = "\
-    /// city,country,pop
-    /// Boston,United States,4628910
+    /// GET /index.html, 200
+    /// GET /missing.html, 404
     /// ";
-    ///     let rdr = Reader::from_reader(data.as_bytes());
-    ///     let mut iter = rdr.into_records();
+    ///     let mut rdr = ReaderBuilder::new()
+    ///         .has_headers(false)
+    ///         .single_field(true)
+    ///         .from_reader(data.as_bytes());
     ///
-    ///     if let Some(result) = iter.next() {
+    ///     let mut lines = vec![];
+    ///     for result in rdr.records() {
     ///         let record = result?;
-    ///         assert_eq!(record, vec!["Boston", "United States", "4628910"]);
-    ///         Ok(())
-    ///     } else {
-    ///         Err(From::from("expected at least one record but got none"))
+    ///         assert_eq!(record.len(), 1);
+    ///         lines.push(record[0].to_string());
     ///     }
+    ///     assert_eq!(lines, vec!["GET /index.html, 200", "GET /missing.html, 404"]);
+    ///     Ok(())
     /// }
     /// ```
-    pub fn into_records(self) -> StringRecordsIntoIter<R> {
-        StringRecordsIntoIter::new(self)
+    pub fn single_field(&mut self, yes: bool) -> &mut ReaderBuilder {
+        self.builder.single_field(yes);
+        self.single_field = yes;
+        self
     }
 
-    /// Returns a borrowed iterator over all records as raw bytes.
+    /// Enable or disable strict handling of quotes in unquoted fields.
     ///
-    /// Each item yielded by this iterator is a `Result<ByteRecord, Error>`.
-    /// Therefore, in order to access the record, callers must handle the
-    /// possibility of error (typically with `try!` or `?`).
+    /// By default, a quote that appears inside an unquoted field, as in
+    /// `a"b`, is accepted as a literal quote. Such data is technically
+    /// malformed according to RFC 4180. When this is enabled, reading a
+    /// record containing such a field returns an
+    /// `ErrorKind::UnescapedQuote` error instead. After the error is
+    /// returned, reading may continue with the next record.
     ///
-    /// If `has_headers` was enabled via a `ReaderBuilder` (which is the
-    /// default), then this does not include the first record.
+    /// This is disabled by default, and has no effect when `quoting` is
+    /// disabled.
     ///
     /// # Example
     ///
     /// ```
     /// use std::error::Error;
-    /// use csv::Reader;
+    /// use csv::{ErrorKind, ReaderBuilder};
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
     ///     let data = "\
     /// city,country,pop
-    /// Boston,United States,4628910
+    /// Boston,The \"United\" States,4628910
     /// ";
-    ///     let mut rdr = Reader::from_reader(data.as_bytes());
-    ///     let mut iter = rdr.byte_records();
+    ///     let mut rdr = ReaderBuilder::new()
+    ///         .strict_quotes(true)
+    ///         .from_reader(data.as_bytes());
     ///
-    ///     if let Some(result) = iter.next() {
-    ///         let record = result?;
-    ///         assert_eq!(record, vec!["Boston", "United States", "4628910"]);
-    ///         Ok(())
+    ///     if let Some(result) = rdr.records().next() {
+    ///         match *result.unwrap_err().kind() {
+    ///             ErrorKind::UnescapedQuote { field, .. } => {
+    ///                 assert_eq!(field, 1);
+    ///                 Ok(())
+    ///             }
+    ///             ref wrong => {
+    ///                 Err(From::from(format!("unexpected error: {:?}", wrong)))
+    ///             }
+    ///         }
     ///     } else {
     ///         Err(From::from("expected at least one record but got none"))
     ///     }
     /// }
     /// ```
-    pub fn byte_records(&mut self) -> ByteRecordsIter<R> {
-        ByteRecordsIter::new(self)
+    pub fn strict_quotes(&mut self, yes: bool) -> &mut ReaderBuilder {
+        self.builder.strict_quotes(yes);
+        self
     }
 
-    /// Returns an owned iterator over all records as raw bytes.
+    /// Enable or disable lenient handling of quotes in quoted fields.
     ///
-    /// Each item yielded by this iterator is a `Result<ByteRecord, Error>`.
-    /// Therefore, in order to access the record, callers must handle the
-    /// possibility of error (typically with `try!` or `?`).
+    /// Some data contains quotes inside quoted fields that aren't escaped,
+    /// as in `"foo "bar" baz"`. By default, the second quote ends the quoted
+    /// part of the field, which yields `foo bar" baz"`. When this is
+    /// enabled, a quote inside a quoted field is instead read as a literal
+    /// quote, unless it is followed by a delimiter, a record terminator or
+    /// the end of the data. This yields `foo "bar" baz` for the example
+    /// above. Doubled quotes are still read as a single quote.
     ///
-    /// This is mostly useful when you want to return a CSV iterator or store
-    /// it somewhere.
+    /// This is a best-effort recovery mode for malformed data, and its
+    /// results can be ambiguous: `"foo "bar", baz"` is still read as the two
+    /// fields `foo "bar` and ` baz"`, since the quote after `bar` is followed
+    /// by a delimiter. When the delimiter is longer than a single byte, a
+    /// quote followed by the first byte of the delimiter ends the quoted
+    /// part of a field.
     ///
-    /// If `has_headers` was enabled via a `ReaderBuilder` (which is the
-    /// default), then this does not include the first record.
+    /// Parsing with lenient quotes is slower than parsing without them. This
+    /// is disabled by default, and has no effect when `quoting` is disabled.
     ///
     /// # Example
     ///
     /// ```
     /// use std::error::Error;
-    /// use csv::Reader;
+    /// use csv::ReaderBuilder;
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
     ///     let data = "\
     /// city,country,pop
-    /// Boston,United States,4628910
+    /// Boston,\"The \"United\" States\",4628910
     /// ";
-    ///     let rdr = Reader::from_reader(data.as_bytes());
-    ///     let mut iter = rdr.into_byte_records();
+    ///     let mut rdr = ReaderBuilder::new()
+    ///         .lenient_quotes(true)
+    ///         .from_reader(data.as_bytes());
     ///
-    ///     if let Some(result) = iter.next() {
+    ///     if let Some(result) = rdr.records().next() {
     ///         let record = result?;
-    ///         assert_eq!(record, vec!["Boston", "United States", "4628910"]);
+    ///         assert_eq!(record, vec!["Boston", "The \"United\" States", "4628910"]);
     ///         Ok(())
     ///     } else {
     ///         Err(From::from("expected at least one record but got none"))
     ///     }
     /// }
     /// ```
-    pub fn into_byte_records(self) -> ByteRecordsIntoIter<R> {
-        ByteRecordsIntoIter::new(self)
+    pub fn lenient_quotes(&mut self, yes: bool) -> &mut ReaderBuilder {
+        self.lenient_quotes = yes;
+        self
     }
 
-    /// Returns a reference to the first row read by this parser.
-    ///
-    /// If no row has been read yet, then this will force parsing of the first
-    /// row.
-    ///
-    /// If there was a problem parsing the row or if it wasn't valid UTF-8,
-    /// then this returns an error.
+    /// The comment character to use when parsing CSV.
     ///
-    /// If the underlying reader emits EOF before any data, then this returns
-    /// an empty record.
+    /// If the start of a record begins with the byte given here, then that
+    /// line is ignored by the CSV parser.
     ///
-    /// Note that this method may be used regardless of whether `has_headers`
-    /// was enabled (but it is enabled by default).
+    /// This is disabled by default.
     ///
     /// # Example
     ///
-    /// This example shows how to get the header row of CSV data. Notice that
-    /// the header row does not appear as a record in the iterator!
-    ///
     /// ```
     /// use std::error::Error;
-    /// use csv::Reader;
+    /// use csv::ReaderBuilder;
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
     ///     let data = "\
     /// city,country,pop
+    /// #Concord,United States,42695
     /// Boston,United States,4628910
     /// ";
-    ///     let mut rdr = Reader::from_reader(data.as_bytes());
-    ///
-    ///     // We can read the headers before iterating.
-    ///     {
-    ///         // `headers` borrows from the reader, so we put this in its
-    ///         // own scope. That way, the borrow ends before we try iterating
-    ///         // below. Alternatively, we could clone the headers.
-    ///         let headers = rdr.headers()?;
-    ///         assert_eq!(headers, vec!["city", "country", "pop"]);
-    ///     }
+    ///     let mut rdr = ReaderBuilder::new()
+    ///         .comment(Some(b'#'))
+    ///         .from_reader(data.as_bytes());
     ///
     ///     if let Some(result) = rdr.records().next() {
     ///         let record = result?;
     ///         assert_eq!(record, vec!["Boston", "United States", "4628910"]);
+    ///         Ok(())
     ///     } else {
-    ///         return Err(From::from(
-    ///             "expected at least one record but got none"))
+    ///         Err(From::from("expected at least one record but got none"))
     ///     }
-    ///
-    ///     // We can also read the headers after iterating.
-    ///     let headers = rdr.headers()?;
-    ///     assert_eq!(headers, vec!["city", "country", "pop"]);
-    ///     Ok(())
     /// }
     /// ```
-    pub fn headers(&mut self) -> Result<&StringRecord> {
-        if self.state.headers.is_none() {
-            let mut record = ByteRecord::new();
-            self.read_byte_record_impl(&mut record)?;
-            self.set_headers_impl(Err(record));
-        }
-        let headers = self.state.headers.as_ref().unwrap();
-        match headers.string_record {
-            Ok(ref record) => Ok(record),
-            Err(ref err) => Err(Error::new(ErrorKind::Utf8 {
-                pos: headers.byte_record.position().map(Clone::clone),
-                err: err.clone(),
-            })),
-        }
+    pub fn comment(&mut self, comment: Option<u8>) -> &mut ReaderBuilder {
+        self.builder.comment(comment);
+        self
     }
 
-    /// Returns a reference to the first row read by this parser as raw bytes.
+    /// Configure this reader for the given dialect of CSV.
     ///
-    /// If no row has been read yet, then this will force parsing of the first
-    /// row.
+    /// This sets the delimiter, quote, escape, `double_quote`, record
+    /// terminator and trimming so that data written by a writer configured
+    /// with `WriterBuilder::dialect` and the same dialect reads back as it
+    /// was written. Any of these options can still be overridden by calling
+    /// its setter after this one.
     ///
-    /// If there was a problem parsing the row then this returns an error.
+    /// Since a reader already accepts `\n`, `\r\n` and quoted fields in
+    /// every dialect, the dialects currently all configure a reader the same
+    /// way.
     ///
-    /// If the underlying reader emits EOF before any data, then this returns
-    /// an empty record.
+    /// # Example
     ///
-    /// Note that this method may be used regardless of whether `has_headers`
-    /// was enabled (but it is enabled by default).
+    /// ```
+    /// use std::error::Error;
+    /// use csv::{Dialect, ReaderBuilder};
     ///
-    /// # Example
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\"city\",\"pop\"\r\n\"Boston\",\"4628910\"\r\n";
+    ///     let mut rdr = ReaderBuilder::new()
+    ///         .dialect(Dialect::Excel)
+    ///         .from_reader(data.as_bytes());
+    ///     assert_eq!(rdr.headers()?, vec!["city", "pop"]);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn dialect(&mut self, dialect: Dialect) -> &mut ReaderBuilder {
+        match dialect {
+            Dialect::Rfc4180
+            | Dialect::Excel
+            | Dialect::Unix
+            | Dialect::PostgresCopy => self
+                .delimiter(b',')
+                .quote(b'"')
+                .escape(None)
+                .double_quote(true)
+                .quoting(true)
+                .terminator(Terminator::CRLF)
+                .trim(Trim::None),
+            _ => unreachable!(),
+        }
+    }
+
+    /// A convenience method for specifying a configuration to read ASCII
+    /// delimited text.
     ///
-    /// This example shows how to get the header row of CSV data. Notice that
-    /// the header row does not appear as a record in the iterator!
+    /// This sets the delimiter and record terminator to the ASCII unit
+    /// separator (`\x1F`) and record separator (`\x1E`), respectively.
+    ///
+    /// # Example
     ///
     /// ```
     /// use std::error::Error;
-    /// use csv::Reader;
+    /// use csv::ReaderBuilder;
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
     ///     let data = "\
-    /// city,country,pop
-    /// Boston,United States,4628910
-    /// ";
-    ///     let mut rdr = Reader::from_reader(data.as_bytes());
-    ///
-    ///     // We can read the headers before iterating.
-    ///     {
-    ///         // `headers` borrows from the reader, so we put this in its
-    ///         // own scope. That way, the borrow ends before we try iterating
-    ///         // below. Alternatively, we could clone the headers.
-    ///         let headers = rdr.byte_headers()?;
-    ///         assert_eq!(headers, vec!["city", "country", "pop"]);
-    ///     }
+    /// city\x1Fcountry\x1Fpop\x1EBoston\x1FUnited States\x1F4628910";
+    ///     let mut rdr = ReaderBuilder::new()
+    ///         .ascii()
+    ///         .from_reader(data.as_bytes());
     ///
-    ///     if let Some(result) = rdr.byte_records().next() {
+    ///     if let Some(result) = rdr.records().next() {
     ///         let record = result?;
     ///         assert_eq!(record, vec!["Boston", "United States", "4628910"]);
+    ///         Ok(())
     ///     } else {
-    ///         return Err(From::from(
-    ///             "expected at least one record but got none"))
+    ///         Err(From::from("expected at least one record but got none"))
     ///     }
-    ///
-    ///     // We can also read the headers after iterating.
-    ///     let headers = rdr.byte_headers()?;
-    ///     assert_eq!(headers, vec!["city", "country", "pop"]);
-    ///     Ok(())
     /// }
     /// ```
-    pub fn byte_headers(&mut self) -> Result<&ByteRecord> {
-        if self.state.headers.is_none() {
-            let mut record = ByteRecord::new();
-            self.read_byte_record_impl(&mut record)?;
-            self.set_headers_impl(Err(record));
-        }
-        Ok(&self.state.headers.as_ref().unwrap().byte_record)
+    pub fn ascii(&mut self) -> &mut ReaderBuilder {
+        self.builder.ascii();
+        self
     }
 
-    /// Set the headers of this CSV parser manually.
+    /// The strings to interpret as `true` and `false` when deserializing
+    /// `bool` values with Serde.
     ///
-    /// This overrides any other setting (including `set_byte_headers`). Any
-    /// automatic detection of headers is disabled. This may be called at any
-    /// time.
+    /// By default, only `true` and `false` are accepted. When this is set,
+    /// only `true_str` and `false_str` are accepted instead, and any other
+    /// value results in a deserialization error. This only applies to the
+    /// `deserialize` and `into_deserialize` iterators.
+    ///
+    /// This is the counterpart to `WriterBuilder::bool_format`.
     ///
     /// # Example
     ///
     /// ```
     /// use std::error::Error;
-    /// use csv::{Reader, StringRecord};
+    ///
+    /// use csv::ReaderBuilder;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Debug, Deserialize, Eq, PartialEq)]
+    /// struct Row {
+    ///     city: String,
+    ///     capital: bool,
+    /// }
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
     ///     let data = "\
-    /// city,country,pop
-    /// Boston,United States,4628910
+    /// city,capital
+    /// Boston,Y
+    /// Concord,N
     /// ";
-    ///     let mut rdr = Reader::from_reader(data.as_bytes());
-    ///
-    ///     assert_eq!(rdr.headers()?, vec!["city", "country", "pop"]);
-    ///     rdr.set_headers(StringRecord::from(vec!["a", "b", "c"]));
-    ///     assert_eq!(rdr.headers()?, vec!["a", "b", "c"]);
-    ///
+    ///     let mut rdr = ReaderBuilder::new()
+    ///         .bool_format("Y", "N")
+    ///         .from_reader(data.as_bytes());
+    ///     let rows = rdr.deserialize().collect::<Result<Vec<Row>, _>>()?;
+    ///     assert_eq!(rows, vec![
+    ///         Row { city: "Boston".to_string(), capital: true },
+    ///         Row { city: "Concord".to_string(), capital: false },
+    ///     ]);
     ///     Ok(())
     /// }
     /// ```
-    pub fn set_headers(&mut self, headers: StringRecord) {
-        self.set_headers_impl(Ok(headers));
+    pub fn bool_format(
+        &mut self,
+        true_str: &str,
+        false_str: &str,
+    ) -> &mut ReaderBuilder {
+        self.deserialize.bool_format =
+            Some((true_str.to_string(), false_str.to_string()));
+        self
     }
 
-    /// Set the headers of this CSV parser manually as raw bytes.
+    /// Match header names to struct field names case insensitively when
+    /// deserializing with Serde.
     ///
-    /// This overrides any other setting (including `set_headers`). Any
-    /// automatic detection of headers is disabled. This may be called at any
-    /// time.
+    /// When enabled, each header name is converted to lowercase before it is
+    /// matched against the names of struct fields (or used as a map key), so
+    /// that a header like `User_ID` matches a field named `user_id`. Since
+    /// only the header names are converted, this requires that the field
+    /// names (after any `serde(rename)`) are themselves lowercase.
+    ///
+    /// This only applies to the `deserialize` and `into_deserialize`
+    /// iterators. The header names returned by `headers` and `byte_headers`
+    /// are not changed.
+    ///
+    /// This is disabled by default.
     ///
     /// # Example
     ///
     /// ```
     /// use std::error::Error;
-    /// use csv::{Reader, ByteRecord};
+    ///
+    /// use csv::ReaderBuilder;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Debug, Deserialize, Eq, PartialEq)]
+    /// struct Row {
+    ///     user_id: u64,
+    ///     name: String,
+    /// }
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
     ///     let data = "\
-    /// city,country,pop
-    /// Boston,United States,4628910
+    /// User_ID,NAME
+    /// 7,Alice
     /// ";
-    ///     let mut rdr = Reader::from_reader(data.as_bytes());
-    ///
-    ///     assert_eq!(rdr.byte_headers()?, vec!["city", "country", "pop"]);
-    ///     rdr.set_byte_headers(ByteRecord::from(vec!["a", "b", "c"]));
-    ///     assert_eq!(rdr.byte_headers()?, vec!["a", "b", "c"]);
-    ///
+    ///     let mut rdr = ReaderBuilder::new()
+    ///         .case_insensitive_headers(true)
+    ///         .from_reader(data.as_bytes());
+    ///     let rows = rdr.deserialize().collect::<Result<Vec<Row>, _>>()?;
+    ///     assert_eq!(rows, vec![Row { user_id: 7, name: "Alice".to_string() }]);
+    ///     assert_eq!(rdr.headers()?, vec!["User_ID", "NAME"]);
     ///     Ok(())
     /// }
     /// ```
-    pub fn set_byte_headers(&mut self, headers: ByteRecord) {
-        self.set_headers_impl(Err(headers));
-    }
-
-    fn set_headers_impl(
+    pub fn case_insensitive_headers(
         &mut self,
-        headers: result::Result<StringRecord, ByteRecord>,
-    ) {
-        // If we have string headers, then get byte headers. But if we have
-        // byte headers, then get the string headers (or a UTF-8 error).
-        let (mut str_headers, mut byte_headers) = match headers {
-            Ok(string) => {
-                let bytes = string.clone().into_byte_record();
-                (Ok(string), bytes)
-            }
-            Err(bytes) => {
-                match StringRecord::from_byte_record(bytes.clone()) {
-                    Ok(str_headers) => (Ok(str_headers), bytes),
-                    Err(err) => (Err(err.utf8_error().clone()), bytes),
-                }
-            }
-        };
-        if self.state.trim.should_trim_headers() {
-            if let Ok(ref mut str_headers) = str_headers.as_mut() {
-                str_headers.trim();
-            }
-            byte_headers.trim();
-        }
-        self.state.headers = Some(Headers {
-            byte_record: byte_headers,
-            string_record: str_headers,
-        });
+        yes: bool,
+    ) -> &mut ReaderBuilder {
+        self.deserialize.case_insensitive_headers = yes;
+        self
     }
 
-    /// Read a single row into the given record. Returns false when no more
-    /// records could be read.
-    ///
-    /// If `has_headers` was enabled via a `ReaderBuilder` (which is the
-    /// default), then this will never read the first record.
+    /// The format of numbers when deserializing integers and floats with
+    /// Serde.
     ///
-    /// This method is useful when you want to read records as fast as
-    /// as possible. It's less ergonomic than an iterator, but it permits the
-    /// caller to reuse the `StringRecord` allocation, which usually results
-    /// in higher throughput.
+    /// By default, numbers are parsed with `str::parse`, so they may not
+    /// contain thousands separators and must use `.` as the decimal point.
+    /// When this is set, every occurrence of the format's thousands separator
+    /// is removed from a field, and its decimal point is interpreted as `.`,
+    /// before the field is parsed as a number. Note that a field containing
+    /// the delimiter, such as `1,234` in comma separated data, must be
+    /// quoted.
     ///
-    /// Records read via this method are guaranteed to have a position set
-    /// on them, even if the reader is at EOF or if an error is returned.
+    /// This only applies to the `deserialize` and `into_deserialize`
+    /// iterators, and only to fields deserialized as integers or floats.
     ///
     /// # Example
     ///
     /// ```
     /// use std::error::Error;
-    /// use csv::{Reader, StringRecord};
+    ///
+    /// use csv::{NumberFormat, ReaderBuilder};
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq)]
+    /// struct Row {
+    ///     city: String,
+    ///     pop: u64,
+    ///     area: f64,
+    /// }
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
     ///     let data = "\
-    /// city,country,pop
-    /// Boston,United States,4628910
+    /// city,pop,area
+    /// Boston,\"4,628,910\",\"1,264.5\"
     /// ";
-    ///     let mut rdr = Reader::from_reader(data.as_bytes());
-    ///     let mut record = StringRecord::new();
-    ///
-    ///     if rdr.read_record(&mut record)? {
-    ///         assert_eq!(record, vec!["Boston", "United States", "4628910"]);
-    ///         Ok(())
-    ///     } else {
-    ///         Err(From::from("expected at least one record but got none"))
-    ///     }
+    ///     let mut rdr = ReaderBuilder::new()
+    ///         .number_format(NumberFormat::new(Some(','), '.'))
+    ///         .from_reader(data.as_bytes());
+    ///     let rows = rdr.deserialize().collect::<Result<Vec<Row>, _>>()?;
+    ///     assert_eq!(rows, vec![
+    ///         Row { city: "Boston".to_string(), pop: 4628910, area: 1264.5 },
+    ///     ]);
+    ///     Ok(())
     /// }
     /// ```
-    pub fn read_record(&mut self, record: &mut StringRecord) -> Result<bool> {
-        let result = record.read(self);
-        // We need to trim again because trimming string records includes
-        // Unicode whitespace. (ByteRecord trimming only includes ASCII
-        // whitespace.)
-        if self.state.trim.should_trim_fields() {
-            record.trim();
-        }
-        result
+    pub fn number_format(
+        &mut self,
+        format: NumberFormat,
+    ) -> &mut ReaderBuilder {
+        self.deserialize.number_format = Some(format);
+        self
     }
 
-    /// Read a single row into the given byte record. Returns false when no
-    /// more records could be read.
-    ///
-    /// If `has_headers` was enabled via a `ReaderBuilder` (which is the
-    /// default), then this will never read the first record.
-    ///
-    /// This method is useful when you want to read records as fast as
-    /// as possible. It's less ergonomic than an iterator, but it permits the
-    /// caller to reuse the `ByteRecord` allocation, which usually results
-    /// in higher throughput.
+    /// Set the capacity (in bytes) of the buffer used in the CSV reader.
+    /// This defaults to a reasonable setting.
+    pub fn buffer_capacity(&mut self, capacity: usize) -> &mut ReaderBuilder {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Set how the buffer that a record's fields are parsed into grows when
+    /// a record doesn't fit in it.
     ///
-    /// Records read via this method are guaranteed to have a position set
-    /// on them, even if the reader is at EOF or if an error is returned.
+    /// This defaults to `GrowthPolicy::Double`. When reading data with very
+    /// large fields, `GrowthPolicy::Linear` bounds how much memory beyond the
+    /// size of a record may be allocated, at the cost of more reallocations.
     ///
     /// # Example
     ///
     /// ```
     /// use std::error::Error;
-    /// use csv::{ByteRecord, Reader};
+    /// use csv::{GrowthPolicy, ReaderBuilder};
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
@@ -1539,10 +1643,12 @@ impl<R: io::Read> Reader<R> {
     /// city,country,pop
     /// Boston,United States,4628910
     /// ";
-    ///     let mut rdr = Reader::from_reader(data.as_bytes());
-    ///     let mut record = ByteRecord::new();
+    ///     let mut rdr = ReaderBuilder::new()
+    ///         .growth(GrowthPolicy::Linear(1 << 20))
+    ///         .from_reader(data.as_bytes());
     ///
-    ///     if rdr.read_byte_record(&mut record)? {
+    ///     if let Some(result) = rdr.records().next() {
+    ///         let record = result?;
     ///         assert_eq!(record, vec!["Boston", "United States", "4628910"]);
     ///         Ok(())
     ///     } else {
@@ -1550,1049 +1656,6105 @@ impl<R: io::Read> Reader<R> {
     ///     }
     /// }
     /// ```
-    pub fn read_byte_record(
-        &mut self,
-        record: &mut ByteRecord,
-    ) -> Result<bool> {
-        if !self.state.seeked && !self.state.has_headers && !self.state.first {
-            // If the caller indicated "no headers" and we haven't yielded the
-            // first record yet, then we should yield our header row if we have
-            // one.
-            if let Some(ref headers) = self.state.headers {
-                self.state.first = true;
-                record.clone_from(&headers.byte_record);
-                if self.state.trim.should_trim_fields() {
-                    record.trim();
-                }
-                return Ok(!record.is_empty());
-            }
-        }
-        let ok = self.read_byte_record_impl(record)?;
-        self.state.first = true;
-        if !self.state.seeked && self.state.headers.is_none() {
-            self.set_headers_impl(Err(record.clone()));
-            // If the end user indicated that we have headers, then we should
-            // never return the first row. Instead, we should attempt to
-            // read and return the next one.
-            if self.state.has_headers {
-                let result = self.read_byte_record_impl(record);
-                if self.state.trim.should_trim_fields() {
-                    record.trim();
-                }
-                return result;
-            }
-        } else if self.state.trim.should_trim_fields() {
-            record.trim();
-        }
-        Ok(ok)
-    }
-
-    /// Read a byte record from the underlying CSV reader, without accounting
-    /// for headers.
-    #[inline(always)]
-    fn read_byte_record_impl(
-        &mut self,
-        record: &mut ByteRecord,
-    ) -> Result<bool> {
-        use csv_core::ReadRecordResult::*;
-
-        record.clear();
-        record.set_position(Some(self.state.cur_pos.clone()));
-        if self.state.eof {
-            return Ok(false);
-        }
-        let (mut outlen, mut endlen) = (0, 0);
-        loop {
-            let (res, nin, nout, nend) = {
-                let input = self.rdr.fill_buf()?;
-                let (fields, ends) = record.as_parts();
-                self.core.read_record(
-                    input,
-                    &mut fields[outlen..],
-                    &mut ends[endlen..],
-                )
-            };
-            self.rdr.consume(nin);
-            let byte = self.state.cur_pos.byte();
-            self.state
-                .cur_pos
-                .set_byte(byte + nin as u64)
-                .set_line(self.core.line());
-            outlen += nout;
-            endlen += nend;
-            match res {
-                InputEmpty => continue,
-                OutputFull => {
-                    record.expand_fields();
-                    continue;
-                }
-                OutputEndsFull => {
-                    record.expand_ends();
-                    continue;
-                }
-                Record => {
-                    record.set_len(endlen);
-                    self.state.add_record(record)?;
-                    return Ok(true);
-                }
-                End => {
-                    self.state.eof = true;
-                    return Ok(false);
-                }
-            }
-        }
+    pub fn growth(&mut self, growth: GrowthPolicy) -> &mut ReaderBuilder {
+        self.growth = growth;
+        self
     }
 
-    /// Return the current position of this CSV reader.
+    /// Set the number of fields that records are expected to have.
     ///
-    /// The byte offset in the position returned can be used to `seek` this
-    /// reader. In particular, seeking to a position returned here on the same
-    /// data will result in parsing the same subsequent record.
+    /// This is purely a performance hint. Records read by the reader are
+    /// given room for this many fields up front, which avoids growing that
+    /// room repeatedly when reading records with many fields into fresh
+    /// records, e.g., via the `records` iterator. It has no effect on which
+    /// records are accepted; see `expect_fields` for that.
     ///
-    /// # Example: reading the position
+    /// By default, no hint is given.
+    ///
+    /// # Example
     ///
     /// ```
     /// use std::error::Error;
-    /// use std::io;
-    /// use csv::{Reader, Position};
+    /// use csv::ReaderBuilder;
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
     ///     let data = "\
-    /// city,country,popcount
+    /// city,country,pop
     /// Boston,United States,4628910
-    /// Concord,United States,42695
     /// ";
-    ///     let rdr = Reader::from_reader(io::Cursor::new(data));
-    ///     let mut iter = rdr.into_records();
-    ///     let mut pos = Position::new();
-    ///     loop {
-    ///         // Read the position immediately before each record.
-    ///         let next_pos = iter.reader().position().clone();
-    ///         if iter.next().is_none() {
-    ///             break;
-    ///         }
-    ///         pos = next_pos;
-    ///     }
+    ///     let mut rdr = ReaderBuilder::new()
+    ///         .expected_fields_hint(3)
+    ///         .from_reader(data.as_bytes());
     ///
-    ///     // `pos` should now be the position immediately before the last
-    ///     // record.
-    ///     assert_eq!(pos.byte(), 51);
-    ///     assert_eq!(pos.line(), 3);
-    ///     assert_eq!(pos.record(), 2);
-    ///     Ok(())
+    ///     if let Some(result) = rdr.records().next() {
+    ///         let record = result?;
+    ///         assert_eq!(record, vec!["Boston", "United States", "4628910"]);
+    ///         Ok(())
+    ///     } else {
+    ///         Err(From::from("expected at least one record but got none"))
+    ///     }
     /// }
     /// ```
-    pub fn position(&self) -> &Position {
-        &self.state.cur_pos
+    pub fn expected_fields_hint(
+        &mut self,
+        fields: usize,
+    ) -> &mut ReaderBuilder {
+        self.fields_hint = fields;
+        self
     }
 
-    /// Returns true if and only if this reader has been exhausted.
+    /// The encoding of the CSV data being read.
     ///
-    /// When this returns true, no more records can be read from this reader
-    /// (unless it has been seeked to another position).
+    /// This defaults to `Encoding::Utf8`, in which case fields are read as
+    /// is. With `Encoding::Latin1`, the CSV data is read as Windows-1252, and
+    /// every field is transcoded to UTF-8, so that it can be read into a
+    /// `StringRecord` or deserialized. Since every byte is valid
+    /// Windows-1252, transcoding never fails.
+    ///
+    /// Since Windows-1252 is a single-byte encoding that agrees with ASCII,
+    /// each record is parsed before its fields are transcoded. Any delimiter,
+    /// quote or other special byte configured on this builder is therefore
+    /// interpreted as a byte of the Windows-1252 input. Positions reported by
+    /// the reader refer to the input as well.
+    ///
+    /// This is only available when the `encoding` feature is enabled.
     ///
     /// # Example
     ///
     /// ```
     /// use std::error::Error;
-    /// use std::io;
-    /// use csv::{Reader, Position};
+    /// use csv::{Encoding, ReaderBuilder};
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
-    ///     let data = "\
-    /// city,country,popcount
-    /// Boston,United States,4628910
-    /// Concord,United States,42695
-    /// ";
-    ///     let mut rdr = Reader::from_reader(io::Cursor::new(data));
-    ///     assert!(!rdr.is_done());
-    ///     for result in rdr.records() {
-    ///         let _ = result?;
-    ///     }
-    ///     assert!(rdr.is_done());
-    ///     Ok(())
+    ///     let data = b"name,city\nJos\xE9,S\xE3o Paulo\n";
+    ///     let mut rdr = ReaderBuilder::new()
+    ///         .encoding(Encoding::Latin1)
+    ///         .from_reader(&data[..]);
+    ///
+    ///     if let Some(result) = rdr.records().next() {
+    ///         let record = result?;
+    ///         assert_eq!(record, vec!["José", "São Paulo"]);
+    ///         Ok(())
+    ///     } else {
+    ///         Err(From::from("expected at least one record but got none"))
+    ///     }
     /// }
     /// ```
-    pub fn is_done(&self) -> bool {
-        self.state.eof
-    }
-
-    /// Returns true if and only if this reader has been configured to
-    /// interpret the first record as a header record.
-    pub fn has_headers(&self) -> bool {
-        self.state.has_headers
+    #[cfg(feature = "encoding")]
+    pub fn encoding(&mut self, encoding: Encoding) -> &mut ReaderBuilder {
+        self.encoding = encoding;
+        self
     }
 
-    /// Returns a reference to the underlying reader.
-    pub fn get_ref(&self) -> &R {
-        self.rdr.get_ref()
+    /// Enable or disable the NFA for parsing CSV.
+    ///
+    /// This is intended to be a debug option. The NFA is always slower than
+    /// the DFA.
+    #[doc(hidden)]
+    pub fn nfa(&mut self, yes: bool) -> &mut ReaderBuilder {
+        self.builder.nfa(yes);
+        self
     }
+}
 
-    /// Returns a mutable reference to the underlying reader.
-    pub fn get_mut(&mut self) -> &mut R {
-        self.rdr.get_mut()
-    }
+/// A already configured CSV reader.
+///
+/// A CSV reader takes as input CSV data and transforms that into standard Rust
+/// values. The most flexible way to read CSV data is as a sequence of records,
+/// where a record is a sequence of fields and each field is a string. However,
+/// a reader can also deserialize CSV data into Rust types like `i64` or
+/// `(String, f64, f64, f64)` or even a custom struct automatically using
+/// Serde.
+///
+/// # Configuration
+///
+/// A CSV reader has a couple convenient constructor methods like `from_path`
+/// and `from_reader`. However, if you want to configure the CSV reader to use
+/// a different delimiter or quote character (among many other things), then
+/// you should use a [`ReaderBuilder`](struct.ReaderBuilder.html) to construct
+/// a `Reader`. For example, to change the field delimiter:
+///
+/// ```
+/// use std::error::Error;
+/// use csv::ReaderBuilder;
+///
+/// # fn main() { example().unwrap(); }
+/// fn example() -> Result<(), Box<dyn Error>> {
+///     let data = "\
+/// city;country;pop
+/// Boston;United States;4628910
+/// ";
+///     let mut rdr = ReaderBuilder::new()
+///         .delimiter(b';')
+///         .from_reader(data.as_bytes());
+///
+///     if let Some(result) = rdr.records().next() {
+///         let record = result?;
+///         assert_eq!(record, vec!["Boston", "United States", "4628910"]);
+///         Ok(())
+///     } else {
+///         Err(From::from("expected at least one record but got none"))
+///     }
+/// }
+/// ```
+///
+/// # Error handling
+///
+/// In general, CSV *parsing* rarely returns an error. That is, there is
+/// almost no such thing as malformed CSV data. Instead, this reader will
+/// prioritize finding a parse over rejecting CSV data that it does not
+/// understand. This choice was inspired by other popular CSV parsers, but
+/// also because it is pragmatic. CSV data varies wildly, so even if the CSV
+/// data is malformed, it might still be possible to work with the data. In
+/// the land of CSV, there is no "right" or "wrong," only "right" and "less
+/// right."
+///
+/// With that said, a number of errors can occur while reading CSV data:
+///
+/// * By default, all records in CSV data must have the same number of fields.
+///   If a record is found with a different number of fields than a prior
+///   record, then an error is returned. This behavior can be disabled by
+///   enabling flexible parsing via the `flexible` method on
+///   [`ReaderBuilder`](struct.ReaderBuilder.html). Records cut to the same
+///   length by the `max_fields` option never cause this error.
+/// * If the data ends inside a quoted field, i.e., the closing quote of the
+///   last field is missing, then an `UnterminatedQuote` error is returned
+///   for the last record. This happens by default, unless quoting is
+///   disabled. Earlier versions of this crate accepted such data silently.
+/// * When the `strict_quotes` option is enabled, a quote inside an unquoted
+///   field returns an `UnescapedQuote` error.
+/// * When the `max_records` option is set, reading more data records than
+///   its limit returns a `TooManyRecords` error.
+/// * When the `verify_trailer` option is enabled, a trailer row whose record
+///   count doesn't match the number of records read returns a
+///   `TrailerMismatch` error.
+/// * When reading CSV data from a resource (like a file), it is possible for
+///   reading from the underlying resource to fail. This will return an error.
+/// * When reading CSV data into `String` or `&str` fields (e.g., via a
+///   [`StringRecord`](struct.StringRecord.html)), UTF-8 is strictly
+///   enforced. If CSV data is invalid UTF-8, then an error is returned. If
+///   you want to read invalid UTF-8, then you should use the byte oriented
+///   APIs such as [`ByteRecord`](struct.ByteRecord.html). If you need explicit
+///   support for another encoding entirely, then you'll need to use another
+///   crate to transcode your CSV data to UTF-8 before parsing it.
+/// * When using Serde to deserialize CSV data into Rust types, it is possible
+///   for a number of additional errors to occur. For example, deserializing
+///   a field `xyz` into an `i32` field will result in an error.
+///
+/// For more details on the precise semantics of errors, see the
+/// [`Error`](enum.Error.html) type.
+#[derive(Debug)]
+pub struct Reader<R> {
+    /// The underlying CSV parser.
+    ///
+    /// We explicitly put this on the heap because CoreReader embeds an entire
+    /// DFA transition table, which along with other things, tallies up to
+    /// almost 500 bytes on the stack.
+    core: Box<CoreReader>,
+    /// The parser used instead of `core` for configurations that it doesn't
+    /// support, i.e., delimiters longer than a single byte and lenient
+    /// quotes.
+    fallback: Option<FallbackReader>,
+    /// The parser used to find the spans of fields for `read_record_spans`,
+    /// created the first time it's needed.
+    span_parser: Option<FallbackReader>,
+    /// The underlying reader.
+    rdr: io::BufReader<R>,
+    /// Various state tracking.
+    ///
+    /// There is more state embedded in the `CoreReader`.
+    state: ReaderState,
+}
 
-    /// Unwraps this CSV reader, returning the underlying reader.
+#[derive(Debug)]
+struct ReaderState {
+    /// When set, this contains the first row of any parsed CSV data.
     ///
-    /// Note that any leftover data inside this reader's internal buffer is
-    /// lost.
-    pub fn into_inner(self) -> R {
-        self.rdr.into_inner()
-    }
+    /// This is always populated, regardless of whether `has_headers` is set.
+    headers: Option<Headers>,
+    /// When set, the first row of parsed CSV data is excluded from things
+    /// that read records, like iterators and `read_record`.
+    has_headers: bool,
+    /// When set, there is no restriction on the length of records. When not
+    /// set, every record must have the same number of fields, or else an error
+    /// is reported.
+    flexible: bool,
+    /// When set, every record must have exactly this many fields.
+    expect_fields: Option<u64>,
+    /// When set, a trailing empty field is removed from every record.
+    ignore_trailing_delimiter: bool,
+    /// When set, records that are empty or contain only whitespace are
+    /// skipped.
+    skip_blank_records: bool,
+    /// When set, records aren't split into fields.
+    single_field: bool,
+    /// When set, reading stops at a blank line after a record.
+    stop_at_blank: bool,
+    /// Whether a physical record has been read since the start of the
+    /// data, so that a blank line that follows it ends a section.
+    after_record: bool,
+    /// Whether the physical record last read ended with `\r`, in which case
+    /// the `\n` of a CRLF terminator is read with the next record.
+    last_cr: bool,
+    /// Whether a blank line preceded the physical record last read.
+    blank_before: bool,
+    /// When set, a trailer row at the end of the data is verified.
+    verify_trailer: bool,
+    /// The number of data records read since the start of the data, which
+    /// is only counted when `verify_trailer` is set.
+    records_read: u64,
+    /// A record read ahead to check that a preceding trailer-like record
+    /// wasn't the last one.
+    after_trailer: Option<ByteRecord>,
+    /// When set, `positions` records the position of every record read.
+    cache_positions: bool,
+    /// The positions of the records read so far, in increasing order of
+    /// record number.
+    positions: Vec<Position>,
+    /// The maximum number of data records that may be read, if any.
+    max_records: Option<u64>,
+    /// The number of data records read since the start of the data.
+    data_records: u64,
+    /// When set, fields after this many are dropped from every record.
+    max_fields: Option<usize>,
+    /// When set, physical records are grouped into logical records.
+    record_boundary: Option<RecordBoundary>,
+    /// The raw bytes of the physical record last read, which are only
+    /// kept when `record_boundary` or `capture_raw` is set. When
+    /// `capture_raw` is set, this holds the raw bytes of every physical
+    /// record read since it was last cleared.
+    raw_record: Vec<u8>,
+    /// The offset in `raw_record` of the physical record last read.
+    raw_start: usize,
+    /// Whether `raw_record` accumulates raw bytes for `read_record_raw`.
+    capture_raw: bool,
+    /// The physical record that starts the next logical record, if it has
+    /// been read already.
+    next_physical: Option<ByteRecord>,
+    /// The raw bytes of `next_physical`.
+    next_raw: Vec<u8>,
+    trim: Trim,
+    /// How to grow a record's field buffer when a record doesn't fit.
+    growth: GrowthPolicy,
+    /// The number of fields that records are expected to have, or `0` if
+    /// unknown.
+    fields_hint: usize,
+    /// Whether records are read by `read_plain_record`, because none of the
+    /// options that `read_physical_record` handles are set, and neither is
+    /// `max_records`.
+    plain: bool,
+    /// The encoding that records are transcoded from to UTF-8.
+    #[cfg(feature = "encoding")]
+    encoding: Encoding,
+    /// The number of fields in the first record parsed.
+    first_field_count: Option<u64>,
+    /// The current position of the parser.
+    ///
+    /// Note that this position is only observable by callers at the start
+    /// of a record. More granular positions are not supported.
+    cur_pos: Position,
+    /// Whether the first record has been read or not.
+    first: bool,
+    /// Whether the reader has been seeked or not.
+    seeked: bool,
+    /// Whether EOF of the underlying reader has been reached or not.
+    eof: bool,
+    /// A record that was read by `peek` but not yet returned to the caller.
+    ///
+    /// When set, the next call to read a record returns this record instead
+    /// of parsing a new one.
+    peeked: Option<StringRecord>,
+    /// Records that were read by `infer_schema` but not yet returned to the
+    /// caller. These are returned, in order, after `peeked`.
+    buffered: VecDeque<ByteRecord>,
+    /// Whether the parser stopped in the middle of a record, e.g., because
+    /// the underlying reader returned an error.
+    in_record: bool,
+    /// The record that `read_fields` reads into.
+    fields: ByteRecord,
+    /// Settings for the Serde deserializer.
+    deserialize: DeserializeConfig,
 }
 
-impl<R: io::Read + io::Seek> Reader<R> {
-    /// Seeks the underlying reader to the position given.
+/// Headers encapsulates any data associated with the headers of CSV data.
+///
+/// The headers always correspond to the first row.
+#[derive(Debug)]
+struct Headers {
+    /// The header, as raw bytes.
+    byte_record: ByteRecord,
+    /// The header, as valid UTF-8 (or a UTF-8 error).
+    string_record: result::Result<StringRecord, Utf8Error>,
+    /// A map from each header name to the index of its first occurrence,
+    /// built on demand by `build_header_index`.
+    index: Option<HashMap<Vec<u8>, usize>>,
+}
+
+impl Reader<Reader<File>> {
+    /// Create a new CSV parser with a default configuration for the given
+    /// file path.
     ///
-    /// This comes with a few caveats:
+    /// To customize CSV parsing, use a `ReaderBuilder`.
     ///
-    /// * Any internal buffer associated with this reader is cleared.
-    /// * If the given position does not correspond to a position immediately
-    ///   before the start of a record, then the behavior of this reader is
-    ///   unspecified.
-    /// * Any special logic that skips the first record in the CSV reader
-    ///   when reading or iterating over records is disabled.
+    /// # Example
     ///
-    /// If the given position has a byte offset equivalent to the current
-    /// position, then no seeking is performed.
+    /// ```no_run
+    /// use std::error::Error;
+    /// use csv::Reader;
     ///
-    /// If the header row has not already been read, then this will attempt
-    /// to read the header row before seeking. Therefore, it is possible that
-    /// this returns an error associated with reading CSV data.
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut rdr = Reader::from_path("foo.csv")?;
+    ///     for result in rdr.records() {
+    ///         let record = result?;
+    ///         println!("{:?}", record);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Reader<File>> {
+        ReaderBuilder::new().from_path(path)
+    }
+}
+
+impl<R: io::Read> Reader<R> {
+    /// Create a new CSV reader given a builder and a source of underlying
+    /// bytes.
+    fn new(builder: &ReaderBuilder, rdr: R) -> Reader<R> {
+        Reader::with_capacity(builder, builder.capacity, rdr)
+    }
+
+    /// Like `new`, but with a buffer capacity that overrides the builder's.
+    fn with_capacity(
+        builder: &ReaderBuilder,
+        capacity: usize,
+        rdr: R,
+    ) -> Reader<R> {
+        let core = Box::new(builder.builder.build());
+        let fallback = if builder.single_field {
+            if builder.lenient_quotes {
+                Some(FallbackReader::new(&[], true, &core))
+            } else {
+                None
+            }
+        } else if !builder.multi_delimiter.is_empty() {
+            Some(FallbackReader::new(
+                &builder.multi_delimiter,
+                builder.lenient_quotes,
+                &core,
+            ))
+        } else if builder.lenient_quotes {
+            Some(FallbackReader::new(&[core.get_delimiter()], true, &core))
+        } else {
+            None
+        };
+        let plain = fallback.is_none()
+            && !builder.stop_at_blank
+            && !builder.verify_trailer
+            && builder.max_fields.is_none()
+            && builder.max_records.is_none()
+            && builder.record_boundary.is_none()
+            && !builder.ignore_trailing_delimiter
+            && !builder.skip_blank_records
+            && builder.fields_hint == 0;
+        #[cfg(feature = "encoding")]
+        let plain = plain && builder.encoding == Encoding::Utf8;
+        Reader {
+            core,
+            fallback,
+            span_parser: None,
+            rdr: io::BufReader::with_capacity(capacity, rdr),
+            state: ReaderState {
+                headers: None,
+                has_headers: builder.has_headers,
+                flexible: builder.flexible,
+                expect_fields: builder.expect_fields.map(|n| n as u64),
+                ignore_trailing_delimiter: builder.ignore_trailing_delimiter,
+                skip_blank_records: builder.skip_blank_records,
+                single_field: builder.single_field,
+                stop_at_blank: builder.stop_at_blank,
+                after_record: false,
+                last_cr: false,
+                blank_before: false,
+                verify_trailer: builder.verify_trailer,
+                records_read: 0,
+                after_trailer: None,
+                cache_positions: builder.cache_positions,
+                positions: vec![],
+                max_records: builder.max_records,
+                data_records: 0,
+                max_fields: builder.max_fields,
+                record_boundary: builder.record_boundary.clone(),
+                raw_record: vec![],
+                raw_start: 0,
+                capture_raw: false,
+                next_physical: None,
+                next_raw: vec![],
+                trim: builder.trim,
+                growth: builder.growth,
+                fields_hint: builder.fields_hint,
+                plain,
+                #[cfg(feature = "encoding")]
+                encoding: builder.encoding,
+                first_field_count: None,
+                cur_pos: Position::new(),
+                first: false,
+                seeked: false,
+                eof: false,
+                peeked: None,
+                buffered: VecDeque::new(),
+                in_record: false,
+                fields: ByteRecord::new(),
+                deserialize: builder.deserialize.clone(),
+            },
+        }
+    }
+
+    /// Create a new CSV parser with a default configuration for the given
+    /// reader.
     ///
-    /// Note that seeking is performed based only on the byte offset in the
-    /// given position. Namely, the record or line numbers in the position may
-    /// be incorrect, but this will cause any future position generated by
-    /// this CSV reader to be similarly incorrect.
+    /// To customize CSV parsing, use a `ReaderBuilder`.
     ///
-    /// # Example: seek to parse a record twice
+    /// # Example
     ///
     /// ```
     /// use std::error::Error;
-    /// use std::io;
-    /// use csv::{Reader, Position};
+    /// use csv::Reader;
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
     ///     let data = "\
-    /// city,country,popcount
+    /// city,country,pop
     /// Boston,United States,4628910
     /// Concord,United States,42695
     /// ";
-    ///     let rdr = Reader::from_reader(io::Cursor::new(data));
-    ///     let mut iter = rdr.into_records();
-    ///     let mut pos = Position::new();
-    ///     loop {
-    ///         // Read the position immediately before each record.
-    ///         let next_pos = iter.reader().position().clone();
-    ///         if iter.next().is_none() {
-    ///             break;
-    ///         }
-    ///         pos = next_pos;
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///     for result in rdr.records() {
+    ///         let record = result?;
+    ///         println!("{:?}", record);
     ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn from_reader(rdr: R) -> Reader<R> {
+        ReaderBuilder::new().from_reader(rdr)
+    }
+
+    /// Returns a borrowed iterator over deserialized records.
+    ///
+    /// Each item yielded by this iterator is a `Result<D, Error>`.
+    /// Therefore, in order to access the record, callers must handle the
+    /// possibility of error (typically with `try!` or `?`).
+    ///
+    /// If `has_headers` was enabled via a `ReaderBuilder` (which is the
+    /// default), then this does not include the first record. Additionally,
+    /// if `has_headers` is enabled, then deserializing into a struct will
+    /// automatically align the values in each row to the fields of a struct
+    /// based on the header row.
+    ///
+    /// # Example
+    ///
+    /// This shows how to deserialize CSV data into normal Rust structs. The
+    /// fields of the header row are used to match up the values in each row
+    /// to the fields of the struct.
+    ///
+    /// ```
+    /// use std::error::Error;
+    ///
+    /// use csv::Reader;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Debug, Deserialize, Eq, PartialEq)]
+    /// struct Row {
+    ///     city: String,
+    ///     country: String,
+    ///     #[serde(rename = "popcount")]
+    ///     population: u64,
+    /// }
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,popcount
+    /// Boston,United States,4628910
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///     let mut iter = rdr.deserialize();
     ///
-    ///     // Now seek the reader back to `pos`. This will let us read the
-    ///     // last record again.
-    ///     iter.reader_mut().seek(pos)?;
-    ///     let mut iter = iter.into_reader().into_records();
     ///     if let Some(result) = iter.next() {
-    ///         let record = result?;
-    ///         assert_eq!(record, vec!["Concord", "United States", "42695"]);
+    ///         let record: Row = result?;
+    ///         assert_eq!(record, Row {
+    ///             city: "Boston".to_string(),
+    ///             country: "United States".to_string(),
+    ///             population: 4628910,
+    ///         });
     ///         Ok(())
     ///     } else {
     ///         Err(From::from("expected at least one record but got none"))
     ///     }
     /// }
     /// ```
-    pub fn seek(&mut self, pos: Position) -> Result<()> {
-        self.byte_headers()?;
-        self.state.seeked = true;
-        if pos.byte() == self.state.cur_pos.byte() {
-            return Ok(());
-        }
-        self.rdr.seek(io::SeekFrom::Start(pos.byte()))?;
-        self.core.reset();
-        self.core.set_line(pos.line());
-        self.state.cur_pos = pos;
-        self.state.eof = false;
-        Ok(())
+    ///
+    /// # Rules
+    ///
+    /// For the most part, any Rust type that maps straight-forwardly to a CSV
+    /// record is supported. This includes maps, structs, tuples and tuple
+    /// structs. Other Rust types, such as `Vec`s, arrays, and enums have
+    /// a more complicated story. In general, when working with CSV data, one
+    /// should avoid *nested sequences* as much as possible.
+    ///
+    /// Maps, structs, tuples and tuple structs map to CSV records in a simple
+    /// way. Tuples and tuple structs decode their fields in the order that
+    /// they are defined. Structs will do the same only if `has_headers` has
+    /// been disabled using [`ReaderBuilder`](struct.ReaderBuilder.html),
+    /// otherwise, structs and maps are deserialized based on the fields
+    /// defined in the header row. (If there is no header row, then
+    /// deserializing into a map will result in an error.)
+    ///
+    /// Nested sequences are supported in a limited capacity. Namely, they
+    /// are flattened. As a result, it's often useful to use a `Vec` to capture
+    /// a "tail" of fields in a record:
+    ///
+    /// ```
+    /// use std::error::Error;
+    ///
+    /// use csv::ReaderBuilder;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Debug, Deserialize, Eq, PartialEq)]
+    /// struct Row {
+    ///     label: String,
+    ///     values: Vec<i32>,
+    /// }
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "foo,1,2,3";
+    ///     let mut rdr = ReaderBuilder::new()
+    ///         .has_headers(false)
+    ///         .from_reader(data.as_bytes());
+    ///     let mut iter = rdr.deserialize();
+    ///
+    ///     if let Some(result) = iter.next() {
+    ///         let record: Row = result?;
+    ///         assert_eq!(record, Row {
+    ///             label: "foo".to_string(),
+    ///             values: vec![1, 2, 3],
+    ///         });
+    ///         Ok(())
+    ///     } else {
+    ///         Err(From::from("expected at least one record but got none"))
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// In the above example, adding another field to the `Row` struct after
+    /// the `values` field will result in a deserialization error. This is
+    /// because the deserializer doesn't know when to stop reading fields
+    /// into the `values` vector, so it will consume the rest of the fields in
+    /// the record leaving none left over for the additional field.
+    ///
+    /// Finally, simple enums in Rust can be deserialized as well. Namely,
+    /// enums must either be variants with no arguments or variants with a
+    /// single argument. Variants with no arguments are deserialized based on
+    /// which variant name the field matches. Variants with a single argument
+    /// are deserialized based on which variant can store the data. The latter
+    /// is only supported when using "untagged" enum deserialization. The
+    /// following example shows both forms in action:
+    ///
+    /// ```
+    /// use std::error::Error;
+    ///
+    /// use csv::Reader;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq)]
+    /// struct Row {
+    ///     label: Label,
+    ///     value: Number,
+    /// }
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq)]
+    /// #[serde(rename_all = "lowercase")]
+    /// enum Label {
+    ///     Celsius,
+    ///     Fahrenheit,
+    /// }
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq)]
+    /// #[serde(untagged)]
+    /// enum Number {
+    ///     Integer(i64),
+    ///     Float(f64),
+    /// }
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// label,value
+    /// celsius,22.2222
+    /// fahrenheit,72
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///     let mut iter = rdr.deserialize();
+    ///
+    ///     // Read the first record.
+    ///     if let Some(result) = iter.next() {
+    ///         let record: Row = result?;
+    ///         assert_eq!(record, Row {
+    ///             label: Label::Celsius,
+    ///             value: Number::Float(22.2222),
+    ///         });
+    ///     } else {
+    ///         return Err(From::from(
+    ///             "expected at least two records but got none"));
+    ///     }
+    ///
+    ///     // Read the second record.
+    ///     if let Some(result) = iter.next() {
+    ///         let record: Row = result?;
+    ///         assert_eq!(record, Row {
+    ///             label: Label::Fahrenheit,
+    ///             value: Number::Integer(72),
+    ///         });
+    ///         Ok(())
+    ///     } else {
+    ///         Err(From::from(
+    ///             "expected at least two records but got only one"))
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// When a header row is present, a whole record can also be deserialized
+    /// into an internally tagged enum, where one column names the variant and
+    /// the remaining columns supply that variant's fields. This is useful for
+    /// files that mix several kinds of rows. Columns used only by other
+    /// variants are ignored. Since the fields are buffered before the variant
+    /// is known, their types are inferred, so a `String` field whose value
+    /// looks like a number won't deserialize:
+    ///
+    /// ```
+    /// use std::error::Error;
+    ///
+    /// use csv::Reader;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq)]
+    /// #[serde(tag = "kind")]
+    /// enum Event {
+    ///     Click { x: u32, y: u32 },
+    ///     Key { code: String },
+    /// }
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// kind,x,y,code
+    /// Click,3,4,
+    /// Key,,,Enter
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///     let events = rdr.deserialize().collect::<Result<Vec<Event>, _>>()?;
+    ///     assert_eq!(events, vec![
+    ///         Event::Click { x: 3, y: 4 },
+    ///         Event::Key { code: "Enter".to_string() },
+    ///     ]);
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// A whole record can likewise be deserialized into a map keyed by the
+    /// header row, such as a `HashMap<String, String>`. The entries are
+    /// given to the map in the order of the columns, so a map that preserves
+    /// insertion order, such as `IndexMap` from the `indexmap` crate with its
+    /// `serde` feature enabled, iterates over them in the file's column
+    /// order, unlike a `HashMap` or a `BTreeMap`.
+    pub fn deserialize<D>(&mut self) -> DeserializeRecordsIter<'_, R, D>
+    where
+        D: DeserializeOwned,
+    {
+        DeserializeRecordsIter::new(self)
+    }
+
+    /// Returns an owned iterator over deserialized records.
+    ///
+    /// Each item yielded by this iterator is a `Result<D, Error>`.
+    /// Therefore, in order to access the record, callers must handle the
+    /// possibility of error (typically with `try!` or `?`).
+    ///
+    /// This is mostly useful when you want to return a CSV iterator or store
+    /// it somewhere.
+    ///
+    /// If `has_headers` was enabled via a `ReaderBuilder` (which is the
+    /// default), then this does not include the first record. Additionally,
+    /// if `has_headers` is enabled, then deserializing into a struct will
+    /// automatically align the values in each row to the fields of a struct
+    /// based on the header row.
+    ///
+    /// For more detailed deserialization rules, see the documentation on the
+    /// `deserialize` method.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    ///
+    /// use csv::Reader;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Debug, Deserialize, Eq, PartialEq)]
+    /// struct Row {
+    ///     city: String,
+    ///     country: String,
+    ///     #[serde(rename = "popcount")]
+    ///     population: u64,
+    /// }
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,popcount
+    /// Boston,United States,4628910
+    /// ";
+    ///     let rdr = Reader::from_reader(data.as_bytes());
+    ///     let mut iter = rdr.into_deserialize();
+    ///
+    ///     if let Some(result) = iter.next() {
+    ///         let record: Row = result?;
+    ///         assert_eq!(record, Row {
+    ///             city: "Boston".to_string(),
+    ///             country: "United States".to_string(),
+    ///             population: 4628910,
+    ///         });
+    ///         Ok(())
+    ///     } else {
+    ///         Err(From::from("expected at least one record but got none"))
+    ///     }
+    /// }
+    /// ```
+    pub fn into_deserialize<D>(self) -> DeserializeRecordsIntoIter<R, D>
+    where
+        D: DeserializeOwned,
+    {
+        DeserializeRecordsIntoIter::new(self)
+    }
+
+    /// Returns a borrowed iterator over all records as strings.
+    ///
+    /// Each item yielded by this iterator is a `Result<StringRecord, Error>`.
+    /// Therefore, in order to access the record, callers must handle the
+    /// possibility of error (typically with `try!` or `?`).
+    ///
+    /// If `has_headers` was enabled via a `ReaderBuilder` (which is the
+    /// default), then this does not include the first record.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Reader;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,pop
+    /// Boston,United States,4628910
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///     let mut iter = rdr.records();
+    ///
+    ///     if let Some(result) = iter.next() {
+    ///         let record = result?;
+    ///         assert_eq!(record, vec!["Boston", "United States", "4628910"]);
+    ///         Ok(())
+    ///     } else {
+    ///         Err(From::from("expected at least one record but got none"))
+    ///     }
+    /// }
+    /// ```
+    pub fn records(&mut self) -> StringRecordsIter<'_, R> {
+        StringRecordsIter::new(self)
+    }
+
+    /// Returns a borrowed iterator over all records as strings, along with
+    /// the index of each record.
+    ///
+    /// This is like `records`, except that each item is paired with its
+    /// index, starting at `0` for the first item yielded. Since the header
+    /// record isn't yielded, it isn't counted. An error counts as an item,
+    /// so the indices of the records that follow it aren't shifted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Reader;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,pop
+    /// Boston,United States,4628910
+    /// Concord,United States,42695
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///     for (i, result) in rdr.enumerate_records() {
+    ///         let record = result?;
+    ///         println!("{}: {:?}", i, record);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn enumerate_records(&mut self) -> EnumerateRecordsIter<'_, R> {
+        EnumerateRecordsIter::new(self)
+    }
+
+    /// Returns a borrowed iterator over all records as strings, grouped into
+    /// windows of `size` consecutive records.
+    ///
+    /// Each item yielded is a `Vec` of exactly `size` records, except for
+    /// the last one, which holds the remaining records and may be smaller.
+    /// The windows don't overlap. This is like calling `read_batch` in a
+    /// loop, but as an iterator, which suits pipelines that process records
+    /// in batches.
+    ///
+    /// If an error occurs, then it is yielded in place of the window being
+    /// read, and the records of that window read before the error are
+    /// dropped. The next window starts after the error.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `size` is `0`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Reader;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,pop
+    /// Boston,4628910
+    /// Concord,42695
+    /// Springfield,153060
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///     let mut windows = rdr.windows(2);
+    ///
+    ///     let window = windows.next().unwrap()?;
+    ///     assert_eq!(window, vec![
+    ///         vec!["Boston", "4628910"],
+    ///         vec!["Concord", "42695"],
+    ///     ]);
+    ///     let window = windows.next().unwrap()?;
+    ///     assert_eq!(window, vec![vec!["Springfield", "153060"]]);
+    ///     assert!(windows.next().is_none());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn windows(&mut self, size: usize) -> WindowsIter<'_, R> {
+        WindowsIter::new(self, size)
+    }
+
+    /// Returns an owned iterator over all records as strings.
+    ///
+    /// Each item yielded by this iterator is a `Result<StringRecord, Error>`.
+    /// Therefore, in order to access the record, callers must handle the
+    /// possibility of error (typically with `try!` or `?`).
+    ///
+    /// This is mostly useful when you want to return a CSV iterator or store
+    /// it somewhere.
+    ///
+    /// If `has_headers` was enabled via a `ReaderBuilder` (which is the
+    /// default), then this does not include the first record.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Reader;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,pop
+    /// Boston,United States,4628910
+    /// ";
+    ///     let rdr = Reader::from_reader(data.as_bytes());
+    ///     let mut iter = rdr.into_records();
+    ///
+    ///     if let Some(result) = iter.next() {
+    ///         let record = result?;
+    ///         assert_eq!(record, vec!["Boston", "United States", "4628910"]);
+    ///         Ok(())
+    ///     } else {
+    ///         Err(From::from("expected at least one record but got none"))
+    ///     }
+    /// }
+    /// ```
+    pub fn into_records(self) -> StringRecordsIntoIter<R> {
+        StringRecordsIntoIter::new(self)
+    }
+
+    /// Returns a borrowed iterator over all records as raw bytes.
+    ///
+    /// Each item yielded by this iterator is a `Result<ByteRecord, Error>`.
+    /// Therefore, in order to access the record, callers must handle the
+    /// possibility of error (typically with `try!` or `?`).
+    ///
+    /// If `has_headers` was enabled via a `ReaderBuilder` (which is the
+    /// default), then this does not include the first record.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Reader;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,pop
+    /// Boston,United States,4628910
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///     let mut iter = rdr.byte_records();
+    ///
+    ///     if let Some(result) = iter.next() {
+    ///         let record = result?;
+    ///         assert_eq!(record, vec!["Boston", "United States", "4628910"]);
+    ///         Ok(())
+    ///     } else {
+    ///         Err(From::from("expected at least one record but got none"))
+    ///     }
+    /// }
+    /// ```
+    pub fn byte_records(&mut self) -> ByteRecordsIter<'_, R> {
+        ByteRecordsIter::new(self)
+    }
+
+    /// Returns an owned iterator over all records as raw bytes.
+    ///
+    /// Each item yielded by this iterator is a `Result<ByteRecord, Error>`.
+    /// Therefore, in order to access the record, callers must handle the
+    /// possibility of error (typically with `try!` or `?`).
+    ///
+    /// This is mostly useful when you want to return a CSV iterator or store
+    /// it somewhere.
+    ///
+    /// If `has_headers` was enabled via a `ReaderBuilder` (which is the
+    /// default), then this does not include the first record.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Reader;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,pop
+    /// Boston,United States,4628910
+    /// ";
+    ///     let rdr = Reader::from_reader(data.as_bytes());
+    ///     let mut iter = rdr.into_byte_records();
+    ///
+    ///     if let Some(result) = iter.next() {
+    ///         let record = result?;
+    ///         assert_eq!(record, vec!["Boston", "United States", "4628910"]);
+    ///         Ok(())
+    ///     } else {
+    ///         Err(From::from("expected at least one record but got none"))
+    ///     }
+    /// }
+    /// ```
+    pub fn into_byte_records(self) -> ByteRecordsIntoIter<R> {
+        ByteRecordsIntoIter::new(self)
+    }
+
+    /// Returns a borrowed iterator over all fields as a flat stream.
+    ///
+    /// Each item yielded by this iterator is either a single field, as raw
+    /// bytes, or a marker indicating the end of a record. This is useful
+    /// when the structure of records doesn't matter beyond where they end.
+    ///
+    /// As with `byte_records`, the header row is not included when
+    /// `has_headers` is enabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::{FieldEvent, Reader};
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,pop
+    /// Boston,4628910
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///     let events = rdr.fields().collect::<Result<Vec<_>, _>>()?;
+    ///     assert_eq!(events, vec![
+    ///         FieldEvent::Field(b"Boston".to_vec()),
+    ///         FieldEvent::Field(b"4628910".to_vec()),
+    ///         FieldEvent::EndOfRecord,
+    ///     ]);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn fields(&mut self) -> FieldsIter<'_, R> {
+        FieldsIter::new(self)
+    }
+
+    /// Returns a reference to the first row read by this parser.
+    ///
+    /// If no row has been read yet, then this will force parsing of the first
+    /// row.
+    ///
+    /// If there was a problem parsing the row or if it wasn't valid UTF-8,
+    /// then this returns an error.
+    ///
+    /// If the underlying reader emits EOF before any data, then this returns
+    /// an empty record.
+    ///
+    /// Note that this method may be used regardless of whether `has_headers`
+    /// was enabled (but it is enabled by default).
+    ///
+    /// # Example
+    ///
+    /// This example shows how to get the header row of CSV data. Notice that
+    /// the header row does not appear as a record in the iterator!
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Reader;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,pop
+    /// Boston,United States,4628910
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///
+    ///     // We can read the headers before iterating.
+    ///     {
+    ///         // `headers` borrows from the reader, so we put this in its
+    ///         // own scope. That way, the borrow ends before we try iterating
+    ///         // below. Alternatively, we could clone the headers.
+    ///         let headers = rdr.headers()?;
+    ///         assert_eq!(headers, vec!["city", "country", "pop"]);
+    ///     }
+    ///
+    ///     if let Some(result) = rdr.records().next() {
+    ///         let record = result?;
+    ///         assert_eq!(record, vec!["Boston", "United States", "4628910"]);
+    ///     } else {
+    ///         return Err(From::from(
+    ///             "expected at least one record but got none"))
+    ///     }
+    ///
+    ///     // We can also read the headers after iterating.
+    ///     let headers = rdr.headers()?;
+    ///     assert_eq!(headers, vec!["city", "country", "pop"]);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn headers(&mut self) -> Result<&StringRecord> {
+        if self.state.headers.is_none() {
+            let mut record = ByteRecord::new();
+            self.read_byte_record_impl(&mut record)?;
+            self.set_headers_from_data(record);
+        }
+        let headers = self.state.headers.as_ref().unwrap();
+        match headers.string_record {
+            Ok(ref record) => Ok(record),
+            Err(ref err) => Err(Error::new(ErrorKind::Utf8 {
+                pos: headers.byte_record.position().map(Clone::clone),
+                err: err.clone(),
+            })),
+        }
+    }
+
+    /// Returns a reference to the first row read by this parser as raw bytes.
+    ///
+    /// If no row has been read yet, then this will force parsing of the first
+    /// row.
+    ///
+    /// If there was a problem parsing the row then this returns an error.
+    ///
+    /// If the underlying reader emits EOF before any data, then this returns
+    /// an empty record.
+    ///
+    /// Note that this method may be used regardless of whether `has_headers`
+    /// was enabled (but it is enabled by default).
+    ///
+    /// # Example
+    ///
+    /// This example shows how to get the header row of CSV data. Notice that
+    /// the header row does not appear as a record in the iterator!
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Reader;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,pop
+    /// Boston,United States,4628910
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///
+    ///     // We can read the headers before iterating.
+    ///     {
+    ///         // `headers` borrows from the reader, so we put this in its
+    ///         // own scope. That way, the borrow ends before we try iterating
+    ///         // below. Alternatively, we could clone the headers.
+    ///         let headers = rdr.byte_headers()?;
+    ///         assert_eq!(headers, vec!["city", "country", "pop"]);
+    ///     }
+    ///
+    ///     if let Some(result) = rdr.byte_records().next() {
+    ///         let record = result?;
+    ///         assert_eq!(record, vec!["Boston", "United States", "4628910"]);
+    ///     } else {
+    ///         return Err(From::from(
+    ///             "expected at least one record but got none"))
+    ///     }
+    ///
+    ///     // We can also read the headers after iterating.
+    ///     let headers = rdr.byte_headers()?;
+    ///     assert_eq!(headers, vec!["city", "country", "pop"]);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn byte_headers(&mut self) -> Result<&ByteRecord> {
+        if self.state.headers.is_none() {
+            let mut record = ByteRecord::new();
+            self.read_byte_record_impl(&mut record)?;
+            self.set_headers_from_data(record);
+        }
+        Ok(&self.state.headers.as_ref().unwrap().byte_record)
+    }
+
+    /// Returns true if and only if the CSV data contains no rows at all.
+    ///
+    /// Input is considered empty if it has no bytes, or only contains record
+    /// terminators (i.e., empty lines) or comments. Empty input is not an
+    /// error: `headers` and `byte_headers` return an empty record, and
+    /// iterating over records yields nothing, regardless of whether
+    /// `has_headers` is enabled. Note though that with `has_headers` enabled,
+    /// input with a header row but no other rows is not empty.
+    ///
+    /// If no row has been read yet, then this forces parsing of the first
+    /// row, just like `byte_headers`. Similarly, if this reader was asked to
+    /// `seek` before it parsed the first row, then this returns an error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Reader;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut rdr = Reader::from_reader("\n\n".as_bytes());
+    ///     assert!(rdr.is_empty_input()?);
+    ///     assert!(rdr.headers()?.is_empty());
+    ///     assert!(rdr.records().next().is_none());
+    ///
+    ///     let mut rdr = Reader::from_reader("city,pop\n".as_bytes());
+    ///     assert!(!rdr.is_empty_input()?);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn is_empty_input(&mut self) -> Result<bool> {
+        Ok(self.byte_headers()?.is_empty())
+    }
+
+    /// Build the map from header names to column indices used by
+    /// `header_position`.
+    ///
+    /// If no row has been read yet, then this forces parsing of the first
+    /// row, just like `byte_headers`. The map is otherwise built the first
+    /// time `header_position` is called, so calling this up front makes the
+    /// cost of every later lookup predictable. The map is rebuilt if the
+    /// headers are replaced with `set_headers` or `set_byte_headers`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Reader;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,pop
+    /// Boston,United States,4628910
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///     rdr.build_header_index()?;
+    ///     let pop = rdr.header_position("pop")?.unwrap();
+    ///     for result in rdr.records() {
+    ///         let record = result?;
+    ///         assert_eq!(&record[pop], "4628910");
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn build_header_index(&mut self) -> Result<()> {
+        self.byte_headers()?;
+        let headers = self.state.headers.as_mut().unwrap();
+        if headers.index.is_none() {
+            let mut index = HashMap::with_capacity(headers.byte_record.len());
+            for (i, name) in headers.byte_record.iter().enumerate() {
+                index.entry(name.to_vec()).or_insert(i);
+            }
+            headers.index = Some(index);
+        }
+        Ok(())
+    }
+
+    /// Returns the index of the column with the given header name.
+    ///
+    /// If the name occurs more than once in the header row, then the index
+    /// of its first occurrence is returned. If it doesn't occur at all, then
+    /// `None` is returned.
+    ///
+    /// This reads the header row and builds the map from header names to
+    /// indices if that hasn't happened yet. See `build_header_index`.
+    pub fn header_position<T: AsRef<[u8]>>(
+        &mut self,
+        name: T,
+    ) -> Result<Option<usize>> {
+        self.build_header_index()?;
+        let headers = self.state.headers.as_ref().unwrap();
+        Ok(headers.index.as_ref().unwrap().get(name.as_ref()).cloned())
+    }
+
+    /// Returns a map from each header name to the index of its column.
+    ///
+    /// If a name occurs more than once in the header row, then it maps to
+    /// the index of its first occurrence. Unlike `header_position`, which
+    /// looks names up in an index kept by this reader, this reads the header
+    /// row and returns a new map owned by the caller.
+    ///
+    /// If the header row is not valid UTF-8, then an error is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Reader;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,pop
+    /// Boston,United States,4628910
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///     let columns = rdr.header_map()?;
+    ///
+    ///     for result in rdr.records() {
+    ///         let record = result?;
+    ///         assert_eq!(&record[columns["pop"]], "4628910");
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn header_map(&mut self) -> Result<HashMap<String, usize>> {
+        let headers = self.headers()?;
+        let mut map = HashMap::with_capacity(headers.len());
+        for (i, name) in headers.iter().enumerate() {
+            map.entry(name.to_string()).or_insert(i);
+        }
+        Ok(map)
+    }
+
+    /// Set the headers of this CSV parser manually.
+    ///
+    /// This overrides any other setting (including `set_byte_headers`). Any
+    /// automatic detection of headers is disabled. This may be called at any
+    /// time.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::{Reader, StringRecord};
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,pop
+    /// Boston,United States,4628910
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///
+    ///     assert_eq!(rdr.headers()?, vec!["city", "country", "pop"]);
+    ///     rdr.set_headers(StringRecord::from(vec!["a", "b", "c"]));
+    ///     assert_eq!(rdr.headers()?, vec!["a", "b", "c"]);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn set_headers(&mut self, headers: StringRecord) {
+        self.set_headers_impl(Ok(headers));
+    }
+
+    /// Set the headers of this CSV parser manually as raw bytes.
+    ///
+    /// This overrides any other setting (including `set_headers`). Any
+    /// automatic detection of headers is disabled. This may be called at any
+    /// time.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::{Reader, ByteRecord};
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,pop
+    /// Boston,United States,4628910
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///
+    ///     assert_eq!(rdr.byte_headers()?, vec!["city", "country", "pop"]);
+    ///     rdr.set_byte_headers(ByteRecord::from(vec!["a", "b", "c"]));
+    ///     assert_eq!(rdr.byte_headers()?, vec!["a", "b", "c"]);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn set_byte_headers(&mut self, headers: ByteRecord) {
+        self.set_headers_impl(Err(headers));
+    }
+
+    /// Set the headers to the first row of the data, which was just read.
+    fn set_headers_from_data(&mut self, record: ByteRecord) {
+        if self.state.has_headers {
+            // The header row isn't a data record.
+            self.state.records_read =
+                self.state.records_read.saturating_sub(1);
+        }
+        self.set_headers_impl(Err(record));
+    }
+
+    fn set_headers_impl(
+        &mut self,
+        headers: result::Result<StringRecord, ByteRecord>,
+    ) {
+        // If we have string headers, then get byte headers. But if we have
+        // byte headers, then get the string headers (or a UTF-8 error).
+        let (mut str_headers, mut byte_headers) = match headers {
+            Ok(string) => {
+                let bytes = string.clone().into_byte_record();
+                (Ok(string), bytes)
+            }
+            Err(bytes) => {
+                match StringRecord::from_byte_record(bytes.clone()) {
+                    Ok(str_headers) => (Ok(str_headers), bytes),
+                    Err(err) => (Err(err.utf8_error().clone()), bytes),
+                }
+            }
+        };
+        if self.state.trim.should_trim_headers() {
+            if let Ok(ref mut str_headers) = str_headers.as_mut() {
+                str_headers.trim();
+            }
+            byte_headers.trim();
+        }
+        self.state.headers = Some(Headers {
+            byte_record: byte_headers,
+            string_record: str_headers,
+            index: None,
+        });
+    }
+
+    /// Read a single row into the given record. Returns false when no more
+    /// records could be read.
+    ///
+    /// If `has_headers` was enabled via a `ReaderBuilder` (which is the
+    /// default), then this will never read the first record.
+    ///
+    /// This method is useful when you want to read records as fast as
+    /// as possible. It's less ergonomic than an iterator, but it permits the
+    /// caller to reuse the `StringRecord` allocation, which usually results
+    /// in higher throughput.
+    ///
+    /// Records read via this method are guaranteed to have a position set
+    /// on them, even if the reader is at EOF or if an error is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::{Reader, StringRecord};
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,pop
+    /// Boston,United States,4628910
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///     let mut record = StringRecord::new();
+    ///
+    ///     if rdr.read_record(&mut record)? {
+    ///         assert_eq!(record, vec!["Boston", "United States", "4628910"]);
+    ///         Ok(())
+    ///     } else {
+    ///         Err(From::from("expected at least one record but got none"))
+    ///     }
+    /// }
+    /// ```
+    pub fn read_record(&mut self, record: &mut StringRecord) -> Result<bool> {
+        let result = record.read(self);
+        // We need to trim again because trimming string records includes
+        // Unicode whitespace. (ByteRecord trimming only includes ASCII
+        // whitespace.)
+        if self.state.trim.should_trim_fields() {
+            record.trim();
+        }
+        result
+    }
+
+    /// Read at most `n` records, returning fewer only when no more records
+    /// could be read.
+    ///
+    /// The reader is left positioned just after the last record returned, so
+    /// that reading may continue from there. This is useful for reading CSV
+    /// data a page at a time.
+    ///
+    /// If `has_headers` was enabled via a `ReaderBuilder` (which is the
+    /// default), then this will never read the first record.
+    ///
+    /// If an error occurs, then it is returned and the records read before
+    /// it are discarded. The reader is positioned just after the record that
+    /// caused the error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Reader;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,pop
+    /// Boston,United States,4628910
+    /// Concord,United States,42695
+    /// Springfield,United States,153060
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///
+    ///     let page = rdr.read_n_records(2)?;
+    ///     assert_eq!(page.len(), 2);
+    ///     assert_eq!(page[1], vec!["Concord", "United States", "42695"]);
+    ///
+    ///     let page = rdr.read_n_records(2)?;
+    ///     assert_eq!(page, vec![
+    ///         vec!["Springfield", "United States", "153060"],
+    ///     ]);
+    ///     assert!(rdr.read_n_records(2)?.is_empty());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn read_n_records(&mut self, n: usize) -> Result<Vec<StringRecord>> {
+        let mut records = vec![];
+        let mut record = StringRecord::new();
+        while records.len() < n && self.read_record(&mut record)? {
+            records.push(record.clone());
+        }
+        Ok(records)
+    }
+
+    /// Read a single record with exactly `N` fields into an array.
+    ///
+    /// If there are no more records, then this returns `None`. If the
+    /// record read doesn't have exactly `N` fields, then an `UnequalLengths`
+    /// error is returned, whose `expected_len` is `N`.
+    ///
+    /// Like `read_record`, this never reads the header row if `has_headers`
+    /// is enabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Reader;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,pop
+    /// Boston,United States,4628910
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///
+    ///     if let Some([city, _, pop]) = rdr.read_array()? {
+    ///         assert_eq!(city, "Boston");
+    ///         assert_eq!(pop, "4628910");
+    ///     }
+    ///     assert!(rdr.read_array::<3>()?.is_none());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn read_array<const N: usize>(
+        &mut self,
+    ) -> Result<Option<[String; N]>> {
+        let mut record = StringRecord::new();
+        if !self.read_record(&mut record)? {
+            return Ok(None);
+        }
+        if record.len() != N {
+            return Err(Error::new(ErrorKind::UnequalLengths {
+                pos: record.position().cloned(),
+                expected_len: N as u64,
+                len: record.len() as u64,
+            }));
+        }
+        Ok(Some(std::array::from_fn(|i| record[i].to_string())))
+    }
+
+    /// Read all remaining records and collect the field at index `i` of
+    /// each.
+    ///
+    /// If `has_headers` is enabled, then the header record is not included.
+    /// If a record has no field at index `i`, then a
+    /// `ErrorKind::MissingColumn` error is returned, whose name is the
+    /// header of the column if there is one, or else the index `i`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Reader;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// name,email
+    /// Alice,alice@example.com
+    /// Bob,bob@example.com
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///     let emails = rdr.column(1)?;
+    ///     assert_eq!(emails, vec!["alice@example.com", "bob@example.com"]);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn column(&mut self, i: usize) -> Result<Vec<String>> {
+        let mut values = vec![];
+        let mut record = StringRecord::new();
+        while self.read_record(&mut record)? {
+            match record.get(i) {
+                Some(value) => values.push(value.to_string()),
+                None => {
+                    let name = match self.state.headers {
+                        Some(ref headers) if self.state.has_headers => {
+                            headers.byte_record.get(i).map(|name| {
+                                String::from_utf8_lossy(name).into_owned()
+                            })
+                        }
+                        _ => None,
+                    };
+                    return Err(Error::new(ErrorKind::MissingColumn {
+                        pos: record.position().cloned(),
+                        name: name.unwrap_or_else(|| i.to_string()),
+                    }));
+                }
+            }
+        }
+        Ok(values)
+    }
+
+    /// Read a single row into the given byte record. Returns false when no
+    /// more records could be read.
+    ///
+    /// If `has_headers` was enabled via a `ReaderBuilder` (which is the
+    /// default), then this will never read the first record.
+    ///
+    /// This method is useful when you want to read records as fast as
+    /// as possible. It's less ergonomic than an iterator, but it permits the
+    /// caller to reuse the `ByteRecord` allocation, which usually results
+    /// in higher throughput.
+    ///
+    /// Records read via this method are guaranteed to have a position set
+    /// on them, even if the reader is at EOF or if an error is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::{ByteRecord, Reader};
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,pop
+    /// Boston,United States,4628910
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///     let mut record = ByteRecord::new();
+    ///
+    ///     if rdr.read_byte_record(&mut record)? {
+    ///         assert_eq!(record, vec!["Boston", "United States", "4628910"]);
+    ///         Ok(())
+    ///     } else {
+    ///         Err(From::from("expected at least one record but got none"))
+    ///     }
+    /// }
+    /// ```
+    pub fn read_byte_record(
+        &mut self,
+        record: &mut ByteRecord,
+    ) -> Result<bool> {
+        if self.state.plain
+            && self.state.first
+            && self.state.headers.is_some()
+            && !self.state.capture_raw
+            && self.state.peeked.is_none()
+            && self.state.buffered.is_empty()
+        {
+            let ok = self.read_plain_record(record)?;
+            if self.state.trim.should_trim_fields() {
+                record.trim();
+            }
+            return Ok(ok);
+        }
+        if let Some(peeked) = self.state.peeked.take() {
+            *record = peeked.into_byte_record();
+            return Ok(true);
+        }
+        if let Some(buffered) = self.state.buffered.pop_front() {
+            *record = buffered;
+            return Ok(true);
+        }
+        if !self.state.seeked && !self.state.has_headers && !self.state.first {
+            // If the caller indicated "no headers" and we haven't yielded the
+            // first record yet, then we should yield our header row if we have
+            // one.
+            if let Some(ref headers) = self.state.headers {
+                self.state.first = true;
+                record.clone_from(&headers.byte_record);
+                if self.state.trim.should_trim_fields() {
+                    record.trim();
+                }
+                return self.count_data_record(Ok(!record.is_empty()));
+            }
+        }
+        let ok = self.read_byte_record_impl(record)?;
+        self.state.first = true;
+        if !self.state.seeked && self.state.headers.is_none() {
+            self.set_headers_from_data(record.clone());
+            // If the end user indicated that we have headers, then we should
+            // never return the first row. Instead, we should attempt to
+            // read and return the next one.
+            if self.state.has_headers {
+                let result = self.read_byte_record_impl(record);
+                if self.state.trim.should_trim_fields() {
+                    record.trim();
+                }
+                let result = self.count_data_record(result);
+                return self.end_section(record, result);
+            }
+        } else if self.state.trim.should_trim_fields() {
+            record.trim();
+        }
+        if self.state.plain {
+            // Neither `max_records` nor `stop_at_blank` is set.
+            return Ok(ok);
+        }
+        let result = self.count_data_record(Ok(ok));
+        self.end_section(record, result)
+    }
+
+    /// Count a data record that was just read, and check that it doesn't
+    /// exceed the limit set by `max_records`.
+    #[inline(always)]
+    fn count_data_record(&mut self, result: Result<bool>) -> Result<bool> {
+        if let Ok(true) = result {
+            self.state.data_records += 1;
+            if let Some(limit) = self.state.max_records {
+                if self.state.data_records > limit {
+                    return Err(Error::new(ErrorKind::TooManyRecords {
+                        limit,
+                    }));
+                }
+            }
+        }
+        result
+    }
+
+    /// If a blank line preceded the record just read and `stop_at_blank` is
+    /// enabled, keep the record for the next read and report that no record
+    /// was found instead.
+    #[inline(always)]
+    fn end_section(
+        &mut self,
+        record: &mut ByteRecord,
+        result: Result<bool>,
+    ) -> Result<bool> {
+        if !self.state.blank_before {
+            return result;
+        }
+        self.state.blank_before = false;
+        if let Ok(true) = result {
+            self.state.buffered.push_front(record.clone());
+            record.clear();
+            return Ok(false);
+        }
+        result
+    }
+
+    /// Read a single row into the given byte record, along with the raw
+    /// bytes it was parsed from. Returns false when no more records could be
+    /// read.
+    ///
+    /// This is like `read_byte_record`, except that `raw` is also filled
+    /// with the exact bytes consumed from the underlying reader to read the
+    /// record, including its quotes and its terminator. This is useful when
+    /// the original data must be retained or passed through verbatim.
+    /// Bytes consumed along the way that aren't part of any record, such as
+    /// comments, blank records that are skipped, or the header row when it
+    /// is read by this call, precede the record's own bytes in `raw`. When
+    /// no more records could be read, `raw` holds whatever trailing bytes
+    /// were consumed. As a result, concatenating every `raw` buffer filled
+    /// by calls to this method reproduces the data read.
+    ///
+    /// Records that were read ahead, e.g., by `peek`, were already consumed,
+    /// so `raw` is left empty for them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::{ByteRecord, Reader};
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,pop
+    /// \"Boston\" , 4628910
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///     let mut record = ByteRecord::new();
+    ///     let mut raw = vec![];
+    ///
+    ///     assert!(rdr.read_record_raw(&mut record, &mut raw)?);
+    ///     assert_eq!(record, vec!["Boston ", " 4628910"]);
+    ///     assert_eq!(raw, data.as_bytes());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn read_record_raw(
+        &mut self,
+        record: &mut ByteRecord,
+        raw: &mut Vec<u8>,
+    ) -> Result<bool> {
+        self.state.raw_record.clear();
+        self.state.capture_raw = true;
+        let result = self.read_byte_record(record);
+        self.state.capture_raw = false;
+        raw.clear();
+        raw.extend_from_slice(&self.state.raw_record);
+        result
+    }
+
+    /// Read a single row and fill `spans` with the byte ranges of its
+    /// fields. Returns false when no more records could be read.
+    ///
+    /// Each span is a pair of the start and end byte offsets of a field in
+    /// the underlying data, like those reported by `Position::byte`. A span
+    /// covers the data of its field, so the enclosing quotes of a quoted
+    /// field are excluded: its span starts just after the opening quote,
+    /// which tells quoted fields apart from unquoted ones. Escaped or
+    /// doubled quotes inside a field are part of its span. This is useful
+    /// for tools like editors that need to map fields back to the data, for
+    /// example, to highlight or select a cell.
+    ///
+    /// The spans describe the data as it was read, before any trimming,
+    /// and they only cover the last physical record when
+    /// `ReaderBuilder::record_boundary` groups several of them together.
+    /// Records that were read ahead, e.g., by `peek`, were already consumed,
+    /// so `spans` is left empty for them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Reader;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,pop
+    /// \"Concord, MA\",42695
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///     let mut spans = vec![];
+    ///
+    ///     assert!(rdr.read_record_spans(&mut spans)?);
+    ///     assert_eq!(spans, vec![(10, 21), (23, 28)]);
+    ///     assert_eq!(&data[spans[0].0..spans[0].1], "Concord, MA");
+    ///     assert!(!rdr.read_record_spans(&mut spans)?);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn read_record_spans(
+        &mut self,
+        spans: &mut Vec<(usize, usize)>,
+    ) -> Result<bool> {
+        let mut record = ByteRecord::new();
+        let mut raw = vec![];
+        let more = self.read_record_raw(&mut record, &mut raw)?;
+        spans.clear();
+        if !more {
+            return Ok(false);
+        }
+        let mut end = self.state.cur_pos.byte() as usize;
+        if self.state.next_physical.is_some() {
+            end -= self.state.next_raw.len();
+        }
+        let base = end - raw.len();
+        if self.span_parser.is_none() {
+            self.span_parser = Some(self.new_span_parser());
+        }
+        self.span_parser.as_mut().unwrap().field_spans(&raw, spans);
+        for span in spans.iter_mut() {
+            span.0 += base;
+            span.1 += base;
+        }
+        Ok(true)
+    }
+
+    /// Create a parser that follows the configuration of this reader and
+    /// can find the spans of the fields in a record.
+    fn new_span_parser(&self) -> FallbackReader {
+        match self.fallback {
+            Some(ref fallback) => {
+                let mut parser = fallback.clone();
+                parser.reset();
+                parser
+            }
+            None if self.state.single_field => {
+                FallbackReader::new(&[], false, &self.core)
+            }
+            None => FallbackReader::new(
+                &[self.core.get_delimiter()],
+                false,
+                &self.core,
+            ),
+        }
+    }
+
+    /// Read a single row and return an iterator over its fields. Returns
+    /// `None` when no more records could be read.
+    ///
+    /// This is like `read_byte_record`, except that the record is read into
+    /// a buffer owned by this reader, and the fields returned borrow from
+    /// that buffer. The fields are only valid until the next read, which the
+    /// borrow of this reader enforces. Since the buffer is reused for every
+    /// call, this avoids allocating a record entirely.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Reader;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,pop
+    /// Boston,United States,4628910
+    /// Concord,United States,42695
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///     let mut total = 0;
+    ///     while let Some(fields) = rdr.read_fields()? {
+    ///         total += fields.map(|field| field.len()).sum::<usize>();
+    ///     }
+    ///     assert_eq!(total, 51);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn read_fields(&mut self) -> Result<Option<Fields<'_>>> {
+        let mut record = mem::take(&mut self.state.fields);
+        let result = self.read_byte_record(&mut record);
+        self.state.fields = record;
+        if !result? {
+            return Ok(None);
+        }
+        Ok(Some(Fields { iter: self.state.fields.iter() }))
+    }
+
+    /// Read up to `max` records into the given batch. Returns the number of
+    /// records read, which is `0` when no more records could be read.
+    ///
+    /// When this returns, `batch` contains exactly the records that were
+    /// read, in order. Records already in `batch` are reused to store the
+    /// new records, so reading batches of the same size repeatedly into the
+    /// same vector avoids allocating. A batch with fewer than `max` records
+    /// means the end of the data was reached.
+    ///
+    /// If an error occurs, then `batch` contains the records that were read
+    /// successfully before the error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Reader;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,pop
+    /// Boston,4628910
+    /// Concord,42695
+    /// Springfield,153060
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///     let mut batch = vec![];
+    ///
+    ///     assert_eq!(rdr.read_batch(&mut batch, 2)?, 2);
+    ///     assert_eq!(batch[1], vec!["Concord", "42695"]);
+    ///     assert_eq!(rdr.read_batch(&mut batch, 2)?, 1);
+    ///     assert_eq!(batch, vec![vec!["Springfield", "153060"]]);
+    ///     assert_eq!(rdr.read_batch(&mut batch, 2)?, 0);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn read_batch(
+        &mut self,
+        batch: &mut Vec<ByteRecord>,
+        max: usize,
+    ) -> Result<usize> {
+        let mut n = 0;
+        while n < max {
+            if n == batch.len() {
+                batch.push(ByteRecord::new());
+            }
+            match self.read_byte_record(&mut batch[n]) {
+                Ok(true) => n += 1,
+                Ok(false) => break,
+                Err(err) => {
+                    batch.truncate(n);
+                    return Err(err);
+                }
+            }
+        }
+        batch.truncate(n);
+        Ok(n)
+    }
+
+    /// Returns a reference to the next record without consuming it.
+    ///
+    /// The record is read and cached, such that the next call to
+    /// `read_record`, `read_byte_record` or any of the record iterators
+    /// returns it instead of reading a new record. Calling `peek` repeatedly
+    /// without reading in between returns the same record.
+    ///
+    /// If there are no more records, then this returns `None`.
+    ///
+    /// Note that the position of this reader (as reported by `position`)
+    /// reflects the end of the peeked record. The peeked record itself
+    /// retains the position at which it started.
+    ///
+    /// # Example
+    ///
+    /// This example shows how to use `peek` to look ahead one record in
+    /// order to group consecutive records with the same key.
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::{Reader, StringRecord};
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// country,city
+    /// United States,Boston
+    /// United States,Concord
+    /// France,Paris
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///     let mut record = StringRecord::new();
+    ///     let mut group = vec![];
+    ///
+    ///     assert!(rdr.read_record(&mut record)?);
+    ///     group.push(record[1].to_string());
+    ///     while let Some(next) = rdr.peek()? {
+    ///         if next[0] != record[0] {
+    ///             break;
+    ///         }
+    ///         rdr.read_record(&mut record)?;
+    ///         group.push(record[1].to_string());
+    ///     }
+    ///     assert_eq!(group, vec!["Boston", "Concord"]);
+    ///
+    ///     // The record that ended the group is still available.
+    ///     assert!(rdr.read_record(&mut record)?);
+    ///     assert_eq!(record, vec!["France", "Paris"]);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn peek(&mut self) -> Result<Option<&StringRecord>> {
+        if self.state.peeked.is_none() {
+            let mut record = StringRecord::new();
+            if !self.read_record(&mut record)? {
+                return Ok(None);
+            }
+            self.state.peeked = Some(record);
+        }
+        Ok(self.state.peeked.as_ref())
+    }
+
+    /// Read records for as long as they satisfy a predicate.
+    ///
+    /// Records are read and collected until `pred` returns false for one of
+    /// them, or until there are no more records. The record for which
+    /// `pred` returned false isn't consumed: like a record returned by
+    /// `peek`, it is returned by the next read instead.
+    ///
+    /// # Example
+    ///
+    /// This example shows how to read the records preceding a sentinel row.
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::{Reader, StringRecord};
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,pop
+    /// Boston,4628910
+    /// Concord,42695
+    /// END,
+    /// Paris,2161000
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///     let records = rdr.read_while(|record| &record[0] != "END")?;
+    ///     assert_eq!(records.len(), 2);
+    ///     assert_eq!(records[1], vec!["Concord", "42695"]);
+    ///
+    ///     // The sentinel row is still available.
+    ///     let mut record = StringRecord::new();
+    ///     assert!(rdr.read_record(&mut record)?);
+    ///     assert_eq!(record, vec!["END", ""]);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn read_while<F>(&mut self, mut pred: F) -> Result<Vec<StringRecord>>
+    where
+        F: FnMut(&StringRecord) -> bool,
+    {
+        let mut records = vec![];
+        while let Some(record) = self.peek()? {
+            if !pred(record) {
+                break;
+            }
+            records.extend(self.state.peeked.take());
+        }
+        Ok(records)
+    }
+
+    /// Fold every remaining record into a single value.
+    ///
+    /// Starting with `init`, this calls `f` with the accumulated value and
+    /// each record in turn, and returns the final value. The records are read
+    /// into a single buffer that is reused, so memory use doesn't grow with
+    /// the number of records. If reading a record fails, then the error is
+    /// returned and the records that follow it aren't read.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Reader;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,pop
+    /// Boston,4628910
+    /// Concord,42695
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///     let total = rdr.fold_records(0, |total, record| {
+    ///         total + record[1].parse::<u64>().unwrap_or(0)
+    ///     })?;
+    ///     assert_eq!(total, 4671605);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn fold_records<B, F>(&mut self, init: B, mut f: F) -> Result<B>
+    where
+        F: FnMut(B, &StringRecord) -> B,
+    {
+        let mut record = StringRecord::new();
+        let mut acc = init;
+        while self.read_record(&mut record)? {
+            acc = f(acc, &record);
+        }
+        Ok(acc)
+    }
+
+    /// Read every remaining record and report how many fields they have.
+    ///
+    /// The report returned counts the records with each number of fields,
+    /// and gives the most common number of fields. This is useful for
+    /// finding out how many fields the records of ragged CSV data ought to
+    /// have. The header row isn't counted.
+    ///
+    /// Records with a different number of fields than the previous records
+    /// are counted even if `flexible` is disabled. Any other error is
+    /// returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::ReaderBuilder;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,pop
+    /// Boston,United States,4628910
+    /// Concord,United States
+    /// Paris,France,2161000
+    /// ";
+    ///     let mut rdr = ReaderBuilder::new()
+    ///         .has_headers(false)
+    ///         .from_reader(data.as_bytes());
+    ///     let report = rdr.analyze_widths()?;
+    ///     assert_eq!(report.modal_width(), Some(3));
+    ///     assert_eq!(report.counts()[&2], 1);
+    ///     assert_eq!(report.counts()[&3], 3);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn analyze_widths(&mut self) -> Result<WidthReport> {
+        let mut report = WidthReport::new();
+        let mut record = ByteRecord::new();
+        loop {
+            match self.read_byte_record(&mut record) {
+                Ok(true) => report.add_record(record.len()),
+                Ok(false) => return Ok(report),
+                Err(err) => match *err.kind() {
+                    ErrorKind::UnequalLengths { len, .. } => {
+                        report.add_record(len as usize)
+                    }
+                    _ => return Err(err),
+                },
+            }
+        }
+    }
+
+    /// Read every remaining record and split them into `n` chunks with
+    /// roughly equal numbers of records.
+    ///
+    /// Each chunk is returned as the position of its first record along
+    /// with the number of records in it, in order. A chunk can be processed
+    /// on its own by seeking a reader over the same data to its position and
+    /// reading that many records. The numbers of records of any two chunks
+    /// differ by at most one. If there are fewer than `n` records, then
+    /// fewer chunks are returned, each with a single record. The header row
+    /// isn't part of any chunk.
+    ///
+    /// This is useful for splitting work when the cost of processing a
+    /// record doesn't depend on its size, so that splitting the data into
+    /// byte ranges of equal size would be unbalanced. The position of every
+    /// record is kept in memory until all of them have been read.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `n` is `0`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::{ReaderBuilder, StringRecord};
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,pop
+    /// Boston,4628910
+    /// Concord,42695
+    /// Paris,2161000
+    /// Springfield,153060
+    /// ";
+    ///     let mut rdr = ReaderBuilder::new().from_bytes(data.as_bytes());
+    ///     let ranges = rdr.record_ranges(2)?;
+    ///     assert_eq!(ranges.len(), 2);
+    ///     assert_eq!(ranges[1].1, 2);
+    ///
+    ///     let mut record = StringRecord::new();
+    ///     rdr.seek(ranges[1].0.clone())?;
+    ///     assert!(rdr.read_record(&mut record)?);
+    ///     assert_eq!(record, vec!["Paris", "2161000"]);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn record_ranges(&mut self, n: usize) -> Result<Vec<(Position, u64)>> {
+        assert!(n > 0, "the number of chunks must be at least 1");
+        let mut starts = vec![];
+        let mut record = ByteRecord::new();
+        while self.read_byte_record(&mut record)? {
+            starts.push(
+                record.position().cloned().unwrap_or_else(Position::new),
+            );
+        }
+        let total = starts.len();
+        let mut ranges = Vec::with_capacity(cmp::min(n, total));
+        for k in 0..n {
+            let (start, end) = (k * total / n, (k + 1) * total / n);
+            if end > start {
+                ranges.push((starts[start].clone(), (end - start) as u64));
+            }
+        }
+        Ok(ranges)
+    }
+
+    /// Deserialize every remaining record, collecting errors instead of
+    /// stopping at the first one.
+    ///
+    /// This is like `deserialize`, except that it reads all of the
+    /// remaining records at once, and a record that fails to be read or
+    /// deserialized doesn't stop the others from being read. The report
+    /// returned contains the records that were deserialized, the error for
+    /// every record that wasn't (along with its position), the number of
+    /// deserialization failures caused by each column and the number of
+    /// empty fields in each column.
+    ///
+    /// To stop at the first error instead, use `deserialize`.
+    ///
+    /// # Errors
+    ///
+    /// I/O errors can't be recovered from, so if one occurs, it's returned
+    /// and the report is discarded. An error reading the header row is also
+    /// returned, since no record can be deserialized without it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    ///
+    /// use csv::Reader;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Debug, Deserialize)]
+    /// struct Row {
+    ///     city: String,
+    ///     pop: u64,
+    /// }
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,pop
+    /// Boston,4628910
+    /// Concord,unknown
+    /// Springfield,
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///     let report = rdr.deserialize_validated::<Row>()?;
+    ///     assert_eq!(report.rows().len(), 1);
+    ///     assert_eq!(report.errors().len(), 2);
+    ///     assert_eq!(report.errors()[0].0.line(), 3);
+    ///     assert_eq!(report.column_failures(), &[0, 2]);
+    ///     assert_eq!(report.null_counts(), &[0, 1]);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn deserialize_validated<D>(&mut self) -> Result<ValidationReport<D>>
+    where
+        D: DeserializeOwned,
+    {
+        let headers = if self.has_headers() {
+            Some(self.headers()?.clone())
+        } else {
+            None
+        };
+        let mut report = ValidationReport::new();
+        let mut record = StringRecord::new();
+        loop {
+            match self.read_record(&mut record) {
+                Ok(true) => {}
+                Ok(false) => return Ok(report),
+                Err(err) => {
+                    if err.is_io_error() {
+                        return Err(err);
+                    }
+                    let pos = match err.position() {
+                        Some(pos) => pos.clone(),
+                        None => self.position().clone(),
+                    };
+                    report.add_error(pos, err);
+                    continue;
+                }
+            }
+            report.count_nulls(&record);
+            let result = deserialize_string_record_with(
+                &record,
+                headers.as_ref(),
+                &self.state.deserialize,
+            );
+            match result {
+                Ok(row) => report.add_row(row),
+                Err(err) => {
+                    let pos = match record.position() {
+                        Some(pos) => pos.clone(),
+                        None => self.position().clone(),
+                    };
+                    report.add_error(pos, err);
+                }
+            }
+        }
+    }
+
+    /// Infer the type of every column from the next `sample_rows` records.
+    ///
+    /// Each column is assigned the narrowest type that fits all of its
+    /// non-empty values: an integer, a float, a boolean (`true` or `false`),
+    /// a date (`YYYY-MM-DD`) or, failing all of those, a string. A column
+    /// that has at least one empty value is flagged as nullable.
+    ///
+    /// The sampled records are buffered, so subsequent reads return them
+    /// before reading any further input. Note that `position` reflects the
+    /// end of the sample until the buffered records have all been read.
+    ///
+    /// If the CSV data has headers, then they are read (if they have not
+    /// been already) but are not included in the sample.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::{FieldType, Reader};
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,population,founded
+    /// Boston,4628910,1630-09-07
+    /// Concord,,1635-09-12
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///     let schema = rdr.infer_schema(10)?;
+    ///     assert_eq!(schema[0].field_type(), FieldType::String);
+    ///     assert_eq!(schema[1].field_type(), FieldType::Integer);
+    ///     assert!(schema[1].is_nullable());
+    ///     assert_eq!(schema[2].field_type(), FieldType::Date);
+    ///
+    ///     // The sampled records are still available.
+    ///     assert_eq!(rdr.records().count(), 2);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn infer_schema(
+        &mut self,
+        sample_rows: usize,
+    ) -> Result<Vec<InferredType>> {
+        let mut sample = Vec::with_capacity(sample_rows.min(1024));
+        let mut record = ByteRecord::new();
+        let mut result = Ok(());
+        while sample.len() < sample_rows {
+            match self.read_byte_record(&mut record) {
+                Ok(true) => sample.push(record.clone()),
+                Ok(false) => break,
+                Err(err) => {
+                    result = Err(err);
+                    break;
+                }
+            }
+        }
+        let types = schema::infer(&sample);
+        // Put the sample back in front of anything that was already
+        // buffered, so that records are always returned in order.
+        for record in sample.into_iter().rev() {
+            self.state.buffered.push_front(record);
+        }
+        result.map(|()| types)
+    }
+
+    /// Guess whether the first row of the CSV data is a header row.
+    ///
+    /// This is a heuristic for when it isn't known in advance whether the
+    /// data has headers. The first row is guessed to be a header when all of
+    /// its fields are non-empty strings, and the second row has a value that
+    /// isn't a string (an integer, a float, a boolean or a date) in at least
+    /// one of the same columns. In particular, `false` is returned when there
+    /// are fewer than two rows, since there is nothing to compare with.
+    ///
+    /// This works regardless of whether `has_headers` is enabled. The rows
+    /// read to make the guess are buffered, so subsequent reads return them
+    /// as usual. Note that `position` reflects the end of those rows until
+    /// the buffered records have all been read.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::ReaderBuilder;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,pop
+    /// Boston,4628910
+    /// ";
+    ///     let mut rdr = ReaderBuilder::new()
+    ///         .has_headers(false)
+    ///         .from_reader(data.as_bytes());
+    ///     assert!(rdr.guess_has_headers()?);
+    ///
+    ///     // The rows that were read to make the guess are still available.
+    ///     assert_eq!(rdr.records().count(), 2);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn guess_has_headers(&mut self) -> Result<bool> {
+        let mut rows = Vec::with_capacity(2);
+        if self.state.has_headers {
+            rows.push(self.byte_headers()?.clone());
+        }
+        let skip = rows.len();
+        let mut record = ByteRecord::new();
+        let mut result = Ok(());
+        while rows.len() < 2 {
+            match self.read_byte_record(&mut record) {
+                Ok(true) => rows.push(record.clone()),
+                Ok(false) => break,
+                Err(err) => {
+                    result = Err(err);
+                    break;
+                }
+            }
+        }
+        let guess =
+            rows.len() == 2 && schema::looks_like_header(&rows[0], &rows[1]);
+        for record in rows.into_iter().skip(skip).rev() {
+            self.state.buffered.push_front(record);
+        }
+        result.map(|()| guess)
+    }
+
+    /// Read a byte record from the underlying CSV reader, without accounting
+    /// for headers.
+    #[inline(always)]
+    fn read_byte_record_impl(
+        &mut self,
+        record: &mut ByteRecord,
+    ) -> Result<bool> {
+        if self.state.plain && !self.state.capture_raw {
+            return self.read_plain_record(record);
+        }
+        self.read_configured_record(record)
+    }
+
+    /// Like `read_byte_record_impl`, but for a reader with some of the
+    /// options set that `read_plain_record` doesn't handle.
+    ///
+    /// This is kept out of line so that it doesn't bloat the fast path.
+    #[inline(never)]
+    fn read_configured_record(
+        &mut self,
+        record: &mut ByteRecord,
+    ) -> Result<bool> {
+        if !self.state.verify_trailer {
+            return self.read_logical_record(record);
+        }
+        let ok = match self.state.after_trailer.take() {
+            Some(next) => {
+                *record = next;
+                true
+            }
+            None => self.read_logical_record(record)?,
+        };
+        if !ok {
+            return Ok(false);
+        }
+        let expected = match trailer_count(record) {
+            None => {
+                self.state.records_read += 1;
+                return Ok(true);
+            }
+            Some(expected) => expected,
+        };
+        let mut next = ByteRecord::new();
+        if self.read_logical_record(&mut next)? {
+            // The trailer-like record isn't last, so it's data, and it is
+            // checked like any other record.
+            self.state.after_trailer = Some(next);
+            self.state.add_record(record)?;
+            self.state.records_read += 1;
+            return Ok(true);
+        }
+        let actual = self.state.records_read;
+        if self.state.seeked || expected == actual {
+            record.clear();
+            return Ok(false);
+        }
+        Err(Error::new(ErrorKind::TrailerMismatch {
+            pos: record.position().cloned(),
+            expected,
+            actual,
+        }))
+    }
+
+    /// Returns true if the given record may be a trailer to verify. The
+    /// checks on the number of fields of such a record are left to
+    /// `read_byte_record_impl`, since they don't apply to a trailer.
+    #[inline(always)]
+    fn may_be_trailer(&self, record: &ByteRecord) -> bool {
+        self.state.verify_trailer && trailer_count(record).is_some()
+    }
+
+    /// Read a logical record from the underlying CSV reader, which is a
+    /// single physical record unless `record_boundary` is set.
+    #[inline(always)]
+    fn read_logical_record(
+        &mut self,
+        record: &mut ByteRecord,
+    ) -> Result<bool> {
+        if self.state.record_boundary.is_none() {
+            return self.read_physical_record(record);
+        }
+        let starts_record = match self.state.next_physical.take() {
+            Some(mut next) => {
+                // The record number wasn't known when it was read.
+                if let Some(mut pos) = next.position().cloned() {
+                    pos.set_record(self.state.cur_pos.record());
+                    next.set_position(Some(pos));
+                }
+                *record = next;
+                let raw = mem::take(&mut self.state.next_raw);
+                if self.state.capture_raw {
+                    self.state.raw_record.extend_from_slice(&raw);
+                }
+                true
+            }
+            None => self.read_physical_record(record)?,
+        };
+        if !starts_record {
+            return Ok(false);
+        }
+        let mut next = ByteRecord::new();
+        while self.read_physical_record(&mut next)? {
+            let boundary = self.state.record_boundary.as_ref().unwrap();
+            let raw = &self.state.raw_record[self.state.raw_start..];
+            if (boundary.0)(trim_terminators(raw, &self.core)) {
+                self.state.next_physical = Some(next);
+                self.state.next_raw =
+                    self.state.raw_record.split_off(self.state.raw_start);
+                break;
+            }
+            record.extend_from_record(&next);
+        }
+        if !self.may_be_trailer(record) {
+            self.state.add_record(record)?;
+        }
+        Ok(true)
+    }
+
+    /// Read a single record from the underlying CSV reader when none of the
+    /// options that `read_physical_record` handles are set.
+    ///
+    /// This is kept separate so that the default configuration doesn't pay
+    /// for the bookkeeping those options need.
+    #[inline(always)]
+    fn read_plain_record(&mut self, record: &mut ByteRecord) -> Result<bool> {
+        use csv_core::ReadRecordResult::*;
+
+        record.clear();
+        record.set_position(Some(self.state.cur_pos.clone()));
+        if self.state.eof {
+            return Ok(false);
+        }
+        let (mut outlen, mut endlen) = (0, 0);
+        let mut unterminated_quote = false;
+        self.state.in_record = true;
+        loop {
+            let (res, nin, nout, nend) = {
+                let input = self.rdr.fill_buf()?;
+                if input.is_empty() {
+                    unterminated_quote = self.core.is_in_quoted_field();
+                }
+                let (fields, ends) = record.as_parts();
+                self.core.read_record(
+                    input,
+                    &mut fields[outlen..],
+                    &mut ends[endlen..],
+                )
+            };
+            self.rdr.consume(nin);
+            let byte = self.state.cur_pos.byte();
+            self.state
+                .cur_pos
+                .set_byte(byte + nin as u64)
+                .set_line(self.core.line());
+            outlen += nout;
+            endlen += nend;
+            match res {
+                InputEmpty => continue,
+                OutputFull => {
+                    record.expand_fields(self.state.growth);
+                    continue;
+                }
+                OutputEndsFull => {
+                    record.expand_ends();
+                    continue;
+                }
+                Record => {
+                    record.set_len(endlen);
+                    self.state.in_record = false;
+                    let unescaped_quote = self.core.take_unescaped_quote();
+                    self.state.add_record(record)?;
+                    if let Some(field) = unescaped_quote {
+                        return Err(Error::new(ErrorKind::UnescapedQuote {
+                            pos: record.position().cloned(),
+                            field,
+                        }));
+                    }
+                    if unterminated_quote {
+                        return Err(Error::new(
+                            ErrorKind::UnterminatedQuote {
+                                pos: record.position().cloned(),
+                                field: record.len() as u64 - 1,
+                            },
+                        ));
+                    }
+                    return Ok(true);
+                }
+                End => {
+                    self.state.in_record = false;
+                    self.state.eof = true;
+                    return Ok(false);
+                }
+            }
+        }
+    }
+
+    /// Read a single physical record from the underlying CSV reader.
+    ///
+    /// When `record_boundary` is set, the raw bytes of the record are kept,
+    /// and the checks on the number of fields are left to
+    /// `read_logical_record`. When `capture_raw` is set, the raw bytes are
+    /// appended to those already kept.
+    #[inline(always)]
+    fn read_physical_record(
+        &mut self,
+        record: &mut ByteRecord,
+    ) -> Result<bool> {
+        use csv_core::ReadRecordResult::*;
+
+        if !self.state.capture_raw {
+            self.state.raw_record.clear();
+        }
+        self.state.raw_start = self.state.raw_record.len();
+        self.state.blank_before = false;
+        record.clear();
+        record.set_position(Some(self.state.cur_pos.clone()));
+        if self.state.eof {
+            return Ok(false);
+        }
+        // Whether the bytes read so far only consist of record terminators,
+        // and whether any of them form a blank line.
+        let mut leading = self.state.stop_at_blank && self.state.after_record;
+        let mut skip_lf = self.state.last_cr;
+        let mut blank = false;
+        record.reserve_ends(self.state.fields_hint);
+        let (mut outlen, mut endlen) = (0, 0);
+        // The number of fields skipped because of `max_fields`.
+        let mut skipped = 0;
+        let max_fields = self.state.max_fields.unwrap_or(usize::MAX);
+        let mut unterminated_quote = false;
+        self.state.in_record = true;
+        loop {
+            let (res, nin, nout, nend) = {
+                let input = self.rdr.fill_buf()?;
+                if input.is_empty() {
+                    unterminated_quote = match self.fallback {
+                        Some(ref fallback) => fallback.is_in_quoted_field(),
+                        None => self.core.is_in_quoted_field(),
+                    };
+                }
+                let result = if let Some(ref mut fallback) = self.fallback {
+                    let (res, nin) = fallback.read_record(input, record);
+                    (res, nin, 0, 0)
+                } else if endlen >= max_fields {
+                    // The remaining fields are parsed into scratch buffers
+                    // and discarded.
+                    let (mut out, mut ends) = ([0; 1024], [0; 32]);
+                    let (res, nin, _, nend) =
+                        self.core.read_record(input, &mut out, &mut ends);
+                    skipped += nend;
+                    (res, nin, 0, 0)
+                } else {
+                    let (fields, ends) = record.as_parts();
+                    let ends_end = cmp::min(ends.len(), max_fields);
+                    self.core.read_record(
+                        input,
+                        &mut fields[outlen..],
+                        &mut ends[endlen..ends_end],
+                    )
+                };
+                if self.state.capture_raw
+                    || self.state.record_boundary.is_some()
+                {
+                    self.state
+                        .raw_record
+                        .extend_from_slice(&input[..result.1]);
+                }
+                if self.state.stop_at_blank && result.1 > 0 {
+                    let term = self.core.get_terminator();
+                    if leading {
+                        for &b in &input[..result.1] {
+                            if mem::take(&mut skip_lf) && b == b'\n' {
+                                continue;
+                            }
+                            if !is_terminator(term, b) {
+                                leading = false;
+                                break;
+                            }
+                            blank = true;
+                        }
+                    }
+                    self.state.last_cr =
+                        matches!(term, csv_core::Terminator::CRLF)
+                            && input[result.1 - 1] == b'\r';
+                }
+                result
+            };
+            self.rdr.consume(nin);
+            let byte = self.state.cur_pos.byte();
+            let line = self.parser_line();
+            self.state.cur_pos.set_byte(byte + nin as u64).set_line(line);
+            outlen += nout;
+            endlen += nend;
+            match res {
+                InputEmpty => continue,
+                OutputFull => {
+                    if endlen < max_fields {
+                        record.expand_fields(self.state.growth);
+                    }
+                    continue;
+                }
+                OutputEndsFull => {
+                    if endlen < max_fields {
+                        record.expand_ends();
+                    }
+                    continue;
+                }
+                Record => {
+                    // The fallback parser adds fields to the record itself.
+                    if self.fallback.is_none() {
+                        record.set_len(endlen);
+                    } else if record.len() > max_fields {
+                        skipped = record.len() - max_fields;
+                        record.truncate(max_fields);
+                    }
+                    self.state.in_record = false;
+                    // An unterminated quoted field is always the last one.
+                    let unterminated_field = if unterminated_quote {
+                        Some((record.len() + skipped) as u64 - 1)
+                    } else {
+                        None
+                    };
+                    // If fields were skipped, then so was any trailing
+                    // delimiter.
+                    if self.state.ignore_trailing_delimiter
+                        && skipped == 0
+                        && record.len() > 1
+                        && record.get(record.len() - 1) == Some(&b""[..])
+                    {
+                        record.truncate(record.len() - 1);
+                    }
+                    if self.state.skip_blank_records && is_blank(record) {
+                        // Blank records aren't returned, but they still
+                        // count toward the record number.
+                        blank |= self.state.stop_at_blank
+                            && self.state.after_record;
+                        self.core.take_unescaped_quote();
+                        unterminated_quote = false;
+                        let i = self.state.cur_pos.record();
+                        self.state
+                            .cur_pos
+                            .set_record(i.checked_add(1).unwrap());
+                        record.clear();
+                        record.set_position(Some(self.state.cur_pos.clone()));
+                        outlen = 0;
+                        endlen = 0;
+                        skipped = 0;
+                        self.state.in_record = true;
+                        continue;
+                    }
+                    #[cfg(feature = "encoding")]
+                    {
+                        if self.state.encoding == Encoding::Latin1 {
+                            encoding::latin1_to_utf8(record);
+                        }
+                    }
+                    let unescaped_quote = self.core.take_unescaped_quote();
+                    self.state.after_record = true;
+                    if blank {
+                        // A new section may have a different number of
+                        // fields.
+                        self.state.first_field_count = None;
+                        self.state.blank_before = true;
+                    }
+                    if self.state.record_boundary.is_none()
+                        && !self.may_be_trailer(record)
+                    {
+                        self.state.add_record(record)?;
+                    }
+                    if let Some(field) = unescaped_quote {
+                        return Err(Error::new(ErrorKind::UnescapedQuote {
+                            pos: record.position().cloned(),
+                            field,
+                        }));
+                    }
+                    if let Some(field) = unterminated_field {
+                        return Err(Error::new(
+                            ErrorKind::UnterminatedQuote {
+                                pos: record.position().cloned(),
+                                field,
+                            },
+                        ));
+                    }
+                    return Ok(true);
+                }
+                End => {
+                    self.state.in_record = false;
+                    self.state.eof = true;
+                    return Ok(false);
+                }
+            }
+        }
+    }
+
+    /// Return the current line number of the parser in use.
+    fn parser_line(&self) -> u64 {
+        match self.fallback {
+            Some(ref fallback) => fallback.line(),
+            None => self.core.line(),
+        }
+    }
+
+    /// Reset the parser in use, e.g., after seeking, and set its line
+    /// number.
+    fn reset_parser(&mut self, line: u64) {
+        self.core.reset();
+        self.core.set_line(line);
+        if let Some(ref mut fallback) = self.fallback {
+            fallback.reset();
+            fallback.set_line(line);
+        }
+    }
+
+    /// Skip the remainder of a partially read record, so that the next call
+    /// to read a record starts at a record boundary.
+    ///
+    /// A read can stop in the middle of a record when the underlying reader
+    /// returns an error. In that case, calling this method discards input up
+    /// to and including the next record terminator that appears outside of
+    /// quotes, which permits best-effort parsing to continue with the
+    /// following record. The discarded record still counts toward the record
+    /// number reported by `position`.
+    ///
+    /// Errors that are found after a full record has been parsed, like
+    /// `UnequalLengths` or `Utf8` errors, already leave the reader at the
+    /// start of the next record. In that case, this does nothing, and the
+    /// expected field count established by the first record is kept.
+    ///
+    /// Note that resyncing relies on the parser's view of which bytes are
+    /// quoted. If some input never reached the parser (for example, because
+    /// an error occurred on the underlying reader), then the quoting may be
+    /// ambiguous and this method may stop at a terminator in the middle of
+    /// a record.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::ReaderBuilder;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,pop
+    /// Boston,United States
+    /// Concord,United States,42695
+    /// ";
+    ///     let mut rdr = ReaderBuilder::new().from_reader(data.as_bytes());
+    ///     let mut good = vec![];
+    ///     let mut bad = 0;
+    ///     loop {
+    ///         let mut record = csv::StringRecord::new();
+    ///         match rdr.read_record(&mut record) {
+    ///             Ok(true) => good.push(record),
+    ///             Ok(false) => break,
+    ///             Err(_) => {
+    ///                 bad += 1;
+    ///                 rdr.resync()?;
+    ///             }
+    ///         }
+    ///     }
+    ///     assert_eq!(bad, 1);
+    ///     assert_eq!(good, vec![vec!["Concord", "United States", "42695"]]);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn resync(&mut self) -> Result<()> {
+        use csv_core::ReadRecordResult::*;
+
+        if !self.state.in_record {
+            return Ok(());
+        }
+        // The field data of the skipped record is thrown away, so scratch
+        // buffers that are reused on every iteration suffice.
+        let (mut out, mut ends) = ([0; 1024], [0; 32]);
+        let mut scratch = ByteRecord::new();
+        loop {
+            let (res, nin) = {
+                let input = self.rdr.fill_buf()?;
+                if let Some(ref mut fallback) = self.fallback {
+                    scratch.clear();
+                    fallback.read_record(input, &mut scratch)
+                } else {
+                    let (res, nin, _, _) =
+                        self.core.read_record(input, &mut out, &mut ends);
+                    (res, nin)
+                }
+            };
+            self.rdr.consume(nin);
+            let byte = self.state.cur_pos.byte();
+            let line = self.parser_line();
+            self.state.cur_pos.set_byte(byte + nin as u64).set_line(line);
+            match res {
+                InputEmpty | OutputFull | OutputEndsFull => continue,
+                Record => {
+                    self.core.take_unescaped_quote();
+                    let i = self.state.cur_pos.record();
+                    self.state.cur_pos.set_record(i.checked_add(1).unwrap());
+                    break;
+                }
+                End => {
+                    self.state.eof = true;
+                    break;
+                }
+            }
+        }
+        self.state.in_record = false;
+        Ok(())
+    }
+
+    /// Return the current position of this CSV reader.
+    ///
+    /// The byte offset in the position returned can be used to `seek` this
+    /// reader. In particular, seeking to a position returned here on the same
+    /// data will result in parsing the same subsequent record.
+    ///
+    /// # Example: reading the position
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use std::io;
+    /// use csv::{Reader, Position};
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,popcount
+    /// Boston,United States,4628910
+    /// Concord,United States,42695
+    /// ";
+    ///     let rdr = Reader::from_reader(io::Cursor::new(data));
+    ///     let mut iter = rdr.into_records();
+    ///     let mut pos = Position::new();
+    ///     loop {
+    ///         // Read the position immediately before each record.
+    ///         let next_pos = iter.reader().position().clone();
+    ///         if iter.next().is_none() {
+    ///             break;
+    ///         }
+    ///         pos = next_pos;
+    ///     }
+    ///
+    ///     // `pos` should now be the position immediately before the last
+    ///     // record.
+    ///     assert_eq!(pos.byte(), 51);
+    ///     assert_eq!(pos.line(), 3);
+    ///     assert_eq!(pos.record(), 2);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn position(&self) -> &Position {
+        &self.state.cur_pos
+    }
+
+    /// Returns true if and only if this reader has been exhausted.
+    ///
+    /// When this returns true, no more records can be read from this reader
+    /// (unless it has been seeked to another position).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use std::io;
+    /// use csv::{Reader, Position};
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,popcount
+    /// Boston,United States,4628910
+    /// Concord,United States,42695
+    /// ";
+    ///     let mut rdr = Reader::from_reader(io::Cursor::new(data));
+    ///     assert!(!rdr.is_done());
+    ///     for result in rdr.records() {
+    ///         let _ = result?;
+    ///     }
+    ///     assert!(rdr.is_done());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn is_done(&self) -> bool {
+        self.state.eof
+    }
+
+    /// Detect a byte order mark (BOM) at the start of the CSV data.
+    ///
+    /// This returns the encoding indicated by the BOM, or `None` if the data
+    /// doesn't start with one. A UTF-8 BOM is consumed, so that it doesn't
+    /// end up in the first field. A UTF-16 BOM is left in place, since this
+    /// crate can't parse UTF-16. Such data needs to be transcoded to UTF-8
+    /// before it can be read, and reading it anyway yields garbled fields.
+    ///
+    /// This must be called before anything is read. Otherwise, it always
+    /// returns `None`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::{Bom, Reader};
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\u{FEFF}city,pop\nBoston,4628910\n";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///     assert_eq!(rdr.detect_bom()?, Some(Bom::Utf8));
+    ///     assert_eq!(rdr.headers()?, vec!["city", "pop"]);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn detect_bom(&mut self) -> Result<Option<Bom>> {
+        if self.state.cur_pos.byte() > 0
+            || self.state.headers.is_some()
+            || self.state.seeked
+        {
+            return Ok(None);
+        }
+        let input = self.rdr.fill_buf()?;
+        let bom = if input.starts_with(b"\xEF\xBB\xBF") {
+            Bom::Utf8
+        } else if input.starts_with(b"\xFF\xFE") {
+            Bom::Utf16Le
+        } else if input.starts_with(b"\xFE\xFF") {
+            Bom::Utf16Be
+        } else {
+            return Ok(None);
+        };
+        if let Bom::Utf8 = bom {
+            self.rdr.consume(3);
+            self.state.cur_pos.set_byte(3);
+        }
+        Ok(Some(bom))
+    }
+
+    /// Returns the number of fields in the first record parsed, which is the
+    /// header record if there is one.
+    ///
+    /// This returns `None` if no record has been parsed yet. Unless
+    /// `flexible` is enabled, every record has this many fields.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Reader;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,pop
+    /// Boston,United States,4628910
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///     assert_eq!(rdr.field_count(), None);
+    ///     rdr.headers()?;
+    ///     assert_eq!(rdr.field_count(), Some(3));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn field_count(&self) -> Option<usize> {
+        self.state.first_field_count.map(|n| n as usize)
+    }
+
+    /// Returns true if and only if this reader has been configured to
+    /// interpret the first record as a header record.
+    pub fn has_headers(&self) -> bool {
+        self.state.has_headers
+    }
+
+    /// Returns the positions of the records read so far.
+    ///
+    /// The positions are ordered by record number, and are only recorded
+    /// when `ReaderBuilder::cache_positions` is enabled. Otherwise, this is
+    /// always empty.
+    pub fn position_cache(&self) -> &[Position] {
+        &self.state.positions
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        self.rdr.get_ref()
+    }
+
+    /// Returns a mutable reference to the underlying reader.
+    pub fn get_mut(&mut self) -> &mut R {
+        self.rdr.get_mut()
+    }
+
+    /// Unwraps this CSV reader, returning the underlying reader.
+    ///
+    /// Note that any leftover data inside this reader's internal buffer is
+    /// lost.
+    pub fn into_inner(self) -> R {
+        self.rdr.into_inner()
+    }
+
+    /// Replaces the underlying reader, returning a CSV reader that reads
+    /// from `rdr` with the same configuration.
+    ///
+    /// The returned reader behaves as if it were newly built: its position
+    /// is reset, and the header row of the new data is read again. Unlike
+    /// building a new reader, this reuses the configured parser along with
+    /// the buffers of this reader's internal records, and only allocates a
+    /// new input buffer of the same capacity. This makes it cheaper to
+    /// process many small inputs with the same configuration.
+    ///
+    /// Note that any leftover data inside this reader's internal buffer is
+    /// lost, as is the underlying reader being replaced.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Reader;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut rdr = Reader::from_reader("city\nBoston\n".as_bytes());
+    ///     assert_eq!(rdr.records().count(), 1);
+    ///
+    ///     let mut rdr = rdr.reset_with("name\nann\nbob\n".as_bytes());
+    ///     assert_eq!(rdr.headers()?, vec!["name"]);
+    ///     assert_eq!(rdr.records().count(), 2);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn reset_with<R2: io::Read>(self, rdr: R2) -> Reader<R2> {
+        let capacity = self.rdr.capacity();
+        let mut reset = Reader {
+            core: self.core,
+            fallback: self.fallback,
+            span_parser: self.span_parser,
+            rdr: io::BufReader::with_capacity(capacity, rdr),
+            state: self.state,
+        };
+        reset.reset_parser(1);
+        reset.state.reset();
+        reset.state.positions.clear();
+        reset
+    }
+}
+
+impl<R: io::Read + io::Seek> Reader<R> {
+    /// Seeks the underlying reader to the position given.
+    ///
+    /// This comes with a few caveats:
+    ///
+    /// * Any internal buffer associated with this reader is cleared.
+    /// * If the given position does not correspond to a position immediately
+    ///   before the start of a record, then the behavior of this reader is
+    ///   unspecified.
+    /// * Any special logic that skips the first record in the CSV reader
+    ///   when reading or iterating over records is disabled.
+    ///
+    /// If the given position has a byte offset equivalent to the current
+    /// position, then no seeking is performed.
+    ///
+    /// If the header row has not already been read, then this will attempt
+    /// to read the header row before seeking. Therefore, it is possible that
+    /// this returns an error associated with reading CSV data.
+    ///
+    /// Note that seeking is performed based only on the byte offset in the
+    /// given position. Namely, the record or line numbers in the position may
+    /// be incorrect, but this will cause any future position generated by
+    /// this CSV reader to be similarly incorrect.
+    ///
+    /// # Example: seek to parse a record twice
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use std::io;
+    /// use csv::{Reader, Position};
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,popcount
+    /// Boston,United States,4628910
+    /// Concord,United States,42695
+    /// ";
+    ///     let rdr = Reader::from_reader(io::Cursor::new(data));
+    ///     let mut iter = rdr.into_records();
+    ///     let mut pos = Position::new();
+    ///     loop {
+    ///         // Read the position immediately before each record.
+    ///         let next_pos = iter.reader().position().clone();
+    ///         if iter.next().is_none() {
+    ///             break;
+    ///         }
+    ///         pos = next_pos;
+    ///     }
+    ///
+    ///     // Now seek the reader back to `pos`. This will let us read the
+    ///     // last record again.
+    ///     iter.reader_mut().seek(pos)?;
+    ///     let mut iter = iter.into_reader().into_records();
+    ///     if let Some(result) = iter.next() {
+    ///         let record = result?;
+    ///         assert_eq!(record, vec!["Concord", "United States", "42695"]);
+    ///         Ok(())
+    ///     } else {
+    ///         Err(From::from("expected at least one record but got none"))
+    ///     }
+    /// }
+    /// ```
+    pub fn seek(&mut self, pos: Position) -> Result<()> {
+        self.byte_headers()?;
+        self.state.seeked = true;
+        self.state.peeked = None;
+        self.state.buffered.clear();
+        self.state.next_physical = None;
+        self.state.after_trailer = None;
+        if pos.byte() == self.state.cur_pos.byte() {
+            return Ok(());
+        }
+        self.rdr.seek(io::SeekFrom::Start(pos.byte()))?;
+        self.reset_parser(pos.line());
+        self.state.cur_pos = pos;
+        self.state.eof = false;
+        self.state.in_record = false;
+        Ok(())
+    }
+
+    /// Seeks the underlying reader to the start of a record that was read
+    /// before.
+    ///
+    /// `record` is a record number as reported by `Position::record`, so
+    /// when the data has a header row, the header row is record `0` and the
+    /// first data record is record `1`. The position of the record is taken
+    /// from the cache filled when `ReaderBuilder::cache_positions` is
+    /// enabled, and the same caveats as for `seek` apply.
+    ///
+    /// An error is returned if the record hasn't been read yet, or if
+    /// position caching isn't enabled.
+    ///
+    /// # Example: seek back to a visited record
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use std::io;
+    /// use csv::ReaderBuilder;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,pop
+    /// Boston,United States,4628910
+    /// Concord,United States,42695
+    /// ";
+    ///     let mut rdr = ReaderBuilder::new()
+    ///         .cache_positions(true)
+    ///         .from_reader(io::Cursor::new(data));
+    ///     for result in rdr.records() {
+    ///         result?;
+    ///     }
+    ///
+    ///     rdr.seek_cached(2)?;
+    ///     let record = rdr.records().next().unwrap()?;
+    ///     assert_eq!(record, vec!["Concord", "United States", "42695"]);
+    ///     assert!(rdr.seek_cached(3).is_err());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn seek_cached(&mut self, record: u64) -> Result<()> {
+        let pos = match self
+            .state
+            .positions
+            .binary_search_by_key(&record, Position::record)
+        {
+            Ok(i) => self.state.positions[i].clone(),
+            Err(_) => {
+                let msg = format!("record {} has not been read", record);
+                return Err(Error::from(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    msg,
+                )));
+            }
+        };
+        self.seek(pos)
+    }
+
+    /// This is like `seek`, but provides direct control over how the seeking
+    /// operation is performed via `io::SeekFrom`.
+    ///
+    /// The `pos` position given *should* correspond the position indicated
+    /// by `seek_from`, but there is no requirement. If the `pos` position
+    /// given is incorrect, then the position information returned by this
+    /// reader will be similarly incorrect.
+    ///
+    /// If the header row has not already been read, then this will attempt
+    /// to read the header row before seeking. Therefore, it is possible that
+    /// this returns an error associated with reading CSV data.
+    ///
+    /// Unlike `seek`, this will always cause an actual seek to be performed.
+    pub fn seek_raw(
+        &mut self,
+        seek_from: io::SeekFrom,
+        pos: Position,
+    ) -> Result<()> {
+        self.byte_headers()?;
+        self.state.seeked = true;
+        self.rdr.seek(seek_from)?;
+        self.reset_parser(pos.line());
+        self.state.peeked = None;
+        self.state.buffered.clear();
+        self.state.next_physical = None;
+        self.state.after_trailer = None;
+        self.state.cur_pos = pos;
+        self.state.eof = false;
+        self.state.in_record = false;
+        Ok(())
+    }
+
+    /// Rewind the underlying reader to the beginning of the data.
+    ///
+    /// Unlike seeking to the start with `seek`, this puts the reader back
+    /// into the state it was in when it was created: the header row is read
+    /// again, and is once again excluded from the records read if
+    /// `has_headers` is enabled. This is useful for algorithms that make
+    /// more than one pass over the data.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use std::io;
+    /// use csv::{Reader, StringRecord};
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,pop
+    /// Boston,United States,4628910
+    /// Concord,United States,42695
+    /// ";
+    ///     let mut rdr = Reader::from_reader(io::Cursor::new(data));
+    ///     let first: Vec<StringRecord> =
+    ///         rdr.records().collect::<Result<_, _>>()?;
+    ///
+    ///     rdr.rewind()?;
+    ///     assert_eq!(rdr.headers()?, vec!["city", "country", "pop"]);
+    ///     let second: Vec<StringRecord> =
+    ///         rdr.records().collect::<Result<_, _>>()?;
+    ///     assert_eq!(first, second);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn rewind(&mut self) -> Result<()> {
+        self.rdr.seek(io::SeekFrom::Start(0))?;
+        self.reset_parser(1);
+        self.state.reset();
+        Ok(())
+    }
+
+    /// Estimate the number of records in the data without parsing all of
+    /// it.
+    ///
+    /// This parses the first records of the data, measures their average
+    /// length in bytes and divides the length of the data by it, which is
+    /// useful for showing progress before reading the data in full. The
+    /// result is only an **estimate**: it's exact for data with few enough
+    /// records, and otherwise only accurate when the records are of similar
+    /// length. The header row isn't counted when `has_headers` is enabled,
+    /// but options that skip or drop records, such as comments, aren't
+    /// taken into account.
+    ///
+    /// The records are read directly from the underlying reader, which is
+    /// seeked back to where it was afterwards, so this doesn't change what
+    /// is read next.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use std::io;
+    /// use csv::Reader;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,country,pop
+    /// Boston,United States,4628910
+    /// Concord,United States,42695
+    /// ";
+    ///     let mut rdr = Reader::from_reader(io::Cursor::new(data));
+    ///     assert_eq!(rdr.estimate_record_count()?, 2);
+    ///     assert_eq!(rdr.records().count(), 2);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn estimate_record_count(&mut self) -> Result<u64> {
+        let mut parser = self.new_span_parser();
+        let has_headers = self.state.has_headers;
+        // The buffered reader is bypassed, so that its buffer remains valid
+        // once the underlying reader is back at its position.
+        let rdr = self.rdr.get_mut();
+        let restore = rdr.stream_position()?;
+        let result = estimate_records(&mut *rdr, &mut parser, has_headers);
+        rdr.seek(io::SeekFrom::Start(restore))?;
+        result
+    }
+}
+
+/// The number of records sampled by `Reader::estimate_record_count`.
+const ESTIMATE_SAMPLE_SIZE: u64 = 100;
+
+/// Estimate the number of records in `rdr` from the average length of the
+/// records at its start, as described by `Reader::estimate_record_count`.
+fn estimate_records<R: io::Read + io::Seek>(
+    mut rdr: R,
+    parser: &mut FallbackReader,
+    has_headers: bool,
+) -> Result<u64> {
+    use csv_core::ReadRecordResult::*;
+
+    let len = rdr.seek(io::SeekFrom::End(0))?;
+    rdr.seek(io::SeekFrom::Start(0))?;
+    let mut rdr = io::BufReader::new(rdr);
+    let mut record = ByteRecord::new();
+    let mut header = has_headers;
+    let (mut start, mut end, mut count) = (0, 0, 0);
+    while count < ESTIMATE_SAMPLE_SIZE {
+        let (res, nin) = {
+            let input = rdr.fill_buf()?;
+            parser.read_record(input, &mut record)
+        };
+        rdr.consume(nin);
+        end += nin as u64;
+        match res {
+            Record if header => {
+                header = false;
+                start = end;
+            }
+            Record => count += 1,
+            End => return Ok(count),
+            _ => continue,
+        }
+        record.clear();
+    }
+    let sampled = (end - start) as f64;
+    Ok(((len - start) as f64 * count as f64 / sampled).round() as u64)
+}
+
+/// Returns true if `b` is a record terminator.
+fn is_terminator(term: csv_core::Terminator, b: u8) -> bool {
+    match term {
+        csv_core::Terminator::CRLF => b == b'\r' || b == b'\n',
+        csv_core::Terminator::Any(t) => b == t,
+        _ => false,
+    }
+}
+
+/// Strip the leading and trailing record terminators from the raw bytes of
+/// a physical record.
+fn trim_terminators<'r>(raw: &'r [u8], core: &CoreReader) -> &'r [u8] {
+    let term = core.get_terminator();
+    let start = raw.iter().position(|&b| !is_terminator(term, b));
+    let end = raw.iter().rposition(|&b| !is_terminator(term, b));
+    match (start, end) {
+        (Some(start), Some(end)) => &raw[start..=end],
+        _ => &[],
+    }
+}
+
+/// Returns the count of the given record if it looks like a `#COUNT`
+/// trailer.
+fn trailer_count(record: &ByteRecord) -> Option<u64> {
+    if record.len() != 2 || &record[0] != COUNT_LABEL {
+        return None;
+    }
+    str::from_utf8(&record[1]).ok()?.parse().ok()
+}
+
+/// Returns true if the given record has no fields, or if all of its fields
+/// are empty or consist only of ASCII whitespace.
+fn is_blank(record: &ByteRecord) -> bool {
+    record.as_slice().iter().all(|b| b.is_ascii_whitespace())
+}
+
+impl ReaderState {
+    /// Reset the state tied to a position in the data, as if nothing had
+    /// been read yet.
+    fn reset(&mut self) {
+        self.headers = None;
+        self.first_field_count = None;
+        self.cur_pos = Position::new();
+        self.first = false;
+        self.seeked = false;
+        self.records_read = 0;
+        self.eof = false;
+        self.peeked = None;
+        self.buffered.clear();
+        self.next_physical = None;
+        self.after_trailer = None;
+        self.in_record = false;
+        self.after_record = false;
+        self.last_cr = false;
+        self.data_records = 0;
+        self.blank_before = false;
+    }
+
+    #[inline(always)]
+    fn add_record(&mut self, record: &ByteRecord) -> Result<()> {
+        let i = self.cur_pos.record();
+        self.cur_pos.set_record(i.checked_add(1).unwrap());
+        if let Some(expected) = self.expect_fields {
+            if record.len() as u64 != expected {
+                return Err(Error::new(ErrorKind::UnequalLengths {
+                    pos: record.position().cloned(),
+                    expected_len: expected,
+                    len: record.len() as u64,
+                }));
+            }
+        }
+        match self.first_field_count {
+            None => self.first_field_count = Some(record.len() as u64),
+            Some(expected) => {
+                if !self.flexible && record.len() as u64 != expected {
+                    return Err(Error::new(ErrorKind::UnequalLengths {
+                        pos: record.position().cloned(),
+                        expected_len: expected,
+                        len: record.len() as u64,
+                    }));
+                }
+            }
+        }
+        if self.cache_positions {
+            if let Some(pos) = record.position() {
+                match self.positions.last() {
+                    Some(last) if last.record() >= pos.record() => {}
+                    _ => self.positions.push(pos.clone()),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An owned iterator over deserialized records.
+///
+/// The type parameter `R` refers to the underlying `io::Read` type, and `D`
+/// refers to the type that this iterator will deserialize a record into.
+pub struct DeserializeRecordsIntoIter<R, D> {
+    rdr: Reader<R>,
+    rec: StringRecord,
+    headers: Option<StringRecord>,
+    _priv: PhantomData<D>,
+}
+
+impl<R: io::Read, D: DeserializeOwned> DeserializeRecordsIntoIter<R, D> {
+    fn new(mut rdr: Reader<R>) -> DeserializeRecordsIntoIter<R, D> {
+        let headers = if !rdr.state.has_headers {
+            None
+        } else {
+            rdr.headers().ok().map(Clone::clone)
+        };
+        DeserializeRecordsIntoIter {
+            rdr: rdr,
+            rec: StringRecord::new(),
+            headers: headers,
+            _priv: PhantomData,
+        }
+    }
+
+    /// Return a reference to the underlying CSV reader.
+    pub fn reader(&self) -> &Reader<R> {
+        &self.rdr
+    }
+
+    /// Return a mutable reference to the underlying CSV reader.
+    pub fn reader_mut(&mut self) -> &mut Reader<R> {
+        &mut self.rdr
+    }
+
+    /// Drop this iterator and return the underlying CSV reader.
+    pub fn into_reader(self) -> Reader<R> {
+        self.rdr
+    }
+}
+
+impl<R: io::Read, D: DeserializeOwned> Iterator
+    for DeserializeRecordsIntoIter<R, D>
+{
+    type Item = Result<D>;
+
+    fn next(&mut self) -> Option<Result<D>> {
+        match self.rdr.read_record(&mut self.rec) {
+            Err(err) => Some(Err(err)),
+            Ok(false) => None,
+            Ok(true) => Some(deserialize_string_record_with(
+                &self.rec,
+                self.headers.as_ref(),
+                &self.rdr.state.deserialize,
+            )),
+        }
+    }
+}
+
+/// A borrowed iterator over deserialized records.
+///
+/// The lifetime parameter `'r` refers to the lifetime of the underlying
+/// CSV `Reader`. The type parameter `R` refers to the underlying `io::Read`
+/// type, and `D` refers to the type that this iterator will deserialize a
+/// record into.
+pub struct DeserializeRecordsIter<'r, R: 'r, D> {
+    rdr: &'r mut Reader<R>,
+    rec: StringRecord,
+    headers: Option<StringRecord>,
+    _priv: PhantomData<D>,
+}
+
+impl<'r, R: io::Read, D: DeserializeOwned> DeserializeRecordsIter<'r, R, D> {
+    fn new(rdr: &'r mut Reader<R>) -> DeserializeRecordsIter<'r, R, D> {
+        let headers = if !rdr.state.has_headers {
+            None
+        } else {
+            rdr.headers().ok().map(Clone::clone)
+        };
+        DeserializeRecordsIter {
+            rdr: rdr,
+            rec: StringRecord::new(),
+            headers: headers,
+            _priv: PhantomData,
+        }
+    }
+
+    /// Return a reference to the underlying CSV reader.
+    pub fn reader(&self) -> &Reader<R> {
+        &self.rdr
+    }
+
+    /// Return a mutable reference to the underlying CSV reader.
+    pub fn reader_mut(&mut self) -> &mut Reader<R> {
+        &mut self.rdr
+    }
+}
+
+impl<'r, R: io::Read, D: DeserializeOwned> Iterator
+    for DeserializeRecordsIter<'r, R, D>
+{
+    type Item = Result<D>;
+
+    fn next(&mut self) -> Option<Result<D>> {
+        match self.rdr.read_record(&mut self.rec) {
+            Err(err) => Some(Err(err)),
+            Ok(false) => None,
+            Ok(true) => Some(deserialize_string_record_with(
+                &self.rec,
+                self.headers.as_ref(),
+                &self.rdr.state.deserialize,
+            )),
+        }
+    }
+}
+
+/// An owned iterator over records as strings.
+pub struct StringRecordsIntoIter<R> {
+    rdr: Reader<R>,
+    rec: StringRecord,
+}
+
+impl<R: io::Read> StringRecordsIntoIter<R> {
+    fn new(rdr: Reader<R>) -> StringRecordsIntoIter<R> {
+        StringRecordsIntoIter { rdr: rdr, rec: StringRecord::new() }
+    }
+
+    /// Return a reference to the underlying CSV reader.
+    pub fn reader(&self) -> &Reader<R> {
+        &self.rdr
+    }
+
+    /// Return a mutable reference to the underlying CSV reader.
+    pub fn reader_mut(&mut self) -> &mut Reader<R> {
+        &mut self.rdr
+    }
+
+    /// Drop this iterator and return the underlying CSV reader.
+    pub fn into_reader(self) -> Reader<R> {
+        self.rdr
+    }
+}
+
+impl<R: io::Read> Iterator for StringRecordsIntoIter<R> {
+    type Item = Result<StringRecord>;
+
+    fn next(&mut self) -> Option<Result<StringRecord>> {
+        match self.rdr.read_record(&mut self.rec) {
+            Err(err) => Some(Err(err)),
+            Ok(true) => Some(Ok(self.rec.clone())),
+            Ok(false) => None,
+        }
+    }
+}
+
+/// A borrowed iterator over records as strings.
+///
+/// The lifetime parameter `'r` refers to the lifetime of the underlying
+/// CSV `Reader`.
+pub struct StringRecordsIter<'r, R: 'r> {
+    rdr: &'r mut Reader<R>,
+    rec: StringRecord,
+}
+
+impl<'r, R: io::Read> StringRecordsIter<'r, R> {
+    fn new(rdr: &'r mut Reader<R>) -> StringRecordsIter<'r, R> {
+        StringRecordsIter { rdr: rdr, rec: StringRecord::new() }
+    }
+
+    /// Return a reference to the underlying CSV reader.
+    pub fn reader(&self) -> &Reader<R> {
+        &self.rdr
+    }
+
+    /// Return a mutable reference to the underlying CSV reader.
+    pub fn reader_mut(&mut self) -> &mut Reader<R> {
+        &mut self.rdr
+    }
+}
+
+impl<'r, R: io::Read> Iterator for StringRecordsIter<'r, R> {
+    type Item = Result<StringRecord>;
+
+    fn next(&mut self) -> Option<Result<StringRecord>> {
+        match self.rdr.read_record(&mut self.rec) {
+            Err(err) => Some(Err(err)),
+            Ok(true) => Some(Ok(self.rec.clone())),
+            Ok(false) => None,
+        }
+    }
+}
+
+/// A borrowed iterator over records as strings, along with their indices.
+///
+/// The lifetime parameter `'r` refers to the lifetime of the underlying
+/// CSV `Reader`.
+pub struct EnumerateRecordsIter<'r, R: 'r> {
+    it: StringRecordsIter<'r, R>,
+    /// The index of the next item to yield.
+    index: u64,
+}
+
+impl<'r, R: io::Read> EnumerateRecordsIter<'r, R> {
+    fn new(rdr: &'r mut Reader<R>) -> EnumerateRecordsIter<'r, R> {
+        EnumerateRecordsIter { it: StringRecordsIter::new(rdr), index: 0 }
+    }
+
+    /// Return a reference to the underlying CSV reader.
+    pub fn reader(&self) -> &Reader<R> {
+        self.it.reader()
+    }
+
+    /// Return a mutable reference to the underlying CSV reader.
+    pub fn reader_mut(&mut self) -> &mut Reader<R> {
+        self.it.reader_mut()
+    }
+}
+
+impl<'r, R: io::Read> Iterator for EnumerateRecordsIter<'r, R> {
+    type Item = (u64, Result<StringRecord>);
+
+    fn next(&mut self) -> Option<(u64, Result<StringRecord>)> {
+        let result = self.it.next()?;
+        let index = self.index;
+        self.index += 1;
+        Some((index, result))
+    }
+}
+
+/// A borrowed iterator over windows of consecutive records as strings.
+///
+/// The lifetime parameter `'r` refers to the lifetime of the underlying
+/// CSV `Reader`.
+pub struct WindowsIter<'r, R: 'r> {
+    rdr: &'r mut Reader<R>,
+    rec: StringRecord,
+    size: usize,
+}
+
+impl<'r, R: io::Read> WindowsIter<'r, R> {
+    fn new(rdr: &'r mut Reader<R>, size: usize) -> WindowsIter<'r, R> {
+        assert!(size > 0, "window size must be non-zero");
+        WindowsIter { rdr, rec: StringRecord::new(), size }
+    }
+
+    /// Return a reference to the underlying CSV reader.
+    pub fn reader(&self) -> &Reader<R> {
+        self.rdr
+    }
+
+    /// Return a mutable reference to the underlying CSV reader.
+    pub fn reader_mut(&mut self) -> &mut Reader<R> {
+        self.rdr
+    }
+}
+
+impl<'r, R: io::Read> Iterator for WindowsIter<'r, R> {
+    type Item = Result<Vec<StringRecord>>;
+
+    fn next(&mut self) -> Option<Result<Vec<StringRecord>>> {
+        let mut window = Vec::with_capacity(self.size);
+        while window.len() < self.size {
+            match self.rdr.read_record(&mut self.rec) {
+                Ok(true) => window.push(self.rec.clone()),
+                Ok(false) => break,
+                Err(err) => return Some(Err(err)),
+            }
+        }
+        if window.is_empty() {
+            None
+        } else {
+            Some(Ok(window))
+        }
+    }
+}
+
+/// An owned iterator over records as raw bytes.
+pub struct ByteRecordsIntoIter<R> {
+    rdr: Reader<R>,
+    rec: ByteRecord,
+}
+
+impl<R: io::Read> ByteRecordsIntoIter<R> {
+    fn new(rdr: Reader<R>) -> ByteRecordsIntoIter<R> {
+        ByteRecordsIntoIter { rdr: rdr, rec: ByteRecord::new() }
+    }
+
+    /// Return a reference to the underlying CSV reader.
+    pub fn reader(&self) -> &Reader<R> {
+        &self.rdr
+    }
+
+    /// Return a mutable reference to the underlying CSV reader.
+    pub fn reader_mut(&mut self) -> &mut Reader<R> {
+        &mut self.rdr
+    }
+
+    /// Drop this iterator and return the underlying CSV reader.
+    pub fn into_reader(self) -> Reader<R> {
+        self.rdr
+    }
+}
+
+impl<R: io::Read> Iterator for ByteRecordsIntoIter<R> {
+    type Item = Result<ByteRecord>;
+
+    fn next(&mut self) -> Option<Result<ByteRecord>> {
+        match self.rdr.read_byte_record(&mut self.rec) {
+            Err(err) => Some(Err(err)),
+            Ok(true) => Some(Ok(self.rec.clone())),
+            Ok(false) => None,
+        }
+    }
+}
+
+/// A borrowed iterator over records as raw bytes.
+///
+/// The lifetime parameter `'r` refers to the lifetime of the underlying
+/// CSV `Reader`.
+pub struct ByteRecordsIter<'r, R: 'r> {
+    rdr: &'r mut Reader<R>,
+    rec: ByteRecord,
+}
+
+impl<'r, R: io::Read> ByteRecordsIter<'r, R> {
+    fn new(rdr: &'r mut Reader<R>) -> ByteRecordsIter<'r, R> {
+        ByteRecordsIter { rdr: rdr, rec: ByteRecord::new() }
+    }
+
+    /// Return a reference to the underlying CSV reader.
+    pub fn reader(&self) -> &Reader<R> {
+        &self.rdr
+    }
+
+    /// Return a mutable reference to the underlying CSV reader.
+    pub fn reader_mut(&mut self) -> &mut Reader<R> {
+        &mut self.rdr
+    }
+}
+
+impl<'r, R: io::Read> Iterator for ByteRecordsIter<'r, R> {
+    type Item = Result<ByteRecord>;
+
+    fn next(&mut self) -> Option<Result<ByteRecord>> {
+        match self.rdr.read_byte_record(&mut self.rec) {
+            Err(err) => Some(Err(err)),
+            Ok(true) => Some(Ok(self.rec.clone())),
+            Ok(false) => None,
+        }
+    }
+}
+
+/// The encoding indicated by a byte order mark, as returned by
+/// `Reader::detect_bom`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Bom {
+    /// The bytes `EF BB BF`, for UTF-8.
+    Utf8,
+    /// The bytes `FF FE`, for little endian UTF-16.
+    Utf16Le,
+    /// The bytes `FE FF`, for big endian UTF-16.
+    Utf16Be,
+    /// Hints that destructuring should not be exhaustive.
+    ///
+    /// This enum may grow additional variants, so this makes sure clients
+    /// don't count on exhaustive matching. (Otherwise, adding a new variant
+    /// could break existing code.)
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+/// An item yielded by the iterator returned by `Reader::fields`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FieldEvent {
+    /// A single field.
+    Field(Vec<u8>),
+    /// The end of a record. This follows the last field of every record.
+    EndOfRecord,
+}
+
+/// A borrowed iterator over the fields of all records as a flat stream.
+///
+/// The lifetime parameter `'r` refers to the lifetime of the underlying
+/// CSV `Reader`.
+pub struct FieldsIter<'r, R: 'r> {
+    rdr: &'r mut Reader<R>,
+    rec: ByteRecord,
+    /// The index of the next field in `rec` to yield.
+    field: usize,
+    /// Whether an `EndOfRecord` event is still owed for `rec`.
+    in_record: bool,
+}
+
+impl<'r, R: io::Read> FieldsIter<'r, R> {
+    fn new(rdr: &'r mut Reader<R>) -> FieldsIter<'r, R> {
+        FieldsIter { rdr, rec: ByteRecord::new(), field: 0, in_record: false }
+    }
+
+    /// Return a reference to the underlying CSV reader.
+    pub fn reader(&self) -> &Reader<R> {
+        self.rdr
+    }
+
+    /// Return a mutable reference to the underlying CSV reader.
+    pub fn reader_mut(&mut self) -> &mut Reader<R> {
+        self.rdr
+    }
+}
+
+impl<'r, R: io::Read> Iterator for FieldsIter<'r, R> {
+    type Item = Result<FieldEvent>;
+
+    fn next(&mut self) -> Option<Result<FieldEvent>> {
+        if !self.in_record {
+            match self.rdr.read_byte_record(&mut self.rec) {
+                Err(err) => return Some(Err(err)),
+                Ok(false) => return None,
+                Ok(true) => {
+                    self.field = 0;
+                    self.in_record = true;
+                }
+            }
+        }
+        match self.rec.get(self.field) {
+            Some(field) => {
+                self.field += 1;
+                Some(Ok(FieldEvent::Field(field.to_vec())))
+            }
+            None => {
+                self.in_record = false;
+                Some(Ok(FieldEvent::EndOfRecord))
+            }
+        }
+    }
+}
+
+/// An iterator over the fields of the record read by `Reader::read_fields`.
+///
+/// The lifetime parameter `'r` refers to the lifetime of the underlying CSV
+/// `Reader`, whose buffer the fields borrow from.
+pub struct Fields<'r> {
+    iter: ByteRecordIter<'r>,
+}
+
+impl<'r> Iterator for Fields<'r> {
+    type Item = &'r [u8];
+
+    #[inline]
+    fn next(&mut self) -> Option<&'r [u8]> {
+        self.iter.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'r> DoubleEndedIterator for Fields<'r> {
+    #[inline]
+    fn next_back(&mut self) -> Option<&'r [u8]> {
+        self.iter.next_back()
+    }
+}
+
+impl<'r> ExactSizeIterator for Fields<'r> {}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use crate::byte_record::ByteRecord;
+    use crate::error::ErrorKind;
+    use crate::schema::FieldType;
+    use crate::string_record::StringRecord;
+    use crate::writer::WriterBuilder;
+
+    use super::{
+        Bom, Dialect, FieldEvent, GrowthPolicy, NumberFormat, Position,
+        ReaderBuilder, Trim,
+    };
+
+    fn b(s: &str) -> &[u8] {
+        s.as_bytes()
+    }
+    fn s(b: &[u8]) -> &str {
+        ::std::str::from_utf8(b).unwrap()
+    }
+
+    fn newpos(byte: u64, line: u64, record: u64) -> Position {
+        let mut p = Position::new();
+        p.set_byte(byte).set_line(line).set_record(record);
+        p
+    }
+
+    #[test]
+    fn read_byte_record() {
+        let data = b("foo,\"b,ar\",baz\nabc,mno,xyz");
+        let mut rdr =
+            ReaderBuilder::new().has_headers(false).from_reader(data);
+        let mut rec = ByteRecord::new();
+
+        assert!(rdr.read_byte_record(&mut rec).unwrap());
+        assert_eq!(3, rec.len());
+        assert_eq!("foo", s(&rec[0]));
+        assert_eq!("b,ar", s(&rec[1]));
+        assert_eq!("baz", s(&rec[2]));
+
+        assert!(rdr.read_byte_record(&mut rec).unwrap());
+        assert_eq!(3, rec.len());
+        assert_eq!("abc", s(&rec[0]));
+        assert_eq!("mno", s(&rec[1]));
+        assert_eq!("xyz", s(&rec[2]));
+
+        assert!(!rdr.read_byte_record(&mut rec).unwrap());
+    }
+
+    #[test]
+    fn read_trimmed_records_and_headers() {
+        let data = b("foo,  bar,\tbaz\n  1,  2,  3\n1\t,\t,3\t\t");
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(true)
+            .trim(Trim::All)
+            .from_reader(data);
+        let mut rec = ByteRecord::new();
+        assert!(rdr.read_byte_record(&mut rec).unwrap());
+        assert_eq!("1", s(&rec[0]));
+        assert_eq!("2", s(&rec[1]));
+        assert_eq!("3", s(&rec[2]));
+        let mut rec = StringRecord::new();
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!("1", &rec[0]);
+        assert_eq!("", &rec[1]);
+        assert_eq!("3", &rec[2]);
+        {
+            let headers = rdr.headers().unwrap();
+            assert_eq!(3, headers.len());
+            assert_eq!("foo", &headers[0]);
+            assert_eq!("bar", &headers[1]);
+            assert_eq!("baz", &headers[2]);
+        }
+    }
+
+    #[test]
+    fn read_trimmed_header() {
+        let data = b("foo,  bar,\tbaz\n  1,  2,  3\n1\t,\t,3\t\t");
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(true)
+            .trim(Trim::Headers)
+            .from_reader(data);
+        let mut rec = ByteRecord::new();
+        assert!(rdr.read_byte_record(&mut rec).unwrap());
+        assert_eq!("  1", s(&rec[0]));
+        assert_eq!("  2", s(&rec[1]));
+        assert_eq!("  3", s(&rec[2]));
+        {
+            let headers = rdr.headers().unwrap();
+            assert_eq!(3, headers.len());
+            assert_eq!("foo", &headers[0]);
+            assert_eq!("bar", &headers[1]);
+            assert_eq!("baz", &headers[2]);
+        }
+    }
+
+    #[test]
+    fn read_trimed_header_invalid_utf8() {
+        let data = &b"foo,  b\xFFar,\tbaz\na,b,c\nd,e,f"[..];
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(true)
+            .trim(Trim::Headers)
+            .from_reader(data);
+        let mut rec = StringRecord::new();
+
+        // force the headers to be read
+        let _ = rdr.read_record(&mut rec);
+        // Check the byte headers are trimmed
+        {
+            let headers = rdr.byte_headers().unwrap();
+            assert_eq!(3, headers.len());
+            assert_eq!(b"foo", &headers[0]);
+            assert_eq!(b"b\xFFar", &headers[1]);
+            assert_eq!(b"baz", &headers[2]);
+        }
+        match *rdr.headers().unwrap_err().kind() {
+            ErrorKind::Utf8 { pos: Some(ref pos), ref err } => {
+                assert_eq!(pos, &newpos(0, 1, 0));
+                assert_eq!(err.field(), 1);
+                assert_eq!(err.valid_up_to(), 3);
+            }
+            ref err => panic!("match failed, got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn read_trimmed_records() {
+        let data = b("foo,  bar,\tbaz\n  1,  2,  3\n1\t,\t,3\t\t");
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(true)
+            .trim(Trim::Fields)
+            .from_reader(data);
+        let mut rec = ByteRecord::new();
+        assert!(rdr.read_byte_record(&mut rec).unwrap());
+        assert_eq!("1", s(&rec[0]));
+        assert_eq!("2", s(&rec[1]));
+        assert_eq!("3", s(&rec[2]));
+        {
+            let headers = rdr.headers().unwrap();
+            assert_eq!(3, headers.len());
+            assert_eq!("foo", &headers[0]);
+            assert_eq!("  bar", &headers[1]);
+            assert_eq!("\tbaz", &headers[2]);
+        }
+    }
+
+    #[test]
+    fn read_record_unequal_fails() {
+        let data = b("foo\nbar,baz");
+        let mut rdr =
+            ReaderBuilder::new().has_headers(false).from_reader(data);
+        let mut rec = ByteRecord::new();
+
+        assert!(rdr.read_byte_record(&mut rec).unwrap());
+        assert_eq!(1, rec.len());
+        assert_eq!("foo", s(&rec[0]));
+
+        match rdr.read_byte_record(&mut rec) {
+            Err(err) => match *err.kind() {
+                ErrorKind::UnequalLengths {
+                    expected_len: 1,
+                    ref pos,
+                    len: 2,
+                } => {
+                    assert_eq!(pos, &Some(newpos(4, 2, 1)));
+                }
+                ref wrong => panic!("match failed, got {:?}", wrong),
+            },
+            wrong => panic!("match failed, got {:?}", wrong),
+        }
+    }
+
+    #[test]
+    fn read_record_unequal_ok() {
+        let data = b("foo\nbar,baz");
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(data);
+        let mut rec = ByteRecord::new();
+
+        assert!(rdr.read_byte_record(&mut rec).unwrap());
+        assert_eq!(1, rec.len());
+        assert_eq!("foo", s(&rec[0]));
+
+        assert!(rdr.read_byte_record(&mut rec).unwrap());
+        assert_eq!(2, rec.len());
+        assert_eq!("bar", s(&rec[0]));
+        assert_eq!("baz", s(&rec[1]));
+
+        assert!(!rdr.read_byte_record(&mut rec).unwrap());
+    }
+
+    // This tests that even if we get a CSV error, we can continue reading
+    // if we want.
+    #[test]
+    fn read_record_unequal_continue() {
+        let data = b("foo\nbar,baz\nquux");
+        let mut rdr =
+            ReaderBuilder::new().has_headers(false).from_reader(data);
+        let mut rec = ByteRecord::new();
+
+        assert!(rdr.read_byte_record(&mut rec).unwrap());
+        assert_eq!(1, rec.len());
+        assert_eq!("foo", s(&rec[0]));
+
+        match rdr.read_byte_record(&mut rec) {
+            Err(err) => match err.kind() {
+                &ErrorKind::UnequalLengths {
+                    expected_len: 1,
+                    ref pos,
+                    len: 2,
+                } => {
+                    assert_eq!(pos, &Some(newpos(4, 2, 1)));
+                }
+                wrong => panic!("match failed, got {:?}", wrong),
+            },
+            wrong => panic!("match failed, got {:?}", wrong),
+        }
+
+        assert!(rdr.read_byte_record(&mut rec).unwrap());
+        assert_eq!(1, rec.len());
+        assert_eq!("quux", s(&rec[0]));
+
+        assert!(!rdr.read_byte_record(&mut rec).unwrap());
+    }
+
+    #[test]
+    fn read_record_headers() {
+        let data = b("foo,bar,baz\na,b,c\nd,e,f");
+        let mut rdr = ReaderBuilder::new().has_headers(true).from_reader(data);
+        let mut rec = StringRecord::new();
+
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(3, rec.len());
+        assert_eq!("a", &rec[0]);
+
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(3, rec.len());
+        assert_eq!("d", &rec[0]);
+
+        assert!(!rdr.read_record(&mut rec).unwrap());
+
+        {
+            let headers = rdr.byte_headers().unwrap();
+            assert_eq!(3, headers.len());
+            assert_eq!(b"foo", &headers[0]);
+            assert_eq!(b"bar", &headers[1]);
+            assert_eq!(b"baz", &headers[2]);
+        }
+        {
+            let headers = rdr.headers().unwrap();
+            assert_eq!(3, headers.len());
+            assert_eq!("foo", &headers[0]);
+            assert_eq!("bar", &headers[1]);
+            assert_eq!("baz", &headers[2]);
+        }
+    }
+
+    #[test]
+    fn read_record_headers_invalid_utf8() {
+        let data = &b"foo,b\xFFar,baz\na,b,c\nd,e,f"[..];
+        let mut rdr = ReaderBuilder::new().has_headers(true).from_reader(data);
+        let mut rec = StringRecord::new();
+
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(3, rec.len());
+        assert_eq!("a", &rec[0]);
+
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(3, rec.len());
+        assert_eq!("d", &rec[0]);
+
+        assert!(!rdr.read_record(&mut rec).unwrap());
+
+        // Check that we can read the headers as raw bytes, but that
+        // if we read them as strings, we get an appropriate UTF-8 error.
+        {
+            let headers = rdr.byte_headers().unwrap();
+            assert_eq!(3, headers.len());
+            assert_eq!(b"foo", &headers[0]);
+            assert_eq!(b"b\xFFar", &headers[1]);
+            assert_eq!(b"baz", &headers[2]);
+        }
+        match *rdr.headers().unwrap_err().kind() {
+            ErrorKind::Utf8 { pos: Some(ref pos), ref err } => {
+                assert_eq!(pos, &newpos(0, 1, 0));
+                assert_eq!(err.field(), 1);
+                assert_eq!(err.valid_up_to(), 1);
+            }
+            ref err => panic!("match failed, got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn read_record_no_headers_before() {
+        let data = b("foo,bar,baz\na,b,c\nd,e,f");
+        let mut rdr =
+            ReaderBuilder::new().has_headers(false).from_reader(data);
+        let mut rec = StringRecord::new();
+
+        {
+            let headers = rdr.headers().unwrap();
+            assert_eq!(3, headers.len());
+            assert_eq!("foo", &headers[0]);
+            assert_eq!("bar", &headers[1]);
+            assert_eq!("baz", &headers[2]);
+        }
+
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(3, rec.len());
+        assert_eq!("foo", &rec[0]);
+
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(3, rec.len());
+        assert_eq!("a", &rec[0]);
+
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(3, rec.len());
+        assert_eq!("d", &rec[0]);
+
+        assert!(!rdr.read_record(&mut rec).unwrap());
     }
 
-    /// This is like `seek`, but provides direct control over how the seeking
-    /// operation is performed via `io::SeekFrom`.
-    ///
-    /// The `pos` position given *should* correspond the position indicated
-    /// by `seek_from`, but there is no requirement. If the `pos` position
-    /// given is incorrect, then the position information returned by this
-    /// reader will be similarly incorrect.
-    ///
-    /// If the header row has not already been read, then this will attempt
-    /// to read the header row before seeking. Therefore, it is possible that
-    /// this returns an error associated with reading CSV data.
-    ///
-    /// Unlike `seek`, this will always cause an actual seek to be performed.
-    pub fn seek_raw(
-        &mut self,
-        seek_from: io::SeekFrom,
-        pos: Position,
-    ) -> Result<()> {
-        self.byte_headers()?;
-        self.state.seeked = true;
-        self.rdr.seek(seek_from)?;
-        self.core.reset();
-        self.core.set_line(pos.line());
-        self.state.cur_pos = pos;
-        self.state.eof = false;
-        Ok(())
+    #[test]
+    fn read_record_no_headers_after() {
+        let data = b("foo,bar,baz\na,b,c\nd,e,f");
+        let mut rdr =
+            ReaderBuilder::new().has_headers(false).from_reader(data);
+        let mut rec = StringRecord::new();
+
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(3, rec.len());
+        assert_eq!("foo", &rec[0]);
+
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(3, rec.len());
+        assert_eq!("a", &rec[0]);
+
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(3, rec.len());
+        assert_eq!("d", &rec[0]);
+
+        assert!(!rdr.read_record(&mut rec).unwrap());
+
+        let headers = rdr.headers().unwrap();
+        assert_eq!(3, headers.len());
+        assert_eq!("foo", &headers[0]);
+        assert_eq!("bar", &headers[1]);
+        assert_eq!("baz", &headers[2]);
     }
-}
 
-impl ReaderState {
-    #[inline(always)]
-    fn add_record(&mut self, record: &ByteRecord) -> Result<()> {
-        let i = self.cur_pos.record();
-        self.cur_pos.set_record(i.checked_add(1).unwrap());
-        if !self.flexible {
-            match self.first_field_count {
-                None => self.first_field_count = Some(record.len() as u64),
-                Some(expected) => {
-                    if record.len() as u64 != expected {
-                        return Err(Error::new(ErrorKind::UnequalLengths {
-                            pos: record.position().map(Clone::clone),
-                            expected_len: expected,
-                            len: record.len() as u64,
-                        }));
-                    }
-                }
-            }
-        }
-        Ok(())
+    #[test]
+    fn seek() {
+        let data = b("foo,bar,baz\na,b,c\nd,e,f\ng,h,i");
+        let mut rdr = ReaderBuilder::new().from_reader(io::Cursor::new(data));
+        rdr.seek(newpos(18, 3, 2)).unwrap();
+
+        let mut rec = StringRecord::new();
+
+        assert_eq!(18, rdr.position().byte());
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(3, rec.len());
+        assert_eq!("d", &rec[0]);
+
+        assert_eq!(24, rdr.position().byte());
+        assert_eq!(4, rdr.position().line());
+        assert_eq!(3, rdr.position().record());
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(3, rec.len());
+        assert_eq!("g", &rec[0]);
+
+        assert!(!rdr.read_record(&mut rec).unwrap());
     }
-}
 
-/// An owned iterator over deserialized records.
-///
-/// The type parameter `R` refers to the underlying `io::Read` type, and `D`
-/// refers to the type that this iterator will deserialize a record into.
-pub struct DeserializeRecordsIntoIter<R, D> {
-    rdr: Reader<R>,
-    rec: StringRecord,
-    headers: Option<StringRecord>,
-    _priv: PhantomData<D>,
-}
+    // Test that we can read headers after seeking even if the headers weren't
+    // explicit read before seeking.
+    #[test]
+    fn seek_headers_after() {
+        let data = b("foo,bar,baz\na,b,c\nd,e,f\ng,h,i");
+        let mut rdr = ReaderBuilder::new().from_reader(io::Cursor::new(data));
+        rdr.seek(newpos(18, 3, 2)).unwrap();
+        assert_eq!(rdr.headers().unwrap(), vec!["foo", "bar", "baz"]);
+    }
 
-impl<R: io::Read, D: DeserializeOwned> DeserializeRecordsIntoIter<R, D> {
-    fn new(mut rdr: Reader<R>) -> DeserializeRecordsIntoIter<R, D> {
-        let headers = if !rdr.state.has_headers {
-            None
-        } else {
-            rdr.headers().ok().map(Clone::clone)
-        };
-        DeserializeRecordsIntoIter {
-            rdr: rdr,
-            rec: StringRecord::new(),
-            headers: headers,
-            _priv: PhantomData,
-        }
+    // Test that we can read headers after seeking if the headers were read
+    // before seeking.
+    #[test]
+    fn seek_headers_before_after() {
+        let data = b("foo,bar,baz\na,b,c\nd,e,f\ng,h,i");
+        let mut rdr = ReaderBuilder::new().from_reader(io::Cursor::new(data));
+        let headers = rdr.headers().unwrap().clone();
+        rdr.seek(newpos(18, 3, 2)).unwrap();
+        assert_eq!(&headers, rdr.headers().unwrap());
     }
 
-    /// Return a reference to the underlying CSV reader.
-    pub fn reader(&self) -> &Reader<R> {
-        &self.rdr
+    // Test that even if we didn't read headers before seeking, if we seek to
+    // the current byte offset, then no seeking is done and therefore we can
+    // still read headers after seeking.
+    #[test]
+    fn seek_headers_no_actual_seek() {
+        let data = b("foo,bar,baz\na,b,c\nd,e,f\ng,h,i");
+        let mut rdr = ReaderBuilder::new().from_reader(io::Cursor::new(data));
+        rdr.seek(Position::new()).unwrap();
+        assert_eq!("foo", &rdr.headers().unwrap()[0]);
     }
 
-    /// Return a mutable reference to the underlying CSV reader.
-    pub fn reader_mut(&mut self) -> &mut Reader<R> {
-        &mut self.rdr
+    // Test that position info is reported correctly in absence of headers.
+    #[test]
+    fn positions_no_headers() {
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader("a,b,c\nx,y,z".as_bytes())
+            .into_records();
+
+        let pos = rdr.next().unwrap().unwrap().position().unwrap().clone();
+        assert_eq!(pos.byte(), 0);
+        assert_eq!(pos.line(), 1);
+        assert_eq!(pos.record(), 0);
+
+        let pos = rdr.next().unwrap().unwrap().position().unwrap().clone();
+        assert_eq!(pos.byte(), 6);
+        assert_eq!(pos.line(), 2);
+        assert_eq!(pos.record(), 1);
     }
 
-    /// Drop this iterator and return the underlying CSV reader.
-    pub fn into_reader(self) -> Reader<R> {
-        self.rdr
+    // Test that position info is reported correctly with headers.
+    #[test]
+    fn positions_headers() {
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(true)
+            .from_reader("a,b,c\nx,y,z".as_bytes())
+            .into_records();
+
+        let pos = rdr.next().unwrap().unwrap().position().unwrap().clone();
+        assert_eq!(pos.byte(), 6);
+        assert_eq!(pos.line(), 2);
+        assert_eq!(pos.record(), 1);
     }
-}
 
-impl<R: io::Read, D: DeserializeOwned> Iterator
-    for DeserializeRecordsIntoIter<R, D>
-{
-    type Item = Result<D>;
+    // Test that reading headers on empty data yields an empty record.
+    #[test]
+    fn headers_on_empty_data() {
+        let mut rdr = ReaderBuilder::new().from_reader("".as_bytes());
+        let r = rdr.byte_headers().unwrap();
+        assert_eq!(r.len(), 0);
+    }
 
-    fn next(&mut self) -> Option<Result<D>> {
-        match self.rdr.read_record(&mut self.rec) {
-            Err(err) => Some(Err(err)),
-            Ok(false) => None,
-            Ok(true) => Some(self.rec.deserialize(self.headers.as_ref())),
+    // Test that reading the first record on empty data works.
+    #[test]
+    fn no_headers_on_empty_data() {
+        let mut rdr =
+            ReaderBuilder::new().has_headers(false).from_reader("".as_bytes());
+        assert_eq!(rdr.records().count(), 0);
+    }
+
+    // Test that reading the first record on empty data works, even if
+    // we've tried to read headers before hand.
+    #[test]
+    fn no_headers_on_empty_data_after_headers() {
+        let mut rdr =
+            ReaderBuilder::new().has_headers(false).from_reader("".as_bytes());
+        assert_eq!(rdr.headers().unwrap().len(), 0);
+        assert_eq!(rdr.records().count(), 0);
+    }
+
+    // Test that a peeked record is returned by the next read exactly once.
+    #[test]
+    fn peek_then_read() {
+        let data = b("foo,bar\na,b\nc,d");
+        let mut rdr = ReaderBuilder::new().from_reader(data);
+        let mut rec = StringRecord::new();
+
+        assert_eq!(rdr.peek().unwrap().unwrap(), vec!["a", "b"]);
+        assert_eq!(rdr.peek().unwrap().unwrap(), vec!["a", "b"]);
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(rec, vec!["a", "b"]);
+        assert_eq!(rec.position().unwrap(), &newpos(8, 2, 1));
+
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(rec, vec!["c", "d"]);
+        assert!(!rdr.read_record(&mut rec).unwrap());
+    }
+
+    #[test]
+    fn peek_then_iterate() {
+        let data = b("a,b\nc,d");
+        let mut rdr =
+            ReaderBuilder::new().has_headers(false).from_reader(data);
+
+        assert_eq!(rdr.peek().unwrap().unwrap(), vec!["a", "b"]);
+        let records: Vec<ByteRecord> =
+            rdr.byte_records().map(|r| r.unwrap()).collect();
+        assert_eq!(records, vec![vec!["a", "b"], vec!["c", "d"]]);
+    }
+
+    #[test]
+    fn peek_eof() {
+        let data = b("foo,bar\na,b");
+        let mut rdr = ReaderBuilder::new().from_reader(data);
+        let mut rec = StringRecord::new();
+
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert!(rdr.peek().unwrap().is_none());
+        assert!(rdr.peek().unwrap().is_none());
+        assert!(!rdr.read_record(&mut rec).unwrap());
+    }
+
+    #[test]
+    fn read_while() {
+        let data = b("a
+1
+2
+END
+3
+");
+        let mut rdr = ReaderBuilder::new().from_reader(data);
+        let records = rdr.read_while(|r| &r[0] != "END").unwrap();
+        assert_eq!(records, vec![vec!["1"], vec!["2"]]);
+        assert!(rdr.read_while(|r| &r[0] != "END").unwrap().is_empty());
+
+        let mut rec = StringRecord::new();
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(rec, vec!["END"]);
+        assert_eq!(rdr.read_while(|_| true).unwrap(), vec![vec!["3"]]);
+        assert!(!rdr.read_record(&mut rec).unwrap());
+    }
+
+    #[test]
+    fn read_array() {
+        let data = b("a,b,c\n1,2,3\nx,y\n4,5,6\n");
+        let mut rdr = ReaderBuilder::new().flexible(true).from_reader(data);
+        let [a, b, c] = rdr.read_array().unwrap().unwrap();
+        assert_eq!((a, b, c), ("1".into(), "2".into(), "3".into()));
+
+        let err = rdr.read_array::<3>().unwrap_err();
+        match *err.kind() {
+            ErrorKind::UnequalLengths { expected_len: 3, len: 2, ref pos } => {
+                assert_eq!(pos.as_ref().unwrap().line(), 3);
+            }
+            ref x => panic!("expected UnequalLengths, got {:?}", x),
         }
+        assert_eq!(
+            rdr.read_array::<3>().unwrap(),
+            Some(["4".to_string(), "5".to_string(), "6".to_string()])
+        );
+        assert_eq!(rdr.read_array::<3>().unwrap(), None);
     }
-}
 
-/// A borrowed iterator over deserialized records.
-///
-/// The lifetime parameter `'r` refers to the lifetime of the underlying
-/// CSV `Reader`. The type parameter `R` refers to the underlying `io::Read`
-/// type, and `D` refers to the type that this iterator will deserialize a
-/// record into.
-pub struct DeserializeRecordsIter<'r, R: 'r, D> {
-    rdr: &'r mut Reader<R>,
-    rec: StringRecord,
-    headers: Option<StringRecord>,
-    _priv: PhantomData<D>,
-}
+    #[test]
+    fn record_ranges() {
+        let mut data = String::from("n\n");
+        for i in 0..100 {
+            data.push_str(&format!("{}\n", i));
+        }
+        let mut rdr = ReaderBuilder::new().from_bytes(data.as_bytes());
+        let ranges = rdr.record_ranges(4).unwrap();
+        let counts: Vec<u64> = ranges.iter().map(|r| r.1).collect();
+        assert_eq!(counts, vec![25, 25, 25, 25]);
 
-impl<'r, R: io::Read, D: DeserializeOwned> DeserializeRecordsIter<'r, R, D> {
-    fn new(rdr: &'r mut Reader<R>) -> DeserializeRecordsIter<'r, R, D> {
-        let headers = if !rdr.state.has_headers {
-            None
-        } else {
-            rdr.headers().ok().map(Clone::clone)
-        };
-        DeserializeRecordsIter {
-            rdr: rdr,
-            rec: StringRecord::new(),
-            headers: headers,
-            _priv: PhantomData,
+        let mut rec = StringRecord::new();
+        for (k, (pos, count)) in ranges.into_iter().enumerate() {
+            rdr.seek(pos).unwrap();
+            for i in 0..count {
+                assert!(rdr.read_record(&mut rec).unwrap());
+                assert_eq!(rec[0], (k as u64 * 25 + i).to_string());
+            }
         }
+
+        let mut rdr = ReaderBuilder::new().from_bytes(b"n\n1\n2\n3\n4\n5\n");
+        let counts: Vec<u64> =
+            rdr.record_ranges(3).unwrap().iter().map(|r| r.1).collect();
+        assert_eq!(counts, vec![1, 2, 2]);
+
+        let mut rdr = ReaderBuilder::new().from_bytes(b"n\n1\n2\n");
+        let counts: Vec<u64> =
+            rdr.record_ranges(5).unwrap().iter().map(|r| r.1).collect();
+        assert_eq!(counts, vec![1, 1]);
     }
 
-    /// Return a reference to the underlying CSV reader.
-    pub fn reader(&self) -> &Reader<R> {
-        &self.rdr
+    #[test]
+    fn analyze_widths() {
+        let data = b("a,b,c\n1,2,3\n4,5\n6,7,8,9\n1,2,3\n4,5\n7,8,9\n");
+        let mut rdr = ReaderBuilder::new().from_reader(data);
+        let report = rdr.analyze_widths().unwrap();
+        let counts: Vec<(usize, u64)> =
+            report.counts().iter().map(|(&w, &n)| (w, n)).collect();
+        assert_eq!(counts, vec![(2, 2), (3, 3), (4, 1)]);
+        assert_eq!(report.modal_width(), Some(3));
+
+        let mut rdr = ReaderBuilder::new().from_reader(b("a,b\n"));
+        let report = rdr.analyze_widths().unwrap();
+        assert!(report.counts().is_empty());
+        assert_eq!(report.modal_width(), None);
     }
 
-    /// Return a mutable reference to the underlying CSV reader.
-    pub fn reader_mut(&mut self) -> &mut Reader<R> {
-        &mut self.rdr
+    #[test]
+    fn fold_records() {
+        let data = b("n,x\n1,a\n2,b\n3,c\n");
+        let mut rdr = ReaderBuilder::new().from_reader(data);
+        let sums = rdr
+            .fold_records(vec![], |mut sums: Vec<i32>, r| {
+                let prev = sums.last().cloned().unwrap_or(0);
+                sums.push(prev + r[0].parse::<i32>().unwrap());
+                sums
+            })
+            .unwrap();
+        assert_eq!(sums, vec![1, 3, 6]);
+        assert_eq!(rdr.fold_records(0, |n, _| n + 1).unwrap(), 0);
+
+        let mut rdr = ReaderBuilder::new().from_reader(b("a\n1\n2,3\n4\n"));
+        let err = rdr.fold_records(0, |n, _| n + 1).unwrap_err();
+        match *err.kind() {
+            ErrorKind::UnequalLengths { .. } => {}
+            ref x => panic!("expected UnequalLengths, got {:?}", x),
+        }
     }
-}
 
-impl<'r, R: io::Read, D: DeserializeOwned> Iterator
-    for DeserializeRecordsIter<'r, R, D>
-{
-    type Item = Result<D>;
+    #[test]
+    fn deserialize_bool_format() {
+        use serde::Deserialize;
 
-    fn next(&mut self) -> Option<Result<D>> {
-        match self.rdr.read_record(&mut self.rec) {
-            Err(err) => Some(Err(err)),
-            Ok(false) => None,
-            Ok(true) => Some(self.rec.deserialize(self.headers.as_ref())),
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Row {
+            x: bool,
+            y: bool,
         }
+
+        let data = b("x,y\nY,N\ntrue,false\n");
+        let mut rdr =
+            ReaderBuilder::new().bool_format("Y", "N").from_reader(data);
+        let mut it = rdr.deserialize::<Row>();
+        assert_eq!(it.next().unwrap().unwrap(), Row { x: true, y: false });
+        assert!(it.next().unwrap().is_err());
+        assert!(it.next().is_none());
     }
-}
 
-/// An owned iterator over records as strings.
-pub struct StringRecordsIntoIter<R> {
-    rdr: Reader<R>,
-    rec: StringRecord,
-}
+    #[test]
+    fn bool_format_roundtrip() {
+        use serde::{Deserialize, Serialize};
 
-impl<R: io::Read> StringRecordsIntoIter<R> {
-    fn new(rdr: Reader<R>) -> StringRecordsIntoIter<R> {
-        StringRecordsIntoIter { rdr: rdr, rec: StringRecord::new() }
-    }
+        use crate::writer::WriterBuilder;
 
-    /// Return a reference to the underlying CSV reader.
-    pub fn reader(&self) -> &Reader<R> {
-        &self.rdr
-    }
+        #[derive(Debug, Deserialize, PartialEq, Serialize)]
+        struct Row {
+            name: String,
+            active: bool,
+        }
 
-    /// Return a mutable reference to the underlying CSV reader.
-    pub fn reader_mut(&mut self) -> &mut Reader<R> {
-        &mut self.rdr
+        let rows = vec![
+            Row { name: "a".to_string(), active: true },
+            Row { name: "b".to_string(), active: false },
+        ];
+        let mut wtr =
+            WriterBuilder::new().bool_format("Y", "N").from_writer(vec![]);
+        for row in &rows {
+            wtr.serialize(row).unwrap();
+        }
+        let data = wtr.into_inner().unwrap();
+        assert_eq!(s(&data), "name,active\na,Y\nb,N\n");
+
+        let mut rdr =
+            ReaderBuilder::new().bool_format("Y", "N").from_reader(&*data);
+        let got: Vec<Row> =
+            rdr.deserialize().collect::<Result<_, _>>().unwrap();
+        assert_eq!(got, rows);
     }
 
-    /// Drop this iterator and return the underlying CSV reader.
-    pub fn into_reader(self) -> Reader<R> {
-        self.rdr
+    #[test]
+    fn positions_survive_collect() {
+        let data = b("foo,bar\na,b\nc,d\n");
+        let mut rdr = ReaderBuilder::new().from_reader(data);
+        let records: Vec<StringRecord> =
+            rdr.records().collect::<Result<_, _>>().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].position(), Some(&newpos(8, 2, 1)));
+        assert_eq!(records[1].position(), Some(&newpos(12, 3, 2)));
+
+        let rdr = ReaderBuilder::new().from_reader(data);
+        let records: Vec<ByteRecord> =
+            rdr.into_byte_records().collect::<Result<_, _>>().unwrap();
+        assert_eq!(records[1].position(), Some(&newpos(12, 3, 2)));
+
+        assert_eq!(StringRecord::from(vec!["a", "b"]).position(), None);
     }
-}
 
-impl<R: io::Read> Iterator for StringRecordsIntoIter<R> {
-    type Item = Result<StringRecord>;
+    #[test]
+    fn strict_quotes() {
+        let data = b("a\"b,c\nx,y\n");
 
-    fn next(&mut self) -> Option<Result<StringRecord>> {
-        match self.rdr.read_record(&mut self.rec) {
-            Err(err) => Some(Err(err)),
-            Ok(true) => Some(Ok(self.rec.clone())),
-            Ok(false) => None,
+        let mut rdr =
+            ReaderBuilder::new().has_headers(false).from_reader(data);
+        let mut rec = StringRecord::new();
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(rec, vec!["a\"b", "c"]);
+
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .strict_quotes(true)
+            .from_reader(data);
+        match *rdr.read_record(&mut rec).unwrap_err().kind() {
+            ErrorKind::UnescapedQuote { ref pos, field } => {
+                assert_eq!(pos, &Some(newpos(0, 1, 0)));
+                assert_eq!(field, 0);
+            }
+            ref err => panic!("unexpected error: {:?}", err),
         }
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(rec, vec!["x", "y"]);
+        assert!(!rdr.read_record(&mut rec).unwrap());
     }
-}
 
-/// A borrowed iterator over records as strings.
-///
-/// The lifetime parameter `'r` refers to the lifetime of the underlying
-/// CSV `Reader`.
-pub struct StringRecordsIter<'r, R: 'r> {
-    rdr: &'r mut Reader<R>,
-    rec: StringRecord,
-}
+    #[test]
+    fn resync_after_unequal_lengths() {
+        let data = b("a,b\n1,2,3\nx,y\n");
+        let mut rdr =
+            ReaderBuilder::new().has_headers(false).from_reader(data);
+        let mut rec = StringRecord::new();
 
-impl<'r, R: io::Read> StringRecordsIter<'r, R> {
-    fn new(rdr: &'r mut Reader<R>) -> StringRecordsIter<'r, R> {
-        StringRecordsIter { rdr: rdr, rec: StringRecord::new() }
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert!(rdr.read_record(&mut rec).is_err());
+        rdr.resync().unwrap();
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(rec, vec!["x", "y"]);
+        assert!(!rdr.read_record(&mut rec).unwrap());
     }
 
-    /// Return a reference to the underlying CSV reader.
-    pub fn reader(&self) -> &Reader<R> {
-        &self.rdr
-    }
+    #[test]
+    fn resync_after_io_error() {
+        // Yields each chunk in turn, where `None` indicates an I/O error.
+        struct Chunks(Vec<Option<&'static [u8]>>);
+
+        impl io::Read for Chunks {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if self.0.is_empty() {
+                    return Ok(0);
+                }
+                match self.0.remove(0) {
+                    None => Err(io::Error::new(io::ErrorKind::Other, "oops")),
+                    Some(chunk) => {
+                        buf[..chunk.len()].copy_from_slice(chunk);
+                        Ok(chunk.len())
+                    }
+                }
+            }
+        }
 
-    /// Return a mutable reference to the underlying CSV reader.
-    pub fn reader_mut(&mut self) -> &mut Reader<R> {
-        &mut self.rdr
+        let chunks =
+            Chunks(vec![Some(b"a,b\nc,\"d"), None, Some(b"\ne\"\nf,g\n")]);
+        let mut rdr =
+            ReaderBuilder::new().has_headers(false).from_reader(chunks);
+        let mut rec = StringRecord::new();
+
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(rec, vec!["a", "b"]);
+        assert!(rdr.read_record(&mut rec).unwrap_err().is_io_error());
+        rdr.resync().unwrap();
+        assert_eq!(rdr.position(), &newpos(12, 4, 2));
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(rec, vec!["f", "g"]);
+        assert!(!rdr.read_record(&mut rec).unwrap());
     }
-}
 
-impl<'r, R: io::Read> Iterator for StringRecordsIter<'r, R> {
-    type Item = Result<StringRecord>;
+    #[test]
+    fn fields_flat_stream() {
+        use super::FieldEvent::{EndOfRecord, Field};
 
-    fn next(&mut self) -> Option<Result<StringRecord>> {
-        match self.rdr.read_record(&mut self.rec) {
-            Err(err) => Some(Err(err)),
-            Ok(true) => Some(Ok(self.rec.clone())),
-            Ok(false) => None,
+        let data = b("a,b,c\n1,2,\"3\n\"\n");
+        let mut rdr =
+            ReaderBuilder::new().has_headers(false).from_reader(data);
+        let events: Vec<FieldEvent> =
+            rdr.fields().collect::<Result<_, _>>().unwrap();
+        assert_eq!(
+            events,
+            vec![
+                Field(b"a".to_vec()),
+                Field(b"b".to_vec()),
+                Field(b"c".to_vec()),
+                EndOfRecord,
+                Field(b"1".to_vec()),
+                Field(b"2".to_vec()),
+                Field(b"3\n".to_vec()),
+                EndOfRecord,
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_input() {
+        for &data in &["", "\n", "\r\n"] {
+            for &has_headers in &[true, false] {
+                let mut rdr = ReaderBuilder::new()
+                    .has_headers(has_headers)
+                    .from_reader(data.as_bytes());
+                assert!(rdr.is_empty_input().unwrap());
+                assert!(rdr.headers().unwrap().is_empty());
+                assert!(rdr.byte_headers().unwrap().is_empty());
+                assert!(rdr.records().next().is_none());
+
+                // Reading records before asking for headers must agree.
+                let mut rdr = ReaderBuilder::new()
+                    .has_headers(has_headers)
+                    .from_reader(data.as_bytes());
+                assert!(rdr.byte_records().next().is_none());
+                assert!(rdr.headers().unwrap().is_empty());
+                assert!(rdr.is_empty_input().unwrap());
+            }
         }
     }
-}
 
-/// An owned iterator over records as raw bytes.
-pub struct ByteRecordsIntoIter<R> {
-    rdr: Reader<R>,
-    rec: ByteRecord,
-}
+    #[test]
+    fn empty_input_header_only() {
+        let mut rdr = ReaderBuilder::new().from_reader(b("a,b\n"));
+        assert!(!rdr.is_empty_input().unwrap());
+        assert!(rdr.records().next().is_none());
 
-impl<R: io::Read> ByteRecordsIntoIter<R> {
-    fn new(rdr: Reader<R>) -> ByteRecordsIntoIter<R> {
-        ByteRecordsIntoIter { rdr: rdr, rec: ByteRecord::new() }
+        let mut rdr =
+            ReaderBuilder::new().has_headers(false).from_reader(b("a,b\n"));
+        assert!(!rdr.is_empty_input().unwrap());
+        assert_eq!(rdr.records().count(), 1);
     }
 
-    /// Return a reference to the underlying CSV reader.
-    pub fn reader(&self) -> &Reader<R> {
-        &self.rdr
+    #[test]
+    fn infer_schema_mixed() {
+        let data = "\
+id,score,active,joined,name,note
+1,1.5,true,2020-01-31,alice,
+2,3,false,2021-02-28,bob,x
+-3,,true,2022-12-01,7,
+";
+        let mut rdr = ReaderBuilder::new().from_reader(data.as_bytes());
+        rdr.peek().unwrap();
+        let schema = rdr.infer_schema(100).unwrap();
+        let types: Vec<FieldType> =
+            schema.iter().map(|t| t.field_type()).collect();
+        assert_eq!(
+            types,
+            vec![
+                FieldType::Integer,
+                FieldType::Float,
+                FieldType::Boolean,
+                FieldType::Date,
+                FieldType::String,
+                FieldType::String,
+            ]
+        );
+        let nullable: Vec<bool> =
+            schema.iter().map(|t| t.is_nullable()).collect();
+        assert_eq!(nullable, vec![false, true, false, false, false, true]);
+
+        // A second, smaller sample sees the same leading records.
+        let schema = rdr.infer_schema(1).unwrap();
+        assert_eq!(schema[1].field_type(), FieldType::Float);
+
+        let ids: Vec<String> = rdr
+            .records()
+            .map(|r| r.unwrap().get(0).unwrap().to_string())
+            .collect();
+        assert_eq!(ids, vec!["1", "2", "-3"]);
     }
 
-    /// Return a mutable reference to the underlying CSV reader.
-    pub fn reader_mut(&mut self) -> &mut Reader<R> {
-        &mut self.rdr
-    }
+    #[test]
+    fn expect_fields() {
+        let data = b("a,b,c\n1,2,3,4\n5,6,7\n");
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .expect_fields(Some(3))
+            .from_reader(data);
+        let mut rec = ByteRecord::new();
 
-    /// Drop this iterator and return the underlying CSV reader.
-    pub fn into_reader(self) -> Reader<R> {
-        self.rdr
+        assert!(rdr.read_byte_record(&mut rec).unwrap());
+        match *rdr.read_byte_record(&mut rec).unwrap_err().kind() {
+            ErrorKind::UnequalLengths { ref pos, expected_len, len } => {
+                assert_eq!(pos.as_ref().unwrap().line(), 2);
+                assert_eq!(expected_len, 3);
+                assert_eq!(len, 4);
+            }
+            ref wrong => panic!("match failed, got {:?}", wrong),
+        }
+        assert!(rdr.read_byte_record(&mut rec).unwrap());
+        assert_eq!(rec, vec!["5", "6", "7"]);
     }
-}
 
-impl<R: io::Read> Iterator for ByteRecordsIntoIter<R> {
-    type Item = Result<ByteRecord>;
+    #[test]
+    fn case_insensitive_headers() {
+        use serde::Deserialize;
 
-    fn next(&mut self) -> Option<Result<ByteRecord>> {
-        match self.rdr.read_byte_record(&mut self.rec) {
-            Err(err) => Some(Err(err)),
-            Ok(true) => Some(Ok(self.rec.clone())),
-            Ok(false) => None,
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Row {
+            user_id: u64,
+            name: String,
         }
+
+        let data = b("NAME,User_ID\nalice,1\nbob,2\n");
+        let mut rdr = ReaderBuilder::new()
+            .case_insensitive_headers(true)
+            .from_reader(data);
+        let rows: Vec<Row> = rdr.deserialize().map(|r| r.unwrap()).collect();
+        assert_eq!(
+            rows,
+            vec![
+                Row { user_id: 1, name: "alice".to_string() },
+                Row { user_id: 2, name: "bob".to_string() },
+            ]
+        );
+        assert_eq!(rdr.headers().unwrap(), vec!["NAME", "User_ID"]);
+
+        let mut rdr = ReaderBuilder::new().from_reader(data);
+        assert!(rdr.deserialize::<Row>().next().unwrap().is_err());
     }
-}
 
-/// A borrowed iterator over records as raw bytes.
-///
-/// The lifetime parameter `'r` refers to the lifetime of the underlying
-/// CSV `Reader`.
-pub struct ByteRecordsIter<'r, R: 'r> {
-    rdr: &'r mut Reader<R>,
-    rec: ByteRecord,
-}
+    #[test]
+    fn single_field() {
+        let data = b("a,b\n\"c,\nd\",e\n");
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .single_field(true)
+            .from_reader(data);
+        let records: Vec<_> = rdr.records().map(|r| r.unwrap()).collect();
+        assert_eq!(records, vec![vec!["a,b"], vec!["c,\nd,e"]]);
+        assert_eq!(records[1].position().unwrap().line(), 2);
 
-impl<'r, R: io::Read> ByteRecordsIter<'r, R> {
-    fn new(rdr: &'r mut Reader<R>) -> ByteRecordsIter<'r, R> {
-        ByteRecordsIter { rdr: rdr, rec: ByteRecord::new() }
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .single_field(true)
+            .quoting(false)
+            .multi_delimiter(b"||")
+            .from_reader(&b"x||y\n\"z,w\n"[..]);
+        let records: Vec<_> = rdr.records().map(|r| r.unwrap()).collect();
+        assert_eq!(records, vec![vec!["x||y"], vec!["\"z,w"]]);
     }
 
-    /// Return a reference to the underlying CSV reader.
-    pub fn reader(&self) -> &Reader<R> {
-        &self.rdr
+    #[test]
+    fn header_index() {
+        let data = b("a,b,a\n1,2,3\n");
+        let mut rdr = ReaderBuilder::new().from_reader(data);
+        rdr.build_header_index().unwrap();
+        assert_eq!(rdr.position().line(), 2);
+
+        let mut rec = StringRecord::new();
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(rec, vec!["1", "2", "3"]);
+        assert_eq!(rec.position().unwrap().line(), 2);
+        assert_eq!(rdr.header_position("a").unwrap(), Some(0));
+        assert_eq!(rdr.header_position("b").unwrap(), Some(1));
+        assert_eq!(rdr.header_position("c").unwrap(), None);
+
+        rdr.set_headers(StringRecord::from(vec!["x", "y", "z"]));
+        assert_eq!(rdr.header_position("a").unwrap(), None);
+        assert_eq!(rdr.header_position(b"z").unwrap(), Some(2));
     }
 
-    /// Return a mutable reference to the underlying CSV reader.
-    pub fn reader_mut(&mut self) -> &mut Reader<R> {
-        &mut self.rdr
+    #[test]
+    fn deserialize_number_format() {
+        let data = b("\"1,234\",\"-1,000,000\"\n");
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .number_format(NumberFormat::new(Some(','), '.'))
+            .from_reader(data);
+        let row: (i32, i64) = rdr.deserialize().next().unwrap().unwrap();
+        assert_eq!(row, (1234, -1000000));
+
+        let data = b("\"1.234,56\";12,5;7\n");
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .delimiter(b';')
+            .number_format(NumberFormat::new(Some('.'), ','))
+            .from_reader(data);
+        let row: (f64, f32, String) =
+            rdr.deserialize().next().unwrap().unwrap();
+        assert_eq!(row, (1234.56, 12.5, "7".to_string()));
+
+        let mut rdr =
+            ReaderBuilder::new().has_headers(false).from_reader(data);
+        assert!(rdr.deserialize::<(f64,)>().next().unwrap().is_err());
     }
-}
 
-impl<'r, R: io::Read> Iterator for ByteRecordsIter<'r, R> {
-    type Item = Result<ByteRecord>;
+    #[test]
+    fn from_bytes() {
+        let mut rdr = ReaderBuilder::new().from_bytes(b"a,b\n1,2\n\"3\",4\n");
+        let records: Vec<StringRecord> =
+            rdr.records().map(|r| r.unwrap()).collect();
+        assert_eq!(records, vec![vec!["1", "2"], vec!["3", "4"]]);
+        assert_eq!(rdr.headers().unwrap(), vec!["a", "b"]);
+
+        let pos = rdr.position().clone();
+        assert_eq!(pos.byte(), 14);
+
+        let mut rdr = ReaderBuilder::new().from_bytes(b"");
+        assert!(rdr.records().next().is_none());
+    }
 
-    fn next(&mut self) -> Option<Result<ByteRecord>> {
-        match self.rdr.read_byte_record(&mut self.rec) {
-            Err(err) => Some(Err(err)),
-            Ok(true) => Some(Ok(self.rec.clone())),
-            Ok(false) => None,
+    #[test]
+    fn deserialize_validated() {
+        use serde::Deserialize;
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Row {
+            name: String,
+            age: u32,
+            score: Option<f64>,
+        }
+
+        let data = b("\
+name,age,score
+alice,30,1.5
+bob,old,2.5
+carol,40,
+dave,-1,3.0
+erin,50
+frank,60,4.0
+");
+        let mut rdr = ReaderBuilder::new().from_reader(data);
+        let report = rdr.deserialize_validated::<Row>().unwrap();
+        assert_eq!(
+            report.rows(),
+            &[
+                Row { name: "alice".to_string(), age: 30, score: Some(1.5) },
+                Row { name: "carol".to_string(), age: 40, score: None },
+                Row { name: "frank".to_string(), age: 60, score: Some(4.0) },
+            ]
+        );
+        let lines: Vec<u64> =
+            report.errors().iter().map(|&(ref pos, _)| pos.line()).collect();
+        assert_eq!(lines, vec![3, 5, 6]);
+        match *report.errors()[2].1.kind() {
+            ErrorKind::UnequalLengths { .. } => {}
+            ref wrong => panic!("match failed, got {:?}", wrong),
         }
+        assert_eq!(report.column_failures(), &[0, 2]);
+        assert_eq!(report.null_counts(), &[0, 0, 1]);
+        assert!(!report.is_valid());
+    }
+
+    #[test]
+    fn growth_linear() {
+        let data = format!("a,{}\n", "x".repeat(100_000));
+        let capacity = |growth| {
+            let mut rdr = ReaderBuilder::new()
+                .has_headers(false)
+                .growth(growth)
+                .from_reader(data.as_bytes());
+            let mut rec = ByteRecord::new();
+            assert!(rdr.read_byte_record(&mut rec).unwrap());
+            assert_eq!(rec.get(1).map(|f| f.len()), Some(100_000));
+            rec.fields_capacity()
+        };
+
+        assert_eq!(capacity(GrowthPolicy::Double), 131_072);
+        let linear = capacity(GrowthPolicy::Linear(1_000));
+        assert!(linear >= 100_001 && linear <= 101_000, "{}", linear);
+        let custom = capacity(GrowthPolicy::Custom(|len| len + 50_000));
+        assert_eq!(custom, 150_000);
+    }
+
+    #[test]
+    fn read_n_records() {
+        let data = b("a\n1\n2\n3\n4\n5\n");
+        let mut rdr = ReaderBuilder::new().from_reader(data);
+
+        let first = rdr.read_n_records(3).unwrap();
+        assert_eq!(first, vec![vec!["1"], vec!["2"], vec!["3"]]);
+        assert_eq!(rdr.position().line(), 5);
+
+        let rest = rdr.read_n_records(10).unwrap();
+        assert_eq!(rest, vec![vec!["4"], vec!["5"]]);
+        assert!(rdr.read_n_records(10).unwrap().is_empty());
+        assert!(rdr.read_n_records(0).unwrap().is_empty());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::io;
+    #[test]
+    fn guess_has_headers() {
+        let guess = |data: &'static str, has_headers: bool| {
+            let mut rdr = ReaderBuilder::new()
+                .has_headers(has_headers)
+                .from_reader(data.as_bytes());
+            let guess = rdr.guess_has_headers().unwrap();
+            (guess, rdr.records().count())
+        };
 
-    use crate::byte_record::ByteRecord;
-    use crate::error::ErrorKind;
-    use crate::string_record::StringRecord;
+        let header = "name,age,joined\nalice,30,2020-01-02\nbob,41,\n";
+        assert_eq!(guess(header, false), (true, 3));
+        assert_eq!(guess(header, true), (true, 2));
 
-    use super::{Position, ReaderBuilder, Trim};
+        let no_header = "alice,30,2020-01-02\nbob,41,\n";
+        assert_eq!(guess(no_header, false), (false, 2));
+        assert_eq!(guess("a,b\nc,d\n", false), (false, 2));
+        assert_eq!(guess("a,b\n", false), (false, 1));
+        assert_eq!(guess("", false), (false, 0));
+    }
 
-    fn b(s: &str) -> &[u8] {
-        s.as_bytes()
+    #[test]
+    fn quoted_headers_with_delimiters() {
+        use serde::Deserialize;
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Row {
+            #[serde(rename = "First, Last")]
+            name: String,
+            #[serde(rename = "Age")]
+            age: u32,
+        }
+
+        let data = b("\"First, Last\",Age\n\"Doe, Jane\",42\n");
+        let mut rdr = ReaderBuilder::new().from_reader(data);
+        assert_eq!(rdr.headers().unwrap(), vec!["First, Last", "Age"]);
+
+        let rows: Vec<Row> =
+            rdr.deserialize().collect::<Result<_, _>>().unwrap();
+        assert_eq!(rows, vec![Row { name: "Doe, Jane".to_string(), age: 42 }]);
     }
-    fn s(b: &[u8]) -> &str {
-        ::std::str::from_utf8(b).unwrap()
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn encoding_latin1() {
+        use crate::Encoding;
+
+        let data = b"name,note\n\"Ren\xE9e\",na\xEFve \x80\n";
+        let mut rdr = ReaderBuilder::new()
+            .encoding(Encoding::Latin1)
+            .from_reader(&data[..]);
+        assert_eq!(rdr.headers().unwrap(), vec!["name", "note"]);
+        let mut rec = StringRecord::new();
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(rec, vec!["Renée", "naïve €"]);
+        assert_eq!(rec.position().unwrap().byte(), 10);
+
+        // Without transcoding, the input isn't valid UTF-8.
+        let mut rdr = ReaderBuilder::new().from_reader(&data[..]);
+        assert!(rdr.read_record(&mut rec).is_err());
     }
 
-    fn newpos(byte: u64, line: u64, record: u64) -> Position {
-        let mut p = Position::new();
-        p.set_byte(byte).set_line(line).set_record(record);
-        p
+    #[test]
+    fn ignore_trailing_delimiter() {
+        let read = |yes: bool| {
+            let mut rdr = ReaderBuilder::new()
+                .has_headers(false)
+                .flexible(true)
+                .ignore_trailing_delimiter(yes)
+                .from_reader(b("a,b,\nc,d\n,\ne,,\n"));
+            rdr.byte_records()
+                .map(|r| r.unwrap().iter().map(|f| f.to_vec()).collect())
+                .collect::<Vec<Vec<Vec<u8>>>>()
+        };
+        let s = |fields: &[&str]| {
+            fields.iter().map(|f| f.as_bytes().to_vec()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            read(false),
+            vec![
+                s(&["a", "b", ""]),
+                s(&["c", "d"]),
+                s(&["", ""]),
+                s(&["e", "", ""])
+            ]
+        );
+        assert_eq!(
+            read(true),
+            vec![s(&["a", "b"]), s(&["c", "d"]), s(&[""]), s(&["e", ""])]
+        );
     }
 
     #[test]
-    fn read_byte_record() {
-        let data = b("foo,\"b,ar\",baz\nabc,mno,xyz");
+    fn read_batch() {
+        let data = b("1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n");
         let mut rdr =
             ReaderBuilder::new().has_headers(false).from_reader(data);
-        let mut rec = ByteRecord::new();
+        let mut batch = vec![];
 
-        assert!(rdr.read_byte_record(&mut rec).unwrap());
-        assert_eq!(3, rec.len());
-        assert_eq!("foo", s(&rec[0]));
-        assert_eq!("b,ar", s(&rec[1]));
-        assert_eq!("baz", s(&rec[2]));
+        assert_eq!(rdr.read_batch(&mut batch, 4).unwrap(), 4);
+        assert_eq!(batch, vec![vec!["1"], vec!["2"], vec!["3"], vec!["4"]]);
+        let fields = batch[0].as_slice().as_ptr();
 
-        assert!(rdr.read_byte_record(&mut rec).unwrap());
-        assert_eq!(3, rec.len());
-        assert_eq!("abc", s(&rec[0]));
-        assert_eq!("mno", s(&rec[1]));
-        assert_eq!("xyz", s(&rec[2]));
+        assert_eq!(rdr.read_batch(&mut batch, 4).unwrap(), 4);
+        assert_eq!(batch, vec![vec!["5"], vec!["6"], vec!["7"], vec!["8"]]);
+        // The records from the first batch were reused.
+        assert_eq!(batch[0].as_slice().as_ptr(), fields);
 
-        assert!(!rdr.read_byte_record(&mut rec).unwrap());
+        assert_eq!(rdr.read_batch(&mut batch, 4).unwrap(), 2);
+        assert_eq!(batch, vec![vec!["9"], vec!["10"]]);
+        assert_eq!(rdr.read_batch(&mut batch, 4).unwrap(), 0);
+        assert!(batch.is_empty());
     }
 
     #[test]
-    fn read_trimmed_records_and_headers() {
-        let data = b("foo,  bar,\tbaz\n  1,  2,  3\n1\t,\t,3\t\t");
-        let mut rdr = ReaderBuilder::new()
-            .has_headers(true)
-            .trim(Trim::All)
-            .from_reader(data);
-        let mut rec = ByteRecord::new();
-        assert!(rdr.read_byte_record(&mut rec).unwrap());
-        assert_eq!("1", s(&rec[0]));
-        assert_eq!("2", s(&rec[1]));
-        assert_eq!("3", s(&rec[2]));
+    fn multi_delimiter() {
+        let data = b("h1||h2\na|b||\"c||d\"\n\n1||2\n");
+        let mut rdr =
+            ReaderBuilder::new().multi_delimiter(b"||").from_reader(data);
+        assert_eq!(rdr.headers().unwrap(), vec!["h1", "h2"]);
+
         let mut rec = StringRecord::new();
         assert!(rdr.read_record(&mut rec).unwrap());
-        assert_eq!("1", &rec[0]);
-        assert_eq!("", &rec[1]);
-        assert_eq!("3", &rec[2]);
-        {
-            let headers = rdr.headers().unwrap();
-            assert_eq!(3, headers.len());
-            assert_eq!("foo", &headers[0]);
-            assert_eq!("bar", &headers[1]);
-            assert_eq!("baz", &headers[2]);
-        }
+        assert_eq!(rec, vec!["a|b", "c||d"]);
+        assert_eq!(rec.position().unwrap().line(), 2);
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(rec, vec!["1", "2"]);
+        assert!(!rdr.read_record(&mut rec).unwrap());
+
+        // The last delimiter setting wins.
+        let mut rdr = ReaderBuilder::new()
+            .multi_delimiter(b"||")
+            .delimiter(b'|')
+            .has_headers(false)
+            .from_reader(b("a||b\n"));
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(rec, vec!["a", "", "b"]);
     }
 
     #[test]
-    fn read_trimmed_header() {
-        let data = b("foo,  bar,\tbaz\n  1,  2,  3\n1\t,\t,3\t\t");
+    fn rewind() {
+        let data = "h1,h2\na,b\nc,d\n";
+        let mut rdr = ReaderBuilder::new().from_reader(io::Cursor::new(data));
+        let first: Vec<ByteRecord> =
+            rdr.byte_records().map(|r| r.unwrap()).collect();
+        assert_eq!(first, vec![vec!["a", "b"], vec!["c", "d"]]);
+
+        rdr.rewind().unwrap();
+        assert_eq!(rdr.position().byte(), 0);
+        let second: Vec<ByteRecord> =
+            rdr.byte_records().map(|r| r.unwrap()).collect();
+        assert_eq!(first, second);
+        assert_eq!(second[0].position(), first[0].position());
+        assert_eq!(rdr.headers().unwrap(), vec!["h1", "h2"]);
+
+        // Without headers, the first row is a record again after rewinding.
         let mut rdr = ReaderBuilder::new()
-            .has_headers(true)
-            .trim(Trim::Headers)
-            .from_reader(data);
-        let mut rec = ByteRecord::new();
-        assert!(rdr.read_byte_record(&mut rec).unwrap());
-        assert_eq!("  1", s(&rec[0]));
-        assert_eq!("  2", s(&rec[1]));
-        assert_eq!("  3", s(&rec[2]));
-        {
-            let headers = rdr.headers().unwrap();
-            assert_eq!(3, headers.len());
-            assert_eq!("foo", &headers[0]);
-            assert_eq!("bar", &headers[1]);
-            assert_eq!("baz", &headers[2]);
-        }
+            .has_headers(false)
+            .from_reader(io::Cursor::new(data));
+        assert_eq!(rdr.byte_records().count(), 3);
+        rdr.rewind().unwrap();
+        assert_eq!(rdr.byte_records().count(), 3);
     }
 
     #[test]
-    fn read_trimed_header_invalid_utf8() {
-        let data = &b"foo,  b\xFFar,\tbaz\na,b,c\nd,e,f"[..];
+    fn lenient_quotes() {
+        let data = b("\"foo \"bar\" baz\"\n");
         let mut rdr = ReaderBuilder::new()
-            .has_headers(true)
-            .trim(Trim::Headers)
+            .has_headers(false)
+            .lenient_quotes(true)
             .from_reader(data);
         let mut rec = StringRecord::new();
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(rec, vec!["foo \"bar\" baz"]);
 
-        // force the headers to be read
-        let _ = rdr.read_record(&mut rec);
-        // Check the byte headers are trimmed
-        {
-            let headers = rdr.byte_headers().unwrap();
-            assert_eq!(3, headers.len());
-            assert_eq!(b"foo", &headers[0]);
-            assert_eq!(b"b\xFFar", &headers[1]);
-            assert_eq!(b"baz", &headers[2]);
+        let mut rdr =
+            ReaderBuilder::new().has_headers(false).from_reader(data);
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(rec, vec!["foo bar\" baz\""]);
+    }
+
+    #[test]
+    fn skip_blank_records() {
+        let data = b("a,b\n1,2\n  \t\n\" \", \n3,4\n");
+        let mut rdr =
+            ReaderBuilder::new().skip_blank_records(true).from_reader(data);
+        let mut rec = StringRecord::new();
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(rec, vec!["1", "2"]);
+        assert_eq!(rec.position().unwrap().record(), 1);
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(rec, vec!["3", "4"]);
+        assert_eq!(rec.position().unwrap().record(), 4);
+        assert_eq!(rec.position().unwrap().line(), 5);
+        assert!(!rdr.read_record(&mut rec).unwrap());
+
+        // Without the setting, blank records are ordinary records.
+        let mut rdr = ReaderBuilder::new().flexible(true).from_reader(data);
+        assert_eq!(rdr.records().count(), 4);
+    }
+
+    #[test]
+    fn final_record_terminator() {
+        let read = |data: &'static str| {
+            let mut rdr = ReaderBuilder::new()
+                .has_headers(false)
+                .from_reader(data.as_bytes());
+            let records: Vec<_> =
+                rdr.records().map(|r| r.map_err(|e| e.to_string())).collect();
+            records
+        };
+
+        // A terminator after the last record is optional, and doesn't add
+        // a phantom empty record.
+        for data in &["a,b\nc,d\n", "a,b\nc,d", "a,b\r\nc,d\r\n"] {
+            let got = read(data);
+            assert_eq!(got.len(), 2, "{:?}", data);
+            assert_eq!(got[1].as_ref().unwrap(), &vec!["c", "d"]);
         }
-        match *rdr.headers().unwrap_err().kind() {
-            ErrorKind::Utf8 { pos: Some(ref pos), ref err } => {
-                assert_eq!(pos, &newpos(0, 1, 0));
-                assert_eq!(err.field(), 1);
-                assert_eq!(err.valid_up_to(), 3);
+        let got = read("a,b\nc,\"d e\"");
+        assert_eq!(got[1].as_ref().unwrap(), &vec!["c", "d e"]);
+
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(b("a,b\nc,\"d\ne"));
+        let mut rec = StringRecord::new();
+        assert!(rdr.read_record(&mut rec).unwrap());
+        let err = rdr.read_record(&mut rec).unwrap_err();
+        match *err.kind() {
+            ErrorKind::UnterminatedQuote { ref pos, field: 1 } => {
+                assert_eq!(pos.as_ref().unwrap().record(), 1);
             }
-            ref err => panic!("match failed, got {:?}", err),
+            ref x => panic!("expected UnterminatedQuote, got {:?}", x),
         }
+        assert_eq!(rec, vec!["c", "d\ne"]);
+        assert!(!rdr.read_record(&mut rec).unwrap());
     }
 
     #[test]
-    fn read_trimmed_records() {
-        let data = b("foo,  bar,\tbaz\n  1,  2,  3\n1\t,\t,3\t\t");
+    fn max_fields() {
+        let wide: Vec<String> = (0..10).map(|i| i.to_string()).collect();
+        let wide = wide.join(",");
+        let data = format!("{}\n{}\n\"a,b\",c\n", wide, wide);
         let mut rdr = ReaderBuilder::new()
-            .has_headers(true)
-            .trim(Trim::Fields)
-            .from_reader(data);
+            .has_headers(false)
+            .flexible(true)
+            .max_fields(Some(3))
+            .from_reader(data.as_bytes());
         let mut rec = ByteRecord::new();
         assert!(rdr.read_byte_record(&mut rec).unwrap());
-        assert_eq!("1", s(&rec[0]));
-        assert_eq!("2", s(&rec[1]));
-        assert_eq!("3", s(&rec[2]));
-        {
-            let headers = rdr.headers().unwrap();
-            assert_eq!(3, headers.len());
-            assert_eq!("foo", &headers[0]);
-            assert_eq!("  bar", &headers[1]);
-            assert_eq!("\tbaz", &headers[2]);
-        }
+        assert_eq!(rec, vec!["0", "1", "2"]);
+        assert_eq!(rdr.position().line(), 2);
+        assert!(rdr.read_byte_record(&mut rec).unwrap());
+        assert_eq!(rec, vec!["0", "1", "2"]);
+        assert_eq!(rdr.position().line(), 3);
+        assert!(rdr.read_byte_record(&mut rec).unwrap());
+        assert_eq!(rec, vec!["a,b", "c"]);
+        assert!(!rdr.read_byte_record(&mut rec).unwrap());
     }
 
     #[test]
-    fn read_record_unequal_fails() {
-        let data = b("foo\nbar,baz");
-        let mut rdr =
-            ReaderBuilder::new().has_headers(false).from_reader(data);
-        let mut rec = ByteRecord::new();
-
-        assert!(rdr.read_byte_record(&mut rec).unwrap());
-        assert_eq!(1, rec.len());
-        assert_eq!("foo", s(&rec[0]));
-
-        match rdr.read_byte_record(&mut rec) {
-            Err(err) => match *err.kind() {
-                ErrorKind::UnequalLengths {
-                    expected_len: 1,
-                    ref pos,
-                    len: 2,
-                } => {
-                    assert_eq!(pos, &Some(newpos(4, 2, 1)));
-                }
-                ref wrong => panic!("match failed, got {:?}", wrong),
-            },
-            wrong => panic!("match failed, got {:?}", wrong),
-        }
+    fn max_fields_long_skipped_data() {
+        // The skipped fields are larger than the scratch buffers.
+        let long = "x".repeat(5000);
+        let fields: Vec<&str> = (0..100).map(|_| &*long).collect();
+        let data = format!("a,{}\nb\n", fields.join(","));
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .max_fields(Some(1))
+            .from_reader(data.as_bytes());
+        let records: Vec<ByteRecord> =
+            rdr.byte_records().map(|r| r.unwrap()).collect();
+        assert_eq!(records, vec![vec!["a"], vec!["b"]]);
     }
 
     #[test]
-    fn read_record_unequal_ok() {
-        let data = b("foo\nbar,baz");
+    fn read_fields() {
+        let data = b("foo,bar\nabc,\"d,e\",f\n\nxyz\n");
         let mut rdr = ReaderBuilder::new()
             .has_headers(false)
             .flexible(true)
             .from_reader(data);
-        let mut rec = ByteRecord::new();
+        let mut total = 0;
+        let mut counts = vec![];
+        while let Some(fields) = rdr.read_fields().unwrap() {
+            counts.push(fields.len());
+            total += fields.map(|field| field.len()).sum::<usize>();
+        }
+        assert_eq!(total, 16);
+        assert_eq!(counts, vec![2, 3, 1]);
+        assert!(rdr.read_fields().unwrap().is_none());
 
-        assert!(rdr.read_byte_record(&mut rec).unwrap());
-        assert_eq!(1, rec.len());
-        assert_eq!("foo", s(&rec[0]));
+        let mut rdr = ReaderBuilder::new().from_reader(b("h1,h2\na,b\n"));
+        let fields: Vec<&[u8]> = rdr.read_fields().unwrap().unwrap().collect();
+        assert_eq!(fields, vec![b("a"), b("b")]);
+    }
 
-        assert!(rdr.read_byte_record(&mut rec).unwrap());
-        assert_eq!(2, rec.len());
-        assert_eq!("bar", s(&rec[0]));
-        assert_eq!("baz", s(&rec[1]));
+    #[test]
+    fn detect_bom() {
+        let cases: Vec<(&[u8], Option<Bom>)> = vec![
+            (b"\xEF\xBB\xBFa,b\n", Some(Bom::Utf8)),
+            (b"\xFF\xFEa\x00,\x00", Some(Bom::Utf16Le)),
+            (b"\xFE\xFF\x00a\x00,", Some(Bom::Utf16Be)),
+            (b"a,b\n", None),
+            (b"", None),
+        ];
+        for (data, expected) in cases {
+            let mut rdr = ReaderBuilder::new().from_reader(data);
+            assert_eq!(rdr.detect_bom().unwrap(), expected);
+        }
 
-        assert!(!rdr.read_byte_record(&mut rec).unwrap());
+        let data = b("\u{FEFF}a,b\n1,2\n");
+        let mut rdr = ReaderBuilder::new().from_reader(data);
+        assert_eq!(rdr.detect_bom().unwrap(), Some(Bom::Utf8));
+        assert_eq!(rdr.detect_bom().unwrap(), None);
+        assert_eq!(rdr.byte_headers().unwrap(), vec!["a", "b"]);
+        assert_eq!(rdr.position().byte(), 7);
+        assert_eq!(rdr.detect_bom().unwrap(), None);
     }
 
-    // This tests that even if we get a CSV error, we can continue reading
-    // if we want.
     #[test]
-    fn read_record_unequal_continue() {
-        let data = b("foo\nbar,baz\nquux");
+    fn field_count() {
+        let data = b("a,b,c\n1,2\n");
+        let mut rdr = ReaderBuilder::new().flexible(true).from_reader(data);
+        assert_eq!(rdr.field_count(), None);
+        assert_eq!(rdr.headers().unwrap().len(), 3);
+        assert_eq!(rdr.field_count(), Some(3));
+        let mut rec = StringRecord::new();
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(rec.len(), 2);
+        assert_eq!(rdr.field_count(), Some(3));
+
         let mut rdr =
             ReaderBuilder::new().has_headers(false).from_reader(data);
-        let mut rec = ByteRecord::new();
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(rdr.field_count(), Some(3));
+    }
 
-        assert!(rdr.read_byte_record(&mut rec).unwrap());
-        assert_eq!(1, rec.len());
-        assert_eq!("foo", s(&rec[0]));
+    #[test]
+    fn enumerate_records() {
+        let data = b("h1,h2\na,b\nc\nd,e\nf,g\n");
+        let mut rdr = ReaderBuilder::new().from_reader(data);
+        let items: Vec<(u64, bool)> =
+            rdr.enumerate_records().map(|(i, r)| (i, r.is_ok())).collect();
+        assert_eq!(items, vec![(0, true), (1, false), (2, true), (3, true)]);
 
-        match rdr.read_byte_record(&mut rec) {
-            Err(err) => match err.kind() {
-                &ErrorKind::UnequalLengths {
-                    expected_len: 1,
-                    ref pos,
-                    len: 2,
-                } => {
-                    assert_eq!(pos, &Some(newpos(4, 2, 1)));
-                }
-                wrong => panic!("match failed, got {:?}", wrong),
-            },
-            wrong => panic!("match failed, got {:?}", wrong),
+        let mut rdr =
+            ReaderBuilder::new().has_headers(false).from_reader(data);
+        let mut it = rdr.enumerate_records();
+        let (i, rec) = it.next().unwrap();
+        assert_eq!(
+            (i, rec.unwrap()),
+            (0, StringRecord::from(vec!["h1", "h2"]))
+        );
+        let (i, rec) = it.next().unwrap();
+        assert_eq!((i, rec.unwrap()), (1, StringRecord::from(vec!["a", "b"])));
+    }
+
+    #[test]
+    fn column() {
+        let data = b("a,b,c\n1,2,3\n4,5,6\n7,8,9\n");
+        let mut rdr = ReaderBuilder::new().from_reader(data);
+        assert_eq!(rdr.column(1).unwrap(), vec!["2", "5", "8"]);
+        assert!(rdr.column(1).unwrap().is_empty());
+
+        let mut rdr =
+            ReaderBuilder::new().has_headers(false).from_reader(data);
+        assert_eq!(rdr.column(2).unwrap(), vec!["c", "3", "6", "9"]);
+
+        let data = b("a,b,c\n1,2,3\n4,5\n");
+        let mut rdr = ReaderBuilder::new().flexible(true).from_reader(data);
+        let err = rdr.column(2).unwrap_err();
+        match *err.kind() {
+            ErrorKind::MissingColumn { pos: Some(ref pos), ref name } => {
+                assert_eq!(pos.line(), 3);
+                assert_eq!(name, "c");
+            }
+            ref x => panic!("expected MissingColumn, got {:?}", x),
+        }
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(data);
+        match *rdr.column(3).unwrap_err().kind() {
+            ErrorKind::MissingColumn { ref name, .. } => assert_eq!(name, "3"),
+            ref x => panic!("expected MissingColumn, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn dialect_round_trip() {
+        let records = vec![
+            vec!["name", "note"],
+            vec!["a \"quoted\" word", "x,y"],
+            vec![" padded ", "line\nbreak"],
+            vec!["", "42"],
+        ];
+        let dialects = vec![
+            Dialect::Rfc4180,
+            Dialect::Excel,
+            Dialect::Unix,
+            Dialect::PostgresCopy,
+        ];
+        for dialect in dialects {
+            let mut wtr =
+                WriterBuilder::new().dialect(dialect).from_writer(vec![]);
+            for record in &records {
+                wtr.write_record(record).unwrap();
+            }
+            let data = wtr.into_inner().unwrap();
+
+            let mut rdr = ReaderBuilder::new()
+                .delimiter(b';')
+                .trim(Trim::All)
+                .dialect(dialect)
+                .has_headers(false)
+                .from_reader(&data[..]);
+            let got: Vec<StringRecord> =
+                rdr.records().map(|r| r.unwrap()).collect();
+            assert_eq!(got, records, "dialect {:?}", dialect);
         }
+    }
 
-        assert!(rdr.read_byte_record(&mut rec).unwrap());
-        assert_eq!(1, rec.len());
-        assert_eq!("quux", s(&rec[0]));
+    #[test]
+    fn escape_unquoted() {
+        let data = b("a\\,b,c\\\nd\n\\\"x\\\",y\n");
+        let mut rec = StringRecord::new();
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .escape(Some(b'\\'))
+            .escape_unquoted(true)
+            .from_reader(data);
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(rec, vec!["a,b", "c\nd"]);
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(rec, vec!["\"x\"", "y"]);
+        assert!(!rdr.read_record(&mut rec).unwrap());
 
-        assert!(!rdr.read_byte_record(&mut rec).unwrap());
+        // The fallback parser recognizes such escapes too.
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .multi_delimiter(b"||")
+            .escape(Some(b'\\'))
+            .escape_unquoted(true)
+            .from_reader(b("a\\||b||c\n"));
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(rec, vec!["a||b", "c"]);
     }
 
     #[test]
-    fn read_record_headers() {
-        let data = b("foo,bar,baz\na,b,c\nd,e,f");
-        let mut rdr = ReaderBuilder::new().has_headers(true).from_reader(data);
-        let mut rec = StringRecord::new();
+    fn record_boundary() {
+        let data = b("---,h\nx,y\n---\na,b\n\nc,d\n---,2\ne,\"f\ng\"\n");
+        let mut rdr = ReaderBuilder::new()
+            .flexible(true)
+            .record_boundary(Box::new(|line| line.starts_with(b"---")))
+            .from_reader(data);
+        assert_eq!(rdr.headers().unwrap(), vec!["---", "h", "x", "y"]);
 
+        let mut rec = StringRecord::new();
         assert!(rdr.read_record(&mut rec).unwrap());
-        assert_eq!(3, rec.len());
-        assert_eq!("a", &rec[0]);
-
+        assert_eq!(rec, vec!["---", "a", "b", "c", "d"]);
+        assert_eq!(rec.position().unwrap().line(), 3);
+        assert_eq!(rec.position().unwrap().record(), 1);
         assert!(rdr.read_record(&mut rec).unwrap());
-        assert_eq!(3, rec.len());
-        assert_eq!("d", &rec[0]);
-
+        assert_eq!(rec, vec!["---", "2", "e", "f\ng"]);
+        assert_eq!(rec.position().unwrap().line(), 7);
+        assert_eq!(rec.position().unwrap().record(), 2);
         assert!(!rdr.read_record(&mut rec).unwrap());
 
-        {
-            let headers = rdr.byte_headers().unwrap();
-            assert_eq!(3, headers.len());
-            assert_eq!(b"foo", &headers[0]);
-            assert_eq!(b"bar", &headers[1]);
-            assert_eq!(b"baz", &headers[2]);
-        }
-        {
-            let headers = rdr.headers().unwrap();
-            assert_eq!(3, headers.len());
-            assert_eq!("foo", &headers[0]);
-            assert_eq!("bar", &headers[1]);
-            assert_eq!("baz", &headers[2]);
+        // Field counts are checked on logical records.
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .record_boundary(Box::new(|line| line.starts_with(b"#")))
+            .from_reader(b("#1\na,b\n#2\nc,d,e\n"));
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(rec, vec!["#1", "a", "b"]);
+        match *rdr.read_record(&mut rec).unwrap_err().kind() {
+            ErrorKind::UnequalLengths { expected_len: 3, len: 4, .. } => {}
+            ref x => panic!("expected UnequalLengths, got {:?}", x),
         }
     }
 
+    // Test that a huge last field without a record terminator is returned
+    // in full at the end of the data.
     #[test]
-    fn read_record_headers_invalid_utf8() {
-        let data = &b"foo,b\xFFar,baz\na,b,c\nd,e,f"[..];
-        let mut rdr = ReaderBuilder::new().has_headers(true).from_reader(data);
-        let mut rec = StringRecord::new();
+    fn huge_unterminated_last_field() {
+        let mut data = b"a,b\nx,".to_vec();
+        data.resize(data.len() + 5 * (1 << 20), b'z');
+        let mut rdr = ReaderBuilder::new().from_reader(&data[..]);
 
-        assert!(rdr.read_record(&mut rec).unwrap());
-        assert_eq!(3, rec.len());
-        assert_eq!("a", &rec[0]);
+        let mut rec = ByteRecord::new();
+        assert!(rdr.read_byte_record(&mut rec).unwrap());
+        assert_eq!(rec.len(), 2);
+        assert_eq!(&rec[0], b"x");
+        assert_eq!(rec[1].len(), 5 * (1 << 20));
+        assert!(rec[1].iter().all(|&b| b == b'z'));
+        assert!(!rdr.read_byte_record(&mut rec).unwrap());
+        assert!(!rdr.read_byte_record(&mut rec).unwrap());
+    }
 
-        assert!(rdr.read_record(&mut rec).unwrap());
-        assert_eq!(3, rec.len());
-        assert_eq!("d", &rec[0]);
+    #[test]
+    fn read_record_raw() {
+        let data = "h1,h2\r\n# note\n\"a\"\"\", b \n\n\"x\ny\",z\nlast,1";
+        let mut rdr = ReaderBuilder::new()
+            .comment(Some(b'#'))
+            .from_reader(data.as_bytes());
+        let (mut rec, mut raw) = (ByteRecord::new(), vec![]);
+        let mut all = vec![];
+        let mut raws = vec![];
+        while rdr.read_record_raw(&mut rec, &mut raw).unwrap() {
+            all.extend_from_slice(&raw);
+            raws.push(String::from_utf8(raw.clone()).unwrap());
+        }
+        all.extend_from_slice(&raw);
+        assert_eq!(all, data.as_bytes());
+        assert_eq!(raws[0], "h1,h2\r\n# note\n\"a\"\"\", b \n");
+        assert_eq!(raws[1], "\n\"x\ny\",z\n");
+        assert_eq!(raws[2], "last,1");
+        assert_eq!(rec, ByteRecord::new());
+
+        // Logical records keep the raw bytes of all of their lines.
+        let data = "#1\na,b\n#2\nc,d\n";
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .record_boundary(Box::new(|line| line.starts_with(b"#")))
+            .from_reader(data.as_bytes());
+        assert!(rdr.read_record_raw(&mut rec, &mut raw).unwrap());
+        assert_eq!(rec, vec!["#1", "a", "b"]);
+        assert_eq!(raw, b"#1\na,b\n");
+        assert!(rdr.read_record_raw(&mut rec, &mut raw).unwrap());
+        assert_eq!(raw, b"#2\nc,d\n");
+        assert!(!rdr.read_record_raw(&mut rec, &mut raw).unwrap());
+        assert!(raw.is_empty());
+    }
 
-        assert!(!rdr.read_record(&mut rec).unwrap());
+    #[test]
+    fn escape_before_closing_quote() {
+        for delimiter in &[&b","[..], b",,"] {
+            let read = |data: &str| {
+                let mut rdr = ReaderBuilder::new()
+                    .has_headers(false)
+                    .escape(Some(b'\\'))
+                    .multi_delimiter(delimiter)
+                    .from_reader(io::Cursor::new(data.to_string()));
+                let mut rec = ByteRecord::new();
+                let res = rdr.read_byte_record(&mut rec);
+                (res, rec)
+            };
+            let sep = String::from_utf8(delimiter.to_vec()).unwrap();
 
-        // Check that we can read the headers as raw bytes, but that
-        // if we read them as strings, we get an appropriate UTF-8 error.
-        {
-            let headers = rdr.byte_headers().unwrap();
-            assert_eq!(3, headers.len());
-            assert_eq!(b"foo", &headers[0]);
-            assert_eq!(b"b\xFFar", &headers[1]);
-            assert_eq!(b"baz", &headers[2]);
-        }
-        match *rdr.headers().unwrap_err().kind() {
-            ErrorKind::Utf8 { pos: Some(ref pos), ref err } => {
-                assert_eq!(pos, &newpos(0, 1, 0));
-                assert_eq!(err.field(), 1);
-                assert_eq!(err.valid_up_to(), 1);
+            let (res, rec) = read(&format!("\"abc\\\\\"{}x\n", sep));
+            assert!(res.unwrap());
+            assert_eq!(rec, vec![r"abc\", "x"]);
+
+            let (res, rec) = read(&format!("\"abc\\\"\"{}x\n", sep));
+            assert!(res.unwrap());
+            assert_eq!(rec, vec![r#"abc""#, "x"]);
+
+            let (res, rec) = read(&format!("\"abc\\\"{}x\n", sep));
+            match *res.unwrap_err().kind() {
+                ErrorKind::UnterminatedQuote { field: 0, .. } => {}
+                ref x => panic!("expected UnterminatedQuote, got {:?}", x),
             }
-            ref err => panic!("match failed, got {:?}", err),
+            assert_eq!(rec, vec![format!("abc\"{}x\n", sep)]);
         }
     }
 
     #[test]
-    fn read_record_no_headers_before() {
-        let data = b("foo,bar,baz\na,b,c\nd,e,f");
-        let mut rdr =
-            ReaderBuilder::new().has_headers(false).from_reader(data);
-        let mut rec = StringRecord::new();
-
-        {
-            let headers = rdr.headers().unwrap();
-            assert_eq!(3, headers.len());
-            assert_eq!("foo", &headers[0]);
-            assert_eq!("bar", &headers[1]);
-            assert_eq!("baz", &headers[2]);
+    fn lone_carriage_returns() {
+        // With the default terminator, a lone CR ends a record just like LF
+        // and CRLF do, and empty lines between records are skipped.
+        for data in &["a\rb", "a\r\nb", "a\n\rb", "a\r\rb\r", "a\rb\r\n"] {
+            for delimiter in &[&b","[..], b",,"] {
+                let mut rdr = ReaderBuilder::new()
+                    .has_headers(false)
+                    .multi_delimiter(delimiter)
+                    .from_reader(data.as_bytes());
+                let mut rec = ByteRecord::new();
+                assert!(rdr.read_byte_record(&mut rec).unwrap());
+                assert_eq!(rec, vec!["a"], "{:?}", data);
+                assert!(rdr.read_byte_record(&mut rec).unwrap());
+                assert_eq!(rec, vec!["b"], "{:?}", data);
+                assert!(!rdr.read_byte_record(&mut rec).unwrap());
+            }
         }
+    }
 
-        assert!(rdr.read_record(&mut rec).unwrap());
-        assert_eq!(3, rec.len());
-        assert_eq!("foo", &rec[0]);
-
-        assert!(rdr.read_record(&mut rec).unwrap());
-        assert_eq!(3, rec.len());
-        assert_eq!("a", &rec[0]);
+    #[test]
+    fn verify_trailer() {
+        let read = |data: &str, has_headers: bool| {
+            let mut rdr = ReaderBuilder::new()
+                .has_headers(has_headers)
+                .verify_trailer(true)
+                .from_reader(data.as_bytes());
+            rdr.byte_records().collect::<Vec<_>>()
+        };
 
-        assert!(rdr.read_record(&mut rec).unwrap());
-        assert_eq!(3, rec.len());
-        assert_eq!("d", &rec[0]);
+        let got = read("h\na\nb\n#COUNT,2\n", true);
+        assert_eq!(got.len(), 2);
+        assert_eq!(got[1].as_ref().unwrap(), &vec!["b"]);
+        assert_eq!(read("a\nb\n#COUNT,2", false).len(), 2);
+        assert_eq!(read("h\n#COUNT,0\n", true).len(), 0);
+
+        let got = read("h\na\nb\n#COUNT,3\n", true);
+        assert_eq!(got.len(), 3);
+        match *got[2].as_ref().unwrap_err().kind() {
+            ErrorKind::TrailerMismatch { ref pos, expected: 3, actual: 2 } => {
+                assert_eq!(pos.as_ref().unwrap().line(), 4);
+            }
+            ref x => panic!("expected TrailerMismatch, got {:?}", x),
+        }
 
-        assert!(!rdr.read_record(&mut rec).unwrap());
+        // Only the last record can be a trailer.
+        let got = read("#COUNT,1\n#COUNT,x\n", false);
+        assert_eq!(got.len(), 2);
+        assert_eq!(got[0].as_ref().unwrap(), &vec!["#COUNT", "1"]);
+        assert_eq!(got[1].as_ref().unwrap(), &vec!["#COUNT", "x"]);
+        assert_eq!(read("#COUNT,1\n#COUNT,1\n", false).len(), 1);
     }
 
     #[test]
-    fn read_record_no_headers_after() {
-        let data = b("foo,bar,baz\na,b,c\nd,e,f");
+    fn verify_written_trailer() {
+        let mut wtr = crate::WriterBuilder::new()
+            .trailer(crate::TrailerKind::Count)
+            .from_writer(vec![]);
+        wtr.set_header(&["a", "b"]).unwrap();
+        for i in 0..10 {
+            wtr.write_record(&[i.to_string(), i.to_string()]).unwrap();
+        }
+        let data = wtr.into_inner().unwrap();
         let mut rdr =
-            ReaderBuilder::new().has_headers(false).from_reader(data);
-        let mut rec = StringRecord::new();
+            ReaderBuilder::new().verify_trailer(true).from_reader(&*data);
+        let mut count = 0;
+        for result in rdr.records() {
+            result.unwrap();
+            count += 1;
+        }
+        assert_eq!(count, 10);
+    }
 
-        assert!(rdr.read_record(&mut rec).unwrap());
-        assert_eq!(3, rec.len());
-        assert_eq!("foo", &rec[0]);
+    #[test]
+    fn seek_cached() {
+        let data = "a,b\n1,2\n3,4\n5,6\n";
+        let mut rdr = ReaderBuilder::new()
+            .cache_positions(true)
+            .from_reader(io::Cursor::new(data));
+        assert!(rdr.seek_cached(1).is_err());
+        let mut rec = StringRecord::new();
+        while rdr.read_record(&mut rec).unwrap() {}
+        let records: Vec<u64> =
+            rdr.position_cache().iter().map(|p| p.record()).collect();
+        assert_eq!(records, vec![0, 1, 2, 3]);
 
+        rdr.seek_cached(2).unwrap();
         assert!(rdr.read_record(&mut rec).unwrap());
-        assert_eq!(3, rec.len());
-        assert_eq!("a", &rec[0]);
-
+        assert_eq!(rec, vec!["3", "4"]);
+        assert_eq!(rec.position().unwrap().line(), 3);
         assert!(rdr.read_record(&mut rec).unwrap());
-        assert_eq!(3, rec.len());
-        assert_eq!("d", &rec[0]);
-
+        assert_eq!(rec, vec!["5", "6"]);
         assert!(!rdr.read_record(&mut rec).unwrap());
+        assert_eq!(rdr.position_cache().len(), 4);
+        assert!(rdr.seek_cached(4).is_err());
 
-        let headers = rdr.headers().unwrap();
-        assert_eq!(3, headers.len());
-        assert_eq!("foo", &headers[0]);
-        assert_eq!("bar", &headers[1]);
-        assert_eq!("baz", &headers[2]);
+        let mut rdr = ReaderBuilder::new().from_reader(io::Cursor::new(data));
+        while rdr.read_record(&mut rec).unwrap() {}
+        assert!(rdr.position_cache().is_empty());
+        assert!(rdr.seek_cached(1).is_err());
     }
 
     #[test]
-    fn seek() {
-        let data = b("foo,bar,baz\na,b,c\nd,e,f\ng,h,i");
-        let mut rdr = ReaderBuilder::new().from_reader(io::Cursor::new(data));
-        rdr.seek(newpos(18, 3, 2)).unwrap();
-
-        let mut rec = StringRecord::new();
-
-        assert_eq!(18, rdr.position().byte());
-        assert!(rdr.read_record(&mut rec).unwrap());
-        assert_eq!(3, rec.len());
-        assert_eq!("d", &rec[0]);
-
-        assert_eq!(24, rdr.position().byte());
-        assert_eq!(4, rdr.position().line());
-        assert_eq!(3, rdr.position().record());
-        assert!(rdr.read_record(&mut rec).unwrap());
-        assert_eq!(3, rec.len());
-        assert_eq!("g", &rec[0]);
+    fn read_record_spans() {
+        let data = "a,b,c\n\"x,\"\"y\"\"\",,plain\r\n\n#note\n\"q\"r,s,\"\"\n";
+        let mut rdr = ReaderBuilder::new()
+            .comment(Some(b'#'))
+            .from_reader(data.as_bytes());
+        let mut spans = vec![];
+        let mut fields = vec![];
+        while rdr.read_record_spans(&mut spans).unwrap() {
+            fields.push(
+                spans.iter().map(|&(s, e)| &data[s..e]).collect::<Vec<_>>(),
+            );
+        }
+        assert!(spans.is_empty());
+        assert_eq!(
+            fields,
+            vec![vec!["x,\"\"y\"\"", "", "plain"], vec!["q\"r", "s", ""]]
+        );
 
-        assert!(!rdr.read_record(&mut rec).unwrap());
+        let data = "k||v\n1||\"a||b\"\n";
+        let mut rdr = ReaderBuilder::new()
+            .multi_delimiter(b"||")
+            .from_reader(data.as_bytes());
+        assert!(rdr.read_record_spans(&mut spans).unwrap());
+        assert_eq!(spans, vec![(5, 6), (9, 13)]);
+        assert_eq!(&data[9..13], "a||b");
     }
 
-    // Test that we can read headers after seeking even if the headers weren't
-    // explicit read before seeking.
     #[test]
-    fn seek_headers_after() {
-        let data = b("foo,bar,baz\na,b,c\nd,e,f\ng,h,i");
-        let mut rdr = ReaderBuilder::new().from_reader(io::Cursor::new(data));
-        rdr.seek(newpos(18, 3, 2)).unwrap();
-        assert_eq!(rdr.headers().unwrap(), vec!["foo", "bar", "baz"]);
+    fn trailing_delimiter_at_eof() {
+        for &multi in &[false, true] {
+            let mut builder = ReaderBuilder::new();
+            builder.has_headers(false);
+            if multi {
+                builder.multi_delimiter(b",");
+            }
+            let mut rdr = builder.from_reader("a,b,".as_bytes());
+            let mut rec = ByteRecord::new();
+            assert!(rdr.read_byte_record(&mut rec).unwrap());
+            assert_eq!(rec, vec!["a", "b", ""]);
+            assert!(!rdr.read_byte_record(&mut rec).unwrap());
+            assert!(rdr.is_done());
+            assert!(!rdr.read_byte_record(&mut rec).unwrap());
+        }
     }
 
-    // Test that we can read headers after seeking if the headers were read
-    // before seeking.
     #[test]
-    fn seek_headers_before_after() {
-        let data = b("foo,bar,baz\na,b,c\nd,e,f\ng,h,i");
-        let mut rdr = ReaderBuilder::new().from_reader(io::Cursor::new(data));
-        let headers = rdr.headers().unwrap().clone();
-        rdr.seek(newpos(18, 3, 2)).unwrap();
-        assert_eq!(&headers, rdr.headers().unwrap());
+    fn reset_with() {
+        let mut rdr = ReaderBuilder::new()
+            .delimiter(b';')
+            .from_reader("a;b\n1;2\n".as_bytes());
+        let mut rec = StringRecord::new();
+        let mut seen = vec![];
+        for data in &["x;y\n3;4\n5;6", "z\n7\n", ""] {
+            rdr = rdr.reset_with(data.as_bytes());
+            assert_eq!(rdr.position().byte(), 0);
+            seen.push(rdr.headers().unwrap().clone());
+            while rdr.read_record(&mut rec).unwrap() {
+                seen.push(rec.clone());
+            }
+            assert!(rdr.is_done());
+        }
+        assert_eq!(
+            seen,
+            vec![
+                vec!["x", "y"],
+                vec!["3", "4"],
+                vec!["5", "6"],
+                vec!["z"],
+                vec!["7"],
+                vec![],
+            ]
+        );
     }
 
-    // Test that even if we didn't read headers before seeking, if we seek to
-    // the current byte offset, then no seeking is done and therefore we can
-    // still read headers after seeking.
     #[test]
-    fn seek_headers_no_actual_seek() {
-        let data = b("foo,bar,baz\na,b,c\nd,e,f\ng,h,i");
-        let mut rdr = ReaderBuilder::new().from_reader(io::Cursor::new(data));
-        rdr.seek(Position::new()).unwrap();
-        assert_eq!("foo", &rdr.headers().unwrap()[0]);
+    fn header_map() {
+        let data = b("id,name,id,value\n1,a,2,b\n");
+        let mut rdr = ReaderBuilder::new().from_reader(data);
+        let map = rdr.header_map().unwrap();
+        assert_eq!(map.len(), 3);
+        assert_eq!(map["id"], 0);
+        assert_eq!(map["name"], 1);
+        assert_eq!(map["value"], 3);
+
+        let mut rec = StringRecord::new();
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(rec, vec!["1", "a", "2", "b"]);
+
+        let mut rdr = ReaderBuilder::new().from_reader(&b"\xFF,a\n"[..]);
+        assert!(rdr.header_map().is_err());
     }
 
-    // Test that position info is reported correctly in absence of headers.
     #[test]
-    fn positions_no_headers() {
+    fn stop_at_blank() {
+        let data = "a,b\n1,2\n3,4\n\n\nx\r\ny\r\n\r\nz\n";
         let mut rdr = ReaderBuilder::new()
-            .has_headers(false)
-            .from_reader("a,b,c\nx,y,z".as_bytes())
-            .into_records();
+            .stop_at_blank(true)
+            .from_reader(data.as_bytes());
+        let mut sections = vec![];
+        while !rdr.is_done() {
+            let section: Vec<_> = rdr.records().map(|r| r.unwrap()).collect();
+            sections.push(section);
+        }
+        assert_eq!(
+            sections,
+            vec![
+                vec![vec!["1", "2"], vec!["3", "4"]],
+                vec![vec!["x"], vec!["y"]],
+                vec![vec!["z"]],
+            ]
+        );
+
+        // A blank line right after the header row ends an empty section,
+        // and blank lines at the start of the data are ignored.
+        let data = "\n\nh\n\n1\n2\n";
+        let mut rdr = ReaderBuilder::new()
+            .stop_at_blank(true)
+            .from_reader(data.as_bytes());
+        assert_eq!(rdr.records().count(), 0);
+        assert_eq!(rdr.headers().unwrap(), vec!["h"]);
+        assert_eq!(rdr.records().count(), 2);
 
-        let pos = rdr.next().unwrap().unwrap().position().unwrap().clone();
-        assert_eq!(pos.byte(), 0);
-        assert_eq!(pos.line(), 1);
-        assert_eq!(pos.record(), 0);
+        // Lone carriage returns and whitespace-only records.
+        let data = "1\r2\r\r3\n \n4\n";
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .stop_at_blank(true)
+            .skip_blank_records(true)
+            .from_reader(data.as_bytes());
+        let mut counts = vec![];
+        while !rdr.is_done() {
+            counts.push(rdr.records().count());
+        }
+        assert_eq!(counts, vec![2, 1, 1]);
 
-        let pos = rdr.next().unwrap().unwrap().position().unwrap().clone();
-        assert_eq!(pos.byte(), 6);
-        assert_eq!(pos.line(), 2);
-        assert_eq!(pos.record(), 1);
+        let data = "1\n\n2\n";
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(data.as_bytes());
+        assert_eq!(rdr.records().count(), 2);
     }
 
-    // Test that position info is reported correctly with headers.
     #[test]
-    fn positions_headers() {
+    fn max_records() {
+        let data = "h\n1\n2\n3\n4\n";
         let mut rdr = ReaderBuilder::new()
-            .has_headers(true)
-            .from_reader("a,b,c\nx,y,z".as_bytes())
-            .into_records();
+            .max_records(Some(2))
+            .from_reader(data.as_bytes());
+        let mut rec = ByteRecord::new();
+        assert!(rdr.read_byte_record(&mut rec).unwrap());
+        assert!(rdr.read_byte_record(&mut rec).unwrap());
+        assert_eq!(rec, vec!["2"]);
+        match *rdr.read_byte_record(&mut rec).unwrap_err().kind() {
+            ErrorKind::TooManyRecords { limit: 2 } => {}
+            ref x => panic!("expected TooManyRecords, got {:?}", x),
+        }
+        assert_eq!(rdr.position().line(), 5);
 
-        let pos = rdr.next().unwrap().unwrap().position().unwrap().clone();
-        assert_eq!(pos.byte(), 6);
-        assert_eq!(pos.line(), 2);
-        assert_eq!(pos.record(), 1);
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .max_records(Some(3))
+            .from_reader("1\n2\n3\n".as_bytes());
+        assert_eq!(rdr.byte_headers().unwrap(), vec!["1"]);
+        let records: Vec<_> = rdr.byte_records().collect();
+        assert_eq!(records.len(), 3);
+        assert!(records.iter().all(|r| r.is_ok()));
+
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .max_records(Some(0))
+            .from_reader("1\n".as_bytes());
+        assert!(rdr.read_byte_record(&mut rec).is_err());
     }
 
-    // Test that reading headers on empty data yields an empty record.
     #[test]
-    fn headers_on_empty_data() {
-        let mut rdr = ReaderBuilder::new().from_reader("".as_bytes());
-        let r = rdr.byte_headers().unwrap();
-        assert_eq!(r.len(), 0);
+    fn quoting_disabled_literal_quotes() {
+        let data = "\"a\"\tb \"c\"\n\"\t\"\"\n";
+        for &multi in &[false, true] {
+            let mut builder = ReaderBuilder::new();
+            builder.has_headers(false).delimiter(b'\t').quoting(false);
+            if multi {
+                builder.multi_delimiter(b"\t");
+            }
+            let mut rdr = builder.from_reader(data.as_bytes());
+            let records: Vec<_> = rdr.records().map(|r| r.unwrap()).collect();
+            assert_eq!(
+                records,
+                vec![vec!["\"a\"", "b \"c\""], vec!["\"", "\"\""]]
+            );
+        }
     }
 
-    // Test that reading the first record on empty data works.
     #[test]
-    fn no_headers_on_empty_data() {
-        let mut rdr =
-            ReaderBuilder::new().has_headers(false).from_reader("".as_bytes());
-        assert_eq!(rdr.records().count(), 0);
+    fn estimate_record_count() {
+        let mut data = String::from("id,name\n");
+        for i in 0..5000 {
+            data.push_str(&format!("{:05},name-{}\n", i, i % 7));
+        }
+        let mut rdr = ReaderBuilder::new().from_reader(io::Cursor::new(data));
+        let mut rec = StringRecord::new();
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert!(rdr.read_record(&mut rec).unwrap());
+
+        let estimate = rdr.estimate_record_count().unwrap();
+        assert!(4900 <= estimate && estimate <= 5100, "{}", estimate);
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(rec, vec!["00002", "name-2"]);
+        assert_eq!(rdr.records().count(), 4997);
+
+        let data = "a;b\r\n1;2\r\n3;4\r\n";
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .multi_delimiter(b";")
+            .from_reader(io::Cursor::new(data));
+        assert_eq!(rdr.estimate_record_count().unwrap(), 3);
+        let mut rdr = ReaderBuilder::new().from_reader(io::Cursor::new(""));
+        assert_eq!(rdr.estimate_record_count().unwrap(), 0);
     }
 
-    // Test that reading the first record on empty data works, even if
-    // we've tried to read headers before hand.
     #[test]
-    fn no_headers_on_empty_data_after_headers() {
+    fn windows() {
+        let data = b("h\n1\n2\n3\n4\n5\n6\n7\n");
+        let mut rdr = ReaderBuilder::new().from_reader(data);
+        let windows: Vec<Vec<StringRecord>> =
+            rdr.windows(3).map(|w| w.unwrap()).collect();
+        let sizes: Vec<usize> = windows.iter().map(Vec::len).collect();
+        assert_eq!(sizes, vec![3, 3, 1]);
+        assert_eq!(windows[1][0], vec!["4"]);
+        assert_eq!(windows[2][0], vec!["7"]);
+        assert!(rdr.windows(3).next().is_none());
+
         let mut rdr =
-            ReaderBuilder::new().has_headers(false).from_reader("".as_bytes());
-        assert_eq!(rdr.headers().unwrap().len(), 0);
-        assert_eq!(rdr.records().count(), 0);
+            ReaderBuilder::new().from_reader(b("a,b\n1,2\n3\n4,5\n"));
+        let results: Vec<_> = rdr.windows(2).collect();
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_err());
+        assert_eq!(results[1].as_ref().unwrap(), &vec![vec!["4", "5"]]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn windows_zero_size() {
+        let mut rdr = ReaderBuilder::new().from_reader(b("a\n1\n"));
+        rdr.windows(0);
     }
 }
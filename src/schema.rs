@@ -0,0 +1,213 @@
+use std::str;
+
+use crate::byte_record::ByteRecord;
+
+/// The type of a column, as inferred by `Reader::infer_schema`.
+///
+/// Types are listed from narrowest to widest. Every value of an `Integer`
+/// column is also a valid `Float`, and every value is a valid `String`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FieldType {
+    /// Every value is a signed or unsigned 64-bit integer.
+    Integer,
+    /// Every value is a floating point number.
+    Float,
+    /// Every value is either `true` or `false`.
+    Boolean,
+    /// Every value is a date in the `YYYY-MM-DD` format.
+    Date,
+    /// Values are arbitrary strings, or the column has no values.
+    String,
+    /// Hints that destructuring should not be exhaustive.
+    ///
+    /// This enum may grow additional variants, so this makes sure clients
+    /// don't count on exhaustive matching. (Otherwise, adding a new variant
+    /// could break existing code.)
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+/// The inferred type of a single column.
+///
+/// This is returned by `Reader::infer_schema`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct InferredType {
+    field_type: FieldType,
+    nullable: bool,
+}
+
+impl InferredType {
+    /// Returns the narrowest type that fits every non-empty value in this
+    /// column.
+    pub fn field_type(&self) -> FieldType {
+        self.field_type
+    }
+
+    /// Returns true if and only if this column had at least one empty value.
+    ///
+    /// A value is also considered empty when a record has too few fields to
+    /// include this column.
+    pub fn is_nullable(&self) -> bool {
+        self.nullable
+    }
+}
+
+/// Infer the type of every column in the given sample of records.
+///
+/// The number of columns is the number of fields in the longest record.
+pub(crate) fn infer(records: &[ByteRecord]) -> Vec<InferredType> {
+    let ncols = records.iter().map(|r| r.len()).max().unwrap_or(0);
+    let mut types: Vec<Option<FieldType>> = vec![None; ncols];
+    let mut nullable = vec![false; ncols];
+    for record in records {
+        for i in 0..ncols {
+            let field = record.get(i).unwrap_or(b"");
+            if field.is_empty() {
+                nullable[i] = true;
+                continue;
+            }
+            let ty = field_type(field);
+            types[i] = Some(match types[i] {
+                None => ty,
+                Some(prev) => widen(prev, ty),
+            });
+        }
+    }
+    types
+        .into_iter()
+        .zip(nullable)
+        .map(|(ty, nullable)| InferredType {
+            field_type: ty.unwrap_or(FieldType::String),
+            nullable,
+        })
+        .collect()
+}
+
+/// Guess whether `first` is a header row for data that continues with
+/// `second`.
+///
+/// This is true when every field of `first` is a non-empty string while at
+/// least one field of `second` in the same column is not, e.g., a number.
+pub(crate) fn looks_like_header(
+    first: &ByteRecord,
+    second: &ByteRecord,
+) -> bool {
+    let all_names = first
+        .iter()
+        .all(|f| !f.is_empty() && field_type(f) == FieldType::String);
+    let typed_value = second
+        .iter()
+        .take(first.len())
+        .any(|f| !f.is_empty() && field_type(f) != FieldType::String);
+    !first.is_empty() && all_names && typed_value
+}
+
+/// Returns the narrowest type that fits both `a` and `b`.
+fn widen(a: FieldType, b: FieldType) -> FieldType {
+    use self::FieldType::*;
+
+    match (a, b) {
+        _ if a == b => a,
+        (Integer, Float) | (Float, Integer) => Float,
+        _ => String,
+    }
+}
+
+/// Returns the narrowest type that fits a single non-empty field.
+fn field_type(field: &[u8]) -> FieldType {
+    let s = match str::from_utf8(field) {
+        Err(_) => return FieldType::String,
+        Ok(s) => s,
+    };
+    if s.parse::<i64>().is_ok() || s.parse::<u64>().is_ok() {
+        FieldType::Integer
+    } else if s.parse::<f64>().is_ok() {
+        FieldType::Float
+    } else if s == "true" || s == "false" {
+        FieldType::Boolean
+    } else if is_date(s.as_bytes()) {
+        FieldType::Date
+    } else {
+        FieldType::String
+    }
+}
+
+/// Returns true if and only if `s` is a valid date of the form `YYYY-MM-DD`.
+fn is_date(s: &[u8]) -> bool {
+    fn digits(s: &[u8]) -> Option<u32> {
+        if !s.iter().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        Some(s.iter().fold(0, |n, &b| n * 10 + (b - b'0') as u32))
+    }
+
+    if s.len() != 10 || s[4] != b'-' || s[7] != b'-' {
+        return false;
+    }
+    let (year, month, day) =
+        match (digits(&s[..4]), digits(&s[5..7]), digits(&s[8..])) {
+            (Some(y), Some(m), Some(d)) => (y, m, d),
+            _ => return false,
+        };
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+    day >= 1 && day <= days
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::byte_record::ByteRecord;
+
+    use super::{infer, is_date, FieldType};
+
+    #[test]
+    fn widen_types() {
+        let records = vec![
+            ByteRecord::from(vec!["1", "1", "true", "x", "2020-02-29"]),
+            ByteRecord::from(vec!["-2", "1.5", "false", "1", "1999-12-31"]),
+        ];
+        let types: Vec<FieldType> =
+            infer(&records).iter().map(|t| t.field_type()).collect();
+        assert_eq!(
+            types,
+            vec![
+                FieldType::Integer,
+                FieldType::Float,
+                FieldType::Boolean,
+                FieldType::String,
+                FieldType::Date,
+            ]
+        );
+    }
+
+    #[test]
+    fn nullable_and_ragged() {
+        let records = vec![
+            ByteRecord::from(vec!["1", ""]),
+            ByteRecord::from(vec!["2", "", "true"]),
+        ];
+        let types = infer(&records);
+        assert_eq!(types.len(), 3);
+        assert!(!types[0].is_nullable());
+        assert!(types[1].is_nullable());
+        assert_eq!(types[1].field_type(), FieldType::String);
+        assert!(types[2].is_nullable());
+        assert_eq!(types[2].field_type(), FieldType::Boolean);
+    }
+
+    #[test]
+    fn dates() {
+        assert!(is_date(b"2000-02-29"));
+        assert!(!is_date(b"1900-02-29"));
+        assert!(!is_date(b"2001-13-01"));
+        assert!(!is_date(b"2001-04-31"));
+        assert!(!is_date(b"2001-4-30"));
+        assert!(!is_date(b"20010430"));
+    }
+}
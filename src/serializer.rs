@@ -2,17 +2,20 @@ use std::fmt;
 use std::io;
 use std::mem;
 
+use bstr::ByteSlice;
 use itoa;
 use ryu;
 use serde::ser::{
-    Error as SerdeError, Serialize, SerializeMap, SerializeSeq,
+    Error as SerdeError, Impossible, Serialize, SerializeMap, SerializeSeq,
     SerializeStruct, SerializeStructVariant, SerializeTuple,
     SerializeTupleStruct, SerializeTupleVariant, Serializer,
 };
 use serde::serde_if_integer128;
 
+use crate::byte_record::ByteRecord;
 use crate::error::{Error, ErrorKind};
 use crate::writer::Writer;
+use crate::TupleHeaderStyle;
 
 /// Serialize the given value to the given writer, and return an error if
 /// anything went wrong.
@@ -20,11 +23,57 @@ pub fn serialize<S: Serialize, W: io::Write>(
     wtr: &mut Writer<W>,
     value: S,
 ) -> Result<(), Error> {
-    value.serialize(&mut SeRecord { wtr: wtr })
+    value.serialize(&mut SeRecord::new(wtr))
+}
+
+/// Serialize the keys of the given map, and return them as the fields of a
+/// record.
+///
+/// This returns an error if the value isn't a map.
+pub fn serialize_map_keys<S: Serialize, W: io::Write>(
+    wtr: &Writer<W>,
+    value: S,
+) -> Result<ByteRecord, Error> {
+    let mut ser = SeMapKeys { wtr, keys: ByteRecord::new() };
+    value.serialize(&mut ser)?;
+    Ok(ser.keys)
+}
+
+/// Serialize the given scalar value, and return it as the bytes of a single
+/// field.
+///
+/// This returns an error if the value is a container, such as a struct or a
+/// sequence.
+pub fn serialize_field<S: Serialize, W: io::Write>(
+    wtr: &Writer<W>,
+    value: S,
+) -> Result<Vec<u8>, Error> {
+    let mut field = vec![];
+    value.serialize(SeField { wtr, buf: &mut field })?;
+    Ok(field)
 }
 
 struct SeRecord<'w, W: 'w + io::Write> {
     wtr: &'w mut Writer<W>,
+    /// The values of the map being serialized, in the column order set by
+    /// `Writer::write_header_from_map`.
+    map_values: Vec<Option<Vec<u8>>>,
+    /// The column of the map value that is serialized next.
+    map_column: usize,
+    /// The keys of the struct with flattened fields being serialized, if
+    /// its keys are to become the columns of maps serialized afterwards.
+    new_map_columns: Option<ByteRecord>,
+}
+
+impl<'w, W: io::Write> SeRecord<'w, W> {
+    fn new(wtr: &'w mut Writer<W>) -> Self {
+        SeRecord {
+            wtr,
+            map_values: vec![],
+            map_column: 0,
+            new_map_columns: None,
+        }
+    }
 }
 
 impl<'a, 'w, W: io::Write> Serializer for &'a mut SeRecord<'w, W> {
@@ -39,31 +88,31 @@ impl<'a, 'w, W: io::Write> Serializer for &'a mut SeRecord<'w, W> {
     type SerializeStructVariant = Self;
 
     fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
-        if v {
-            self.wtr.write_field("true")
-        } else {
-            self.wtr.write_field("false")
-        }
+        self.wtr.write_bool(v)
     }
 
     fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
         let mut buffer = itoa::Buffer::new();
-        self.wtr.write_field(buffer.format(v))
+        let field = self.wtr.number_field(buffer.format(v));
+        self.wtr.write_field(&*field)
     }
 
     fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
         let mut buffer = itoa::Buffer::new();
-        self.wtr.write_field(buffer.format(v))
+        let field = self.wtr.number_field(buffer.format(v));
+        self.wtr.write_field(&*field)
     }
 
     fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
         let mut buffer = itoa::Buffer::new();
-        self.wtr.write_field(buffer.format(v))
+        let field = self.wtr.number_field(buffer.format(v));
+        self.wtr.write_field(&*field)
     }
 
     fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
         let mut buffer = itoa::Buffer::new();
-        self.wtr.write_field(buffer.format(v))
+        let field = self.wtr.number_field(buffer.format(v));
+        self.wtr.write_field(&*field)
     }
 
     serde_if_integer128! {
@@ -74,22 +123,26 @@ impl<'a, 'w, W: io::Write> Serializer for &'a mut SeRecord<'w, W> {
 
     fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
         let mut buffer = itoa::Buffer::new();
-        self.wtr.write_field(buffer.format(v))
+        let field = self.wtr.number_field(buffer.format(v));
+        self.wtr.write_field(&*field)
     }
 
     fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
         let mut buffer = itoa::Buffer::new();
-        self.wtr.write_field(buffer.format(v))
+        let field = self.wtr.number_field(buffer.format(v));
+        self.wtr.write_field(&*field)
     }
 
     fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
         let mut buffer = itoa::Buffer::new();
-        self.wtr.write_field(buffer.format(v))
+        let field = self.wtr.number_field(buffer.format(v));
+        self.wtr.write_field(&*field)
     }
 
     fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
         let mut buffer = itoa::Buffer::new();
-        self.wtr.write_field(buffer.format(v))
+        let field = self.wtr.number_field(buffer.format(v));
+        self.wtr.write_field(&*field)
     }
 
     serde_if_integer128! {
@@ -100,12 +153,16 @@ impl<'a, 'w, W: io::Write> Serializer for &'a mut SeRecord<'w, W> {
 
     fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
         let mut buffer = ryu::Buffer::new();
-        self.wtr.write_field(buffer.format(v))
+        let field = self.wtr.number_field(buffer.format(v));
+        let field = self.wtr.float_field(&field);
+        self.wtr.write_field(field)
     }
 
     fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
         let mut buffer = ryu::Buffer::new();
-        self.wtr.write_field(buffer.format(v))
+        let field = self.wtr.number_field(buffer.format(v));
+        let field = self.wtr.float_field(&field);
+        self.wtr.write_field(field)
     }
 
     fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
@@ -203,14 +260,28 @@ impl<'a, 'w, W: io::Write> Serializer for &'a mut SeRecord<'w, W> {
 
     fn serialize_map(
         self,
-        _len: Option<usize>,
+        len: Option<usize>,
     ) -> Result<Self::SerializeMap, Self::Error> {
-        // The right behavior for serializing maps isn't clear.
-        Err(Error::custom(
-            "serializing maps is not supported, \
-             if you have a use case, please file an issue at \
-             https://github.com/BurntSushi/rust-csv",
-        ))
+        // Maps are only supported once their columns have been fixed, since
+        // otherwise the right behavior isn't clear. The exception is a
+        // struct with flattened fields, which Serde serializes as a map of
+        // unknown length, since its keys fix the columns.
+        let columns = match self.wtr.map_columns() {
+            None if len.is_none() => {
+                self.new_map_columns = Some(ByteRecord::new());
+                0
+            }
+            None => {
+                return Err(Error::custom(
+                    "serializing maps is not supported unless \
+                     Writer::write_header_from_map has been called",
+                ))
+            }
+            Some(columns) => columns.len(),
+        };
+        self.map_values.clear();
+        self.map_values.resize(columns, None);
+        Ok(self)
     }
 
     fn serialize_struct(
@@ -302,20 +373,49 @@ impl<'a, 'w, W: io::Write> SerializeMap for &'a mut SeRecord<'w, W> {
 
     fn serialize_key<T: ?Sized + Serialize>(
         &mut self,
-        _key: &T,
+        key: &T,
     ) -> Result<(), Self::Error> {
-        unreachable!()
+        let mut field = vec![];
+        key.serialize(SeField { wtr: &*self.wtr, buf: &mut field })?;
+        if let Some(ref mut columns) = self.new_map_columns {
+            columns.push_field(&field);
+            self.map_column = self.map_values.len();
+            self.map_values.push(None);
+            return Ok(());
+        }
+        let columns = self.wtr.map_columns().unwrap();
+        self.map_column =
+            match columns.iter().position(|column| column == &field[..]) {
+                Some(i) => i,
+                None => {
+                    return Err(Error::custom(format!(
+                        "map key {:?} is not in the header",
+                        field.as_bstr()
+                    )))
+                }
+            };
+        Ok(())
     }
 
     fn serialize_value<T: ?Sized + Serialize>(
         &mut self,
-        _value: &T,
+        value: &T,
     ) -> Result<(), Self::Error> {
-        unreachable!()
+        let mut field = vec![];
+        value.serialize(SeField { wtr: &*self.wtr, buf: &mut field })?;
+        self.map_values[self.map_column] = Some(field);
+        Ok(())
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        unreachable!()
+        if let Some(columns) = self.new_map_columns.take() {
+            self.wtr.set_map_columns(columns);
+        }
+        for value in self.map_values.drain(..) {
+            self.wtr
+                .write_field(value.as_ref().map_or(&[][..], |v| &v[..]))?;
+        }
+        Ok(())
     }
 }
 
@@ -325,9 +425,12 @@ impl<'a, 'w, W: io::Write> SerializeStruct for &'a mut SeRecord<'w, W> {
 
     fn serialize_field<T: ?Sized + Serialize>(
         &mut self,
-        _key: &'static str,
+        key: &'static str,
         value: &T,
     ) -> Result<(), Self::Error> {
+        if self.wtr.skips_column(key) {
+            return Ok(());
+        }
         value.serialize(&mut **self)
     }
 
@@ -353,6 +456,443 @@ impl<'a, 'w, W: io::Write> SerializeStructVariant for &'a mut SeRecord<'w, W> {
     }
 }
 
+/// Serializes a single scalar into the bytes of one field.
+///
+/// This is used for the keys and values of maps, which need to be buffered
+/// so that they can be written in column order, and for
+/// `Writer::serialize_field`.
+struct SeField<'a, W: 'a + io::Write> {
+    wtr: &'a Writer<W>,
+    buf: &'a mut Vec<u8>,
+}
+
+impl<'a, W: io::Write> SeField<'a, W> {
+    fn write<T: AsRef<[u8]>>(self, field: T) -> Result<(), Error> {
+        self.buf.extend_from_slice(field.as_ref());
+        Ok(())
+    }
+}
+
+fn error_container_as_field<T: fmt::Display>(name: T) -> Error {
+    Error::custom(format!(
+        "cannot serialize {} container as a single field",
+        name
+    ))
+}
+
+impl<'a, W: io::Write> Serializer for SeField<'a, W> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Impossible<(), Error>;
+    type SerializeTuple = Impossible<(), Error>;
+    type SerializeTupleStruct = Impossible<(), Error>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Impossible<(), Error>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        let field = self.wtr.bool_str(v);
+        self.buf.extend_from_slice(field.as_bytes());
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.write(itoa::Buffer::new().format(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        self.write(itoa::Buffer::new().format(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        self.write(itoa::Buffer::new().format(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        self.write(itoa::Buffer::new().format(v))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
+        self.write(v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        self.write(itoa::Buffer::new().format(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        self.write(itoa::Buffer::new().format(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        self.write(itoa::Buffer::new().format(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        self.write(itoa::Buffer::new().format(v))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
+        self.write(v.to_string())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        let mut buffer = ryu::Buffer::new();
+        let field = self.wtr.float_field(buffer.format(v));
+        self.buf.extend_from_slice(&field);
+        Ok(())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        let mut buffer = ryu::Buffer::new();
+        let field = self.wtr.float_field(buffer.format(v));
+        self.buf.extend_from_slice(&field);
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        self.write(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, value: &str) -> Result<Self::Ok, Self::Error> {
+        self.write(value)
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<Self::Ok, Self::Error> {
+        self.write(value)
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(
+        self,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(
+        self,
+        name: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.write(name)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.write(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_seq(
+        self,
+        _len: Option<usize>,
+    ) -> Result<Self::SerializeSeq, Self::Error> {
+        Err(error_container_as_field("sequence"))
+    }
+
+    fn serialize_tuple(
+        self,
+        _len: usize,
+    ) -> Result<Self::SerializeTuple, Self::Error> {
+        Err(error_container_as_field("tuple"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        Err(error_container_as_field(name))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Err(error_container_as_field(name))
+    }
+
+    fn serialize_map(
+        self,
+        _len: Option<usize>,
+    ) -> Result<Self::SerializeMap, Self::Error> {
+        Err(error_container_as_field("map"))
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Err(error_container_as_field(name))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Err(error_container_as_field(name))
+    }
+}
+
+/// Collects the keys of a map, for `Writer::write_header_from_map`.
+///
+/// Serializing anything other than a map returns an error.
+struct SeMapKeys<'w, W: 'w + io::Write> {
+    wtr: &'w Writer<W>,
+    keys: ByteRecord,
+}
+
+fn error_not_map<T: fmt::Display>(name: T) -> Error {
+    Error::custom(format!(
+        "cannot write a header from {}, expected a map",
+        name
+    ))
+}
+
+impl<'a, 'w, W: io::Write> Serializer for &'a mut SeMapKeys<'w, W> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Impossible<(), Error>;
+    type SerializeTuple = Impossible<(), Error>;
+    type SerializeTupleStruct = Impossible<(), Error>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Self;
+    type SerializeStruct = Impossible<(), Error>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_bool(self, _v: bool) -> Result<Self::Ok, Self::Error> {
+        Err(error_not_map("bool"))
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<Self::Ok, Self::Error> {
+        Err(error_not_map("i8"))
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<Self::Ok, Self::Error> {
+        Err(error_not_map("i16"))
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<Self::Ok, Self::Error> {
+        Err(error_not_map("i32"))
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<Self::Ok, Self::Error> {
+        Err(error_not_map("i64"))
+    }
+
+    fn serialize_i128(self, _v: i128) -> Result<Self::Ok, Self::Error> {
+        Err(error_not_map("i128"))
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<Self::Ok, Self::Error> {
+        Err(error_not_map("u8"))
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<Self::Ok, Self::Error> {
+        Err(error_not_map("u16"))
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<Self::Ok, Self::Error> {
+        Err(error_not_map("u32"))
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<Self::Ok, Self::Error> {
+        Err(error_not_map("u64"))
+    }
+
+    fn serialize_u128(self, _v: u128) -> Result<Self::Ok, Self::Error> {
+        Err(error_not_map("u128"))
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<Self::Ok, Self::Error> {
+        Err(error_not_map("f32"))
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<Self::Ok, Self::Error> {
+        Err(error_not_map("f64"))
+    }
+
+    fn serialize_char(self, _v: char) -> Result<Self::Ok, Self::Error> {
+        Err(error_not_map("char"))
+    }
+
+    fn serialize_str(self, _value: &str) -> Result<Self::Ok, Self::Error> {
+        Err(error_not_map("str"))
+    }
+
+    fn serialize_bytes(self, _value: &[u8]) -> Result<Self::Ok, Self::Error> {
+        Err(error_not_map("bytes"))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Err(error_not_map("none"))
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(
+        self,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        Err(error_not_map("unit"))
+    }
+
+    fn serialize_unit_struct(
+        self,
+        name: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        Err(error_not_map(name))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        Err(error_not_map(name))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        Err(error_not_map(name))
+    }
+
+    fn serialize_seq(
+        self,
+        _len: Option<usize>,
+    ) -> Result<Self::SerializeSeq, Self::Error> {
+        Err(error_not_map("sequence"))
+    }
+
+    fn serialize_tuple(
+        self,
+        _len: usize,
+    ) -> Result<Self::SerializeTuple, Self::Error> {
+        Err(error_not_map("tuple"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        Err(error_not_map(name))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Err(error_not_map(name))
+    }
+
+    fn serialize_map(
+        self,
+        _len: Option<usize>,
+    ) -> Result<Self::SerializeMap, Self::Error> {
+        Ok(self)
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Err(error_not_map(name))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Err(error_not_map(name))
+    }
+}
+
+impl<'a, 'w, W: io::Write> SerializeMap for &'a mut SeMapKeys<'w, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(
+        &mut self,
+        key: &T,
+    ) -> Result<(), Self::Error> {
+        let mut field = vec![];
+        key.serialize(SeField { wtr: self.wtr, buf: &mut field })?;
+        self.keys.push_field(&field);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(
+        &mut self,
+        _value: &T,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+}
+
 impl SerdeError for Error {
     fn custom<T: fmt::Display>(msg: T) -> Error {
         Error::new(ErrorKind::Serialize(msg.to_string()))
@@ -388,7 +928,8 @@ pub fn serialize_header<S: Serialize, W: io::Write>(
     value: S,
 ) -> Result<bool, Error> {
     let mut ser = SeHeader::new(wtr);
-    value.serialize(&mut ser).map(|_| ser.wrote_header())
+    value.serialize(&mut ser)?;
+    ser.finish()
 }
 
 /// State machine for `SeHeader`.
@@ -397,7 +938,9 @@ pub fn serialize_header<S: Serialize, W: io::Write>(
 /// some serialization events cause a state transition, and only for certain
 /// states. For example, encountering a scalar causes a transition if the state
 /// is `Write` or `EncounteredStructField`, but not if the state is
-/// `ErrorIfWrite(err)` or `InStructField`.
+/// `ErrorIfWrite(err)` or `InStructField`. Encountering a struct in the
+/// `InStructField` state isn't an error, and instead transitions back to
+/// `EncounteredStructField`, since its fields are flattened.
 ///
 /// ```text
 ///                              +-----+
@@ -448,11 +991,63 @@ enum HeaderState {
 struct SeHeader<'w, W: 'w + io::Write> {
     wtr: &'w mut Writer<W>,
     state: HeaderState,
+    /// Whether scalars outside of struct fields get positional names. This
+    /// is set when the value being serialized is a tuple and the writer is
+    /// configured with `TupleHeaderStyle::Positional`.
+    positional: bool,
+    /// The number of header fields written so far.
+    columns: u64,
+    /// The header names, which are only written once the entire value has
+    /// been serialized, since the name of a struct field is dropped if its
+    /// value turns out to be a nested struct.
+    names: Vec<String>,
+    /// The names of the struct fields enclosing the value currently being
+    /// serialized.
+    path: Vec<&'static str>,
+    /// The keys of the top-level struct with flattened fields, which become
+    /// the columns of maps serialized afterwards.
+    map_columns: Option<ByteRecord>,
 }
 
 impl<'w, W: io::Write> SeHeader<'w, W> {
     fn new(wtr: &'w mut Writer<W>) -> Self {
-        SeHeader { wtr: wtr, state: HeaderState::Write }
+        SeHeader {
+            wtr,
+            state: HeaderState::Write,
+            positional: false,
+            columns: 0,
+            names: vec![],
+            path: vec![],
+            map_columns: None,
+        }
+    }
+
+    /// Write the header names collected so far, and return whether a header
+    /// was written.
+    fn finish(mut self) -> Result<bool, Error> {
+        for name in &self.names {
+            self.wtr.write_field(name)?;
+        }
+        if let Some(columns) = self.map_columns.take() {
+            self.wtr.set_map_columns(columns);
+        }
+        Ok(self.wrote_header())
+    }
+
+    /// Returns the header name for the struct field `key`, prefixed with the
+    /// names of its enclosing struct fields if `nested_header_sep` is set.
+    fn header_name(&self, key: &str) -> String {
+        let sep = match self.wtr.nested_header_sep() {
+            Some(sep) if !self.path.is_empty() => sep,
+            _ => return key.to_string(),
+        };
+        let mut name = String::new();
+        for outer in &self.path {
+            name.push_str(outer);
+            name.push(sep);
+        }
+        name.push_str(key);
+        name
     }
 
     fn wrote_header(&self) -> bool {
@@ -470,6 +1065,12 @@ impl<'w, W: io::Write> SeHeader<'w, W> {
         use self::HeaderState::*;
 
         match self.state {
+            Write | EncounteredStructField if self.positional => {
+                self.names.push(format!("field{}", self.columns));
+                self.columns += 1;
+                self.state = EncounteredStructField;
+                Ok(())
+            }
             Write => {
                 self.state = ErrorIfWrite(error_scalar_outside_struct(name));
                 Ok(())
@@ -479,6 +1080,16 @@ impl<'w, W: io::Write> SeHeader<'w, W> {
         }
     }
 
+    /// Start positional naming if this is a top-level tuple and the writer
+    /// is configured for it.
+    fn handle_tuple(&mut self) {
+        if let HeaderState::Write = self.state {
+            if self.wtr.tuple_header_style() == TupleHeaderStyle::Positional {
+                self.positional = true;
+            }
+        }
+    }
+
     fn handle_container<T: fmt::Display>(
         &mut self,
         name: T,
@@ -630,6 +1241,7 @@ impl<'a, 'w, W: io::Write> Serializer for &'a mut SeHeader<'w, W> {
         self,
         _len: usize,
     ) -> Result<Self::SerializeTuple, Self::Error> {
+        self.handle_tuple();
         self.handle_container("tuple")
     }
 
@@ -638,6 +1250,7 @@ impl<'a, 'w, W: io::Write> Serializer for &'a mut SeHeader<'w, W> {
         name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.handle_tuple();
         self.handle_container(name)
     }
 
@@ -653,9 +1266,18 @@ impl<'a, 'w, W: io::Write> Serializer for &'a mut SeHeader<'w, W> {
 
     fn serialize_map(
         self,
-        _len: Option<usize>,
+        len: Option<usize>,
     ) -> Result<Self::SerializeMap, Self::Error> {
-        // The right behavior for serializing maps isn't clear.
+        // A top-level map of unknown length is a struct with flattened
+        // fields, whose keys are treated like struct field names.
+        if let (HeaderState::Write, None) = (&self.state, len) {
+            if self.wtr.map_columns().is_none() {
+                self.map_columns = Some(ByteRecord::new());
+            }
+            self.state = HeaderState::EncounteredStructField;
+            return Ok(self);
+        }
+        // The right behavior for serializing other maps isn't clear.
         Err(Error::custom(
             "serializing maps is not supported, \
              if you have a use case, please file an issue at \
@@ -665,10 +1287,17 @@ impl<'a, 'w, W: io::Write> Serializer for &'a mut SeHeader<'w, W> {
 
     fn serialize_struct(
         self,
-        name: &'static str,
+        _name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStruct, Self::Error> {
-        self.handle_container(name)
+        if let HeaderState::InStructField = self.state {
+            // This is a struct nested in a struct field, so its fields are
+            // flattened into the header in place of the enclosing field.
+            self.names.pop();
+            self.columns -= 1;
+            self.state = HeaderState::EncounteredStructField;
+        }
+        Ok(self)
     }
 
     fn serialize_struct_variant(
@@ -752,20 +1381,31 @@ impl<'a, 'w, W: io::Write> SerializeMap for &'a mut SeHeader<'w, W> {
 
     fn serialize_key<T: ?Sized + Serialize>(
         &mut self,
-        _key: &T,
+        key: &T,
     ) -> Result<(), Self::Error> {
-        unreachable!()
+        let mut field = vec![];
+        key.serialize(SeField { wtr: &*self.wtr, buf: &mut field })?;
+        if let Some(ref mut columns) = self.map_columns {
+            columns.push_field(&field);
+        }
+        self.names.push(String::from_utf8_lossy(&field).into_owned());
+        self.columns += 1;
+        Ok(())
     }
 
     fn serialize_value<T: ?Sized + Serialize>(
         &mut self,
-        _value: &T,
+        value: &T,
     ) -> Result<(), Self::Error> {
-        unreachable!()
+        // As with struct fields, check that the value is a scalar.
+        self.state = HeaderState::InStructField;
+        value.serialize(&mut **self)?;
+        self.state = HeaderState::EncounteredStructField;
+        Ok(())
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        unreachable!()
+        Ok(())
     }
 }
 
@@ -778,17 +1418,25 @@ impl<'a, 'w, W: io::Write> SerializeStruct for &'a mut SeHeader<'w, W> {
         key: &'static str,
         value: &T,
     ) -> Result<(), Self::Error> {
+        if self.wtr.skips_column(key) {
+            return Ok(());
+        }
         // Grab old state and update state to `EncounteredStructField`.
         let old_state =
             mem::replace(&mut self.state, HeaderState::EncounteredStructField);
         if let HeaderState::ErrorIfWrite(err) = old_state {
             return Err(err);
         }
-        self.wtr.write_field(key)?;
+        let name = self.header_name(key);
+        self.names.push(name);
+        self.columns += 1;
 
-        // Check that there aren't any containers in the value.
+        // Check that there aren't any containers in the value, other than
+        // nested structs.
         self.state = HeaderState::InStructField;
+        self.path.push(key);
         value.serialize(&mut **self)?;
+        self.path.pop();
         self.state = HeaderState::EncounteredStructField;
 
         Ok(())
@@ -822,13 +1470,13 @@ mod tests {
     use serde::{serde_if_integer128, Serialize};
 
     use crate::error::{Error, ErrorKind};
-    use crate::writer::Writer;
+    use crate::writer::{Writer, WriterBuilder};
 
     use super::{SeHeader, SeRecord};
 
     fn serialize<S: Serialize>(s: S) -> String {
         let mut wtr = Writer::from_writer(vec![]);
-        s.serialize(&mut SeRecord { wtr: &mut wtr }).unwrap();
+        s.serialize(&mut SeRecord::new(&mut wtr)).unwrap();
         wtr.write_record(None::<&[u8]>).unwrap();
         String::from_utf8(wtr.into_inner().unwrap()).unwrap()
     }
@@ -840,14 +1488,14 @@ mod tests {
         let wrote = {
             let mut ser = SeHeader::new(&mut wtr);
             s.serialize(&mut ser).unwrap();
-            ser.wrote_header()
+            ser.finish().unwrap()
         };
         (wrote, String::from_utf8(wtr.into_inner().unwrap()).unwrap())
     }
 
     fn serialize_err<S: Serialize>(s: S) -> Error {
         let mut wtr = Writer::from_writer(vec![]);
-        s.serialize(&mut SeRecord { wtr: &mut wtr }).unwrap_err()
+        s.serialize(&mut SeRecord::new(&mut wtr)).unwrap_err()
     }
 
     fn serialize_header_err<S: Serialize>(s: S) -> Error {
@@ -1176,6 +1824,40 @@ mod tests {
         assert_eq!(got, "true,5,hi\n");
     }
 
+    #[test]
+    fn struct_headers_quoted() {
+        use serde::Deserialize;
+
+        use crate::reader::ReaderBuilder;
+
+        #[derive(Debug, Deserialize, PartialEq, Serialize)]
+        struct Foo {
+            #[serde(rename = "a,b")]
+            x: i32,
+            #[serde(rename = "say \"hi\"")]
+            y: String,
+            #[serde(rename = "line\nbreak")]
+            z: bool,
+        }
+
+        let row = Foo { x: 1, y: "hi, there".into(), z: true };
+        let mut wtr = Writer::from_writer(vec![]);
+        wtr.serialize(&row).unwrap();
+        let data = wtr.into_inner().unwrap();
+        assert_eq!(
+            data.to_str().unwrap(),
+            "\"a,b\",\"say \"\"hi\"\"\",\"line\nbreak\"\n1,\"hi, there\",true\n"
+        );
+        let mut rdr = ReaderBuilder::new().from_reader(&data[..]);
+        assert_eq!(
+            rdr.headers().unwrap(),
+            vec!["a,b", "say \"hi\"", "line\nbreak"]
+        );
+        let rows: Vec<Foo> =
+            rdr.deserialize().collect::<Result<_, _>>().unwrap();
+        assert_eq!(rows, vec![row]);
+    }
+
     #[test]
     fn struct_headers_nested() {
         #[derive(Clone, Serialize)]
@@ -1197,11 +1879,74 @@ mod tests {
         let got = serialize(row.clone());
         assert_eq!(got, "foo,bar,5\n");
 
-        let err = serialize_header_err(row.clone());
-        match *err.kind() {
-            ErrorKind::Serialize(_) => {}
-            ref x => panic!("expected ErrorKind::Serialize but got '{:?}'", x),
+        let (wrote, got) = serialize_header(row.clone());
+        assert!(wrote);
+        assert_eq!(got, "label,label2,value");
+    }
+
+    #[test]
+    fn struct_headers_nested_sep() {
+        #[derive(Serialize)]
+        struct Outer {
+            id: i32,
+            a: Middle,
         }
+        #[derive(Serialize)]
+        struct Middle {
+            x: i32,
+            b: Inner,
+            y: Option<Inner>,
+        }
+        #[derive(Serialize)]
+        struct Inner {
+            z: i32,
+        }
+
+        let row = Outer {
+            id: 1,
+            a: Middle { x: 2, b: Inner { z: 3 }, y: Some(Inner { z: 4 }) },
+        };
+        let mut wtr = WriterBuilder::new()
+            .nested_header_sep(Some('.'))
+            .from_writer(vec![]);
+        wtr.serialize(&row).unwrap();
+        let got = String::from_utf8(wtr.into_inner().unwrap()).unwrap();
+        assert_eq!(got, "id,a.x,a.b.z,a.y\n1,2,3,4\n");
+    }
+
+    #[test]
+    fn struct_headers_flatten() {
+        use std::collections::BTreeMap;
+
+        #[derive(Serialize)]
+        struct Row {
+            id: i32,
+            name: &'static str,
+            #[serde(flatten)]
+            extra: BTreeMap<String, String>,
+        }
+
+        let row = |id, extra: &[(&str, &str)]| Row {
+            id,
+            name: "n",
+            extra: extra
+                .iter()
+                .map(|&(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        };
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        wtr.serialize(row(1, &[("b", "x"), ("a", "y")])).unwrap();
+        wtr.serialize(row(2, &[("b", "z")])).unwrap();
+        assert!(wtr.serialize(row(3, &[("c", "w")])).is_err());
+        let got = String::from_utf8(wtr.into_inner().unwrap()).unwrap();
+        assert_eq!(got, "id,name,a,b\n1,n,y,x\n2,n,,z\n");
+
+        let mut wtr =
+            WriterBuilder::new().has_headers(false).from_writer(vec![]);
+        wtr.serialize(row(1, &[("a", "y")])).unwrap();
+        wtr.serialize(row(2, &[])).unwrap();
+        let got = String::from_utf8(wtr.into_inner().unwrap()).unwrap();
+        assert_eq!(got, "1,n,y\n2,n,\n");
     }
 
     #[test]
@@ -0,0 +1,207 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use crate::byte_record::ByteRecord;
+use crate::error::Result;
+use crate::writer::{Writer, WriterBuilder};
+
+/// A CSV writer that splits its records across several files.
+///
+/// Records are written to a sequence of files, called shards, each holding
+/// at most a fixed number of records. The shards are named after a base
+/// path, with the index of the shard inserted before its extension, so the
+/// base path `out.csv` yields `out.0.csv`, `out.1.csv` and so on. A shard is
+/// only created once a record is written to it, so no empty shards are
+/// created.
+///
+/// If `has_headers` is enabled on the builder, then the header row set with
+/// `write_header` is written at the top of every shard.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::error::Error;
+/// use csv::{ShardingWriter, WriterBuilder};
+///
+/// # fn main() { example().unwrap(); }
+/// fn example() -> Result<(), Box<dyn Error>> {
+///     let mut wtr = ShardingWriter::new(WriterBuilder::new(), "out.csv", 2);
+///     wtr.write_header(&["city", "pop"])?;
+///     wtr.write_record(&["Boston", "4628910"])?;
+///     wtr.write_record(&["Concord", "42695"])?;
+///     wtr.write_record(&["Springfield", "153060"])?;
+///
+///     let paths = wtr.finish()?;
+///     assert_eq!(paths.len(), 2);
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ShardingWriter {
+    builder: WriterBuilder,
+    base: PathBuf,
+    shard_size: u64,
+    header: Option<ByteRecord>,
+    wtr: Option<Writer<File>>,
+    /// The number of records written to the current shard.
+    written: u64,
+    /// The paths of the shards created so far.
+    paths: Vec<PathBuf>,
+}
+
+impl ShardingWriter {
+    /// Create a new sharding writer that writes at most `shard_size`
+    /// records to each shard named after `base`.
+    ///
+    /// Every shard is written by a writer built from `builder`.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `shard_size` is `0`.
+    pub fn new<P: AsRef<Path>>(
+        builder: WriterBuilder,
+        base: P,
+        shard_size: u64,
+    ) -> ShardingWriter {
+        assert!(shard_size > 0, "the shard size must be at least 1");
+        ShardingWriter {
+            builder,
+            base: base.as_ref().to_path_buf(),
+            shard_size,
+            header: None,
+            wtr: None,
+            written: 0,
+            paths: vec![],
+        }
+    }
+
+    /// Set the header row, which is written at the top of every shard
+    /// created afterwards if `has_headers` is enabled.
+    ///
+    /// Calling this more than once replaces the previous header row.
+    pub fn write_header<I, T>(&mut self, record: I) -> Result<()>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        self.header = Some(record.into_iter().collect());
+        Ok(())
+    }
+
+    /// Write a single record, starting a new shard first if the current one
+    /// is full.
+    pub fn write_record<I, T>(&mut self, record: I) -> Result<()>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        self.shard()?.write_record(record)?;
+        self.written += 1;
+        Ok(())
+    }
+
+    /// Write a single byte record, starting a new shard first if the
+    /// current one is full.
+    pub fn write_byte_record(&mut self, record: &ByteRecord) -> Result<()> {
+        self.shard()?.write_byte_record(record)?;
+        self.written += 1;
+        Ok(())
+    }
+
+    /// Flush the last shard and return the paths of all of the shards, in
+    /// order.
+    pub fn finish(mut self) -> Result<Vec<PathBuf>> {
+        if let Some(ref mut wtr) = self.wtr {
+            wtr.flush()?;
+        }
+        Ok(self.paths)
+    }
+
+    /// Returns the writer of the shard that the next record is written to.
+    fn shard(&mut self) -> Result<&mut Writer<File>> {
+        if self.wtr.is_none() || self.written == self.shard_size {
+            if let Some(mut wtr) = self.wtr.take() {
+                wtr.flush()?;
+            }
+            let path = self.shard_path(self.paths.len());
+            let mut wtr = self.builder.from_path(&path)?;
+            if self.builder.get_has_headers() {
+                if let Some(ref header) = self.header {
+                    wtr.write_byte_record(header)?;
+                }
+            }
+            self.paths.push(path);
+            self.wtr = Some(wtr);
+            self.written = 0;
+        }
+        Ok(self.wtr.as_mut().unwrap())
+    }
+
+    /// Returns the path of the shard at index `i`.
+    fn shard_path(&self, i: usize) -> PathBuf {
+        let mut name =
+            self.base.file_stem().unwrap_or_default().to_os_string();
+        name.push(format!(".{}", i));
+        if let Some(ext) = self.base.extension() {
+            name.push(".");
+            name.push(ext);
+        }
+        self.base.with_file_name(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    use crate::reader::ReaderBuilder;
+    use crate::writer::WriterBuilder;
+
+    use super::ShardingWriter;
+
+    #[test]
+    fn rotates_shards() {
+        let dir = env::temp_dir().join(format!("csv-shard-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let base = dir.join("out.csv");
+        let mut wtr = ShardingWriter::new(WriterBuilder::new(), &base, 100);
+        wtr.write_header(&["i", "square"]).unwrap();
+        for i in 0..250 {
+            let record = vec![i.to_string(), (i * i).to_string()];
+            wtr.write_record(&record).unwrap();
+        }
+        let paths = wtr.finish().unwrap();
+        assert_eq!(
+            paths,
+            vec![
+                dir.join("out.0.csv"),
+                dir.join("out.1.csv"),
+                dir.join("out.2.csv")
+            ]
+        );
+
+        let mut next = 0;
+        for (path, &count) in paths.iter().zip(&[100, 100, 50]) {
+            let mut rdr = ReaderBuilder::new().from_path(path).unwrap();
+            assert_eq!(rdr.headers().unwrap(), vec!["i", "square"]);
+            let records: Vec<_> = rdr.records().map(|r| r.unwrap()).collect();
+            assert_eq!(records.len(), count);
+            assert_eq!(records[0][0], next.to_string());
+            next += count;
+        }
+
+        let mut builder = WriterBuilder::new();
+        builder.has_headers(false);
+        let mut wtr = ShardingWriter::new(builder, dir.join("raw"), 1);
+        wtr.write_header(&["h"]).unwrap();
+        wtr.write_record(&["a"]).unwrap();
+        let paths = wtr.finish().unwrap();
+        assert_eq!(paths, vec![dir.join("raw.0")]);
+        assert_eq!(fs::read_to_string(&paths[0]).unwrap(), "a\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
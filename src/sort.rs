@@ -0,0 +1,491 @@
+use std::cmp::Ordering;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::mem;
+use std::path::PathBuf;
+use std::process;
+use std::str;
+use std::sync::atomic::{self, AtomicUsize};
+
+use crate::byte_record::ByteRecord;
+use crate::error::Result;
+use crate::reader::{Reader, ReaderBuilder};
+use crate::writer::{Writer, WriterBuilder};
+
+/// The approximate number of bytes of records held in memory at once.
+const RUN_BYTES: usize = 64 * (1 << 20);
+
+/// A counter for naming temporary run files uniquely within this process.
+static RUN_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// How the values of the sort column are compared by `sort_by_column`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SortKey {
+    /// Compare values as numbers.
+    ///
+    /// Values that cannot be parsed as numbers are sorted after all values
+    /// that can, and are compared lexically with one another.
+    Numeric,
+    /// Compare values lexically, byte by byte.
+    Lexical,
+    /// Hints that destructuring should not be exhaustive.
+    ///
+    /// This enum may grow additional variants, so this makes sure clients
+    /// don't count on exhaustive matching. (Otherwise, adding a new variant
+    /// could break existing code.)
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+impl SortKey {
+    fn compare(
+        self,
+        a: &ByteRecord,
+        b: &ByteRecord,
+        column: usize,
+    ) -> Ordering {
+        let a = a.get(column).unwrap_or(b"");
+        let b = b.get(column).unwrap_or(b"");
+        match self {
+            SortKey::Numeric => match (parse_number(a), parse_number(b)) {
+                (Some(x), Some(y)) => {
+                    x.partial_cmp(&y).unwrap_or(Ordering::Equal)
+                }
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => a.cmp(b),
+            },
+            _ => a.cmp(b),
+        }
+    }
+}
+
+fn parse_number(field: &[u8]) -> Option<f64> {
+    let n = str::from_utf8(field).ok()?.trim().parse::<f64>().ok()?;
+    if n.is_nan() {
+        None
+    } else {
+        Some(n)
+    }
+}
+
+/// Sort CSV data by the values in one column.
+///
+/// Every record is read from `rdr` and written to `wtr`, ordered by the
+/// field at index `column` and compared according to `key_type`. Records
+/// that lack the column sort as if it were empty. The sort is stable, so
+/// records with equal keys keep their relative order.
+///
+/// If `rdr` is configured to read headers, then the header row is written
+/// first, untouched.
+///
+/// Sorting does not require the data to fit in memory. Records are read in
+/// bounded runs, each run is sorted and written to a temporary file, and the
+/// runs are then merged into `wtr`. Temporary files are created in the
+/// directory returned by `std::env::temp_dir`, and are removed before this
+/// function returns. If all of the data fits in a single run, then no
+/// temporary files are created.
+///
+/// # Example
+///
+/// ```
+/// use std::error::Error;
+/// use csv::{Reader, SortKey, Writer};
+///
+/// # fn main() { example().unwrap(); }
+/// fn example() -> Result<(), Box<dyn Error>> {
+///     let data = "\
+/// city,pop
+/// Boston,4628910
+/// Concord,42695
+/// Springfield,153060
+/// ";
+///     let mut rdr = Reader::from_reader(data.as_bytes());
+///     let mut wtr = Writer::from_writer(vec![]);
+///     csv::sort_by_column(&mut rdr, &mut wtr, 1, SortKey::Numeric)?;
+///
+///     let data = String::from_utf8(wtr.into_inner()?)?;
+///     assert_eq!(data, "\
+/// city,pop
+/// Concord,42695
+/// Springfield,153060
+/// Boston,4628910
+/// ");
+///     Ok(())
+/// }
+/// ```
+pub fn sort_by_column<R: io::Read, W: io::Write>(
+    rdr: &mut Reader<R>,
+    wtr: &mut Writer<W>,
+    column: usize,
+    key_type: SortKey,
+) -> Result<()> {
+    sort_with_run_bytes(rdr, wtr, column, key_type, RUN_BYTES)
+}
+
+/// Like `sort_by_column`, but with a configurable run size.
+fn sort_with_run_bytes<R: io::Read, W: io::Write>(
+    rdr: &mut Reader<R>,
+    wtr: &mut Writer<W>,
+    column: usize,
+    key_type: SortKey,
+    max_run_bytes: usize,
+) -> Result<()> {
+    if rdr.has_headers() {
+        let headers = rdr.byte_headers()?;
+        if !headers.is_empty() {
+            wtr.write_byte_record(headers)?;
+        }
+    }
+
+    let sort = |run: &mut Vec<ByteRecord>| {
+        run.sort_by(|a, b| key_type.compare(a, b, column));
+    };
+    let mut runs = vec![];
+    let mut run = vec![];
+    let mut run_bytes = 0;
+    let mut record = ByteRecord::new();
+    while rdr.read_byte_record(&mut record)? {
+        run_bytes +=
+            record.as_slice().len() + record.len() * mem::size_of::<usize>();
+        run.push(record.clone());
+        if run_bytes >= max_run_bytes {
+            sort(&mut run);
+            runs.push(Run::write(&run)?);
+            run.clear();
+            run_bytes = 0;
+        }
+    }
+    if runs.is_empty() {
+        sort(&mut run);
+        for record in &run {
+            wtr.write_byte_record(record)?;
+        }
+        return Ok(());
+    }
+    if !run.is_empty() {
+        sort(&mut run);
+        runs.push(Run::write(&run)?);
+    }
+    drop(run);
+    merge(&runs, wtr, column, key_type)
+}
+
+/// Merge sorted runs into `wtr`.
+///
+/// On equal keys, records from earlier runs are written first, which keeps
+/// the overall sort stable.
+fn merge<W: io::Write>(
+    runs: &[Run],
+    wtr: &mut Writer<W>,
+    column: usize,
+    key_type: SortKey,
+) -> Result<()> {
+    let mut readers = vec![];
+    let mut heads = vec![];
+    for run in runs {
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_path(&run.path)?;
+        let mut record = ByteRecord::new();
+        heads.push(if rdr.read_byte_record(&mut record)? {
+            Some(record)
+        } else {
+            None
+        });
+        readers.push(rdr);
+    }
+    loop {
+        let mut min: Option<usize> = None;
+        for (i, head) in heads.iter().enumerate() {
+            let record = match *head {
+                None => continue,
+                Some(ref record) => record,
+            };
+            min = match min {
+                Some(j)
+                    if key_type.compare(
+                        record,
+                        heads[j].as_ref().unwrap(),
+                        column,
+                    ) != Ordering::Less =>
+                {
+                    Some(j)
+                }
+                _ => Some(i),
+            };
+        }
+        let i = match min {
+            None => return Ok(()),
+            Some(i) => i,
+        };
+        let mut record = heads[i].take().unwrap();
+        wtr.write_byte_record(&record)?;
+        if readers[i].read_byte_record(&mut record)? {
+            heads[i] = Some(record);
+        }
+    }
+}
+
+/// A function that orders records for a `SortingWriter`.
+type Comparator = dyn FnMut(&ByteRecord, &ByteRecord) -> Ordering;
+
+/// A CSV writer that sorts its records before writing them.
+///
+/// Records written with `write_record` or `write_byte_record` are buffered,
+/// and are only written to the inner `Writer` when `finish` is called,
+/// ordered by a column or by an arbitrary comparator. The sort is stable,
+/// so records that compare equal keep the order they were written in. A
+/// header row written with `write_header` is written first and isn't
+/// sorted.
+///
+/// Every record is held in memory until `finish` is called, so this is only
+/// suitable for small outputs. To sort CSV data that may not fit in memory,
+/// use `sort_by_column`. If a `SortingWriter` is dropped without calling
+/// `finish`, then its buffered records are discarded.
+///
+/// # Example
+///
+/// ```
+/// use std::error::Error;
+/// use csv::{SortKey, SortingWriter, Writer};
+///
+/// # fn main() { example().unwrap(); }
+/// fn example() -> Result<(), Box<dyn Error>> {
+///     let wtr = Writer::from_writer(vec![]);
+///     let mut wtr = SortingWriter::new(wtr, 1, SortKey::Numeric);
+///     wtr.write_header(&["city", "pop"])?;
+///     wtr.write_record(&["Boston", "4628910"])?;
+///     wtr.write_record(&["Concord", "42695"])?;
+///     wtr.write_record(&["Springfield", "153060"])?;
+///
+///     let data = String::from_utf8(wtr.finish()?.into_inner()?)?;
+///     assert_eq!(data, "\
+/// city,pop
+/// Concord,42695
+/// Springfield,153060
+/// Boston,4628910
+/// ");
+///     Ok(())
+/// }
+/// ```
+pub struct SortingWriter<W: io::Write> {
+    wtr: Writer<W>,
+    compare: Box<Comparator>,
+    header: Option<ByteRecord>,
+    records: Vec<ByteRecord>,
+}
+
+impl<W: io::Write> SortingWriter<W> {
+    /// Create a new sorting writer that orders records by the field at
+    /// index `column`, compared according to `key_type`.
+    ///
+    /// Records that lack the column sort as if it were empty.
+    pub fn new(
+        wtr: Writer<W>,
+        column: usize,
+        key_type: SortKey,
+    ) -> SortingWriter<W> {
+        SortingWriter::with_comparator(wtr, move |a, b| {
+            key_type.compare(a, b, column)
+        })
+    }
+
+    /// Create a new sorting writer that orders records with the given
+    /// comparator.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::{SortingWriter, Writer};
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let wtr = Writer::from_writer(vec![]);
+    ///     // Sort by the length of the first field, longest first.
+    ///     let mut wtr = SortingWriter::with_comparator(wtr, |a, b| {
+    ///         b[0].len().cmp(&a[0].len())
+    ///     });
+    ///     wtr.write_record(&["a"])?;
+    ///     wtr.write_record(&["abc"])?;
+    ///     wtr.write_record(&["ab"])?;
+    ///
+    ///     let data = String::from_utf8(wtr.finish()?.into_inner()?)?;
+    ///     assert_eq!(data, "abc\nab\na\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_comparator<F>(wtr: Writer<W>, compare: F) -> SortingWriter<W>
+    where
+        F: FnMut(&ByteRecord, &ByteRecord) -> Ordering + 'static,
+    {
+        SortingWriter {
+            wtr,
+            compare: Box::new(compare),
+            header: None,
+            records: vec![],
+        }
+    }
+
+    /// Set the header row, which is written before every other record when
+    /// `finish` is called.
+    ///
+    /// The header row isn't sorted with the other records. Calling this
+    /// more than once replaces the previous header row.
+    pub fn write_header<I, T>(&mut self, record: I) -> Result<()>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        self.header = Some(record.into_iter().collect());
+        Ok(())
+    }
+
+    /// Buffer a single record, to be written in sorted order when `finish`
+    /// is called.
+    pub fn write_record<I, T>(&mut self, record: I) -> Result<()>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        self.records.push(record.into_iter().collect());
+        Ok(())
+    }
+
+    /// Buffer a single byte record, to be written in sorted order when
+    /// `finish` is called.
+    pub fn write_byte_record(&mut self, record: &ByteRecord) -> Result<()> {
+        self.records.push(record.clone());
+        Ok(())
+    }
+
+    /// Sort the buffered records and write them to the inner writer.
+    ///
+    /// The header row, if one was set, is written first. The inner writer
+    /// is flushed and returned.
+    pub fn finish(mut self) -> Result<Writer<W>> {
+        if let Some(ref header) = self.header {
+            self.wtr.write_byte_record(header)?;
+        }
+        let compare = &mut self.compare;
+        self.records.sort_by(|a, b| compare(a, b));
+        for record in &self.records {
+            self.wtr.write_byte_record(record)?;
+        }
+        self.wtr.flush()?;
+        Ok(self.wtr)
+    }
+}
+
+/// A sorted run of records stored in a temporary file.
+///
+/// The file is removed when the run is dropped.
+struct Run {
+    path: PathBuf,
+}
+
+impl Run {
+    /// Write the given records to a new temporary file.
+    fn write(records: &[ByteRecord]) -> Result<Run> {
+        let (run, file) = Run::create()?;
+        let mut wtr = WriterBuilder::new().flexible(true).from_writer(file);
+        for record in records {
+            wtr.write_byte_record(record)?;
+        }
+        wtr.flush()?;
+        Ok(run)
+    }
+
+    /// Create a new, empty temporary file.
+    fn create() -> Result<(Run, File)> {
+        let dir = env::temp_dir();
+        loop {
+            let id = RUN_ID.fetch_add(1, atomic::Ordering::Relaxed);
+            let path =
+                dir.join(format!("csv-sort-{}-{}.csv", process::id(), id));
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => return Ok((Run { path }, file)),
+                Err(ref err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                    continue
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::reader::ReaderBuilder;
+    use crate::writer::Writer;
+
+    use super::{sort_with_run_bytes, SortKey, SortingWriter};
+
+    const DATA: &str = "\
+name,n
+b,10
+a,9
+d,
+c,100
+e,x
+f,9
+";
+
+    fn sort(key_type: SortKey, has_headers: bool, run_bytes: usize) -> String {
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(has_headers)
+            .from_reader(DATA.as_bytes());
+        let mut wtr = Writer::from_writer(vec![]);
+        sort_with_run_bytes(&mut rdr, &mut wtr, 1, key_type, run_bytes)
+            .unwrap();
+        String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn numeric() {
+        let expected = "name,n\na,9\nf,9\nb,10\nc,100\nd,\ne,x\n";
+        // Everything in memory, one record per run, and a few per run.
+        assert_eq!(sort(SortKey::Numeric, true, usize::MAX), expected);
+        assert_eq!(sort(SortKey::Numeric, true, 1), expected);
+        assert_eq!(sort(SortKey::Numeric, true, 40), expected);
+    }
+
+    #[test]
+    fn lexical() {
+        let expected = "name,n\nd,\nb,10\nc,100\na,9\nf,9\ne,x\n";
+        assert_eq!(sort(SortKey::Lexical, true, usize::MAX), expected);
+        assert_eq!(sort(SortKey::Lexical, true, 1), expected);
+        assert_eq!(sort(SortKey::Lexical, true, 40), expected);
+    }
+
+    #[test]
+    fn no_headers() {
+        let expected = "a,9\nf,9\nb,10\nc,100\nd,\nname,n\ne,x\n";
+        assert_eq!(sort(SortKey::Numeric, false, 1), expected);
+    }
+
+    #[test]
+    fn sorting_writer() {
+        let mut wtr = SortingWriter::new(
+            Writer::from_writer(vec![]),
+            1,
+            SortKey::Numeric,
+        );
+        wtr.write_header(&["name", "n"]).unwrap();
+        for line in DATA.lines().skip(1) {
+            wtr.write_record(line.split(',')).unwrap();
+        }
+        let wtr = wtr.finish().unwrap();
+        let data = String::from_utf8(wtr.into_inner().unwrap()).unwrap();
+        assert_eq!(data, "name,n\na,9\nf,9\nb,10\nc,100\nd,\ne,x\n");
+    }
+}
@@ -389,6 +389,29 @@ impl StringRecord {
         self.0.truncate(n);
     }
 
+    /// Remove the field at index `i` from this record.
+    ///
+    /// All fields after `i` are shifted down by one.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `i` is not less than the number of fields in this
+    /// record.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use csv::StringRecord;
+    ///
+    /// let mut record = StringRecord::from(vec!["a", "b", "c"]);
+    /// record.remove_field(1);
+    /// assert_eq!(record, vec!["a", "c"]);
+    /// ```
+    #[inline]
+    pub fn remove_field(&mut self, i: usize) {
+        self.0.remove_field(i);
+    }
+
     /// Clear this record so that it has zero fields.
     ///
     /// Note that it is not necessary to clear the record to reuse it with
@@ -561,6 +584,24 @@ impl StringRecord {
         unsafe { str::from_utf8_unchecked(self.0.as_slice()) }
     }
 
+    /// Return the total number of bytes in all fields of this record.
+    ///
+    /// This doesn't include delimiters or quotes, and is equivalent to the
+    /// length of `as_slice`. It is computed in constant time.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use csv::StringRecord;
+    ///
+    /// let record = StringRecord::from(vec!["foo", "quux", "é"]);
+    /// assert_eq!(record.byte_len(), 9);
+    /// ```
+    #[inline]
+    pub fn byte_len(&self) -> usize {
+        self.0.byte_len()
+    }
+
     /// Return a reference to this record's raw
     /// [`ByteRecord`](struct.ByteRecord.html).
     ///
@@ -826,4 +867,15 @@ mod tests {
         let test2 = StringRecord::from(vec!["12", "34"]);
         assert_ne!(test1, test2);
     }
+
+    // Converting to a `ByteRecord` must reuse the same buffer.
+    #[test]
+    fn into_byte_record_no_copy() {
+        let rec = StringRecord::from(vec!["foo", "☃", ""]);
+        let ptr = rec.as_byte_record().as_slice().as_ptr();
+
+        let brec = rec.into_byte_record();
+        assert_eq!(brec.as_slice().as_ptr(), ptr);
+        assert_eq!(brec, vec!["foo", "☃", ""]);
+    }
 }
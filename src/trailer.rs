@@ -0,0 +1,51 @@
+/// The first field of a trailer row written by `TrailerKind::Count`.
+pub(crate) const COUNT_LABEL: &[u8] = b"#COUNT";
+
+/// The first field of a trailer row written by `TrailerKind::Crc32`.
+pub(crate) const CRC32_LABEL: &[u8] = b"#CRC32";
+
+/// The lookup table for the CRC-32 used by zlib, PNG and others, with the
+/// reflected polynomial `0xEDB88320`.
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc =
+                if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Update the CRC-32 `crc` of some data with the data following it.
+///
+/// The CRC-32 of empty data is `0`, so hashing data in pieces starts from
+/// `0` and feeds each piece in turn.
+pub(crate) fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for &b in data {
+        crc = CRC32_TABLE[((crc ^ b as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::crc32_update;
+
+    #[test]
+    fn crc32() {
+        assert_eq!(crc32_update(0, b""), 0);
+        assert_eq!(crc32_update(0, b"123456789"), 0xCBF4_3926);
+        let crc = crc32_update(crc32_update(0, b"1234"), b"56789");
+        assert_eq!(crc, 0xCBF4_3926);
+    }
+}
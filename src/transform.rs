@@ -0,0 +1,123 @@
+use std::io;
+
+use crate::error::Result;
+use crate::reader::Reader;
+use crate::string_record::StringRecord;
+use crate::writer::Writer;
+
+/// Stream every record through a closure that may rewrite or drop it.
+///
+/// Each record read from `rdr` is passed to `f`, which may modify it in
+/// place. If `f` returns `true`, then the record is written to `wtr`, and
+/// otherwise it is dropped. The number of records written is returned. A
+/// single record buffer is reused for the whole stream.
+///
+/// If both `rdr` and `wtr` are configured with headers, then the header row
+/// is written to `wtr` as is, before any records. It is never passed to `f`.
+///
+/// # Example
+///
+/// ```
+/// use std::error::Error;
+/// use csv::{Reader, StringRecord, Writer};
+///
+/// # fn main() { example().unwrap(); }
+/// fn example() -> Result<(), Box<dyn Error>> {
+///     let data = "\
+/// city,pop
+/// Boston,4628910
+/// Concord,42695
+/// ";
+///     let mut rdr = Reader::from_reader(data.as_bytes());
+///     let mut wtr = Writer::from_writer(vec![]);
+///     let count = csv::transform_with(&mut rdr, &mut wtr, |record| {
+///         let pop: u64 = record[1].parse().unwrap_or(0);
+///         *record = StringRecord::from(vec![
+///             record[0].to_string(),
+///             (pop / 1000).to_string(),
+///         ]);
+///         pop > 100_000
+///     })?;
+///
+///     assert_eq!(count, 1);
+///     let data = String::from_utf8(wtr.into_inner()?)?;
+///     assert_eq!(data, "city,pop\nBoston,4628\n");
+///     Ok(())
+/// }
+/// ```
+pub fn transform_with<R, W, F>(
+    rdr: &mut Reader<R>,
+    wtr: &mut Writer<W>,
+    mut f: F,
+) -> Result<u64>
+where
+    R: io::Read,
+    W: io::Write,
+    F: FnMut(&mut StringRecord) -> bool,
+{
+    if rdr.has_headers() {
+        let headers = rdr.byte_headers()?;
+        if !headers.is_empty() {
+            wtr.write_header_row(headers)?;
+        }
+    }
+
+    let mut record = StringRecord::new();
+    let mut count = 0;
+    while rdr.read_record(&mut record)? {
+        if f(&mut record) {
+            wtr.write_record(&record)?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::reader::ReaderBuilder;
+    use crate::writer::{Writer, WriterBuilder};
+
+    use super::transform_with;
+
+    #[test]
+    fn map_and_filter() {
+        let data = "name,city\nann,Boston\n,Concord\nbob,Salem\n";
+        let mut rdr = ReaderBuilder::new().from_reader(data.as_bytes());
+        let mut wtr = Writer::from_writer(vec![]);
+        let count = transform_with(&mut rdr, &mut wtr, |record| {
+            if record[0].is_empty() {
+                return false;
+            }
+            let upper = record[1].to_uppercase();
+            let mut fields: Vec<String> =
+                record.iter().map(String::from).collect();
+            fields[1] = upper;
+            *record = fields.into();
+            true
+        })
+        .unwrap();
+        assert_eq!(count, 2);
+        let data = String::from_utf8(wtr.into_inner().unwrap()).unwrap();
+        assert_eq!(data, "name,city\nann,BOSTON\nbob,SALEM\n");
+    }
+
+    #[test]
+    fn headers_need_both_sides() {
+        let data = "h\n1\n";
+        let mut rdr = ReaderBuilder::new().from_reader(data.as_bytes());
+        let mut wtr =
+            WriterBuilder::new().has_headers(false).from_writer(vec![]);
+        transform_with(&mut rdr, &mut wtr, |_| true).unwrap();
+        let data = String::from_utf8(wtr.into_inner().unwrap()).unwrap();
+        assert_eq!(data, "1\n");
+
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader("h\n1\n".as_bytes());
+        let mut wtr = Writer::from_writer(vec![]);
+        transform_with(&mut rdr, &mut wtr, |_| true).unwrap();
+        let data = String::from_utf8(wtr.into_inner().unwrap()).unwrap();
+        assert_eq!(data, "h\n1\n");
+    }
+}
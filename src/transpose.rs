@@ -0,0 +1,107 @@
+use std::io;
+
+use crate::byte_record::ByteRecord;
+use crate::error::Result;
+use crate::reader::Reader;
+use crate::writer::Writer;
+
+/// Transpose CSV data, turning its rows into columns and its columns into
+/// rows.
+///
+/// The `i`th field of the `j`th record read from `rdr` becomes the `j`th
+/// field of the `i`th record written to `wtr`. If `rdr` is configured to
+/// read headers, then the header row is included as the first row, so it
+/// becomes the first column of the output. Records with fewer fields than
+/// the longest record are padded with empty fields, so every record written
+/// has the same number of fields.
+///
+/// All of the data is held in memory at once, so this is only suitable for
+/// small CSV data.
+///
+/// # Example
+///
+/// ```
+/// use std::error::Error;
+/// use csv::{Reader, Writer};
+///
+/// # fn main() { example().unwrap(); }
+/// fn example() -> Result<(), Box<dyn Error>> {
+///     let data = "\
+/// key,value
+/// host,localhost
+/// port,8080
+/// ";
+///     let mut rdr = Reader::from_reader(data.as_bytes());
+///     let mut wtr = Writer::from_writer(vec![]);
+///     csv::transpose(&mut rdr, &mut wtr)?;
+///
+///     let data = String::from_utf8(wtr.into_inner()?)?;
+///     assert_eq!(data, "key,host,port\nvalue,localhost,8080\n");
+///     Ok(())
+/// }
+/// ```
+pub fn transpose<R: io::Read, W: io::Write>(
+    rdr: &mut Reader<R>,
+    wtr: &mut Writer<W>,
+) -> Result<()> {
+    let mut rows = vec![];
+    if rdr.has_headers() {
+        let headers = rdr.byte_headers()?;
+        if !headers.is_empty() {
+            rows.push(headers.clone());
+        }
+    }
+    for result in rdr.byte_records() {
+        rows.push(result?);
+    }
+
+    let columns = rows.iter().map(ByteRecord::len).max().unwrap_or(0);
+    let mut record = ByteRecord::new();
+    for i in 0..columns {
+        record.clear();
+        for row in &rows {
+            record.push_field(row.get(i).unwrap_or(b""));
+        }
+        wtr.write_byte_record(&record)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::reader::ReaderBuilder;
+    use crate::writer::Writer;
+
+    use super::transpose;
+
+    fn transpose_str(data: &str) -> String {
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(data.as_bytes());
+        let mut wtr = Writer::from_writer(vec![]);
+        transpose(&mut rdr, &mut wtr).unwrap();
+        String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn rectangular() {
+        assert_eq!(transpose_str("a,b,c\n1,2,3\n"), "a,1\nb,2\nc,3\n");
+        assert_eq!(transpose_str(""), "");
+    }
+
+    #[test]
+    fn ragged() {
+        assert_eq!(transpose_str("a\n1,2,3\nx,y\n"), "a,1,x\n,2,y\n,3,\n");
+    }
+
+    #[test]
+    fn headers() {
+        let mut rdr =
+            ReaderBuilder::new().from_reader("h1,h2\n1,2\n".as_bytes());
+        let mut wtr = Writer::from_writer(vec![]);
+        transpose(&mut rdr, &mut wtr).unwrap();
+        let data = String::from_utf8(wtr.into_inner().unwrap()).unwrap();
+        assert_eq!(data, "h1,1\nh2,2\n");
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+
+use crate::byte_record::Position;
+use crate::error::{Error, ErrorKind};
+use crate::string_record::StringRecord;
+
+/// The result of deserializing every record with
+/// `Reader::deserialize_validated`.
+///
+/// This holds the records that were deserialized successfully, along with
+/// an error for each record that wasn't, and some per-column statistics that
+/// are useful for checking the quality of the data.
+#[derive(Debug)]
+pub struct ValidationReport<D> {
+    rows: Vec<D>,
+    errors: Vec<(Position, Error)>,
+    column_failures: Vec<u64>,
+    null_counts: Vec<u64>,
+}
+
+impl<D> ValidationReport<D> {
+    pub(crate) fn new() -> ValidationReport<D> {
+        ValidationReport {
+            rows: vec![],
+            errors: vec![],
+            column_failures: vec![],
+            null_counts: vec![],
+        }
+    }
+
+    /// Returns the records that were deserialized successfully, in order.
+    pub fn rows(&self) -> &[D] {
+        &self.rows
+    }
+
+    /// Consumes this report and returns the records that were deserialized
+    /// successfully, in order.
+    pub fn into_rows(self) -> Vec<D> {
+        self.rows
+    }
+
+    /// Returns the errors for records that could not be read or
+    /// deserialized, in order, each paired with the position of its record.
+    pub fn errors(&self) -> &[(Position, Error)] {
+        &self.errors
+    }
+
+    /// Returns the number of records that failed to deserialize because of
+    /// each column.
+    ///
+    /// The slice is indexed by column. Columns that never caused a failure
+    /// may be missing from the end of the slice.
+    pub fn column_failures(&self) -> &[u64] {
+        &self.column_failures
+    }
+
+    /// Returns the number of empty fields in each column, across every
+    /// record that was read successfully (whether or not it deserialized).
+    ///
+    /// The slice is indexed by column, and has an entry for every column of
+    /// the longest record read.
+    pub fn null_counts(&self) -> &[u64] {
+        &self.null_counts
+    }
+
+    /// Returns true if and only if every record was deserialized.
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub(crate) fn add_row(&mut self, row: D) {
+        self.rows.push(row);
+    }
+
+    pub(crate) fn count_nulls(&mut self, record: &StringRecord) {
+        if self.null_counts.len() < record.len() {
+            self.null_counts.resize(record.len(), 0);
+        }
+        for (i, field) in record.iter().enumerate() {
+            if field.is_empty() {
+                self.null_counts[i] += 1;
+            }
+        }
+    }
+
+    pub(crate) fn add_error(&mut self, pos: Position, err: Error) {
+        if let ErrorKind::Deserialize { ref err, .. } = *err.kind() {
+            if let Some(field) = err.field() {
+                let field = field as usize;
+                if self.column_failures.len() <= field {
+                    self.column_failures.resize(field + 1, 0);
+                }
+                self.column_failures[field] += 1;
+            }
+        }
+        self.errors.push((pos, err));
+    }
+}
+
+/// The number of fields of every record read by `Reader::analyze_widths`.
+///
+/// This is useful for deciding how many fields the records of messy CSV data
+/// ought to have, before deciding how to fix the records that don't.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct WidthReport {
+    counts: BTreeMap<usize, u64>,
+}
+
+impl WidthReport {
+    pub(crate) fn new() -> WidthReport {
+        WidthReport::default()
+    }
+
+    /// Returns the number of records with each number of fields.
+    ///
+    /// The map is keyed by the number of fields, and only contains numbers
+    /// of fields that some record has.
+    pub fn counts(&self) -> &BTreeMap<usize, u64> {
+        &self.counts
+    }
+
+    /// Returns the most common number of fields.
+    ///
+    /// If several numbers of fields are equally common, then the smallest
+    /// of them is returned. If no records were read, then this returns
+    /// `None`.
+    pub fn modal_width(&self) -> Option<usize> {
+        self.counts
+            .iter()
+            .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+            .map(|(&width, _)| width)
+    }
+
+    pub(crate) fn add_record(&mut self, width: usize) {
+        *self.counts.entry(width).or_insert(0) += 1;
+    }
+}
@@ -0,0 +1,109 @@
+use std::io;
+
+use serde::Serialize;
+
+use crate::error::{Error, Result};
+use crate::writer::WriterBuilder;
+
+/// Serialize every record to `wtr` and return it.
+///
+/// This builds a writer from `config`, serializes each of `records` exactly
+/// as `Writer::serialize` does, flushes the writer and returns the
+/// underlying writer. So if `has_headers` is enabled on `config` (which is
+/// the default), then a header row derived from the first record is written
+/// first. It's a shorthand for when all of the records to write are at hand,
+/// such as a `Vec` of structs.
+///
+/// # Example
+///
+/// ```
+/// use std::error::Error;
+/// use csv::WriterBuilder;
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Row<'a> {
+///     city: &'a str,
+///     population: u64,
+/// }
+///
+/// # fn main() { example().unwrap(); }
+/// fn example() -> Result<(), Box<dyn Error>> {
+///     let rows = vec![
+///         Row { city: "Boston", population: 4628910 },
+///         Row { city: "Concord", population: 42695 },
+///     ];
+///     let data = csv::write_records(vec![], &WriterBuilder::new(), &rows)?;
+///
+///     let data = String::from_utf8(data)?;
+///     assert_eq!(data, "\
+/// city,population
+/// Boston,4628910
+/// Concord,42695
+/// ");
+///     Ok(())
+/// }
+/// ```
+pub fn write_records<W, I, S>(
+    wtr: W,
+    config: &WriterBuilder,
+    records: I,
+) -> Result<W>
+where
+    W: io::Write,
+    I: IntoIterator<Item = S>,
+    S: Serialize,
+{
+    let mut wtr = config.from_writer(wtr);
+    for record in records {
+        wtr.serialize(record)?;
+    }
+    wtr.flush()?;
+    wtr.into_inner().map_err(|err| Error::from(err.into_error()))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+
+    use crate::writer::WriterBuilder;
+
+    use super::write_records;
+
+    #[derive(Serialize)]
+    struct Row {
+        id: u32,
+        name: &'static str,
+        score: Option<f64>,
+    }
+
+    fn rows() -> Vec<Row> {
+        vec![
+            Row { id: 1, name: "ann", score: Some(1.5) },
+            Row { id: 2, name: "bob, jr", score: None },
+        ]
+    }
+
+    #[test]
+    fn same_as_writer() {
+        let mut config = WriterBuilder::new();
+        config.delimiter(b';');
+
+        let mut wtr = config.from_writer(vec![]);
+        for row in rows() {
+            wtr.serialize(row).unwrap();
+        }
+        let expected = wtr.into_inner().unwrap();
+
+        let got = write_records(vec![], &config, rows()).unwrap();
+        assert_eq!(got, expected);
+        assert_eq!(got, b"id;name;score\n1;ann;1.5\n2;bob, jr;\n");
+
+        config.has_headers(false);
+        let got = write_records(vec![], &config, &rows()).unwrap();
+        assert_eq!(got, b"1;ann;1.5\n2;bob, jr;\n");
+
+        let none: Vec<Row> = vec![];
+        assert!(write_records(vec![], &config, none).unwrap().is_empty());
+    }
+}
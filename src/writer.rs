@@ -1,18 +1,37 @@
+use std::borrow::Cow;
+use std::cmp;
 use std::fs::File;
 use std::io;
+use std::mem;
 use std::path::Path;
 use std::result;
+use std::str;
+use std::string::FromUtf8Error;
 
+use bstr::ByteSlice;
 use csv_core::{
     self, WriteResult, Writer as CoreWriter,
     WriterBuilder as CoreWriterBuilder,
 };
 use serde::Serialize;
 
-use crate::byte_record::ByteRecord;
-use crate::error::{Error, ErrorKind, IntoInnerError, Result};
-use crate::serializer::{serialize, serialize_header};
-use crate::{QuoteStyle, Terminator};
+use crate::byte_record::{ByteRecord, Position};
+#[cfg(feature = "encoding")]
+use crate::encoding;
+use crate::error::{new_utf8_error, Error, ErrorKind, IntoInnerError, Result};
+use crate::serializer::{
+    serialize, serialize_field, serialize_header, serialize_map_keys,
+};
+use crate::trailer::{self, COUNT_LABEL, CRC32_LABEL};
+#[cfg(feature = "encoding")]
+use crate::Encoding;
+use crate::{
+    ColumnFormat, Dialect, QuoteStyle, Terminator, TrailerKind, Trim,
+    TupleHeaderStyle,
+};
+
+/// A run of spaces used to pad fields to `WriterBuilder::min_field_width`.
+const SPACES: [u8; 64] = [b' '; 64];
 
 /// Builds a CSV writer with various configuration knobs.
 ///
@@ -25,6 +44,31 @@ pub struct WriterBuilder {
     capacity: usize,
     flexible: bool,
     has_headers: bool,
+    bool_format: Option<(String, String)>,
+    decimal_separator: u8,
+    column_formats: Vec<ColumnFormat>,
+    max_records: Option<u64>,
+    never_quote_strict: bool,
+    validate_utf8: bool,
+    max_field_len: Option<usize>,
+    trim: Trim,
+    skip_columns: Vec<String>,
+    min_field_width: usize,
+    field_newlines: Option<Terminator>,
+    sanitize_formulas: bool,
+    tuple_header_style: TupleHeaderStyle,
+    nested_header_sep: Option<char>,
+    flush_every: Option<u64>,
+    collapse_empty_records: bool,
+    trailer: TrailerKind,
+    record_prefix: Vec<u8>,
+    record_suffix: Vec<u8>,
+    multi_delimiter: Vec<u8>,
+    row_numbers: bool,
+    row_number_header: String,
+    position_columns: bool,
+    #[cfg(feature = "encoding")]
+    encoding: Encoding,
 }
 
 impl Default for WriterBuilder {
@@ -34,6 +78,31 @@ impl Default for WriterBuilder {
             capacity: 8 * (1 << 10),
             flexible: false,
             has_headers: true,
+            bool_format: None,
+            decimal_separator: b'.',
+            column_formats: vec![],
+            max_records: None,
+            never_quote_strict: false,
+            validate_utf8: false,
+            max_field_len: None,
+            trim: Trim::default(),
+            skip_columns: vec![],
+            min_field_width: 0,
+            field_newlines: None,
+            sanitize_formulas: false,
+            tuple_header_style: TupleHeaderStyle::default(),
+            nested_header_sep: None,
+            flush_every: None,
+            collapse_empty_records: false,
+            trailer: TrailerKind::default(),
+            record_prefix: vec![],
+            record_suffix: vec![],
+            multi_delimiter: vec![],
+            row_numbers: false,
+            row_number_header: "#".to_string(),
+            position_columns: false,
+            #[cfg(feature = "encoding")]
+            encoding: Encoding::default(),
         }
     }
 }
@@ -116,6 +185,80 @@ impl WriterBuilder {
         Writer::new(self, wtr)
     }
 
+    /// Build a CSV writer from this configuration that adopts the buffer of
+    /// `wtr`.
+    ///
+    /// Since the CSV writer is buffered automatically, wrapping an
+    /// `io::BufWriter` with `from_writer` buffers the data twice. This
+    /// instead unwraps the `io::BufWriter`, writes directly to its inner
+    /// writer and reuses its buffer as the internal buffer of the CSV
+    /// writer. The capacity of that buffer is kept, so `buffer_capacity` is
+    /// ignored.
+    ///
+    /// Any data still buffered in `wtr` stays in the buffer and is written
+    /// before the CSV data. It counts toward the bytes written by the CSV
+    /// writer, so for example, `Writer::position` includes it once the
+    /// buffer is flushed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use std::io::{BufWriter, Write};
+    /// use csv::WriterBuilder;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut buf = BufWriter::new(vec![]);
+    ///     buf.write_all(b"# cities\n")?;
+    ///
+    ///     let mut wtr = WriterBuilder::new().from_buf_writer(buf);
+    ///     wtr.write_record(&["a", "b", "c"])?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "# cities\na,b,c\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn from_buf_writer<W: io::Write>(
+        &self,
+        wtr: io::BufWriter<W>,
+    ) -> Writer<W> {
+        let capacity = wtr.capacity();
+        let (wtr, buffered) = wtr.into_parts();
+        let mut buf = buffered.unwrap_or_else(|err| err.into_inner());
+        let len = buf.len();
+        buf.resize(cmp::max(capacity, len), 0);
+        Writer::with_buffer(self, wtr, Buffer { buf, len })
+    }
+
+    /// Build a CSV writer from this configuration that writes data to
+    /// standard output.
+    ///
+    /// This locks stdout for the lifetime of the returned writer. Only one
+    /// lock on stdout can be held at a time, so any other attempt to write to
+    /// stdout (including `println!` or a second call to `from_stdout`) blocks
+    /// until this writer is dropped.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::error::Error;
+    /// use csv::WriterBuilder;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = WriterBuilder::new().from_stdout();
+    ///     wtr.write_record(&["a", "b", "c"])?;
+    ///     wtr.write_record(&["x", "y", "z"])?;
+    ///     wtr.flush()?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn from_stdout(&self) -> Writer<io::StdoutLock<'static>> {
+        Writer::new(self, io::stdout().lock())
+    }
+
     /// The field delimiter to use when writing CSV.
     ///
     /// The default is `b','`.
@@ -141,6 +284,49 @@ impl WriterBuilder {
     /// ```
     pub fn delimiter(&mut self, delimiter: u8) -> &mut WriterBuilder {
         self.builder.delimiter(delimiter);
+        self.multi_delimiter.clear();
+        self
+    }
+
+    /// The field delimiter to use when writing CSV, as a sequence of bytes.
+    ///
+    /// This is like `delimiter`, but permits delimiters that are longer than
+    /// a single byte, such as `||` or `|~|`. With the default quoting style,
+    /// a field is quoted if it contains the first byte of the delimiter,
+    /// which guarantees that it can't be confused with the delimiter when
+    /// the data is read back with `ReaderBuilder::multi_delimiter`.
+    ///
+    /// Whichever of `delimiter` and `multi_delimiter` is called last takes
+    /// effect.
+    ///
+    /// # Panics
+    ///
+    /// This panics if `delimiter` is empty.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::WriterBuilder;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = WriterBuilder::new()
+    ///         .multi_delimiter(b"||")
+    ///         .from_writer(vec![]);
+    ///     wtr.write_record(&["a", "b", "c"])?;
+    ///     wtr.write_record(&["x", "y|z", ""])?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "a||b||c\nx||\"y|z\"||\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn multi_delimiter(&mut self, delimiter: &[u8]) -> &mut WriterBuilder {
+        assert!(!delimiter.is_empty(), "delimiter must not be empty");
+        self.builder.delimiter(delimiter[0]);
+        self.multi_delimiter =
+            if delimiter.len() == 1 { vec![] } else { delimiter.to_vec() };
         self
     }
 
@@ -153,7 +339,10 @@ impl WriterBuilder {
     ///
     /// This option has no effect when using other methods to write rows. That
     /// is, if you don't use `serialize`, then you must write your header row
-    /// explicitly if you want a header row.
+    /// explicitly if you want a header row. Every row written by
+    /// `write_record` or `write_byte_record` is a record, so to have options
+    /// such as `max_records` or `with_row_numbers` treat a header row written
+    /// this way as a header row, write it with `set_header` instead.
     ///
     /// This is enabled by default.
     ///
@@ -232,6 +421,102 @@ impl WriterBuilder {
         self
     }
 
+    /// Returns whether `has_headers` is enabled.
+    pub(crate) fn get_has_headers(&self) -> bool {
+        self.has_headers
+    }
+
+    /// The header row written when serializing tuples or tuple structs.
+    ///
+    /// Since tuples have no field names, by default no header row is written
+    /// for them (see `has_headers`). With `TupleHeaderStyle::Positional`,
+    /// a header row of positional names, like `field0,field1`, is written
+    /// instead. This has no effect when `has_headers` is disabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::{TupleHeaderStyle, WriterBuilder};
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = WriterBuilder::new()
+    ///         .tuple_header_style(TupleHeaderStyle::Positional)
+    ///         .from_writer(vec![]);
+    ///     wtr.serialize(("Boston", "United States", 4628910))?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "\
+    /// field0,field1,field2
+    /// Boston,United States,4628910
+    /// ");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn tuple_header_style(
+        &mut self,
+        style: TupleHeaderStyle,
+    ) -> &mut WriterBuilder {
+        self.tuple_header_style = style;
+        self
+    }
+
+    /// The separator joining the names of nested struct fields in the header
+    /// row written when serializing structs.
+    ///
+    /// The fields of a struct nested in a struct field are flattened into
+    /// the record, so they get a column each. By default, the header names
+    /// of these columns are just the names of the nested struct's fields.
+    /// When a separator is set, each name is instead prefixed with the names
+    /// of the struct fields enclosing it, followed by the separator, e.g.,
+    /// `outer.inner`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::WriterBuilder;
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Row {
+    ///     city: String,
+    ///     location: Location,
+    /// }
+    ///
+    /// #[derive(Serialize)]
+    /// struct Location {
+    ///     latitude: f64,
+    ///     longitude: f64,
+    /// }
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = WriterBuilder::new()
+    ///         .nested_header_sep(Some('.'))
+    ///         .from_writer(vec![]);
+    ///     wtr.serialize(Row {
+    ///         city: "Boston".to_string(),
+    ///         location: Location { latitude: 42.36, longitude: -71.06 },
+    ///     })?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "\
+    /// city,location.latitude,location.longitude
+    /// Boston,42.36,-71.06
+    /// ");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn nested_header_sep(
+        &mut self,
+        sep: Option<char>,
+    ) -> &mut WriterBuilder {
+        self.nested_header_sep = sep;
+        self
+    }
+
     /// Whether the number of fields in records is allowed to change or not.
     ///
     /// When disabled (which is the default), writing CSV data will return an
@@ -290,128 +575,170 @@ impl WriterBuilder {
         self
     }
 
-    /// The record terminator to use when writing CSV.
+    /// The maximum number of records that may be written.
     ///
-    /// A record terminator can be any single byte. The default is `\n`.
-    ///
-    /// Note that RFC 4180 specifies that record terminators should be `\r\n`.
-    /// To use `\r\n`, use the special `Terminator::CRLF` value.
+    /// When set, attempting to write a record beyond this limit returns an
+    /// `ErrorKind::TooManyRecords` error, and nothing is written for that
+    /// record. A header row, such as one written by `serialize` or set with
+    /// `set_header`, doesn't count toward the limit, but every row written
+    /// by `write_record` does.
     ///
-    /// # Example: CRLF
+    /// This is disabled by default.
     ///
-    /// This shows how to use RFC 4180 compliant record terminators.
+    /// # Example
     ///
     /// ```
     /// use std::error::Error;
-    /// use csv::{Terminator, WriterBuilder};
+    /// use csv::{ErrorKind, WriterBuilder};
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
     ///     let mut wtr = WriterBuilder::new()
-    ///         .terminator(Terminator::CRLF)
+    ///         .max_records(Some(2))
     ///         .from_writer(vec![]);
-    ///     wtr.write_record(&["a", "b", "c"])?;
-    ///     wtr.write_record(&["x", "y", "z"])?;
+    ///     wtr.set_header(&["city", "pop"])?;
+    ///     wtr.write_record(&["Boston", "4628910"])?;
+    ///     wtr.write_record(&["Concord", "42695"])?;
+    ///
+    ///     let err = wtr.write_record(&["Springfield", "155929"]).unwrap_err();
+    ///     match *err.kind() {
+    ///         ErrorKind::TooManyRecords { limit } => assert_eq!(limit, 2),
+    ///         ref wrong => panic!("unexpected error: {:?}", wrong),
+    ///     }
     ///
     ///     let data = String::from_utf8(wtr.into_inner()?)?;
-    ///     assert_eq!(data, "a,b,c\r\nx,y,z\r\n");
+    ///     assert_eq!(data, "city,pop\nBoston,4628910\nConcord,42695\n");
     ///     Ok(())
     /// }
     /// ```
-    pub fn terminator(&mut self, term: Terminator) -> &mut WriterBuilder {
-        self.builder.terminator(term.to_core());
+    pub fn max_records(&mut self, limit: Option<u64>) -> &mut WriterBuilder {
+        self.max_records = limit;
         self
     }
 
-    /// The quoting style to use when writing CSV.
-    ///
-    /// By default, this is set to `QuoteStyle::Necessary`, which will only
-    /// use quotes when they are necessary to preserve the integrity of data.
+    /// Flush the writer after every `n` records.
     ///
-    /// Note that unless the quote style is set to `Never`, an empty field is
-    /// quoted if it is the only field in a record.
+    /// By default, data is only written to the underlying writer when the
+    /// internal buffer (see `buffer_capacity`) is full, or when the writer is
+    /// flushed or dropped. When this is set, the writer is also flushed,
+    /// including the underlying writer, each time another `n` records have
+    /// been written. This bounds how long records can sit in the buffer,
+    /// which is useful when something is consuming the output as it is
+    /// produced. As with `max_records`, a header row does not count as a
+    /// record.
     ///
-    /// # Example: non-numeric quoting
+    /// This is disabled by default. `Some(0)` is treated like `None`.
     ///
-    /// This shows how to quote non-numeric fields only.
+    /// # Example
     ///
     /// ```
     /// use std::error::Error;
-    /// use csv::{QuoteStyle, WriterBuilder};
+    /// use csv::WriterBuilder;
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
     ///     let mut wtr = WriterBuilder::new()
-    ///         .quote_style(QuoteStyle::NonNumeric)
+    ///         .has_headers(false)
+    ///         .flush_every(Some(2))
     ///         .from_writer(vec![]);
-    ///     wtr.write_record(&["a", "5", "c"])?;
-    ///     wtr.write_record(&["3.14", "y", "z"])?;
+    ///     wtr.write_record(&["Boston", "4628910"])?;
+    ///     assert!(wtr.get_ref().is_empty());
     ///
-    ///     let data = String::from_utf8(wtr.into_inner()?)?;
-    ///     assert_eq!(data, "\"a\",5,\"c\"\n3.14,\"y\",\"z\"\n");
+    ///     wtr.write_record(&["Concord", "42695"])?;
+    ///     assert_eq!(wtr.get_ref(), b"Boston,4628910\nConcord,42695\n");
     ///     Ok(())
     /// }
     /// ```
+    pub fn flush_every(&mut self, n: Option<u64>) -> &mut WriterBuilder {
+        self.flush_every = n.filter(|&n| n > 0);
+        self
+    }
+
+    /// Write consecutive empty records as a single empty record.
     ///
-    /// # Example: never quote
+    /// When enabled, calling `write_empty_record` right after an empty
+    /// record was written does nothing, so that no more than one blank line
+    /// appears in a row. An empty record written after any other row is
+    /// still written.
     ///
-    /// This shows how the CSV writer can be made to never write quotes, even
-    /// if it sacrifices the integrity of the data.
+    /// This is disabled by default.
+    ///
+    /// # Example
     ///
     /// ```
     /// use std::error::Error;
-    /// use csv::{QuoteStyle, WriterBuilder};
+    /// use csv::WriterBuilder;
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
     ///     let mut wtr = WriterBuilder::new()
-    ///         .quote_style(QuoteStyle::Never)
+    ///         .collapse_empty_records(true)
     ///         .from_writer(vec![]);
-    ///     wtr.write_record(&["a", "foo\nbar", "c"])?;
-    ///     wtr.write_record(&["g\"h\"i", "y", "z"])?;
+    ///     wtr.write_record(&["a", "b"])?;
+    ///     wtr.write_empty_record()?;
+    ///     wtr.write_empty_record()?;
+    ///     wtr.write_record(&["c", "d"])?;
     ///
     ///     let data = String::from_utf8(wtr.into_inner()?)?;
-    ///     assert_eq!(data, "a,foo\nbar,c\ng\"h\"i,y,z\n");
+    ///     assert_eq!(data, "a,b\n\nc,d\n");
     ///     Ok(())
     /// }
     /// ```
-    pub fn quote_style(&mut self, style: QuoteStyle) -> &mut WriterBuilder {
-        self.builder.quote_style(style.to_core());
+    pub fn collapse_empty_records(&mut self, yes: bool) -> &mut WriterBuilder {
+        self.collapse_empty_records = yes;
         self
     }
 
-    /// The quote character to use when writing CSV.
+    /// Append a trailer row after the last record.
     ///
-    /// The default is `b'"'`.
+    /// The trailer is written when the writer is consumed by `into_inner`,
+    /// or when it is dropped, and records the number of records written or
+    /// a CRC-32 of the data as described by `TrailerKind`. Its fields are
+    /// written as is, and it isn't subject to checks of the number of
+    /// fields. A trailer of the `TrailerKind::Count` kind can be checked by
+    /// `ReaderBuilder::verify_trailer`.
+    ///
+    /// This is `TrailerKind::None` by default, which writes no trailer.
     ///
     /// # Example
     ///
     /// ```
     /// use std::error::Error;
-    /// use csv::WriterBuilder;
+    /// use csv::{TrailerKind, WriterBuilder};
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
     ///     let mut wtr = WriterBuilder::new()
-    ///         .quote(b'\'')
+    ///         .trailer(TrailerKind::Count)
     ///         .from_writer(vec![]);
-    ///     wtr.write_record(&["a", "foo\nbar", "c"])?;
-    ///     wtr.write_record(&["g'h'i", "y\"y\"y", "z"])?;
+    ///     wtr.set_header(&["city", "pop"])?;
+    ///     wtr.write_record(&["Boston", "4628910"])?;
+    ///     wtr.write_record(&["Concord", "42695"])?;
     ///
     ///     let data = String::from_utf8(wtr.into_inner()?)?;
-    ///     assert_eq!(data, "a,'foo\nbar',c\n'g''h''i',y\"y\"y,z\n");
+    ///     assert_eq!(data, "\
+    /// city,pop
+    /// Boston,4628910
+    /// Concord,42695
+    /// #COUNT,2
+    /// ");
     ///     Ok(())
     /// }
     /// ```
-    pub fn quote(&mut self, quote: u8) -> &mut WriterBuilder {
-        self.builder.quote(quote);
+    pub fn trailer(&mut self, kind: TrailerKind) -> &mut WriterBuilder {
+        self.trailer = kind;
         self
     }
 
-    /// Enable double quote escapes.
+    /// Prepend a running row number to every record.
     ///
-    /// This is enabled by default, but it may be disabled. When disabled,
-    /// quotes in field data are escaped instead of doubled.
+    /// When enabled, the first field of every record written is its row
+    /// number, starting at `1` for the first record written by the writer.
+    /// The first field of a header row, such as one written by `serialize`
+    /// or set with `set_header`, is the label set by `row_number_header`
+    /// instead. As with `max_records`, the header row is not counted.
+    ///
+    /// This is disabled by default.
     ///
     /// # Example
     ///
@@ -422,28 +749,26 @@ impl WriterBuilder {
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
     ///     let mut wtr = WriterBuilder::new()
-    ///         .double_quote(false)
+    ///         .with_row_numbers(true)
     ///         .from_writer(vec![]);
-    ///     wtr.write_record(&["a", "foo\"bar", "c"])?;
-    ///     wtr.write_record(&["x", "y", "z"])?;
+    ///     wtr.set_header(&["city", "pop"])?;
+    ///     wtr.write_record(&["Boston", "4628910"])?;
+    ///     wtr.write_record(&["Concord", "42695"])?;
     ///
     ///     let data = String::from_utf8(wtr.into_inner()?)?;
-    ///     assert_eq!(data, "a,\"foo\\\"bar\",c\nx,y,z\n");
+    ///     assert_eq!(data, "#,city,pop\n1,Boston,4628910\n2,Concord,42695\n");
     ///     Ok(())
     /// }
     /// ```
-    pub fn double_quote(&mut self, yes: bool) -> &mut WriterBuilder {
-        self.builder.double_quote(yes);
+    pub fn with_row_numbers(&mut self, yes: bool) -> &mut WriterBuilder {
+        self.row_numbers = yes;
         self
     }
 
-    /// The escape character to use when writing CSV.
-    ///
-    /// In some variants of CSV, quotes are escaped using a special escape
-    /// character like `\` (instead of escaping quotes by doubling them).
+    /// The label written as the first field of the header row when
+    /// `with_row_numbers` is enabled.
     ///
-    /// By default, writing these idiosyncratic escapes is disabled, and is
-    /// only used when `double_quote` is disabled.
+    /// The default is `#`.
     ///
     /// # Example
     ///
@@ -454,962 +779,5002 @@ impl WriterBuilder {
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
     ///     let mut wtr = WriterBuilder::new()
-    ///         .double_quote(false)
-    ///         .escape(b'$')
+    ///         .with_row_numbers(true)
+    ///         .row_number_header("row")
     ///         .from_writer(vec![]);
-    ///     wtr.write_record(&["a", "foo\"bar", "c"])?;
-    ///     wtr.write_record(&["x", "y", "z"])?;
+    ///     wtr.set_header(&["city", "pop"])?;
+    ///     wtr.write_record(&["Boston", "4628910"])?;
     ///
     ///     let data = String::from_utf8(wtr.into_inner()?)?;
-    ///     assert_eq!(data, "a,\"foo$\"bar\",c\nx,y,z\n");
+    ///     assert_eq!(data, "row,city,pop\n1,Boston,4628910\n");
     ///     Ok(())
     /// }
     /// ```
-    pub fn escape(&mut self, escape: u8) -> &mut WriterBuilder {
-        self.builder.escape(escape);
+    pub fn row_number_header<S: Into<String>>(
+        &mut self,
+        label: S,
+    ) -> &mut WriterBuilder {
+        self.row_number_header = label.into();
         self
     }
 
-    /// Set the capacity (in bytes) of the internal buffer used in the CSV
-    /// writer. This defaults to a reasonable setting.
-    pub fn buffer_capacity(&mut self, capacity: usize) -> &mut WriterBuilder {
-        self.capacity = capacity;
+    /// Prepend the source position of every record as two columns.
+    ///
+    /// When enabled, the first two fields of every record written are the
+    /// byte offset and the line number of the record's `Position`, as set
+    /// by the `Reader` that read it. This is useful for tracing records
+    /// back to the data they were read from. Only records written with
+    /// `write_byte_record` or `write_byte_record_preserve_quotes` carry a
+    /// position, so both fields are empty for a record that doesn't have
+    /// one or that is written some other way.
+    ///
+    /// The first two fields of a header row, such as one written by
+    /// `serialize` or set with `set_header`, are `byte` and `line` instead.
+    /// If `with_row_numbers` is also enabled, then the row number comes
+    /// first.
+    ///
+    /// This is disabled by default.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::{Reader, WriterBuilder};
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let data = "\
+    /// city,pop
+    /// Boston,4628910
+    /// Concord,42695
+    /// ";
+    ///     let mut rdr = Reader::from_reader(data.as_bytes());
+    ///     let mut wtr = WriterBuilder::new()
+    ///         .include_position_columns(true)
+    ///         .from_writer(vec![]);
+    ///     wtr.set_header(rdr.headers()?)?;
+    ///     for result in rdr.byte_records() {
+    ///         wtr.write_byte_record(&result?)?;
+    ///     }
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "\
+    /// byte,line,city,pop
+    /// 9,2,Boston,4628910
+    /// 24,3,Concord,42695
+    /// ");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn include_position_columns(
+        &mut self,
+        yes: bool,
+    ) -> &mut WriterBuilder {
+        self.position_columns = yes;
         self
     }
-}
 
-/// A already configured CSV writer.
-///
-/// A CSV writer takes as input Rust values and writes those values in a valid
-/// CSV format as output.
-///
-/// While CSV writing is considerably easier than parsing CSV, a proper writer
-/// will do a number of things for you:
-///
-/// 1. Quote fields when necessary.
-/// 2. Check that all records have the same number of fields.
-/// 3. Write records with a single empty field correctly.
-/// 4. Automatically serialize normal Rust types to CSV records. When that
-///    type is a struct, a header row is automatically written corresponding
-///    to the fields of that struct.
-/// 5. Use buffering intelligently and otherwise avoid allocation. (This means
-///    that callers should not do their own buffering.)
-///
-/// All of the above can be configured using a
-/// [`WriterBuilder`](struct.WriterBuilder.html).
-/// However, a `Writer` has a couple of convenience constructors (`from_path`
-/// and `from_writer`) that use the default configuration.
-///
-/// Note that the default configuration of a `Writer` uses `\n` for record
-/// terminators instead of `\r\n` as specified by RFC 4180. Use the
-/// `terminator` method on `WriterBuilder` to set the terminator to `\r\n` if
-/// it's desired.
-#[derive(Debug)]
-pub struct Writer<W: io::Write> {
-    core: CoreWriter,
-    wtr: Option<W>,
-    buf: Buffer,
-    state: WriterState,
-}
-
-#[derive(Debug)]
-struct WriterState {
-    /// Whether the Serde serializer should attempt to write a header row.
-    header: HeaderState,
-    /// Whether inconsistent record lengths are allowed.
-    flexible: bool,
-    /// The number of fields writtein in the first record. This is compared
-    /// with `fields_written` on all subsequent records to check for
-    /// inconsistent record lengths.
-    first_field_count: Option<u64>,
-    /// The number of fields written in this record. This is used to report
-    /// errors for inconsistent record lengths if `flexible` is disabled.
-    fields_written: u64,
-    /// This is set immediately before flushing the buffer and then unset
-    /// immediately after flushing the buffer. This avoids flushing the buffer
-    /// twice if the inner writer panics.
-    panicked: bool,
-}
-
-/// HeaderState encodes a small state machine for handling header writes.
-#[derive(Debug)]
-enum HeaderState {
-    /// Indicates that we should attempt to write a header.
-    Write,
-    /// Indicates that writing a header was attempt, and a header was written.
-    DidWrite,
-    /// Indicates that writing a header was attempted, but no headers were
-    /// written or the attempt failed.
-    DidNotWrite,
-    /// This state is used when headers are disabled. It cannot transition
-    /// to any other state.
-    None,
-}
-
-/// A simple internal buffer for buffering writes.
-///
-/// We need this because the `csv_core` APIs want to write into a `&mut [u8]`,
-/// which is not available with the `std::io::BufWriter` API.
-#[derive(Debug)]
-struct Buffer {
-    /// The contents of the buffer.
-    buf: Vec<u8>,
-    /// The number of bytes written to the buffer.
-    len: usize,
-}
-
-impl<W: io::Write> Drop for Writer<W> {
-    fn drop(&mut self) {
-        if self.wtr.is_some() && !self.state.panicked {
-            let _ = self.flush();
-        }
-    }
-}
-
-impl Writer<File> {
-    /// Build a CSV writer with a default configuration that writes data to the
-    /// given file path. The file is truncated if it already exists.
+    /// The record terminator to use when writing CSV.
     ///
-    /// If there was a problem opening the file at the given path, then this
-    /// returns the corresponding error.
+    /// A record terminator can be any single byte. The default is `\n`.
     ///
-    /// # Example
+    /// Note that RFC 4180 specifies that record terminators should be `\r\n`.
+    /// To use `\r\n`, use the special `Terminator::CRLF` value.
     ///
-    /// ```no_run
+    /// # Example: CRLF
+    ///
+    /// This shows how to use RFC 4180 compliant record terminators.
+    ///
+    /// ```
     /// use std::error::Error;
-    /// use csv::Writer;
+    /// use csv::{Terminator, WriterBuilder};
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
-    ///     let mut wtr = Writer::from_path("foo.csv")?;
+    ///     let mut wtr = WriterBuilder::new()
+    ///         .terminator(Terminator::CRLF)
+    ///         .from_writer(vec![]);
     ///     wtr.write_record(&["a", "b", "c"])?;
     ///     wtr.write_record(&["x", "y", "z"])?;
-    ///     wtr.flush()?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "a,b,c\r\nx,y,z\r\n");
     ///     Ok(())
     /// }
     /// ```
-    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Writer<File>> {
-        WriterBuilder::new().from_path(path)
-    }
-}
-
-impl<W: io::Write> Writer<W> {
-    fn new(builder: &WriterBuilder, wtr: W) -> Writer<W> {
-        let header_state = if builder.has_headers {
-            HeaderState::Write
-        } else {
-            HeaderState::None
-        };
-        Writer {
-            core: builder.builder.build(),
-            wtr: Some(wtr),
-            buf: Buffer { buf: vec![0; builder.capacity], len: 0 },
-            state: WriterState {
-                header: header_state,
-                flexible: builder.flexible,
-                first_field_count: None,
-                fields_written: 0,
-                panicked: false,
-            },
-        }
+    pub fn terminator(&mut self, term: Terminator) -> &mut WriterBuilder {
+        self.builder.terminator(term.to_core());
+        self
     }
 
-    /// Build a CSV writer with a default configuration that writes data to
-    /// `wtr`.
+    /// The quoting style to use when writing CSV.
     ///
-    /// Note that the CSV writer is buffered automatically, so you should not
-    /// wrap `wtr` in a buffered writer like `io::BufWriter`.
+    /// By default, this is set to `QuoteStyle::Necessary`, which will only
+    /// use quotes when they are necessary to preserve the integrity of data.
     ///
-    /// # Example
+    /// Note that unless the quote style is set to `Never`, an empty field is
+    /// quoted if it is the only field in a record.
+    ///
+    /// # Example: non-numeric quoting
+    ///
+    /// This shows how to quote non-numeric fields only.
     ///
     /// ```
     /// use std::error::Error;
-    /// use csv::Writer;
+    /// use csv::{QuoteStyle, WriterBuilder};
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
-    ///     let mut wtr = Writer::from_writer(vec![]);
-    ///     wtr.write_record(&["a", "b", "c"])?;
-    ///     wtr.write_record(&["x", "y", "z"])?;
+    ///     let mut wtr = WriterBuilder::new()
+    ///         .quote_style(QuoteStyle::NonNumeric)
+    ///         .from_writer(vec![]);
+    ///     wtr.write_record(&["a", "5", "c"])?;
+    ///     wtr.write_record(&["3.14", "y", "z"])?;
     ///
     ///     let data = String::from_utf8(wtr.into_inner()?)?;
-    ///     assert_eq!(data, "a,b,c\nx,y,z\n");
+    ///     assert_eq!(data, "\"a\",5,\"c\"\n3.14,\"y\",\"z\"\n");
     ///     Ok(())
     /// }
     /// ```
-    pub fn from_writer(wtr: W) -> Writer<W> {
-        WriterBuilder::new().from_writer(wtr)
-    }
-
-    /// Serialize a single record using Serde.
     ///
-    /// # Example
+    /// # Example: never quote
     ///
-    /// This shows how to serialize normal Rust structs as CSV records. The
-    /// fields of the struct are used to write a header row automatically.
-    /// (Writing the header row automatically can be disabled by building the
-    /// CSV writer with a [`WriterBuilder`](struct.WriterBuilder.html) and
-    /// calling the `has_headers` method.)
+    /// This shows how the CSV writer can be made to never write quotes, even
+    /// if it sacrifices the integrity of the data.
     ///
     /// ```
     /// use std::error::Error;
-    ///
-    /// use csv::Writer;
-    /// use serde::Serialize;
-    ///
-    /// #[derive(Serialize)]
-    /// struct Row<'a> {
-    ///     city: &'a str,
-    ///     country: &'a str,
-    ///     // Serde allows us to name our headers exactly,
-    ///     // even if they don't match our struct field names.
-    ///     #[serde(rename = "popcount")]
-    ///     population: u64,
-    /// }
+    /// use csv::{QuoteStyle, WriterBuilder};
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
-    ///     let mut wtr = Writer::from_writer(vec![]);
-    ///     wtr.serialize(Row {
-    ///         city: "Boston",
-    ///         country: "United States",
-    ///         population: 4628910,
-    ///     })?;
-    ///     wtr.serialize(Row {
-    ///         city: "Concord",
-    ///         country: "United States",
-    ///         population: 42695,
-    ///     })?;
+    ///     let mut wtr = WriterBuilder::new()
+    ///         .quote_style(QuoteStyle::Never)
+    ///         .from_writer(vec![]);
+    ///     wtr.write_record(&["a", "foo\nbar", "c"])?;
+    ///     wtr.write_record(&["g\"h\"i", "y", "z"])?;
     ///
     ///     let data = String::from_utf8(wtr.into_inner()?)?;
-    ///     assert_eq!(data, "\
-    /// city,country,popcount
-    /// Boston,United States,4628910
-    /// Concord,United States,42695
-    /// ");
+    ///     assert_eq!(data, "a,foo\nbar,c\ng\"h\"i,y,z\n");
     ///     Ok(())
     /// }
     /// ```
+    pub fn quote_style(&mut self, style: QuoteStyle) -> &mut WriterBuilder {
+        self.builder.quote_style(style.to_core());
+        self
+    }
+
+    /// Enable or disable quoting.
     ///
-    /// # Rules
-    ///
-    /// The behavior of `serialize` is fairly simple:
+    /// This is enabled by default, but it may be disabled. When disabled,
+    /// the writer never writes a quote character: every field is written
+    /// verbatim without quoting or escaping, regardless of the quoting style
+    /// and even when quotes are requested by
+    /// `write_byte_record_preserve_quotes`. A record with a single empty
+    /// field is written as an empty line.
     ///
-    /// 1. Nested containers (tuples, `Vec`s, structs, etc.) are always
-    ///    flattened (depth-first order).
+    /// This is intended for formats that forbid quoting. Fields containing a
+    /// delimiter, quote, escape or record terminator then produce corrupt
+    /// CSV data, so this should only be used with data known not to contain
+    /// them, or combined with `never_quote_strict` to reject such fields.
     ///
-    /// 2. If `has_headers` is `true` and the type contains field names, then
-    ///    a header row is automatically generated.
+    /// # Example
     ///
-    /// However, some container types cannot be serialized, and if
-    /// `has_headers` is `true`, there are some additional restrictions on the
-    /// types that can be serialized. See below for details.
+    /// ```
+    /// use std::error::Error;
+    /// use csv::WriterBuilder;
     ///
-    /// For the purpose of this section, Rust types can be divided into three
-    /// categories: scalars, non-struct containers, and structs.
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = WriterBuilder::new()
+    ///         .delimiter(b'\t')
+    ///         .quoting(false)
+    ///         .from_writer(vec![]);
+    ///     wtr.write_record(&["name", "quote"])?;
+    ///     wtr.write_record(&["Wilde", "\"Be yourself\""])?;
     ///
-    /// ## Scalars
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "name\tquote\nWilde\t\"Be yourself\"\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn quoting(&mut self, yes: bool) -> &mut WriterBuilder {
+        self.builder.quoting(yes);
+        self
+    }
+
+    /// Reject fields that would need quotes when quoting is disabled.
     ///
-    /// Single values with no field names are written like the following. Note
-    /// that some of the outputs may be quoted, according to the selected
-    /// quoting style.
+    /// With `QuoteStyle::Never`, or with `quoting` disabled, fields
+    /// containing a delimiter, quote, escape or record terminator are written
+    /// as is, which silently produces corrupt CSV data. When this is enabled
+    /// along with either of them, writing such a field instead returns an
+    /// `ErrorKind::FieldNeedsQuoting` error. A record written by
+    /// `write_record` or `write_byte_record` is checked in full before any
+    /// of it is written, but fields written one at a time, e.g., by
+    /// `write_field` or `serialize`, are checked as they are written.
     ///
-    /// | Name | Example Type | Example Value | Output |
-    /// | ---- | ---- | ---- | ---- |
-    /// | boolean | `bool` | `true` | `true` |
-    /// | integers | `i8`, `i16`, `i32`, `i64`, `i128`, `u8`, `u16`, `u32`, `u64`, `u128` | `5` | `5` |
-    /// | floats | `f32`, `f64` | `3.14` | `3.14` |
-    /// | character | `char` | `'☃'` | `☃` |
-    /// | string | `&str` | `"hi"` | `hi` |
-    /// | bytes | `&[u8]` | `b"hi"[..]` | `hi` |
-    /// | option | `Option` | `None` | *empty* |
-    /// | option |          | `Some(5)` | `5` |
-    /// | unit | `()` | `()` | *empty* |
-    /// | unit struct | `struct Foo;` | `Foo` | `Foo` |
-    /// | unit enum variant | `enum E { A, B }` | `E::A` | `A` |
-    /// | newtype struct | `struct Foo(u8);` | `Foo(5)` | `5` |
-    /// | newtype enum variant | `enum E { A(u8) }` | `E::A(5)` | `5` |
+    /// This is disabled by default, and has no effect when quoting is
+    /// otherwise possible.
     ///
-    /// Note that this table includes simple structs and enums. For example, to
-    /// serialize a field from either an integer or a float type, one can do
-    /// this:
+    /// # Example
     ///
     /// ```
     /// use std::error::Error;
-    ///
-    /// use csv::Writer;
-    /// use serde::Serialize;
-    ///
-    /// #[derive(Serialize)]
-    /// struct Row {
-    ///     label: String,
-    ///     value: Value,
-    /// }
-    ///
-    /// #[derive(Serialize)]
-    /// enum Value {
-    ///     Integer(i64),
-    ///     Float(f64),
-    /// }
+    /// use csv::{ErrorKind, QuoteStyle, WriterBuilder};
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
-    ///     let mut wtr = Writer::from_writer(vec![]);
-    ///     wtr.serialize(Row {
-    ///         label: "foo".to_string(),
-    ///         value: Value::Integer(3),
-    ///     })?;
-    ///     wtr.serialize(Row {
-    ///         label: "bar".to_string(),
-    ///         value: Value::Float(3.14),
-    ///     })?;
+    ///     let mut wtr = WriterBuilder::new()
+    ///         .quote_style(QuoteStyle::Never)
+    ///         .never_quote_strict(true)
+    ///         .from_writer(vec![]);
+    ///     wtr.write_record(&["a", "b", "c"])?;
     ///
-    ///     let data = String::from_utf8(wtr.into_inner()?)?;
-    ///     assert_eq!(data, "\
-    /// label,value
-    /// foo,3
-    /// bar,3.14
-    /// ");
+    ///     let err = wtr.write_record(&["x", "y,z"]).unwrap_err();
+    ///     match *err.kind() {
+    ///         ErrorKind::FieldNeedsQuoting { field_index } => {
+    ///             assert_eq!(field_index, 1);
+    ///         }
+    ///         ref wrong => panic!("unexpected error: {:?}", wrong),
+    ///     }
     ///     Ok(())
     /// }
     /// ```
+    pub fn never_quote_strict(&mut self, yes: bool) -> &mut WriterBuilder {
+        self.never_quote_strict = yes;
+        self
+    }
+
+    /// Reject fields that aren't valid UTF-8.
     ///
-    /// ## Non-Struct Containers
+    /// When enabled, every field written is checked to be valid UTF-8, and
+    /// if one isn't, a `ErrorKind::Utf8` error is returned instead of
+    /// writing it. This guarantees that the output is valid UTF-8, e.g., to
+    /// catch binary data ending up in a text CSV file by mistake. Fields of
+    /// the record that precede the offending field may have already been
+    /// written.
     ///
-    /// Nested containers are flattened to their scalar components, with the
-    /// exeption of a few types that are not allowed:
+    /// This is disabled by default, since checking every field has a cost.
     ///
-    /// | Name | Example Type | Example Value | Output |
-    /// | ---- | ---- | ---- | ---- |
-    /// | sequence | `Vec<u8>` | `vec![1, 2, 3]` | `1,2,3` |
-    /// | tuple | `(u8, bool)` | `(5, true)` | `5,true` |
-    /// | tuple struct | `Foo(u8, bool)` | `Foo(5, true)` | `5,true` |
-    /// | tuple enum variant | `enum E { A(u8, bool) }` | `E::A(5, true)` | *error* |
-    /// | struct enum variant | `enum E { V { a: u8, b: bool } }` | `E::V { a: 5, b: true }` | *error* |
-    /// | map | `BTreeMap<K, V>` | `BTreeMap::new()` | *error* |
+    /// # Example
     ///
-    /// ## Structs
+    /// ```
+    /// use std::error::Error;
+    /// use csv::{ErrorKind, WriterBuilder};
     ///
-    /// Like the other containers, structs are flattened to their scalar
-    /// components:
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = WriterBuilder::new()
+    ///         .validate_utf8(true)
+    ///         .from_writer(vec![]);
+    ///     wtr.write_record(&["a", "b"])?;
     ///
-    /// | Name | Example Type | Example Value | Output |
-    /// | ---- | ---- | ---- | ---- |
-    /// | struct | `struct Foo { a: u8, b: bool }` | `Foo { a: 5, b: true }` | `5,true` |
+    ///     let err = wtr.write_record(&[&b"x"[..], b"\xFFy"]).unwrap_err();
+    ///     match *err.kind() {
+    ///         ErrorKind::Utf8 { ref err, .. } => {
+    ///             assert_eq!(err.field(), 1);
+    ///             assert_eq!(err.valid_up_to(), 0);
+    ///         }
+    ///         ref wrong => panic!("unexpected error: {:?}", wrong),
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn validate_utf8(&mut self, yes: bool) -> &mut WriterBuilder {
+        self.validate_utf8 = yes;
+        self
+    }
+
+    /// Set the maximum length of a field, in bytes.
     ///
-    /// If `has_headers` is `false`, then there are no additional restrictions;
-    /// types can be nested arbitrarily. For example:
+    /// When set, writing a field that is longer than `limit` returns an
+    /// `ErrorKind::FieldTooLong` error instead of writing it. This is useful
+    /// when the CSV data is loaded into a database whose columns have a
+    /// maximum length. The length is measured before quoting and padding,
+    /// but after any other rewriting, such as `encoding`. Fields written by
+    /// `write_field_raw` aren't checked.
+    ///
+    /// A record written by `write_record` or `write_byte_record` is checked
+    /// in full before any of it is written. Fields written one at a time,
+    /// e.g., by `write_field` or `serialize`, are checked as they are
+    /// written, so the fields of the record before the offending field may
+    /// have already been written.
+    ///
+    /// There is no limit by default.
+    ///
+    /// # Example
     ///
     /// ```
     /// use std::error::Error;
+    /// use csv::{ErrorKind, WriterBuilder};
     ///
-    /// use csv::WriterBuilder;
-    /// use serde::Serialize;
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = WriterBuilder::new()
+    ///         .max_field_len(Some(5))
+    ///         .from_writer(vec![]);
+    ///     wtr.write_record(&["code", "city"])?;
     ///
-    /// #[derive(Serialize)]
-    /// struct Row {
-    ///     label: String,
-    ///     values: Vec<f64>,
+    ///     let err = wtr.write_record(&["BOS", "Boston"]).unwrap_err();
+    ///     match *err.kind() {
+    ///         ErrorKind::FieldTooLong { field_index, len, limit } => {
+    ///             assert_eq!((field_index, len, limit), (1, 6, 5));
+    ///         }
+    ///         ref wrong => panic!("unexpected error: {:?}", wrong),
+    ///     }
+    ///     Ok(())
     /// }
+    /// ```
+    pub fn max_field_len(
+        &mut self,
+        limit: Option<usize>,
+    ) -> &mut WriterBuilder {
+        self.max_field_len = limit;
+        self
+    }
+
+    /// Whether fields should be trimmed of leading and trailing whitespace
+    /// before they're written.
+    ///
+    /// This mirrors `ReaderBuilder::trim`, and can be used to clean up data
+    /// from a messy source in one pass. Fields are trimmed before deciding
+    /// whether they need quotes, so a field made only of whitespace is
+    /// written as an empty field. A header row, such as one written by
+    /// `serialize` or set with `set_header`, is only trimmed with
+    /// `Trim::Headers` or `Trim::All`. Fields written by `write_field_raw`
+    /// aren't trimmed.
+    ///
+    /// By default, no trimming is performed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::{Trim, WriterBuilder};
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
     ///     let mut wtr = WriterBuilder::new()
-    ///         .has_headers(false)
+    ///         .trim_fields(Trim::All)
     ///         .from_writer(vec![]);
-    ///     wtr.serialize(Row {
-    ///         label: "foo".to_string(),
-    ///         values: vec![1.1234, 2.5678, 3.14],
-    ///     })?;
+    ///     wtr.write_record(&[" city ", "pop", "note"])?;
+    ///     wtr.write_record(&["  Boston\t", " 4628910", "   "])?;
     ///
     ///     let data = String::from_utf8(wtr.into_inner()?)?;
-    ///     assert_eq!(data, "\
-    /// foo,1.1234,2.5678,3.14
-    /// ");
+    ///     assert_eq!(data, "city,pop,note\nBoston,4628910,\n");
     ///     Ok(())
     /// }
     /// ```
+    pub fn trim_fields(&mut self, trim: Trim) -> &mut WriterBuilder {
+        self.trim = trim;
+        self
+    }
+
+    /// The encoding to write CSV data in.
     ///
-    /// However, if `has_headers` were enabled in the above example, then
-    /// serialization would return an error. Speficially, when `has_headers` is
-    /// `true`, there are two restrictions:
+    /// This defaults to `Encoding::Utf8`, in which case fields are written
+    /// as is. With `Encoding::Latin1`, every field is transcoded from UTF-8
+    /// to Windows-1252 before it is written. Writing a field that isn't valid
+    /// UTF-8, or that contains a character Windows-1252 can't represent,
+    /// then returns an error and nothing is written for that field.
     ///
-    /// 1. Named field values in structs must be scalars.
+    /// This is only available when the `encoding` feature is enabled.
     ///
-    /// 2. All scalars must be named field values in structs.
+    /// # Example
     ///
-    /// Other than these two restrictions, types can be nested arbitrarily.
-    /// Here are a few examples:
+    /// ```
+    /// use std::error::Error;
+    /// use csv::{Encoding, WriterBuilder};
     ///
-    /// | Value | Header | Record |
-    /// | ---- | ---- | ---- |
-    /// | `(Foo { x: 5, y: 6 }, Bar { z: true })` | `x,y,z` | `5,6,true` |
-    /// | `vec![Foo { x: 5, y: 6 }, Foo { x: 7, y: 8 }]` | `x,y,x,y` | `5,6,7,8` |
-    /// | `(Foo { x: 5, y: 6 }, vec![Bar { z: Baz(true) }])` | `x,y,z` | `5,6,true` |
-    /// | `Foo { x: 5, y: (6, 7) }` | *error: restriction 1* | `5,6,7` |
-    /// | `(5, Foo { x: 6, y: 7 }` | *error: restriction 2* | `5,6,7` |
-    /// | `(Foo { x: 5, y: 6 }, true)` | *error: restriction 2* | `5,6,true` |
-    pub fn serialize<S: Serialize>(&mut self, record: S) -> Result<()> {
-        if let HeaderState::Write = self.state.header {
-            let wrote_header = serialize_header(self, &record)?;
-            if wrote_header {
-                self.write_terminator()?;
-                self.state.header = HeaderState::DidWrite;
-            } else {
-                self.state.header = HeaderState::DidNotWrite;
-            };
-        }
-        serialize(self, &record)?;
-        self.write_terminator()?;
-        Ok(())
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = WriterBuilder::new()
+    ///         .encoding(Encoding::Latin1)
+    ///         .from_writer(vec![]);
+    ///     wtr.write_record(&["café", "naïve"])?;
+    ///
+    ///     assert_eq!(wtr.into_inner()?, b"caf\xE9,na\xEFve\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "encoding")]
+    pub fn encoding(&mut self, encoding: Encoding) -> &mut WriterBuilder {
+        self.encoding = encoding;
+        self
     }
 
-    /// Write a single record.
+    /// Pad every field with trailing spaces to at least `width` bytes.
     ///
-    /// This method accepts something that can be turned into an iterator that
-    /// yields elements that can be represented by a `&[u8]`.
+    /// This makes the output easier to read while keeping it valid CSV.
+    /// Fields that are already `width` bytes or longer are written as is,
+    /// and are never truncated. The padding is added to the field data
+    /// before quoting, so when a field is quoted, its padding appears inside
+    /// the quotes. Since the padding becomes part of each field, reading the
+    /// data back with `Trim::Fields` recovers the original fields (assuming
+    /// they had no trailing whitespace of their own).
     ///
-    /// This may be called with an empty iterator, which will cause a record
-    /// terminator to be written. If no fields had been written, then a single
-    /// empty field is written before the terminator.
+    /// This is disabled by default, which corresponds to a width of `0`.
     ///
     /// # Example
     ///
     /// ```
     /// use std::error::Error;
-    /// use csv::Writer;
+    /// use csv::WriterBuilder;
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
-    ///     let mut wtr = Writer::from_writer(vec![]);
-    ///     wtr.write_record(&["a", "b", "c"])?;
-    ///     wtr.write_record(&["x", "y", "z"])?;
+    ///     let mut wtr = WriterBuilder::new()
+    ///         .min_field_width(6)
+    ///         .from_writer(vec![]);
+    ///     wtr.write_record(&["city", "pop"])?;
+    ///     wtr.write_record(&["Boston", "4628910"])?;
     ///
     ///     let data = String::from_utf8(wtr.into_inner()?)?;
-    ///     assert_eq!(data, "a,b,c\nx,y,z\n");
+    ///     assert_eq!(data, "city  ,pop   \nBoston,4628910\n");
     ///     Ok(())
     /// }
     /// ```
-    pub fn write_record<I, T>(&mut self, record: I) -> Result<()>
-    where
-        I: IntoIterator<Item = T>,
-        T: AsRef<[u8]>,
-    {
-        for field in record.into_iter() {
-            self.write_field_impl(field)?;
-        }
-        self.write_terminator()
+    pub fn min_field_width(&mut self, width: usize) -> &mut WriterBuilder {
+        self.min_field_width = width;
+        self
     }
 
-    /// Write a single `ByteRecord`.
-    ///
-    /// This method accepts a borrowed `ByteRecord` and writes its contents
-    /// to the underlying writer.
+    /// Bytes to write at the start of every record, before its first field.
     ///
-    /// This is similar to `write_record` except that it specifically requires
-    /// a `ByteRecord`. This permits the writer to possibly write the record
-    /// more quickly than the more generic `write_record`.
+    /// The bytes are written as is: they are neither quoted nor escaped, and
+    /// aren't considered when deciding whether the first field needs quotes.
+    /// This applies to every record, including a header row.
     ///
-    /// This may be called with an empty record, which will cause a record
-    /// terminator to be written. If no fields had been written, then a single
-    /// empty field is written before the terminator.
+    /// By default, nothing is written before a record.
     ///
     /// # Example
     ///
     /// ```
     /// use std::error::Error;
-    /// use csv::{ByteRecord, Writer};
+    /// use csv::WriterBuilder;
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
-    ///     let mut wtr = Writer::from_writer(vec![]);
-    ///     wtr.write_byte_record(&ByteRecord::from(&["a", "b", "c"][..]))?;
-    ///     wtr.write_byte_record(&ByteRecord::from(&["x", "y", "z"][..]))?;
+    ///     let mut wtr = WriterBuilder::new()
+    ///         .record_prefix("INSERT INTO t VALUES (")
+    ///         .record_suffix(");")
+    ///         .quote(b'\'')
+    ///         .from_writer(vec![]);
+    ///     wtr.write_record(&["1", "Boston, MA"])?;
+    ///     wtr.write_record(&["2", "Concord"])?;
     ///
     ///     let data = String::from_utf8(wtr.into_inner()?)?;
-    ///     assert_eq!(data, "a,b,c\nx,y,z\n");
+    ///     assert_eq!(data, "\
+    /// INSERT INTO t VALUES (1,'Boston, MA');
+    /// INSERT INTO t VALUES (2,Concord);
+    /// ");
     ///     Ok(())
     /// }
     /// ```
-    #[inline(never)]
-    pub fn write_byte_record(&mut self, record: &ByteRecord) -> Result<()> {
-        if record.as_slice().is_empty() {
-            return self.write_record(record);
-        }
-        // The idea here is to find a fast path for shuffling our record into
-        // our buffer as quickly as possible. We do this because the underlying
-        // "core" CSV writer does a lot of book-keeping to maintain its state
-        // oriented API.
-        //
-        // The fast path occurs when we know our record will fit in whatever
-        // space we have left in our buffer. We can actually quickly compute
-        // the upper bound on the space required:
-        let upper_bound =
-            // The data itself plus the worst case: every byte is a quote.
-            (2 * record.as_slice().len())
-            // The number of field delimiters.
-            + (record.len().saturating_sub(1))
-            // The maximum number of quotes inserted around each field.
-            + (2 * record.len())
-            // The maximum number of bytes for the terminator.
-            + 2;
-        if self.buf.writable().len() < upper_bound {
-            return self.write_record(record);
-        }
-        let mut first = true;
-        for field in record.iter() {
-            if !first {
-                self.buf.writable()[0] = self.core.get_delimiter();
-                self.buf.written(1);
-            }
-            first = false;
+    pub fn record_prefix<B: AsRef<[u8]>>(
+        &mut self,
+        prefix: B,
+    ) -> &mut WriterBuilder {
+        self.record_prefix = prefix.as_ref().to_vec();
+        self
+    }
 
-            if !self.core.should_quote(field) {
-                self.buf.writable()[..field.len()].copy_from_slice(field);
-                self.buf.written(field.len());
-            } else {
-                self.buf.writable()[0] = self.core.get_quote();
-                self.buf.written(1);
-                let (res, nin, nout) = csv_core::quote(
-                    field,
-                    self.buf.writable(),
-                    self.core.get_quote(),
-                    self.core.get_escape(),
-                    self.core.get_double_quote(),
-                );
-                debug_assert!(res == WriteResult::InputEmpty);
-                debug_assert!(nin == field.len());
-                self.buf.written(nout);
-                self.buf.writable()[0] = self.core.get_quote();
-                self.buf.written(1);
-            }
-        }
-        self.state.fields_written = record.len() as u64;
-        self.write_terminator_into_buffer()
+    /// Bytes to write at the end of every record, after its last field and
+    /// before the record terminator.
+    ///
+    /// The bytes are written as is: they are neither quoted nor escaped. If
+    /// the last field is quoted, then they follow its closing quote. This
+    /// applies to every record, including a header row.
+    ///
+    /// By default, nothing is written after a record. See `record_prefix`
+    /// for an example.
+    pub fn record_suffix<B: AsRef<[u8]>>(
+        &mut self,
+        suffix: B,
+    ) -> &mut WriterBuilder {
+        self.record_suffix = suffix.as_ref().to_vec();
+        self
     }
 
-    /// Write a single field.
+    /// Rewrite the line endings inside fields to a single style.
     ///
-    /// One should prefer using `write_record` over this method. It is provided
-    /// for cases where writing a field at a time is more convenient than
-    /// writing a record at a time.
+    /// When set, every `\r\n`, `\r` and `\n` sequence within the data of a
+    /// field is replaced with the given terminator before the field is
+    /// written: `Terminator::CRLF` writes `\r\n` and `Terminator::Any(b)`
+    /// writes the byte `b`. Fields that still contain line endings remain
+    /// quoted as usual, so they stay multi-line. This has no effect on the
+    /// terminators written between records, which are controlled by
+    /// `terminator`.
     ///
-    /// Note that if this API is used, `write_record` should be called with an
-    /// empty iterator to write a record terminator.
+    /// This is disabled by default, which writes fields unchanged.
     ///
     /// # Example
     ///
     /// ```
     /// use std::error::Error;
-    /// use csv::Writer;
+    /// use csv::{Terminator, WriterBuilder};
     ///
     /// # fn main() { example().unwrap(); }
     /// fn example() -> Result<(), Box<dyn Error>> {
-    ///     let mut wtr = Writer::from_writer(vec![]);
-    ///     wtr.write_field("a")?;
-    ///     wtr.write_field("b")?;
-    ///     wtr.write_field("c")?;
-    ///     wtr.write_record(None::<&[u8]>)?;
-    ///     wtr.write_field("x")?;
-    ///     wtr.write_field("y")?;
-    ///     wtr.write_field("z")?;
-    ///     wtr.write_record(None::<&[u8]>)?;
+    ///     let mut wtr = WriterBuilder::new()
+    ///         .normalize_field_newlines(Some(Terminator::Any(b'\n')))
+    ///         .from_writer(vec![]);
+    ///     wtr.write_record(&["a\r\nb\rc", "d"])?;
     ///
     ///     let data = String::from_utf8(wtr.into_inner()?)?;
-    ///     assert_eq!(data, "a,b,c\nx,y,z\n");
+    ///     assert_eq!(data, "\"a\nb\nc\",d\n");
     ///     Ok(())
     /// }
     /// ```
-    pub fn write_field<T: AsRef<[u8]>>(&mut self, field: T) -> Result<()> {
-        self.write_field_impl(field)
+    pub fn normalize_field_newlines(
+        &mut self,
+        term: Option<Terminator>,
+    ) -> &mut WriterBuilder {
+        self.field_newlines = term;
+        self
+    }
+
+    /// Neutralize fields that a spreadsheet would interpret as formulas.
+    ///
+    /// Spreadsheet applications evaluate a cell whose text starts with `=`,
+    /// `+`, `-` or `@` as a formula, which makes exporting untrusted data to
+    /// CSV a way to inject formulas (known as CSV injection). When enabled,
+    /// every field starting with one of those characters, or with a tab or
+    /// carriage return, is prefixed with a single quote (`'`), which makes
+    /// spreadsheets treat the field as text. Fields starting with `+` or `-`
+    /// that are plain numbers, like `-1.5`, are left as they are.
+    ///
+    /// Note that the prefix is part of the data written, so it is read back
+    /// by CSV readers. This applies to every field, including those of a
+    /// header row, but not to fields written with `write_field_raw`.
+    ///
+    /// This is disabled by default.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::WriterBuilder;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = WriterBuilder::new()
+    ///         .sanitize_formulas(true)
+    ///         .from_writer(vec![]);
+    ///     wtr.write_record(&["=SUM(A1)", "-42", "@user"])?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "'=SUM(A1),-42,'@user\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn sanitize_formulas(&mut self, yes: bool) -> &mut WriterBuilder {
+        self.sanitize_formulas = yes;
+        self
+    }
+
+    /// The quote character to use when writing CSV.
+    ///
+    /// The default is `b'"'`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::WriterBuilder;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = WriterBuilder::new()
+    ///         .quote(b'\'')
+    ///         .from_writer(vec![]);
+    ///     wtr.write_record(&["a", "foo\nbar", "c"])?;
+    ///     wtr.write_record(&["g'h'i", "y\"y\"y", "z"])?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "a,'foo\nbar',c\n'g''h''i',y\"y\"y,z\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn quote(&mut self, quote: u8) -> &mut WriterBuilder {
+        self.builder.quote(quote);
+        self
+    }
+
+    /// Enable double quote escapes.
+    ///
+    /// This is enabled by default, but it may be disabled. When disabled,
+    /// quotes in field data are escaped instead of doubled. Occurrences of
+    /// the escape character itself are escaped too, so that a reader using
+    /// the same escape character reads back the original field.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::WriterBuilder;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = WriterBuilder::new()
+    ///         .double_quote(false)
+    ///         .from_writer(vec![]);
+    ///     wtr.write_record(&["a", "foo\"bar", "c"])?;
+    ///     wtr.write_record(&["x", "y", "z"])?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "a,\"foo\\\"bar\",c\nx,y,z\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn double_quote(&mut self, yes: bool) -> &mut WriterBuilder {
+        self.builder.double_quote(yes);
+        self
+    }
+
+    /// Whether to quote a record that consists of a single empty field.
+    ///
+    /// This is enabled by default, in which case such a record is written as
+    /// `""`. When disabled, it is written as an empty line instead, i.e.,
+    /// just a record terminator.
+    ///
+    /// Note that a CSV reader skips empty lines, so when this is disabled,
+    /// records consisting of a single empty field are not read back at all.
+    /// When enabled, they're read back as a record with one empty field.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::WriterBuilder;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = WriterBuilder::new()
+    ///         .quote_empty(false)
+    ///         .from_writer(vec![]);
+    ///     wtr.write_record(&["a"])?;
+    ///     wtr.write_record(&[""])?;
+    ///     wtr.write_record(&["b"])?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "a\n\nb\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn quote_empty(&mut self, yes: bool) -> &mut WriterBuilder {
+        self.builder.quote_empty(yes);
+        self
+    }
+
+    /// The escape character to use when writing CSV.
+    ///
+    /// In some variants of CSV, quotes are escaped using a special escape
+    /// character like `\` (instead of escaping quotes by doubling them).
+    ///
+    /// By default, writing these idiosyncratic escapes is disabled, and is
+    /// only used when `double_quote` is disabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::WriterBuilder;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = WriterBuilder::new()
+    ///         .double_quote(false)
+    ///         .escape(b'$')
+    ///         .from_writer(vec![]);
+    ///     wtr.write_record(&["a", "foo\"bar", "c"])?;
+    ///     wtr.write_record(&["x", "y", "z"])?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "a,\"foo$\"bar\",c\nx,y,z\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn escape(&mut self, escape: u8) -> &mut WriterBuilder {
+        self.builder.escape(escape);
+        self
+    }
+
+    /// Configure this writer for the given dialect of CSV.
+    ///
+    /// This sets the delimiter, quote, escape, `double_quote`, quoting style
+    /// and record terminator as described by each `Dialect`. Since this only
+    /// sets the options, any of them can still be overridden by calling its
+    /// setter after this one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::{Dialect, WriterBuilder};
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = WriterBuilder::new()
+    ///         .dialect(Dialect::Unix)
+    ///         .from_writer(vec![]);
+    ///     wtr.write_record(&["a", "b\"c"])?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "\"a\",\"b\"\"c\"\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn dialect(&mut self, dialect: Dialect) -> &mut WriterBuilder {
+        let (term, style) = match dialect {
+            Dialect::Rfc4180 | Dialect::Excel => {
+                (Terminator::CRLF, QuoteStyle::Necessary)
+            }
+            Dialect::Unix => (Terminator::Any(b'\n'), QuoteStyle::Always),
+            Dialect::PostgresCopy => {
+                (Terminator::Any(b'\n'), QuoteStyle::Necessary)
+            }
+            _ => unreachable!(),
+        };
+        self.delimiter(b',')
+            .quote(b'"')
+            .escape(b'\\')
+            .double_quote(true)
+            .quote_style(style)
+            .terminator(term)
+    }
+
+    /// The strings to write for `bool` values when using Serde.
+    ///
+    /// By default, `bool` values are written as `true` and `false`. This
+    /// setting makes the Serde serializer write `true_str` and `false_str`
+    /// instead. Note that this only applies to the `serialize` method; it has
+    /// no effect on records written with `write_record` or `write_field`.
+    ///
+    /// The same strings may be given to `ReaderBuilder::bool_format` in order
+    /// to read them back into `bool` values.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    ///
+    /// use csv::WriterBuilder;
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Row<'a> {
+    ///     city: &'a str,
+    ///     capital: bool,
+    /// }
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = WriterBuilder::new()
+    ///         .bool_format("Y", "N")
+    ///         .from_writer(vec![]);
+    ///     wtr.serialize(Row { city: "Boston", capital: true })?;
+    ///     wtr.serialize(Row { city: "Concord", capital: false })?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "city,capital\nBoston,Y\nConcord,N\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn bool_format(
+        &mut self,
+        true_str: &str,
+        false_str: &str,
+    ) -> &mut WriterBuilder {
+        self.bool_format = Some((true_str.to_string(), false_str.to_string()));
+        self
+    }
+
+    /// The decimal separator to write in floating point numbers when using
+    /// Serde.
+    ///
+    /// By default, `f32` and `f64` values are written with `.` as their
+    /// decimal separator. This setting makes the Serde serializer write the
+    /// given byte instead, e.g., `,` for many European locales. Combined
+    /// with a delimiter other than `,`, such as `;`, this produces data in
+    /// the format expected by spreadsheets in those locales. As with any
+    /// other field, a number is only quoted if it contains the delimiter,
+    /// a quote or a terminator. Note that this only applies to the
+    /// `serialize` method; it has no effect on records written with
+    /// `write_record` or `write_field`.
+    ///
+    /// The `NumberFormat` given to `ReaderBuilder::number_format` can be
+    /// used to read such numbers back.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    ///
+    /// use csv::WriterBuilder;
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Row<'a> {
+    ///     city: &'a str,
+    ///     area: f64,
+    /// }
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = WriterBuilder::new()
+    ///         .delimiter(b';')
+    ///         .decimal_separator(b',')
+    ///         .from_writer(vec![]);
+    ///     wtr.serialize(Row { city: "Boston", area: 232.1 })?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "city;area\nBoston;232,1\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn decimal_separator(&mut self, separator: u8) -> &mut WriterBuilder {
+        self.decimal_separator = separator;
+        self
+    }
+
+    /// Format the numbers in each column consistently when serializing.
+    ///
+    /// The format at index `i` of `formats` applies to every number that
+    /// the Serde serializer writes as the field at index `i` of a record,
+    /// whether it's an integer or a floating point number. This is useful for
+    /// reports where, e.g., an amount must always have two decimal places,
+    /// even if it was computed as `5.0` or `4.999999`. The decimal places are
+    /// written with the separator set by `decimal_separator`.
+    ///
+    /// When formats are set, every record serialized must have exactly as
+    /// many fields as there are formats, and an `ErrorKind::UnequalLengths`
+    /// error is returned otherwise. Use `ColumnFormat::Default` for columns
+    /// that shouldn't be formatted. The header row, and records written with
+    /// `write_record` or `write_field`, aren't affected. By default, no
+    /// formats are set.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    ///
+    /// use csv::{ColumnFormat, WriterBuilder};
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Row<'a> {
+    ///     item: &'a str,
+    ///     quantity: u32,
+    ///     price: f64,
+    /// }
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = WriterBuilder::new()
+    ///         .column_formats(vec![
+    ///             ColumnFormat::Default,
+    ///             ColumnFormat::ZeroPad(3),
+    ///             ColumnFormat::Decimals(2),
+    ///         ])
+    ///         .from_writer(vec![]);
+    ///     wtr.serialize(Row { item: "pen", quantity: 12, price: 1.5 })?;
+    ///     wtr.serialize(Row { item: "ink", quantity: 3, price: 4.999 })?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "\
+    /// item,quantity,price
+    /// pen,012,1.50
+    /// ink,003,5.00
+    /// ");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn column_formats(
+        &mut self,
+        formats: Vec<ColumnFormat>,
+    ) -> &mut WriterBuilder {
+        self.column_formats = formats;
+        self
+    }
+
+    /// Omit the given struct fields when serializing.
+    ///
+    /// When a struct is written with `serialize`, any field whose name is
+    /// one of `names` is skipped, along with its entry in the header row. This
+    /// is a runtime alternative to `#[serde(skip)]`. It has no effect on
+    /// records written with `write_record` or `write_field`, or on values
+    /// without field names, such as tuples.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    ///
+    /// use csv::WriterBuilder;
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Row<'a> {
+    ///     city: &'a str,
+    ///     internal_id: u64,
+    ///     population: u64,
+    /// }
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = WriterBuilder::new()
+    ///         .skip_columns(&["internal_id"])
+    ///         .from_writer(vec![]);
+    ///     wtr.serialize(Row {
+    ///         city: "Boston",
+    ///         internal_id: 17,
+    ///         population: 4628910,
+    ///     })?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "city,population\nBoston,4628910\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn skip_columns<I, T>(&mut self, names: I) -> &mut WriterBuilder
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        self.skip_columns =
+            names.into_iter().map(|name| name.as_ref().to_string()).collect();
+        self
+    }
+
+    /// Set the capacity (in bytes) of the internal buffer used in the CSV
+    /// writer. This defaults to a reasonable setting.
+    pub fn buffer_capacity(&mut self, capacity: usize) -> &mut WriterBuilder {
+        self.capacity = capacity;
+        self
+    }
+}
+
+/// A already configured CSV writer.
+///
+/// A CSV writer takes as input Rust values and writes those values in a valid
+/// CSV format as output.
+///
+/// While CSV writing is considerably easier than parsing CSV, a proper writer
+/// will do a number of things for you:
+///
+/// 1. Quote fields when necessary.
+/// 2. Check that all records have the same number of fields.
+/// 3. Write records with a single empty field correctly.
+/// 4. Automatically serialize normal Rust types to CSV records. When that
+///    type is a struct, a header row is automatically written corresponding
+///    to the fields of that struct.
+/// 5. Use buffering intelligently and otherwise avoid allocation. (This means
+///    that callers should not do their own buffering.)
+///
+/// All of the above can be configured using a
+/// [`WriterBuilder`](struct.WriterBuilder.html).
+/// However, a `Writer` has a couple of convenience constructors (`from_path`
+/// and `from_writer`) that use the default configuration.
+///
+/// Note that the default configuration of a `Writer` uses `\n` for record
+/// terminators instead of `\r\n` as specified by RFC 4180. Use the
+/// `terminator` method on `WriterBuilder` to set the terminator to `\r\n` if
+/// it's desired.
+#[derive(Debug)]
+pub struct Writer<W: io::Write> {
+    core: CoreWriter,
+    wtr: Option<W>,
+    buf: Buffer,
+    state: WriterState,
+}
+
+#[derive(Debug)]
+struct WriterState {
+    /// Whether the Serde serializer should attempt to write a header row.
+    header: HeaderState,
+    /// Whether inconsistent record lengths are allowed.
+    flexible: bool,
+    /// The number of fields writtein in the first record. This is compared
+    /// with `fields_written` on all subsequent records to check for
+    /// inconsistent record lengths.
+    first_field_count: Option<u64>,
+    /// The number of fields written in this record. This is used to report
+    /// errors for inconsistent record lengths if `flexible` is disabled.
+    fields_written: u64,
+    /// This is set immediately before flushing the buffer and then unset
+    /// immediately after flushing the buffer. This avoids flushing the buffer
+    /// twice if the inner writer panics.
+    panicked: bool,
+    /// Whether the row being written is a header row, which doesn't count
+    /// toward `max_records`. This is only set while a header row is written
+    /// by `serialize` or by a method such as `set_header`, never for rows
+    /// written by `write_record`.
+    header_row_pending: bool,
+    /// Whether a field in the current record was written by
+    /// `write_field_raw`, bypassing the core writer.
+    raw_field_written: bool,
+    /// Whether a row has been written, including a header row.
+    wrote_row: bool,
+    /// Whether fields are passed to the core writer as is, because none of
+    /// the options that check, rewrite or pad them are enabled.
+    plain: bool,
+    /// The settings that are rarely changed from their defaults, if any of
+    /// them is.
+    options: Option<Box<WriterOptions>>,
+}
+
+/// The settings of a `Writer` that are rarely changed from their defaults,
+/// along with the state that only they need.
+///
+/// These are boxed and only allocated when one of them is set, so that the
+/// common configuration doesn't make `Writer` (and the errors that hold it)
+/// any larger.
+#[derive(Debug)]
+struct WriterOptions {
+    /// The strings written for `true` and `false` by the Serde serializer,
+    /// when they differ from the defaults.
+    bool_format: Option<(String, String)>,
+    /// The decimal separator written in floats by the Serde serializer.
+    decimal_separator: u8,
+    /// The format of the numbers in each column written by the Serde
+    /// serializer.
+    column_formats: Vec<ColumnFormat>,
+    /// The maximum number of records that may be written, if any.
+    max_records: Option<u64>,
+    /// Whether to reject fields that need quotes, since quoting is disabled.
+    never_quote_strict: bool,
+    /// Whether to reject fields that aren't valid UTF-8.
+    validate_utf8: bool,
+    /// The maximum length of a field, in bytes.
+    max_field_len: Option<usize>,
+    /// Whether fields and headers are trimmed of surrounding whitespace.
+    trim: Trim,
+    /// The names of struct fields that the Serde serializer omits.
+    skip_columns: Vec<String>,
+    /// The minimum width, in bytes, that each field is padded to.
+    min_field_width: usize,
+    /// The line ending that line endings inside fields are rewritten to.
+    field_newlines: Option<Terminator>,
+    /// Whether fields that look like formulas are prefixed with `'`.
+    sanitize_formulas: bool,
+    /// The header row written when serializing tuples.
+    tuple_header_style: TupleHeaderStyle,
+    /// The separator joining the names of nested struct fields in headers.
+    nested_header_sep: Option<char>,
+    /// Flush after each time this many more records have been written.
+    flush_every: Option<u64>,
+    /// The value of `records_written` when `flush_every` last flushed.
+    flushed_at: u64,
+    /// Whether consecutive empty records are written as a single one.
+    collapse_empty_records: bool,
+    /// The record last written by `write_record_dedup`.
+    last_record: Option<ByteRecord>,
+    /// The trailer yet to be written, which is reset to `None` once it has
+    /// been written.
+    trailer: TrailerKind,
+    /// The CRC-32 of the data flushed so far, if `trailer` needs it.
+    crc: u32,
+    /// The columns that serialized maps are written in, as set by
+    /// `write_header_from_map`.
+    map_columns: Option<ByteRecord>,
+    /// The bytes written before the first field of every record.
+    record_prefix: Vec<u8>,
+    /// The bytes written after the last field of every record.
+    record_suffix: Vec<u8>,
+    /// The delimiter written instead of the core writer's single byte
+    /// delimiter, or empty if there is none.
+    multi_delimiter: Vec<u8>,
+    /// The label of the row number column, if row numbers are written.
+    row_number_header: Option<String>,
+    /// Whether the byte offset and line number of records are written.
+    position_columns: bool,
+    /// The position of the record being written by `write_byte_record`,
+    /// if any.
+    record_position: Option<Position>,
+    /// The number of records written so far, not including a header row.
+    /// Only the options need this, so it is only counted when they are set.
+    records_written: u64,
+    /// Whether the last row written was an empty record.
+    wrote_empty_record: bool,
+    /// The number of fields in the header written by `set_header`, which
+    /// the first record written after it must have.
+    header_len: Option<u64>,
+    /// The header set by `set_header` that is yet to be written.
+    pending_header: Option<ByteRecord>,
+    /// The encoding that fields are transcoded to from UTF-8.
+    #[cfg(feature = "encoding")]
+    encoding: Encoding,
+}
+
+/// The options of a `Writer` that has none of them set.
+static DEFAULT_OPTIONS: WriterOptions = WriterOptions {
+    bool_format: None,
+    decimal_separator: b'.',
+    column_formats: Vec::new(),
+    max_records: None,
+    never_quote_strict: false,
+    validate_utf8: false,
+    max_field_len: None,
+    trim: Trim::None,
+    skip_columns: Vec::new(),
+    min_field_width: 0,
+    field_newlines: None,
+    sanitize_formulas: false,
+    tuple_header_style: TupleHeaderStyle::None,
+    nested_header_sep: None,
+    flush_every: None,
+    flushed_at: 0,
+    collapse_empty_records: false,
+    last_record: None,
+    trailer: TrailerKind::None,
+    crc: 0,
+    map_columns: None,
+    record_prefix: Vec::new(),
+    record_suffix: Vec::new(),
+    multi_delimiter: Vec::new(),
+    row_number_header: None,
+    position_columns: false,
+    record_position: None,
+    pending_header: None,
+    records_written: 0,
+    wrote_empty_record: false,
+    header_len: None,
+    #[cfg(feature = "encoding")]
+    encoding: Encoding::Utf8,
+};
+
+impl WriterOptions {
+    /// Returns true if every setting has its default value.
+    fn is_default(&self) -> bool {
+        let d = &DEFAULT_OPTIONS;
+        let same = self.bool_format == d.bool_format
+            && self.decimal_separator == d.decimal_separator
+            && self.column_formats == d.column_formats
+            && self.max_records == d.max_records
+            && self.never_quote_strict == d.never_quote_strict
+            && self.validate_utf8 == d.validate_utf8
+            && self.max_field_len == d.max_field_len
+            && self.trim == d.trim
+            && self.skip_columns == d.skip_columns
+            && self.min_field_width == d.min_field_width
+            && self.field_newlines.is_none()
+            && self.sanitize_formulas == d.sanitize_formulas
+            && self.tuple_header_style == d.tuple_header_style
+            && self.nested_header_sep == d.nested_header_sep
+            && self.flush_every == d.flush_every
+            && self.collapse_empty_records == d.collapse_empty_records
+            && self.trailer == d.trailer
+            && self.record_prefix == d.record_prefix
+            && self.record_suffix == d.record_suffix
+            && self.multi_delimiter == d.multi_delimiter
+            && self.row_number_header == d.row_number_header
+            && self.position_columns == d.position_columns;
+        #[cfg(feature = "encoding")]
+        let same = same && self.encoding == d.encoding;
+        same
+    }
+
+    /// Returns a copy of the default options, for a writer that needs to
+    /// keep state that only the options hold.
+    fn new() -> WriterOptions {
+        WriterOptions {
+            column_formats: Vec::new(),
+            skip_columns: Vec::new(),
+            record_prefix: Vec::new(),
+            record_suffix: Vec::new(),
+            multi_delimiter: Vec::new(),
+            bool_format: None,
+            last_record: None,
+            map_columns: None,
+            row_number_header: None,
+            record_position: None,
+            pending_header: None,
+            header_len: None,
+            ..DEFAULT_OPTIONS
+        }
+    }
+}
+
+/// Return an error if `field`, the field at index `field_index` of the
+/// current record, isn't valid UTF-8.
+fn check_utf8(field: &[u8], field_index: u64) -> Result<()> {
+    match str::from_utf8(field) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(Error::new(ErrorKind::Utf8 {
+            pos: None,
+            err: new_utf8_error(field_index as usize, err.valid_up_to()),
+        })),
+    }
+}
+
+/// Replace every `\r\n`, `\r` and `\n` in `field` with the given terminator.
+fn normalize_newlines(field: &[u8], term: Terminator) -> Vec<u8> {
+    let mut normalized = Vec::with_capacity(field.len());
+    let mut i = 0;
+    while i < field.len() {
+        match field[i] {
+            b'\r' | b'\n' => {
+                match term {
+                    Terminator::Any(b) => normalized.push(b),
+                    _ => normalized.extend_from_slice(b"\r\n"),
+                }
+                if field[i] == b'\r' && field.get(i + 1) == Some(&b'\n') {
+                    i += 1;
+                }
+            }
+            b => normalized.push(b),
+        }
+        i += 1;
+    }
+    normalized
+}
+
+/// Returns true if a spreadsheet may evaluate `field` as a formula.
+fn looks_like_formula(field: &[u8]) -> bool {
+    match field.first() {
+        Some(b'=') | Some(b'@') | Some(b'\t') | Some(b'\r') => true,
+        Some(b'+') | Some(b'-') => match str::from_utf8(field) {
+            Ok(s) => s.parse::<f64>().is_err(),
+            Err(_) => true,
+        },
+        _ => false,
+    }
+}
+
+/// HeaderState encodes a small state machine for handling header writes.
+#[derive(Debug)]
+enum HeaderState {
+    /// Indicates that we should attempt to write a header.
+    Write,
+    /// Indicates that writing a header was attempt, and a header was written.
+    DidWrite,
+    /// Indicates that writing a header was attempted, but no headers were
+    /// written or the attempt failed.
+    DidNotWrite,
+    /// This state is used when headers are disabled. It cannot transition
+    /// to any other state.
+    None,
+}
+
+/// A simple internal buffer for buffering writes.
+///
+/// We need this because the `csv_core` APIs want to write into a `&mut [u8]`,
+/// which is not available with the `std::io::BufWriter` API.
+#[derive(Debug)]
+struct Buffer {
+    /// The contents of the buffer.
+    buf: Vec<u8>,
+    /// The number of bytes written to the buffer.
+    len: usize,
+}
+
+impl<W: io::Write> Drop for Writer<W> {
+    fn drop(&mut self) {
+        if self.wtr.is_some() && !self.state.panicked {
+            let _ = self.write_trailer();
+            let _ = self.flush();
+        }
+    }
+}
+
+impl Writer<File> {
+    /// Build a CSV writer with a default configuration that writes data to the
+    /// given file path. The file is truncated if it already exists.
+    ///
+    /// If there was a problem opening the file at the given path, then this
+    /// returns the corresponding error.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::error::Error;
+    /// use csv::Writer;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = Writer::from_path("foo.csv")?;
+    ///     wtr.write_record(&["a", "b", "c"])?;
+    ///     wtr.write_record(&["x", "y", "z"])?;
+    ///     wtr.flush()?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Writer<File>> {
+        WriterBuilder::new().from_path(path)
+    }
+}
+
+impl<W: io::Write + io::Seek> Writer<W> {
+    /// Return the current byte offset of the underlying writer.
+    ///
+    /// Calling this before writing a record returns the byte offset at which
+    /// that record starts, which can be used to build an index of records
+    /// while writing them.
+    ///
+    /// Note that in order to report an accurate position, this must first
+    /// write the contents of the internal buffer to the underlying writer.
+    /// Calling this before every record therefore defeats the writer's
+    /// buffering, which may be costly when the underlying writer is not
+    /// itself buffered (such as a `File`).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use std::io;
+    /// use csv::Writer;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = Writer::from_writer(io::Cursor::new(vec![]));
+    ///     let mut offsets = vec![];
+    ///     for record in &[["a", "b"], ["foo", "bar"], ["x", "y"]] {
+    ///         offsets.push(wtr.position()?);
+    ///         wtr.write_record(record)?;
+    ///     }
+    ///     assert_eq!(offsets, vec![0, 4, 12]);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn position(&mut self) -> io::Result<u64> {
+        self.flush_buf()?;
+        self.wtr.as_mut().unwrap().stream_position()
+    }
+}
+
+impl Writer<Vec<u8>> {
+    /// Flush the contents of the internal buffer and return the data written
+    /// so far as a `String`.
+    ///
+    /// This returns an error if the data written is not valid UTF-8, which
+    /// can only happen if non-UTF-8 bytes were written via methods like
+    /// `write_byte_record` or `write_field`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Writer;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = Writer::from_writer(vec![]);
+    ///     wtr.write_record(&["a", "b", "c"])?;
+    ///     wtr.write_record(&["x", "y", "z"])?;
+    ///
+    ///     assert_eq!(wtr.into_string()?, "a,b,c\nx,y,z\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn into_string(self) -> result::Result<String, FromUtf8Error> {
+        // Flushing can only fail if the underlying writer fails, and writing
+        // to a `Vec<u8>` never fails.
+        let buf = match self.into_inner() {
+            Ok(buf) => buf,
+            Err(err) => panic!("writing to a Vec<u8> failed: {}", err.error()),
+        };
+        String::from_utf8(buf)
+    }
+
+    /// Serialize a single record with the given configuration, and return
+    /// the bytes that were written.
+    ///
+    /// This is independent of any existing writer: it is equivalent to
+    /// building a fresh writer from `builder`, calling `serialize` once and
+    /// taking the data written, including the record terminator. So if
+    /// `has_headers` is enabled on `builder` (which is the default), then a
+    /// header row is written before the record if one can be derived from
+    /// it. Disable `has_headers` to omit it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::{Writer, WriterBuilder};
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Row<'a> {
+    ///     city: &'a str,
+    ///     population: u64,
+    /// }
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let row = Row { city: "Boston", population: 4628910 };
+    ///
+    ///     let data = Writer::serialize_to_vec(&row, &WriterBuilder::new())?;
+    ///     assert_eq!(data, b"city,population\nBoston,4628910\n");
+    ///
+    ///     let mut builder = WriterBuilder::new();
+    ///     builder.has_headers(false);
+    ///     let data = Writer::serialize_to_vec(&row, &builder)?;
+    ///     assert_eq!(data, b"Boston,4628910\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn serialize_to_vec<S: Serialize>(
+        record: S,
+        builder: &WriterBuilder,
+    ) -> Result<Vec<u8>> {
+        let mut wtr = builder.from_writer(vec![]);
+        wtr.serialize(record)?;
+        // Flushing can only fail if the underlying writer fails, and writing
+        // to a `Vec<u8>` never fails.
+        match wtr.into_inner() {
+            Ok(buf) => Ok(buf),
+            Err(err) => panic!("writing to a Vec<u8> failed: {}", err.error()),
+        }
+    }
+}
+
+impl<W: io::Write> Writer<W> {
+    fn new(builder: &WriterBuilder, wtr: W) -> Writer<W> {
+        let buf = Buffer { buf: vec![0; builder.capacity], len: 0 };
+        Writer::with_buffer(builder, wtr, buf)
+    }
+
+    /// Like `new`, but with the given internal buffer.
+    fn with_buffer(builder: &WriterBuilder, wtr: W, buf: Buffer) -> Writer<W> {
+        let header_state = if builder.has_headers {
+            HeaderState::Write
+        } else {
+            HeaderState::None
+        };
+        let core = builder.builder.build();
+        let never_quote =
+            matches!(core.get_quote_style(), csv_core::QuoteStyle::Never);
+        let options = WriterOptions {
+            bool_format: builder.bool_format.clone(),
+            decimal_separator: builder.decimal_separator,
+            column_formats: builder.column_formats.clone(),
+            max_records: builder.max_records,
+            never_quote_strict: builder.never_quote_strict && never_quote,
+            validate_utf8: builder.validate_utf8,
+            max_field_len: builder.max_field_len,
+            trim: builder.trim,
+            skip_columns: builder.skip_columns.clone(),
+            min_field_width: builder.min_field_width,
+            field_newlines: builder.field_newlines,
+            sanitize_formulas: builder.sanitize_formulas,
+            tuple_header_style: builder.tuple_header_style,
+            nested_header_sep: builder.nested_header_sep,
+            flush_every: builder.flush_every,
+            flushed_at: 0,
+            collapse_empty_records: builder.collapse_empty_records,
+            last_record: None,
+            trailer: builder.trailer,
+            crc: 0,
+            map_columns: None,
+            record_prefix: builder.record_prefix.clone(),
+            record_suffix: builder.record_suffix.clone(),
+            multi_delimiter: builder.multi_delimiter.clone(),
+            row_number_header: if builder.row_numbers {
+                Some(builder.row_number_header.clone())
+            } else {
+                None
+            },
+            position_columns: builder.position_columns,
+            record_position: None,
+            pending_header: None,
+            records_written: 0,
+            wrote_empty_record: false,
+            header_len: None,
+            #[cfg(feature = "encoding")]
+            encoding: builder.encoding,
+        };
+        let mut wtr = Writer {
+            core,
+            wtr: Some(wtr),
+            buf,
+            state: WriterState {
+                header: header_state,
+                flexible: builder.flexible,
+                first_field_count: None,
+                fields_written: 0,
+                panicked: false,
+                header_row_pending: false,
+                raw_field_written: false,
+                wrote_row: false,
+                plain: false,
+                options: if options.is_default() {
+                    None
+                } else {
+                    Some(Box::new(options))
+                },
+            },
+        };
+        wtr.state.plain = wtr.writes_fields_as_is();
+        wtr
+    }
+
+    /// Build a CSV writer with a default configuration that writes data to
+    /// `wtr`.
+    ///
+    /// Note that the CSV writer is buffered automatically, so you should not
+    /// wrap `wtr` in a buffered writer like `io::BufWriter`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Writer;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = Writer::from_writer(vec![]);
+    ///     wtr.write_record(&["a", "b", "c"])?;
+    ///     wtr.write_record(&["x", "y", "z"])?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "a,b,c\nx,y,z\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn from_writer(wtr: W) -> Writer<W> {
+        WriterBuilder::new().from_writer(wtr)
+    }
+
+    /// Serialize a single record using Serde.
+    ///
+    /// # Example
+    ///
+    /// This shows how to serialize normal Rust structs as CSV records. The
+    /// fields of the struct are used to write a header row automatically.
+    /// (Writing the header row automatically can be disabled by building the
+    /// CSV writer with a [`WriterBuilder`](struct.WriterBuilder.html) and
+    /// calling the `has_headers` method.)
+    ///
+    /// ```
+    /// use std::error::Error;
+    ///
+    /// use csv::Writer;
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Row<'a> {
+    ///     city: &'a str,
+    ///     country: &'a str,
+    ///     // Serde allows us to name our headers exactly,
+    ///     // even if they don't match our struct field names.
+    ///     #[serde(rename = "popcount")]
+    ///     population: u64,
+    /// }
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = Writer::from_writer(vec![]);
+    ///     wtr.serialize(Row {
+    ///         city: "Boston",
+    ///         country: "United States",
+    ///         population: 4628910,
+    ///     })?;
+    ///     wtr.serialize(Row {
+    ///         city: "Concord",
+    ///         country: "United States",
+    ///         population: 42695,
+    ///     })?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "\
+    /// city,country,popcount
+    /// Boston,United States,4628910
+    /// Concord,United States,42695
+    /// ");
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Rules
+    ///
+    /// The behavior of `serialize` is fairly simple:
+    ///
+    /// 1. Nested containers (tuples, `Vec`s, structs, etc.) are always
+    ///    flattened (depth-first order).
+    ///
+    /// 2. If `has_headers` is `true` and the type contains field names, then
+    ///    a header row is automatically generated.
+    ///
+    /// However, some container types cannot be serialized, and if
+    /// `has_headers` is `true`, there are some additional restrictions on the
+    /// types that can be serialized. See below for details.
+    ///
+    /// For the purpose of this section, Rust types can be divided into three
+    /// categories: scalars, non-struct containers, and structs.
+    ///
+    /// ## Scalars
+    ///
+    /// Single values with no field names are written like the following. Note
+    /// that some of the outputs may be quoted, according to the selected
+    /// quoting style.
+    ///
+    /// | Name | Example Type | Example Value | Output |
+    /// | ---- | ---- | ---- | ---- |
+    /// | boolean | `bool` | `true` | `true` |
+    /// | integers | `i8`, `i16`, `i32`, `i64`, `i128`, `u8`, `u16`, `u32`, `u64`, `u128` | `5` | `5` |
+    /// | floats | `f32`, `f64` | `3.14` | `3.14` |
+    /// | character | `char` | `'☃'` | `☃` |
+    /// | string | `&str` | `"hi"` | `hi` |
+    /// | bytes | `&[u8]` | `b"hi"[..]` | `hi` |
+    /// | option | `Option` | `None` | *empty* |
+    /// | option |          | `Some(5)` | `5` |
+    /// | unit | `()` | `()` | *empty* |
+    /// | unit struct | `struct Foo;` | `Foo` | `Foo` |
+    /// | unit enum variant | `enum E { A, B }` | `E::A` | `A` |
+    /// | newtype struct | `struct Foo(u8);` | `Foo(5)` | `5` |
+    /// | newtype enum variant | `enum E { A(u8) }` | `E::A(5)` | `5` |
+    ///
+    /// Note that this table includes simple structs and enums. For example, to
+    /// serialize a field from either an integer or a float type, one can do
+    /// this:
+    ///
+    /// ```
+    /// use std::error::Error;
+    ///
+    /// use csv::Writer;
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Row {
+    ///     label: String,
+    ///     value: Value,
+    /// }
+    ///
+    /// #[derive(Serialize)]
+    /// enum Value {
+    ///     Integer(i64),
+    ///     Float(f64),
+    /// }
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = Writer::from_writer(vec![]);
+    ///     wtr.serialize(Row {
+    ///         label: "foo".to_string(),
+    ///         value: Value::Integer(3),
+    ///     })?;
+    ///     wtr.serialize(Row {
+    ///         label: "bar".to_string(),
+    ///         value: Value::Float(3.14),
+    ///     })?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "\
+    /// label,value
+    /// foo,3
+    /// bar,3.14
+    /// ");
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// ## Non-Struct Containers
+    ///
+    /// Nested containers are flattened to their scalar components, with the
+    /// exeption of a few types that are not allowed:
+    ///
+    /// | Name | Example Type | Example Value | Output |
+    /// | ---- | ---- | ---- | ---- |
+    /// | sequence | `Vec<u8>` | `vec![1, 2, 3]` | `1,2,3` |
+    /// | tuple | `(u8, bool)` | `(5, true)` | `5,true` |
+    /// | tuple struct | `Foo(u8, bool)` | `Foo(5, true)` | `5,true` |
+    /// | tuple enum variant | `enum E { A(u8, bool) }` | `E::A(5, true)` | *error* |
+    /// | struct enum variant | `enum E { V { a: u8, b: bool } }` | `E::V { a: 5, b: true }` | *error* |
+    /// | map | `BTreeMap<K, V>` | `BTreeMap::new()` | *error* |
+    ///
+    /// ## Structs
+    ///
+    /// Like the other containers, structs are flattened to their scalar
+    /// components:
+    ///
+    /// | Name | Example Type | Example Value | Output |
+    /// | ---- | ---- | ---- | ---- |
+    /// | struct | `struct Foo { a: u8, b: bool }` | `Foo { a: 5, b: true }` | `5,true` |
+    ///
+    /// If `has_headers` is `false`, then there are no additional restrictions;
+    /// types can be nested arbitrarily. For example:
+    ///
+    /// ```
+    /// use std::error::Error;
+    ///
+    /// use csv::WriterBuilder;
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Row {
+    ///     label: String,
+    ///     values: Vec<f64>,
+    /// }
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = WriterBuilder::new()
+    ///         .has_headers(false)
+    ///         .from_writer(vec![]);
+    ///     wtr.serialize(Row {
+    ///         label: "foo".to_string(),
+    ///         values: vec![1.1234, 2.5678, 3.14],
+    ///     })?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "\
+    /// foo,1.1234,2.5678,3.14
+    /// ");
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// However, if `has_headers` were enabled in the above example, then
+    /// serialization would return an error. Speficially, when `has_headers` is
+    /// `true`, there are two restrictions:
+    ///
+    /// 1. Named field values in structs must be scalars.
+    ///
+    /// 2. All scalars must be named field values in structs.
+    ///
+    /// Other than these two restrictions, types can be nested arbitrarily.
+    /// Here are a few examples:
+    ///
+    /// | Value | Header | Record |
+    /// | ---- | ---- | ---- |
+    /// | `(Foo { x: 5, y: 6 }, Bar { z: true })` | `x,y,z` | `5,6,true` |
+    /// | `vec![Foo { x: 5, y: 6 }, Foo { x: 7, y: 8 }]` | `x,y,x,y` | `5,6,7,8` |
+    /// | `(Foo { x: 5, y: 6 }, vec![Bar { z: Baz(true) }])` | `x,y,z` | `5,6,true` |
+    /// | `Foo { x: 5, y: (6, 7) }` | *error: restriction 1* | `5,6,7` |
+    /// | `(5, Foo { x: 6, y: 7 }` | *error: restriction 2* | `5,6,7` |
+    /// | `(Foo { x: 5, y: 6 }, true)` | *error: restriction 2* | `5,6,true` |
+    ///
+    /// ## Flattened maps
+    ///
+    /// A struct with a `#[serde(flatten)]` map field is written with the
+    /// map's entries as additional columns, in the position of the map field.
+    /// The keys of the first such struct serialized become the columns of
+    /// every later one, like the columns set by `write_header_from_map`: a
+    /// missing key is written as an empty field, and a key that isn't one of
+    /// the columns results in an error. Since the map's values are fields,
+    /// they must be scalars.
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use std::error::Error;
+    ///
+    /// use csv::Writer;
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// struct Row {
+    ///     city: &'static str,
+    ///     #[serde(flatten)]
+    ///     extra: BTreeMap<&'static str, u64>,
+    /// }
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut extra = BTreeMap::new();
+    ///     extra.insert("pop", 4628910);
+    ///     extra.insert("area", 232);
+    ///
+    ///     let mut wtr = Writer::from_writer(vec![]);
+    ///     wtr.serialize(Row { city: "Boston", extra })?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "city,area,pop\nBoston,232,4628910\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn serialize<S: Serialize>(&mut self, record: S) -> Result<()> {
+        self.serialize_header_for(&record)?;
+        self.write_pending_header()?;
+        self.check_record_limit()?;
+        serialize(self, &record)?;
+        self.check_column_formats()?;
+        self.write_terminator()?;
+        Ok(())
+    }
+
+    /// Write the header row for the type `S` without writing any records.
+    ///
+    /// The header is derived by serializing `S::default()` and keeping only
+    /// its field names, exactly as `serialize` would for the first record.
+    /// Subsequent calls to `serialize` then skip writing a header. This is
+    /// useful when records are produced lazily, since it permits emitting
+    /// the header before the first record is available.
+    ///
+    /// This does nothing if a header row has already been written by the
+    /// Serde serializer, if `serialize` has already been called, or if
+    /// `has_headers` is disabled. Like `serialize`, this only writes a
+    /// header when `S` is a struct.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    ///
+    /// use csv::Writer;
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Default, Serialize)]
+    /// struct Row<'a> {
+    ///     city: &'a str,
+    ///     population: u64,
+    /// }
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = Writer::from_writer(vec![]);
+    ///     wtr.write_headers::<Row>()?;
+    ///     wtr.serialize(Row { city: "Boston", population: 4628910 })?;
+    ///     wtr.serialize(Row { city: "Concord", population: 42695 })?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "\
+    /// city,population
+    /// Boston,4628910
+    /// Concord,42695
+    /// ");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn write_headers<S: Serialize + Default>(&mut self) -> Result<()> {
+        self.serialize_header_for(&S::default())
+    }
+
+    /// Write a header row from the keys of a map, and fix the column order of
+    /// maps serialized afterwards.
+    ///
+    /// Only the keys of `sample` are used; its values are not written. Once
+    /// this has been called, `serialize` accepts maps, and writes each map's
+    /// values in the order of these columns, regardless of the order in which
+    /// the map yields them. A column missing from a map is written as an
+    /// empty field, and a key that isn't one of the columns results in an
+    /// error. (Without calling this method, serializing a map is an error.)
+    ///
+    /// The header row is only written if `has_headers` is enabled and no
+    /// header row has been written yet. The column order is updated either
+    /// way.
+    ///
+    /// This returns an error if `sample` isn't a map, or if any of its keys
+    /// isn't a scalar.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use std::error::Error;
+    ///
+    /// use csv::Writer;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut sample = BTreeMap::new();
+    ///     sample.insert("pop", 0);
+    ///     sample.insert("city", 0);
+    ///
+    ///     let mut wtr = Writer::from_writer(vec![]);
+    ///     wtr.write_header_from_map(&sample)?;
+    ///
+    ///     let mut row = BTreeMap::new();
+    ///     row.insert("pop", "4628910");
+    ///     row.insert("city", "Boston");
+    ///     wtr.serialize(&row)?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "city,pop\nBoston,4628910\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn write_header_from_map<M: Serialize>(
+        &mut self,
+        sample: &M,
+    ) -> Result<()> {
+        let columns = serialize_map_keys(self, sample)?;
+        self.write_header_row(&columns)?;
+        self.options_mut().map_columns = Some(columns);
+        Ok(())
+    }
+
+    /// Write a single record given as pairs of column names and values.
+    ///
+    /// The first time this is called, the names of the pairs become the
+    /// columns of every record written by this method, in the order given.
+    /// If `has_headers` is enabled and no header row has been written yet,
+    /// then these names are also written as the header row. Afterwards, the
+    /// pairs may be given in any order, and each value is written in the
+    /// column of its name.
+    ///
+    /// Every call must give exactly one pair for each column. A missing
+    /// column, a name given more than once or a name that isn't one of the
+    /// columns is an error, and nothing is written. If `flexible` is enabled,
+    /// then these are accepted instead: a missing column is written as an
+    /// empty field, the last value of a repeated name is used and the value
+    /// of an unknown name is dropped.
+    ///
+    /// The columns are the same as those set by `write_header_from_map`, so
+    /// if that was called first, then its columns are used instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Writer;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = Writer::from_writer(vec![]);
+    ///     wtr.write_pairs(vec![("city", "Boston"), ("pop", "4628910")])?;
+    ///     wtr.write_pairs(vec![("pop", "42695"), ("city", "Concord")])?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "city,pop\nBoston,4628910\nConcord,42695\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn write_pairs<I, K, V>(&mut self, pairs: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let pairs: Vec<(K, V)> = pairs.into_iter().collect();
+        let columns = match self.options_mut().map_columns.take() {
+            Some(columns) => columns,
+            None => {
+                let columns: ByteRecord =
+                    pairs.iter().map(|(name, _)| name).collect();
+                self.write_header_row(&columns)?;
+                columns
+            }
+        };
+        let flexible = self.state.flexible;
+        let mut values: Vec<Option<&[u8]>> = vec![None; columns.len()];
+        let mut error = None;
+        for (name, value) in &pairs {
+            let name = name.as_ref();
+            match columns.iter().position(|column| column == name) {
+                Some(i) if values[i].is_some() && !flexible => {
+                    error = Some(format!(
+                        "map key {:?} occurs more than once",
+                        name.as_bstr()
+                    ));
+                    break;
+                }
+                Some(i) => values[i] = Some(value.as_ref()),
+                None if flexible => {}
+                None => {
+                    error = Some(format!(
+                        "map key {:?} is not in the header",
+                        name.as_bstr()
+                    ));
+                    break;
+                }
+            }
+        }
+        if error.is_none() && !flexible {
+            if let Some(i) = values.iter().position(Option::is_none) {
+                error = Some(format!(
+                    "map key {:?} is missing",
+                    columns[i].as_bstr()
+                ));
+            }
+        }
+        let result = match error {
+            Some(msg) => Err(Error::new(ErrorKind::Serialize(msg))),
+            None => self.write_record(values.iter().map(|v| v.unwrap_or(b""))),
+        };
+        self.options_mut().map_columns = Some(columns);
+        result
+    }
+
+    /// Write every entry of a map as a record with two fields, its key and
+    /// its value.
+    ///
+    /// This writes a vertical key/value table, with one row per entry, in
+    /// the order that `map` yields its entries. If `has_headers` is enabled
+    /// and no header row has been written yet, then a `key,value` header row
+    /// is written first. (To write a map as a single record instead, with a
+    /// column per key, use `write_pairs` or `serialize`.)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use std::error::Error;
+    ///
+    /// use csv::Writer;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut config = BTreeMap::new();
+    ///     config.insert("host", "localhost");
+    ///     config.insert("port", "8080");
+    ///
+    ///     let mut wtr = Writer::from_writer(vec![]);
+    ///     wtr.write_kv_table(&config)?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "key,value\nhost,localhost\nport,8080\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn write_kv_table<M, K, V>(&mut self, map: M) -> Result<()>
+    where
+        M: IntoIterator<Item = (K, V)>,
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        self.write_header_row(&ByteRecord::from(vec!["key", "value"]))?;
+        for (key, value) in map {
+            self.write_record([key.as_ref(), value.as_ref()])?;
+        }
+        Ok(())
+    }
+
+    /// Write a header row derived from `record` if one still needs to be
+    /// written.
+    fn serialize_header_for<S: Serialize>(
+        &mut self,
+        record: &S,
+    ) -> Result<()> {
+        if let HeaderState::Write = self.state.header {
+            self.state.header_row_pending = true;
+            let result = serialize_header(self, record).and_then(|wrote| {
+                if wrote {
+                    self.write_terminator()?;
+                }
+                Ok(wrote)
+            });
+            self.state.header_row_pending = false;
+            self.state.header = if result? {
+                HeaderState::DidWrite
+            } else {
+                HeaderState::DidNotWrite
+            };
+        }
+        Ok(())
+    }
+
+    /// Set a header row that is written before the first record.
+    ///
+    /// The header isn't written immediately. Instead, it is written when the
+    /// first record is written, so it is never written if no records are.
+    /// The first record must then have as many fields as the header, or else
+    /// an `UnequalLengths` error is returned when it is terminated, even if
+    /// `flexible` is enabled. The header counts as a header row for things
+    /// like `max_records`, regardless of `has_headers`, and `serialize` does
+    /// not write another header row after it.
+    ///
+    /// This returns an error if a row has already been written or started.
+    /// Calling this again before the first record is written replaces the
+    /// header.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Writer;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = Writer::from_writer(vec![]);
+    ///     wtr.set_header(&["city", "pop"])?;
+    ///     assert!(wtr.get_ref().is_empty());
+    ///
+    ///     wtr.write_record(&["Boston", "4628910"])?;
+    ///     wtr.write_record(&["Concord", "42695"])?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "city,pop\nBoston,4628910\nConcord,42695\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn set_header<I, T>(&mut self, header: I) -> Result<()>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        if self.state.wrote_row || self.state.fields_written > 0 {
+            return Err(Error::new(ErrorKind::HeaderAfterRecords));
+        }
+        self.options_mut().pending_header = Some(header.into_iter().collect());
+        self.state.header = HeaderState::DidWrite;
+        Ok(())
+    }
+
+    /// Write the header set by `set_header`, if it is yet to be written and
+    /// no field of the current record has been written.
+    fn write_pending_header(&mut self) -> Result<()> {
+        if self.state.fields_written > 0 {
+            return Ok(());
+        }
+        let header = match self.state.options {
+            None => return Ok(()),
+            Some(ref mut options) => match options.pending_header.take() {
+                None => return Ok(()),
+                Some(header) => header,
+            },
+        };
+        // The row number and position columns count toward the length.
+        let len = self.next_field_index() + header.len() as u64;
+        self.write_header_record(&header)?;
+        self.options_mut().header_len = Some(len);
+        Ok(())
+    }
+
+    /// Write a single record.
+    ///
+    /// This method accepts something that can be turned into an iterator that
+    /// yields elements that can be represented by a `&[u8]`.
+    ///
+    /// This may be called with an empty iterator, which will cause a record
+    /// terminator to be written. If no fields had been written, then a single
+    /// empty field is written before the terminator.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Writer;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = Writer::from_writer(vec![]);
+    ///     wtr.write_record(&["a", "b", "c"])?;
+    ///     wtr.write_record(&["x", "y", "z"])?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "a,b,c\nx,y,z\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn write_record<I, T>(&mut self, record: I) -> Result<()>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        if self.state.options.is_some() {
+            self.write_pending_header()?;
+            self.check_record_limit()?;
+        }
+        if self.state.plain {
+            for field in record.into_iter() {
+                self.write_field_impl(field)?;
+            }
+            return self.write_terminator();
+        }
+        // Every field is checked before any of them is written, so that a
+        // rejected record leaves nothing behind in the output.
+        let fields: Vec<T> = record.into_iter().collect();
+        let start = self.next_field_index();
+        let mut prepared = Vec::with_capacity(fields.len());
+        for (i, field) in fields.iter().enumerate() {
+            prepared.push(self.check_field(field.as_ref(), start + i as u64)?);
+        }
+        for field in &prepared {
+            self.write_prepared_field(field)?;
+        }
+        self.write_terminator()
+    }
+
+    /// Write a single record whose fields are produced on demand.
+    ///
+    /// This calls `field(i)` for every `i` in `0..count`, in order, and
+    /// writes each field as it is produced, followed by a record terminator.
+    /// This permits writing computed fields without first collecting them.
+    /// As with `write_record`, the record must have the same number of
+    /// fields as previous records unless `flexible` is enabled, and a record
+    /// with `count` set to `0` is written as a single empty field.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::borrow::Cow;
+    /// use std::error::Error;
+    /// use csv::Writer;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = Writer::from_writer(vec![]);
+    ///     let names = ["a", "b", "c"];
+    ///     wtr.write_record_with(3, |i| Cow::Borrowed(names[i].as_bytes()))?;
+    ///     wtr.write_record_with(3, |i| Cow::Owned((i * i).to_string().into_bytes()))?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "a,b,c\n0,1,4\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn write_record_with<'a, F>(
+        &mut self,
+        count: usize,
+        mut field: F,
+    ) -> Result<()>
+    where
+        F: FnMut(usize) -> Cow<'a, [u8]>,
+    {
+        self.write_pending_header()?;
+        self.check_record_limit()?;
+        for i in 0..count {
+            self.write_field_impl(field(i))?;
+        }
+        self.write_terminator()
+    }
+
+    /// Write a single record with `width` fields, of which only the given
+    /// ones are non-empty.
+    ///
+    /// Each pair in `fields` is the index of a field along with its value.
+    /// Every field whose index isn't in `fields` is written as an empty
+    /// field. This permits writing wide records that are mostly empty
+    /// without building the whole record first.
+    ///
+    /// The pairs must be sorted by index, without repeating an index, and
+    /// every index must be less than `width`. Otherwise, an error is
+    /// returned and nothing is written.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Writer;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = Writer::from_writer(vec![]);
+    ///     wtr.write_sparse_record(4, &[(0, b"a"), (2, b"c")])?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "a,,c,\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn write_sparse_record(
+        &mut self,
+        width: usize,
+        fields: &[(usize, &[u8])],
+    ) -> Result<()> {
+        for (n, &(i, _)) in fields.iter().enumerate() {
+            if i >= width {
+                return Err(Error::new(ErrorKind::Serialize(format!(
+                    "sparse field index {} is out of bounds for a record \
+                     with {} fields",
+                    i, width
+                ))));
+            }
+            if n > 0 && fields[n - 1].0 >= i {
+                return Err(Error::new(ErrorKind::Serialize(format!(
+                    "sparse field index {} follows index {}, but indices \
+                     must be strictly increasing",
+                    i,
+                    fields[n - 1].0
+                ))));
+            }
+        }
+        let mut rest = fields;
+        self.write_record_with(width, |i| match rest.first() {
+            Some(&(j, value)) if j == i => {
+                rest = &rest[1..];
+                Cow::Borrowed(value)
+            }
+            _ => Cow::Borrowed(&b""[..]),
+        })
+    }
+
+    /// Write an empty record, i.e., just a record terminator.
+    ///
+    /// This is useful for deliberately writing a blank line, e.g., to
+    /// separate sections of a report. Unlike writing a record with no
+    /// fields via `write_record`, which writes a single empty field, only
+    /// the terminator is written, without a record prefix or suffix. The
+    /// empty record doesn't count as a record for any other purpose, so it
+    /// doesn't trip the check for records of unequal length, and doesn't
+    /// count toward `max_records`. If a record is in progress, then it is
+    /// terminated first.
+    ///
+    /// Note that the CSV readers in this crate skip blank lines, so they
+    /// don't read an empty record at all. Other CSV parsers may read it as a
+    /// record with a single empty field.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Writer;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = Writer::from_writer(vec![]);
+    ///     wtr.write_record(&["a", "b"])?;
+    ///     wtr.write_empty_record()?;
+    ///     wtr.write_record(&["c", "d"])?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "a,b\n\nc,d\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn write_empty_record(&mut self) -> Result<()> {
+        if self.state.fields_written > 0 {
+            self.write_terminator()?;
+        }
+        if self.options().collapse_empty_records {
+            if self.options().wrote_empty_record {
+                return Ok(());
+            }
+            self.options_mut().wrote_empty_record = true;
+        }
+        match self.core.get_terminator() {
+            csv_core::Terminator::CRLF => self.write_raw(b"\r\n"),
+            csv_core::Terminator::Any(b) => self.write_raw(&[b]),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Write a single record without a trailing record terminator.
+    ///
+    /// This is like `write_record`, except that once the last field (and its
+    /// closing quote, if any) is written, nothing else follows it. This is
+    /// useful when a single record is embedded in some larger format, such as
+    /// a line of a log message or a value in another protocol.
+    ///
+    /// Since no terminator is written, the end of the record can't be found
+    /// from the CSV data alone. Reading such output back requires knowing
+    /// where the record ends by some other means. Similarly, writing another
+    /// record after this one produces data in which the two records run
+    /// together.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Writer;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = Writer::from_writer(vec![]);
+    ///     wtr.write_record_no_terminator(&["a", "b", "c d"])?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "a,b,c d");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn write_record_no_terminator<I, T>(&mut self, record: I) -> Result<()>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        self.write_pending_header()?;
+        self.check_record_limit()?;
+        for field in record.into_iter() {
+            self.write_field_impl(field)?;
+        }
+        self.check_field_count()?;
+        if self.state.fields_written == 0 {
+            self.write_record_prefix()?;
+        }
+        self.write_finish()?;
+        self.write_record_suffix()?;
+        self.state.fields_written = 0;
+        self.state.raw_field_written = false;
+        self.count_record();
+        self.flush_if_due()
+    }
+
+    /// Write a single `ByteRecord`.
+    ///
+    /// This method accepts a borrowed `ByteRecord` and writes its contents
+    /// to the underlying writer.
+    ///
+    /// This is similar to `write_record` except that it specifically requires
+    /// a `ByteRecord`. This permits the writer to possibly write the record
+    /// more quickly than the more generic `write_record`.
+    ///
+    /// This may be called with an empty record, which will cause a record
+    /// terminator to be written. If no fields had been written, then a single
+    /// empty field is written before the terminator.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::{ByteRecord, Writer};
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = Writer::from_writer(vec![]);
+    ///     wtr.write_byte_record(&ByteRecord::from(&["a", "b", "c"][..]))?;
+    ///     wtr.write_byte_record(&ByteRecord::from(&["x", "y", "z"][..]))?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "a,b,c\nx,y,z\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    #[inline(never)]
+    pub fn write_byte_record(&mut self, record: &ByteRecord) -> Result<()> {
+        if self.state.options.is_some() {
+            return self.write_byte_record_with_options(record);
+        }
+        self.write_byte_record_as_is(record)
+    }
+
+    /// Like `write_byte_record`, but for a writer with some of its rarely
+    /// used settings set, which are checked before the record is written.
+    fn write_byte_record_with_options(
+        &mut self,
+        record: &ByteRecord,
+    ) -> Result<()> {
+        self.write_pending_header()?;
+        self.check_record_limit()?;
+        if self.options().max_field_len.is_some() {
+            for (i, field) in record.iter().enumerate() {
+                self.check_field_len(field, i as u64)?;
+            }
+        }
+        // Padding or rewriting fields is rare enough that it isn't worth
+        // handling in `write_byte_record_as_is`.
+        if self.options().min_field_width > 0
+            || self.rewrites_fields()
+            || self.has_record_affixes()
+            || !self.options().multi_delimiter.is_empty()
+            || self.has_leading_fields()
+        {
+            return self.write_record_at(record);
+        }
+        if self.options().never_quote_strict {
+            for (i, field) in record.iter().enumerate() {
+                self.check_needs_quoting(field, i as u64)?;
+            }
+        }
+        if self.options().validate_utf8 {
+            for (i, field) in record.iter().enumerate() {
+                check_utf8(field, i as u64)?;
+            }
+        }
+        self.write_byte_record_as_is(record)
+    }
+
+    /// Write a `ByteRecord` whose fields need no checks or rewriting.
+    #[inline(always)]
+    fn write_byte_record_as_is(&mut self, record: &ByteRecord) -> Result<()> {
+        if record.as_slice().is_empty() {
+            return self.write_record_at(record);
+        }
+        // The idea here is to find a fast path for shuffling our record into
+        // our buffer as quickly as possible. We do this because the underlying
+        // "core" CSV writer does a lot of book-keeping to maintain its state
+        // oriented API.
+        //
+        // The fast path occurs when we know our record will fit in whatever
+        // space we have left in our buffer. We can actually quickly compute
+        // the upper bound on the space required:
+        let upper_bound =
+            // The data itself plus the worst case: every byte is a quote.
+            (2 * record.as_slice().len())
+            // The number of field delimiters.
+            + (record.len().saturating_sub(1))
+            // The maximum number of quotes inserted around each field.
+            + (2 * record.len())
+            // The maximum number of bytes for the terminator.
+            + 2;
+        if self.buf.writable().len() < upper_bound {
+            return self.write_record_at(record);
+        }
+        let mut first = true;
+        for field in record.iter() {
+            if !first {
+                self.buf.writable()[0] = self.core.get_delimiter();
+                self.buf.written(1);
+            }
+            first = false;
+
+            if !self.core.should_quote(field) {
+                self.buf.writable()[..field.len()].copy_from_slice(field);
+                self.buf.written(field.len());
+            } else {
+                self.buf.writable()[0] = self.core.get_quote();
+                self.buf.written(1);
+                let (res, nin, nout) = csv_core::quote(
+                    field,
+                    self.buf.writable(),
+                    self.core.get_quote(),
+                    self.core.get_escape(),
+                    self.core.get_double_quote(),
+                );
+                debug_assert!(res == WriteResult::InputEmpty);
+                debug_assert!(nin == field.len());
+                self.buf.written(nout);
+                self.buf.writable()[0] = self.core.get_quote();
+                self.buf.written(1);
+            }
+        }
+        self.state.fields_written = record.len() as u64;
+        self.write_terminator_into_buffer()
+    }
+
+    /// Write a single `ByteRecord`, unless it is equal to the record
+    /// previously written by this method.
+    ///
+    /// This returns `true` if the record was written and `false` if it was
+    /// skipped as a duplicate. Only consecutive duplicates are skipped, so
+    /// this collapses runs of identical records into one, like the Unix
+    /// `uniq` command. Records are compared field by field, ignoring their
+    /// positions. A copy of the last record written is kept for the
+    /// comparison. Records written by any other method are not taken into
+    /// account.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::{ByteRecord, Writer};
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = Writer::from_writer(vec![]);
+    ///     let a = ByteRecord::from(vec!["a", "1"]);
+    ///     let b = ByteRecord::from(vec!["b", "2"]);
+    ///     assert!(wtr.write_record_dedup(&a)?);
+    ///     assert!(!wtr.write_record_dedup(&a)?);
+    ///     assert!(wtr.write_record_dedup(&b)?);
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "a,1\nb,2\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn write_record_dedup(&mut self, record: &ByteRecord) -> Result<bool> {
+        if self.options().last_record.as_ref() == Some(record) {
+            return Ok(false);
+        }
+        self.write_byte_record(record)?;
+        match self.options_mut().last_record {
+            Some(ref mut last) => last.clone_from(record),
+            ref mut last @ None => *last = Some(record.clone()),
+        }
+        Ok(true)
+    }
+
+    /// Write a single `ByteRecord`, quoting exactly the fields flagged in
+    /// `quoted`.
+    ///
+    /// The field at index `i` is always quoted when `quoted[i]` is `true`,
+    /// even if this writer's quoting style wouldn't quote it. Every other
+    /// field, including any field beyond the end of `quoted`, is quoted
+    /// according to this writer's quoting style, so that fields requiring
+    /// quotes are still written correctly.
+    ///
+    /// This is useful when reformatting CSV data while keeping the quoting of
+    /// the source intact, where `quoted` records which fields of the source
+    /// record were enclosed in quotes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::{ByteRecord, Writer};
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = Writer::from_writer(vec![]);
+    ///     let record = ByteRecord::from(vec!["a", "b", "c,d"]);
+    ///     wtr.write_byte_record_preserve_quotes(&record, &[false, true])?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "a,\"b\",\"c,d\"\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn write_byte_record_preserve_quotes(
+        &mut self,
+        record: &ByteRecord,
+        quoted: &[bool],
+    ) -> Result<()> {
+        self.write_pending_header()?;
+        self.check_record_limit()?;
+        if record.as_slice().is_empty() {
+            return self.write_record_at(record);
+        }
+        // This bypasses the core writer entirely, since it decides on its own
+        // whether a field is quoted. Its state is left at the start of a
+        // record, which is where it is once this returns.
+        //
+        // Every field is checked before any of them is written, so that a
+        // rejected record leaves nothing behind in the output.
+        let mut fields = Vec::with_capacity(record.len());
+        for (i, field) in record.iter().enumerate() {
+            let field = self.prepare_field(field, i as u64)?;
+            let force = self.core.get_quoting()
+                && quoted.get(i).cloned().unwrap_or(false);
+            if !force && self.options().never_quote_strict {
+                self.check_needs_quoting(&field, i as u64)?;
+            }
+            fields.push((field, force));
+        }
+        let quote = self.core.get_quote();
+        if self.options().position_columns {
+            self.options_mut().record_position = record.position().cloned();
+        }
+        let leading = self.write_leading_fields();
+        if self.options().position_columns {
+            self.options_mut().record_position = None;
+        }
+        let leading = leading?;
+        if leading == 0 {
+            self.write_record_prefix()?;
+        }
+        for (i, (field, force)) in fields.iter().enumerate() {
+            let (field, force) = (&**field, *force);
+            if i > 0 || leading > 0 {
+                self.write_raw_delimiter()?;
+            }
+            let padding =
+                self.options().min_field_width.saturating_sub(field.len());
+            if force || self.core.should_quote(field) {
+                self.write_raw(&[quote])?;
+                self.write_quoted(field)?;
+                self.write_spaces(padding)?;
+                self.write_raw(&[quote])?;
+            } else {
+                self.write_raw(field)?;
+                self.write_spaces(padding)?;
+            }
+        }
+        self.write_record_suffix()?;
+        self.state.fields_written = record.len() as u64 + leading;
+        self.state.raw_field_written = false;
+        if self.buf.writable().len() < 2 {
+            self.flush_buf()?;
+        }
+        self.write_terminator_into_buffer()
+    }
+
+    /// Write a single field.
+    ///
+    /// One should prefer using `write_record` over this method. It is provided
+    /// for cases where writing a field at a time is more convenient than
+    /// writing a record at a time.
+    ///
+    /// Note that if this API is used, `write_record` should be called with an
+    /// empty iterator to write a record terminator.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Writer;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = Writer::from_writer(vec![]);
+    ///     wtr.write_field("a")?;
+    ///     wtr.write_field("b")?;
+    ///     wtr.write_field("c")?;
+    ///     wtr.write_record(None::<&[u8]>)?;
+    ///     wtr.write_field("x")?;
+    ///     wtr.write_field("y")?;
+    ///     wtr.write_field("z")?;
+    ///     wtr.write_record(None::<&[u8]>)?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "a,b,c\nx,y,z\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn write_field<T: AsRef<[u8]>>(&mut self, field: T) -> Result<()> {
+        self.write_pending_header()?;
+        self.check_record_limit()?;
+        self.write_field_impl(field)
+    }
+
+    /// Write a single field whose bytes are written exactly as given.
+    ///
+    /// This is like `write_field`, except that the field is neither quoted
+    /// nor escaped, which is useful when the field is already quoted, e.g.,
+    /// because it was produced by a template. A delimiter is still written
+    /// before the field if it isn't the first field of the record. The field
+    /// is also exempt from other settings that rewrite fields, such as
+    /// `min_field_width` and `field_newlines`.
+    ///
+    /// The bytes must form a valid CSV field by themselves. Otherwise, the
+    /// data written is corrupt: for example, an unquoted field containing the
+    /// delimiter is read back as more than one field, and a field with an
+    /// unbalanced quote can swallow the records that follow it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Writer;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = Writer::from_writer(vec![]);
+    ///     wtr.write_field("a,b")?;
+    ///     wtr.write_field_raw(b"\"c,d\"")?;
+    ///     wtr.write_record(None::<&[u8]>)?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "\"a,b\",\"c,d\"\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn write_field_raw(&mut self, bytes: &[u8]) -> Result<()> {
+        self.write_pending_header()?;
+        self.check_record_limit()?;
+        if self.options().validate_utf8 {
+            check_utf8(bytes, self.state.fields_written)?;
+        }
+        self.write_leading_fields()?;
+        if self.state.fields_written > 0 {
+            self.write_delimiter()?;
+        } else {
+            self.write_record_prefix()?;
+        }
+        self.write_raw(bytes)?;
+        self.state.raw_field_written = true;
+        self.state.fields_written += 1;
+        Ok(())
+    }
+
+    /// Serialize a single scalar value as one field.
+    ///
+    /// The value is formatted the same way as a field of a record written by
+    /// `serialize`, e.g., numbers are written in their shortest form and
+    /// `None` is written as an empty field. Like `write_field`, this
+    /// neither writes a header row nor a record terminator, so it can be
+    /// mixed freely with `write_field` to build up a record.
+    ///
+    /// If the value is a container, such as a struct, a map or a sequence,
+    /// then an error is returned and nothing is written.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::Writer;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = Writer::from_writer(vec![]);
+    ///     wtr.write_field("a")?;
+    ///     wtr.serialize_field(5)?;
+    ///     wtr.serialize_field(Some(1.5))?;
+    ///     wtr.serialize_field(None::<u8>)?;
+    ///     wtr.write_record(None::<&[u8]>)?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "a,5,1.5,\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn serialize_field<S: Serialize>(&mut self, value: S) -> Result<()> {
+        let field = serialize_field(self, value)?;
+        self.write_field(field)
+    }
+
+    /// Write a single `bool` field using the configured `bool_format`.
+    ///
+    /// This is used by the Serde serializer.
+    pub(crate) fn write_bool(&mut self, v: bool) -> Result<()> {
+        // Take the format out of the state so that we can borrow its strings
+        // while writing the field.
+        let format = match self.state.options {
+            None => None,
+            Some(ref mut options) => options.bool_format.take(),
+        };
+        let res = match format {
+            None => self.write_field(if v { "true" } else { "false" }),
+            Some((ref yes, ref no)) => {
+                self.write_field(if v { yes } else { no })
+            }
+        };
+        if let Some(ref mut options) = self.state.options {
+            options.bool_format = format;
+        }
+        res
+    }
+
+    /// Returns the string that the Serde serializer writes for `v`.
+    pub(crate) fn bool_str(&self, v: bool) -> &str {
+        match self.options().bool_format {
+            None => {
+                if v {
+                    "true"
+                } else {
+                    "false"
+                }
+            }
+            Some((ref yes, ref no)) => {
+                if v {
+                    yes
+                } else {
+                    no
+                }
+            }
+        }
+    }
+
+    /// Returns the given formatted float with the configured decimal
+    /// separator.
+    ///
+    /// This is used by the Serde serializer.
+    pub(crate) fn float_field<'s>(&self, float: &'s str) -> Cow<'s, [u8]> {
+        let sep = self.options().decimal_separator;
+        if sep == b'.' {
+            return Cow::Borrowed(float.as_bytes());
+        }
+        let point = |&b: &u8| if b == b'.' { sep } else { b };
+        Cow::Owned(float.as_bytes().iter().map(point).collect())
+    }
+
+    /// Format the given serialized number as required by the column that is
+    /// written next.
+    ///
+    /// This is used by the Serde serializer.
+    pub(crate) fn number_field<'s>(&self, number: &'s str) -> Cow<'s, str> {
+        match self.options().column_formats.get(self.current_column()) {
+            Some(format) => format.apply(number),
+            None => Cow::Borrowed(number),
+        }
+    }
+
+    /// Returns the index of the field of the current record that is written
+    /// next, not counting row numbers and position columns.
+    fn current_column(&self) -> usize {
+        let mut written = self.state.fields_written;
+        if written > 0 {
+            written -= self.options().row_number_header.is_some() as u64;
+            written -= 2 * self.options().position_columns as u64;
+        }
+        written as usize
+    }
+
+    /// Return an error if `column_formats` is set and the record serialized
+    /// doesn't have a field for each format.
+    fn check_column_formats(&self) -> Result<()> {
+        let expected = self.options().column_formats.len() as u64;
+        let len = self.current_column() as u64;
+        if expected == 0 || len == expected {
+            return Ok(());
+        }
+        Err(Error::new(ErrorKind::UnequalLengths {
+            pos: None,
+            expected_len: expected,
+            len,
+        }))
+    }
+
+    /// Returns the columns that serialized maps are written in, if they have
+    /// been set by `write_header_from_map` or by serializing a struct with
+    /// flattened fields.
+    pub(crate) fn map_columns(&self) -> Option<&ByteRecord> {
+        self.options().map_columns.as_ref()
+    }
+
+    /// Fix the columns that serialized maps are written in.
+    pub(crate) fn set_map_columns(&mut self, columns: ByteRecord) {
+        self.options_mut().map_columns = Some(columns);
+    }
+
+    /// Write `headers` as the header row if `has_headers` is enabled and no
+    /// header row has been written yet.
+    pub(crate) fn write_header_row(
+        &mut self,
+        headers: &ByteRecord,
+    ) -> Result<()> {
+        if let HeaderState::Write = self.state.header {
+            self.write_header_record(headers)?;
+            self.state.header = HeaderState::DidWrite;
+        }
+        Ok(())
+    }
+
+    /// Write `header` as a header row, which options such as `max_records`
+    /// and `with_row_numbers` don't treat as a record.
+    fn write_header_record(&mut self, header: &ByteRecord) -> Result<()> {
+        self.state.header_row_pending = true;
+        let result = self.write_record(header);
+        self.state.header_row_pending = false;
+        result
+    }
+
+    /// Returns the header row style for serializing tuples.
+    pub(crate) fn tuple_header_style(&self) -> TupleHeaderStyle {
+        self.options().tuple_header_style
+    }
+
+    /// Returns the separator joining the names of nested struct fields in
+    /// headers written by the Serde serializer, if any.
+    pub(crate) fn nested_header_sep(&self) -> Option<char> {
+        self.options().nested_header_sep
+    }
+
+    /// Returns true if the Serde serializer should omit the struct field
+    /// with the given name.
+    pub(crate) fn skips_column(&self, name: &str) -> bool {
+        self.options().skip_columns.iter().any(|skip| skip == name)
+    }
+
+    /// Implementation of write_field.
+    ///
+    /// This is a separate method so we can force the compiler to inline it
+    /// into write_record.
+    #[inline(always)]
+    fn write_field_impl<T: AsRef<[u8]>>(&mut self, field: T) -> Result<()> {
+        if self.state.plain {
+            if self.state.fields_written > 0 {
+                self.write_delimiter()?;
+            }
+            self.write_field_data(field.as_ref())?;
+            self.state.fields_written += 1;
+            return Ok(());
+        }
+        let field =
+            self.check_field(field.as_ref(), self.next_field_index())?;
+        self.write_prepared_field(&field)
+    }
+
+    /// Prepare the field at index `field_index` of the current record with
+    /// `prepare_field`, and return an error if it can't be written.
+    fn check_field<'f>(
+        &self,
+        field: &'f [u8],
+        field_index: u64,
+    ) -> Result<Cow<'f, [u8]>> {
+        let field = self.prepare_field(field, field_index)?;
+        if self.options().never_quote_strict {
+            self.check_needs_quoting(&field, field_index)?;
+        }
+        Ok(field)
+    }
+
+    /// Returns the index that the next field written gets in the current
+    /// record, counting the row number and position columns that are
+    /// written before the first field.
+    fn next_field_index(&self) -> u64 {
+        if self.state.fields_written > 0 {
+            return self.state.fields_written;
+        }
+        let mut leading = 0;
+        if self.options().row_number_header.is_some() {
+            leading += 1;
+        }
+        if self.options().position_columns {
+            leading += 2;
+        }
+        leading
+    }
+
+    /// Write a field that was returned by `check_field`, along with the
+    /// delimiter, record prefix, leading columns and padding that go with it.
+    fn write_prepared_field(&mut self, field: &[u8]) -> Result<()> {
+        if self.has_leading_fields() {
+            self.write_leading_fields()?;
+        }
+        if self.state.fields_written > 0 {
+            self.write_delimiter()?;
+        } else if !self.options().record_prefix.is_empty() {
+            self.write_record_prefix()?;
+        }
+        let mut padding =
+            self.options().min_field_width.saturating_sub(field.len());
+        self.write_field_data(field)?;
+        // The core writer decides whether to quote a field when it sees the
+        // first chunk of it, so the padding is quoted along with the field.
+        while padding > 0 {
+            let n = cmp::min(padding, SPACES.len());
+            self.write_field_data(&SPACES[..n])?;
+            padding -= n;
+        }
+        self.state.fields_written += 1;
+        Ok(())
+    }
+
+    /// Write the given bytes to the buffer as is, bypassing the core writer.
+    fn write_raw(&mut self, mut data: &[u8]) -> Result<()> {
+        while !data.is_empty() {
+            if self.buf.writable().is_empty() {
+                self.flush_buf()?;
+            }
+            let n = cmp::min(data.len(), self.buf.writable().len());
+            self.buf.writable()[..n].copy_from_slice(&data[..n]);
+            self.buf.written(n);
+            data = &data[n..];
+        }
+        Ok(())
+    }
+
+    /// Write the given bytes to the buffer with quotes escaped, bypassing the
+    /// core writer. This does not write the enclosing quotes.
+    fn write_quoted(&mut self, mut data: &[u8]) -> Result<()> {
+        loop {
+            let (res, nin, nout) = csv_core::quote(
+                data,
+                self.buf.writable(),
+                self.core.get_quote(),
+                self.core.get_escape(),
+                self.core.get_double_quote(),
+            );
+            data = &data[nin..];
+            self.buf.written(nout);
+            match res {
+                WriteResult::InputEmpty => return Ok(()),
+                WriteResult::OutputFull => self.flush_buf()?,
+            }
+        }
+    }
+
+    /// Write `n` spaces to the buffer, bypassing the core writer.
+    fn write_spaces(&mut self, mut n: usize) -> Result<()> {
+        while n > 0 {
+            let len = cmp::min(n, SPACES.len());
+            self.write_raw(&SPACES[..len])?;
+            n -= len;
+        }
+        Ok(())
+    }
+
+    /// Rewrite the field at index `field_index` of the current record as
+    /// required by `trim_fields`, `normalize_field_newlines`,
+    /// `sanitize_formulas` and `encoding`.
+    ///
+    /// This also checks that the field is valid UTF-8 if `validate_utf8` is
+    /// enabled.
+    #[cfg_attr(not(feature = "encoding"), allow(unused_variables))]
+    fn prepare_field<'f>(
+        &self,
+        field: &'f [u8],
+        field_index: u64,
+    ) -> Result<Cow<'f, [u8]>> {
+        if self.options().validate_utf8 {
+            check_utf8(field, field_index)?;
+        }
+        let trim = if self.state.header_row_pending {
+            self.options().trim.should_trim_headers()
+        } else {
+            self.options().trim.should_trim_fields()
+        };
+        let mut field = Cow::Borrowed(if trim { field.trim() } else { field });
+        if let Some(term) = self.options().field_newlines {
+            if field.iter().any(|&b| b == b'\r' || b == b'\n') {
+                field = Cow::Owned(normalize_newlines(&field, term));
+            }
+        }
+        if self.options().sanitize_formulas && looks_like_formula(&field) {
+            let mut sanitized = Vec::with_capacity(field.len() + 1);
+            sanitized.push(b'\'');
+            sanitized.extend_from_slice(&field);
+            field = Cow::Owned(sanitized);
+        }
+        #[cfg(feature = "encoding")]
+        {
+            if self.options().encoding == Encoding::Latin1 && !field.is_ascii()
+            {
+                let encoded = encoding::utf8_to_latin1(&field, field_index)?;
+                field = Cow::Owned(encoded);
+            }
+        }
+        self.check_field_len(&field, field_index)?;
+        Ok(field)
+    }
+
+    /// Returns true if fields may be rewritten before they're written.
+    #[inline]
+    fn rewrites_fields(&self) -> bool {
+        #[cfg(feature = "encoding")]
+        {
+            if self.options().encoding != Encoding::Utf8 {
+                return true;
+            }
+        }
+        self.options().field_newlines.is_some()
+            || self.options().trim != Trim::None
+            || self.options().sanitize_formulas
+    }
+
+    /// Returns the rarely used settings of this writer.
+    #[inline]
+    fn options(&self) -> &WriterOptions {
+        self.state.options.as_deref().unwrap_or(&DEFAULT_OPTIONS)
+    }
+
+    /// Returns the rarely used settings of this writer, allocating them if
+    /// none of them is set so that their state can be kept.
+    fn options_mut(&mut self) -> &mut WriterOptions {
+        self.state
+            .options
+            .get_or_insert_with(|| Box::new(WriterOptions::new()))
+    }
+
+    /// Returns true if fields can be given to the core writer as is, without
+    /// checking, rewriting or padding them first.
+    fn writes_fields_as_is(&self) -> bool {
+        !self.rewrites_fields()
+            && !self.options().validate_utf8
+            && self.options().max_field_len.is_none()
+            && !self.options().never_quote_strict
+            && self.options().min_field_width == 0
+            && self.options().record_prefix.is_empty()
+            && self.options().multi_delimiter.is_empty()
+            && !self.has_leading_fields()
+    }
+
+    /// Write a chunk of the current field's data to the buffer.
+    fn write_field_data(&mut self, mut data: &[u8]) -> Result<()> {
+        loop {
+            let (res, nin, nout) = self.core.field(data, self.buf.writable());
+            data = &data[nin..];
+            self.buf.written(nout);
+            match res {
+                WriteResult::InputEmpty => return Ok(()),
+                WriteResult::OutputFull => self.flush_buf()?,
+            }
+        }
+    }
+
+    /// Flush the contents of the internal buffer to the underlying writer.
+    ///
+    /// If there was a problem writing to the underlying writer, then an error
+    /// is returned.
+    ///
+    /// Note that this also flushes the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.flush_buf()?;
+        self.wtr.as_mut().unwrap().flush()?;
+        Ok(())
+    }
+
+    /// Flush the contents of the internal buffer to the underlying writer,
+    /// without flushing the underlying writer.
+    fn flush_buf(&mut self) -> io::Result<()> {
+        if let Some(ref mut options) = self.state.options {
+            if options.trailer == TrailerKind::Crc32 {
+                options.crc =
+                    trailer::crc32_update(options.crc, self.buf.readable());
+            }
+        }
+        self.state.panicked = true;
+        let result = self.wtr.as_mut().unwrap().write_all(self.buf.readable());
+        self.state.panicked = false;
+        result?;
+        self.buf.clear();
+        Ok(())
+    }
+
+    /// Abandon the record currently being written, if any, so that the next
+    /// field written begins a new record.
+    ///
+    /// This is useful for recovering after an error occurred in the middle
+    /// of writing a record, e.g., from `write_field` or `serialize`.
+    /// Without it, the fields that were written before the error count
+    /// toward the length of the next record.
+    ///
+    /// Note that any bytes of the abandoned record that were already written
+    /// remain in the output, and no record terminator is written after them.
+    /// If that matters, then they need to be handled by the caller, e.g., by
+    /// truncating the output to its length before the record was started.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::error::Error;
+    /// use csv::WriterBuilder;
+    ///
+    /// # fn main() { example().unwrap(); }
+    /// fn example() -> Result<(), Box<dyn Error>> {
+    ///     let mut wtr = WriterBuilder::new().from_writer(vec![]);
+    ///     wtr.write_record(&["a", "b"])?;
+    ///     wtr.write_field("c")?;
+    ///     wtr.reset_record_state();
+    ///     wtr.write_record(&["x", "y"])?;
+    ///
+    ///     let data = String::from_utf8(wtr.into_inner()?)?;
+    ///     assert_eq!(data, "a,b\ncx,y\n");
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn reset_record_state(&mut self) {
+        self.core.reset();
+        self.state.fields_written = 0;
+        self.state.raw_field_written = false;
+    }
+
+    /// Returns a reference to the underlying writer.
+    ///
+    /// Note that this does not include data still in the internal buffer.
+    pub fn get_ref(&self) -> &W {
+        self.wtr.as_ref().unwrap()
+    }
+
+    /// Flush the contents of the internal buffer and return the underlying
+    /// writer.
+    ///
+    /// If a `trailer` is configured, then it is written first.
+    pub fn into_inner(
+        mut self,
+    ) -> result::Result<W, IntoInnerError<Writer<W>>> {
+        match self
+            .write_trailer()
+            .map_err(io::Error::from)
+            .and_then(|()| self.flush())
+        {
+            Ok(()) => Ok(self.wtr.take().unwrap()),
+            Err(err) => Err(IntoInnerError::new(self, err)),
+        }
+    }
+
+    /// Write the configured trailer row, unless it has been written already.
+    fn write_trailer(&mut self) -> Result<()> {
+        let (label, value) = match self.options().trailer {
+            TrailerKind::Count => {
+                (COUNT_LABEL, self.options().records_written.to_string())
+            }
+            TrailerKind::Crc32 => {
+                self.flush_buf()?;
+                (CRC32_LABEL, format!("{:08x}", self.options().crc))
+            }
+            _ => return Ok(()),
+        };
+        self.options_mut().trailer = TrailerKind::None;
+        self.write_raw(label)?;
+        self.write_raw_delimiter()?;
+        self.write_raw(value.as_bytes())?;
+        match self.core.get_terminator() {
+            csv_core::Terminator::CRLF => self.write_raw(b"\r\n"),
+            csv_core::Terminator::Any(b) => self.write_raw(&[b]),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Write a CSV delimiter.
+    fn write_delimiter(&mut self) -> Result<()> {
+        if self.state.options.is_some()
+            && !self.options().multi_delimiter.is_empty()
+        {
+            // Let the core writer close the field, but write the delimiter
+            // in place of its single byte one.
+            let mut out = [0; 2];
+            let (_, nout) = self.core.delimiter(&mut out);
+            self.write_raw(&out[..nout - 1])?;
+            return self.write_raw_delimiter();
+        }
+        loop {
+            let (res, nout) = self.core.delimiter(self.buf.writable());
+            self.buf.written(nout);
+            match res {
+                WriteResult::InputEmpty => return Ok(()),
+                WriteResult::OutputFull => self.flush_buf()?,
+            }
+        }
+    }
+
+    /// Write a CSV terminator.
+    fn write_terminator(&mut self) -> Result<()> {
+        let has_affixes = match self.state.options {
+            None => false,
+            Some(_) => {
+                if self.has_leading_fields() {
+                    self.write_leading_fields()?;
+                }
+                self.has_record_affixes()
+            }
+        };
+        self.check_field_count()?;
+        if has_affixes || self.state.raw_field_written {
+            return self.write_terminator_with_affixes();
+        }
+        loop {
+            let (res, nout) = self.core.terminator(self.buf.writable());
+            self.buf.written(nout);
+            match res {
+                WriteResult::InputEmpty => {
+                    self.state.fields_written = 0;
+                    self.count_record();
+                    return self.flush_if_due();
+                }
+                WriteResult::OutputFull => self.flush_buf()?,
+            }
+        }
+    }
+
+    /// Write a CSV terminator, preceded by the record suffix.
+    ///
+    /// The suffix has to follow the closing quote of the last field, which
+    /// the core writer would otherwise write along with the terminator. This
+    /// is also used when a field was written raw, since the core writer
+    /// would take a record consisting of only that field for an empty one.
+    fn write_terminator_with_affixes(&mut self) -> Result<()> {
+        if self.state.fields_written == 0 {
+            self.write_record_prefix()?;
+        }
+        self.write_finish()?;
+        self.write_record_suffix()?;
+        match self.core.get_terminator() {
+            csv_core::Terminator::CRLF => self.write_raw(b"\r\n")?,
+            csv_core::Terminator::Any(b) => self.write_raw(&[b])?,
+            _ => unreachable!(),
+        }
+        self.state.fields_written = 0;
+        self.state.raw_field_written = false;
+        self.count_record();
+        self.flush_if_due()
+    }
+
+    /// Close the current record in the core writer without writing a
+    /// terminator.
+    fn write_finish(&mut self) -> Result<()> {
+        loop {
+            let (res, nout) = self.core.finish(self.buf.writable());
+            self.buf.written(nout);
+            match res {
+                WriteResult::InputEmpty => return Ok(()),
+                WriteResult::OutputFull => self.flush_buf()?,
+            }
+        }
+    }
+
+    /// Write a `ByteRecord` through `write_record`, making its position
+    /// available to `write_leading_fields`.
+    fn write_record_at(&mut self, record: &ByteRecord) -> Result<()> {
+        if !self.options().position_columns {
+            return self.write_record(record);
+        }
+        self.options_mut().record_position = record.position().cloned();
+        let result = self.write_record(record);
+        self.options_mut().record_position = None;
+        result
+    }
+
+    /// Returns true if row numbers or position columns are written before
+    /// the fields of every record.
+    #[inline]
+    fn has_leading_fields(&self) -> bool {
+        self.options().row_number_header.is_some()
+            || self.options().position_columns
+    }
+
+    /// Write the row number and position columns that start a record, if
+    /// they are enabled and no field of the current record has been written
+    /// yet.
+    ///
+    /// These are written along with the record prefix, bypassing the core
+    /// writer. This returns the number of fields written.
+    fn write_leading_fields(&mut self) -> Result<u64> {
+        if self.state.fields_written > 0 {
+            return Ok(0);
+        }
+        let mut fields = vec![];
+        match self.options().row_number_header {
+            None => {}
+            Some(ref label) if self.state.header_row_pending => {
+                fields.push(label.clone().into_bytes());
+            }
+            Some(_) => fields.push(
+                (self.options().records_written + 1).to_string().into_bytes(),
+            ),
+        }
+        if self.options().position_columns {
+            if self.state.header_row_pending {
+                fields.push(b"byte".to_vec());
+                fields.push(b"line".to_vec());
+            } else if let Some(ref pos) = self.options().record_position {
+                fields.push(pos.byte().to_string().into_bytes());
+                fields.push(pos.line().to_string().into_bytes());
+            } else {
+                fields.push(vec![]);
+                fields.push(vec![]);
+            }
+        }
+        if fields.is_empty() {
+            return Ok(0);
+        }
+        self.write_record_prefix()?;
+        for (i, field) in fields.iter().enumerate() {
+            if i > 0 {
+                self.write_raw_delimiter()?;
+            }
+            if self.core.should_quote(field) {
+                let quote = self.core.get_quote();
+                self.write_raw(&[quote])?;
+                self.write_quoted(field)?;
+                self.write_raw(&[quote])?;
+            } else {
+                self.write_raw(field)?;
+            }
+        }
+        self.state.fields_written = fields.len() as u64;
+        self.state.raw_field_written = true;
+        Ok(fields.len() as u64)
+    }
+
+    /// Returns true if a record prefix or suffix is configured.
+    #[inline]
+    fn has_record_affixes(&self) -> bool {
+        !self.options().record_prefix.is_empty()
+            || !self.options().record_suffix.is_empty()
+    }
+
+    /// Write the field delimiter as is, bypassing the core writer.
+    fn write_raw_delimiter(&mut self) -> Result<()> {
+        if self.options().multi_delimiter.is_empty() {
+            return self.write_raw(&[self.core.get_delimiter()]);
+        }
+        let delimiter = mem::take(&mut self.options_mut().multi_delimiter);
+        let result = self.write_raw(&delimiter);
+        self.options_mut().multi_delimiter = delimiter;
+        result
+    }
+
+    /// Write the record prefix, if any.
+    fn write_record_prefix(&mut self) -> Result<()> {
+        if self.options().record_prefix.is_empty() {
+            return Ok(());
+        }
+        let prefix = mem::take(&mut self.options_mut().record_prefix);
+        let result = self.write_raw(&prefix);
+        self.options_mut().record_prefix = prefix;
+        result
+    }
+
+    /// Write the record suffix, if any.
+    fn write_record_suffix(&mut self) -> Result<()> {
+        if self.options().record_suffix.is_empty() {
+            return Ok(());
+        }
+        let suffix = mem::take(&mut self.options_mut().record_suffix);
+        let result = self.write_raw(&suffix);
+        self.options_mut().record_suffix = suffix;
+        result
+    }
+
+    /// Write a CSV terminator that is guaranteed to fit into the current
+    /// buffer.
+    #[inline(never)]
+    fn write_terminator_into_buffer(&mut self) -> Result<()> {
+        self.check_field_count()?;
+        match self.core.get_terminator() {
+            csv_core::Terminator::CRLF => {
+                self.buf.writable()[0] = b'\r';
+                self.buf.writable()[1] = b'\n';
+                self.buf.written(2);
+            }
+            csv_core::Terminator::Any(b) => {
+                self.buf.writable()[0] = b;
+                self.buf.written(1);
+            }
+            _ => unreachable!(),
+        }
+        self.state.fields_written = 0;
+        self.count_record();
+        self.flush_if_due()
+    }
+
+    /// Return an error if `field`, the field at index `field_index` of the
+    /// current record, contains bytes that require quotes.
+    fn check_needs_quoting(
+        &self,
+        field: &[u8],
+        field_index: u64,
+    ) -> Result<()> {
+        if field.iter().any(|&b| self.core.is_special_byte(b)) {
+            return Err(Error::new(ErrorKind::FieldNeedsQuoting {
+                field_index,
+            }));
+        }
+        Ok(())
+    }
+
+    /// Return an error if `field`, the field at index `field_index` of the
+    /// current record, is longer than `max_field_len`.
+    fn check_field_len(&self, field: &[u8], field_index: u64) -> Result<()> {
+        match self.options().max_field_len {
+            Some(limit) if field.len() > limit => {
+                Err(Error::new(ErrorKind::FieldTooLong {
+                    field_index,
+                    len: field.len() as u64,
+                    limit: limit as u64,
+                }))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Record that a row was just terminated, for the purposes of enforcing
+    /// `max_records`.
+    #[inline]
+    fn count_record(&mut self) {
+        self.state.wrote_row = true;
+        let was_header =
+            mem::replace(&mut self.state.header_row_pending, false);
+        if let Some(ref mut options) = self.state.options {
+            options.wrote_empty_record = false;
+            if !was_header {
+                options.records_written += 1;
+            }
+        }
+    }
+
+    /// Flush if a record was just written and `flush_every` records have been
+    /// written since the last time this flushed.
+    #[inline]
+    fn flush_if_due(&mut self) -> Result<()> {
+        match self.options().flush_every {
+            Some(n)
+                if self.options().records_written
+                    - self.options().flushed_at
+                    >= n =>
+            {
+                let written = self.options().records_written;
+                self.options_mut().flushed_at = written;
+                Ok(self.flush()?)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Return an error if starting a new record would exceed `max_records`.
+    ///
+    /// This does nothing when called in the middle of a record.
+    #[inline]
+    fn check_record_limit(&self) -> Result<()> {
+        if self.state.fields_written > 0 || self.state.header_row_pending {
+            return Ok(());
+        }
+        match self.options().max_records {
+            Some(limit) if self.options().records_written >= limit => {
+                Err(Error::new(ErrorKind::TooManyRecords { limit }))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn check_field_count(&mut self) -> Result<()> {
+        let header_len =
+            self.state.options.as_mut().and_then(|o| o.header_len.take());
+        if let Some(expected) = header_len {
+            if expected != self.state.fields_written {
+                return Err(Error::new(ErrorKind::UnequalLengths {
+                    pos: None,
+                    expected_len: expected,
+                    len: self.state.fields_written,
+                }));
+            }
+        }
+        if !self.state.flexible {
+            match self.state.first_field_count {
+                None => {
+                    self.state.first_field_count =
+                        Some(self.state.fields_written);
+                }
+                Some(expected) if expected != self.state.fields_written => {
+                    return Err(Error::new(ErrorKind::UnequalLengths {
+                        pos: None,
+                        expected_len: expected,
+                        len: self.state.fields_written,
+                    }))
+                }
+                Some(_) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Buffer {
+    /// Returns a slice of the buffer's current contents.
+    ///
+    /// The slice returned may be empty.
+    #[inline]
+    fn readable(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    /// Returns a mutable slice of the remaining space in this buffer.
+    ///
+    /// The slice returned may be empty.
+    #[inline]
+    fn writable(&mut self) -> &mut [u8] {
+        &mut self.buf[self.len..]
+    }
+
+    /// Indicates that `n` bytes have been written to this buffer.
+    #[inline]
+    fn written(&mut self, n: usize) {
+        self.len += n;
+    }
+
+    /// Clear the buffer.
+    #[inline]
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{serde_if_integer128, Serialize};
+
+    use std::borrow::Cow;
+    use std::io::{self, Write};
+
+    use crate::byte_record::ByteRecord;
+    use crate::error::ErrorKind;
+    use crate::string_record::StringRecord;
+    use crate::trailer::crc32_update;
+    use crate::{
+        ColumnFormat, Dialect, QuoteStyle, Terminator, TrailerKind, Trim,
+        TupleHeaderStyle,
+    };
+
+    use super::{Writer, WriterBuilder};
+
+    fn wtr_as_string(wtr: Writer<Vec<u8>>) -> String {
+        String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn one_record() {
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        wtr.write_record(&["a", "b", "c"]).unwrap();
+
+        assert_eq!(wtr_as_string(wtr), "a,b,c\n");
+    }
+
+    #[test]
+    fn one_string_record() {
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        wtr.write_record(&StringRecord::from(vec!["a", "b", "c"])).unwrap();
+
+        assert_eq!(wtr_as_string(wtr), "a,b,c\n");
+    }
+
+    #[test]
+    fn one_byte_record() {
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        wtr.write_record(&ByteRecord::from(vec!["a", "b", "c"])).unwrap();
+
+        assert_eq!(wtr_as_string(wtr), "a,b,c\n");
+    }
+
+    #[test]
+    fn raw_one_byte_record() {
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        wtr.write_byte_record(&ByteRecord::from(vec!["a", "b", "c"])).unwrap();
+
+        assert_eq!(wtr_as_string(wtr), "a,b,c\n");
+    }
+
+    #[test]
+    fn one_empty_record() {
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        wtr.write_record(&[""]).unwrap();
+
+        assert_eq!(wtr_as_string(wtr), "\"\"\n");
+    }
+
+    #[test]
+    fn raw_one_empty_record() {
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        wtr.write_byte_record(&ByteRecord::from(vec![""])).unwrap();
+
+        assert_eq!(wtr_as_string(wtr), "\"\"\n");
+    }
+
+    #[test]
+    fn two_empty_records() {
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        wtr.write_record(&[""]).unwrap();
+        wtr.write_record(&[""]).unwrap();
+
+        assert_eq!(wtr_as_string(wtr), "\"\"\n\"\"\n");
+    }
+
+    #[test]
+    fn raw_two_empty_records() {
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        wtr.write_byte_record(&ByteRecord::from(vec![""])).unwrap();
+        wtr.write_byte_record(&ByteRecord::from(vec![""])).unwrap();
+
+        assert_eq!(wtr_as_string(wtr), "\"\"\n\"\"\n");
+    }
+
+    #[test]
+    fn unequal_records_bad() {
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        wtr.write_record(&ByteRecord::from(vec!["a", "b", "c"])).unwrap();
+        let err = wtr.write_record(&ByteRecord::from(vec!["a"])).unwrap_err();
+        match *err.kind() {
+            ErrorKind::UnequalLengths { ref pos, expected_len, len } => {
+                assert!(pos.is_none());
+                assert_eq!(expected_len, 3);
+                assert_eq!(len, 1);
+            }
+            ref x => {
+                panic!("expected UnequalLengths error, but got '{:?}'", x);
+            }
+        }
+    }
+
+    #[test]
+    fn raw_unequal_records_bad() {
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        wtr.write_byte_record(&ByteRecord::from(vec!["a", "b", "c"])).unwrap();
+        let err =
+            wtr.write_byte_record(&ByteRecord::from(vec!["a"])).unwrap_err();
+        match *err.kind() {
+            ErrorKind::UnequalLengths { ref pos, expected_len, len } => {
+                assert!(pos.is_none());
+                assert_eq!(expected_len, 3);
+                assert_eq!(len, 1);
+            }
+            ref x => {
+                panic!("expected UnequalLengths error, but got '{:?}'", x);
+            }
+        }
+    }
+
+    #[test]
+    fn unequal_records_ok() {
+        let mut wtr = WriterBuilder::new().flexible(true).from_writer(vec![]);
+        wtr.write_record(&ByteRecord::from(vec!["a", "b", "c"])).unwrap();
+        wtr.write_record(&ByteRecord::from(vec!["a"])).unwrap();
+        assert_eq!(wtr_as_string(wtr), "a,b,c\na\n");
+    }
+
+    #[test]
+    fn raw_unequal_records_ok() {
+        let mut wtr = WriterBuilder::new().flexible(true).from_writer(vec![]);
+        wtr.write_byte_record(&ByteRecord::from(vec!["a", "b", "c"])).unwrap();
+        wtr.write_byte_record(&ByteRecord::from(vec!["a"])).unwrap();
+        assert_eq!(wtr_as_string(wtr), "a,b,c\na\n");
+    }
+
+    #[test]
+    fn full_buffer_should_not_flush_underlying() {
+        struct MarkWriteAndFlush(Vec<u8>);
+
+        impl MarkWriteAndFlush {
+            fn to_str(self) -> String {
+                String::from_utf8(self.0).unwrap()
+            }
+        }
+
+        impl Write for MarkWriteAndFlush {
+            fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+                self.0.write(b">")?;
+                let written = self.0.write(data)?;
+                self.0.write(b"<")?;
+
+                Ok(written)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                self.0.write(b"!")?;
+                Ok(())
+            }
+        }
+
+        let underlying = MarkWriteAndFlush(vec![]);
+        let mut wtr =
+            WriterBuilder::new().buffer_capacity(4).from_writer(underlying);
+
+        wtr.write_byte_record(&ByteRecord::from(vec!["a", "b"])).unwrap();
+        wtr.write_byte_record(&ByteRecord::from(vec!["c", "d"])).unwrap();
+        wtr.flush().unwrap();
+        wtr.write_byte_record(&ByteRecord::from(vec!["e", "f"])).unwrap();
+
+        let got = wtr.into_inner().unwrap().to_str();
+
+        // As the buffer size is 4 we should write each record separately, and
+        // flush when explicitly called and implictly in into_inner.
+        assert_eq!(got, ">a,b\n<>c,d\n<!>e,f\n<!");
+    }
+
+    #[test]
+    fn serialize_with_headers() {
+        #[derive(Serialize)]
+        struct Row {
+            foo: i32,
+            bar: f64,
+            baz: bool,
+        }
+
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        wtr.serialize(Row { foo: 42, bar: 42.5, baz: true }).unwrap();
+        assert_eq!(wtr_as_string(wtr), "foo,bar,baz\n42,42.5,true\n");
     }
 
-    /// Implementation of write_field.
-    ///
-    /// This is a separate method so we can force the compiler to inline it
-    /// into write_record.
-    #[inline(always)]
-    fn write_field_impl<T: AsRef<[u8]>>(&mut self, field: T) -> Result<()> {
-        if self.state.fields_written > 0 {
-            self.write_delimiter()?;
+    #[test]
+    fn serialize_no_headers() {
+        #[derive(Serialize)]
+        struct Row {
+            foo: i32,
+            bar: f64,
+            baz: bool,
         }
-        let mut field = field.as_ref();
-        loop {
-            let (res, nin, nout) = self.core.field(field, self.buf.writable());
-            field = &field[nin..];
-            self.buf.written(nout);
-            match res {
-                WriteResult::InputEmpty => {
-                    self.state.fields_written += 1;
-                    return Ok(());
-                }
-                WriteResult::OutputFull => self.flush_buf()?,
+
+        let mut wtr =
+            WriterBuilder::new().has_headers(false).from_writer(vec![]);
+        wtr.serialize(Row { foo: 42, bar: 42.5, baz: true }).unwrap();
+        assert_eq!(wtr_as_string(wtr), "42,42.5,true\n");
+    }
+
+    serde_if_integer128! {
+        #[test]
+        fn serialize_no_headers_128() {
+            #[derive(Serialize)]
+            struct Row {
+                foo: i128,
+                bar: f64,
+                baz: bool,
             }
+
+            let mut wtr =
+                WriterBuilder::new().has_headers(false).from_writer(vec![]);
+            wtr.serialize(Row {
+                foo: 9_223_372_036_854_775_808,
+                bar: 42.5,
+                baz: true,
+            }).unwrap();
+            assert_eq!(wtr_as_string(wtr), "9223372036854775808,42.5,true\n");
         }
     }
 
-    /// Flush the contents of the internal buffer to the underlying writer.
-    ///
-    /// If there was a problem writing to the underlying writer, then an error
-    /// is returned.
-    ///
-    /// Note that this also flushes the underlying writer.
-    pub fn flush(&mut self) -> io::Result<()> {
-        self.flush_buf()?;
-        self.wtr.as_mut().unwrap().flush()?;
-        Ok(())
+    #[test]
+    fn serialize_tuple() {
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        wtr.serialize((true, 1.3, "hi")).unwrap();
+        assert_eq!(wtr_as_string(wtr), "true,1.3,hi\n");
     }
 
-    /// Flush the contents of the internal buffer to the underlying writer,
-    /// without flushing the underlying writer.
-    fn flush_buf(&mut self) -> io::Result<()> {
-        self.state.panicked = true;
-        let result = self.wtr.as_mut().unwrap().write_all(self.buf.readable());
-        self.state.panicked = false;
-        result?;
-        self.buf.clear();
-        Ok(())
+    #[test]
+    fn serialize_bool_format() {
+        #[derive(Serialize)]
+        struct Row {
+            x: bool,
+            y: bool,
+        }
+
+        let mut wtr =
+            WriterBuilder::new().bool_format("Y", "N").from_writer(vec![]);
+        wtr.serialize(Row { x: true, y: false }).unwrap();
+        wtr.write_record(&["true", "false"]).unwrap();
+        assert_eq!(wtr_as_string(wtr), "x,y\nY,N\ntrue,false\n");
     }
 
-    /// Flush the contents of the internal buffer and return the underlying
-    /// writer.
-    pub fn into_inner(
-        mut self,
-    ) -> result::Result<W, IntoInnerError<Writer<W>>> {
-        match self.flush() {
-            Ok(()) => Ok(self.wtr.take().unwrap()),
-            Err(err) => Err(IntoInnerError::new(self, err)),
+    #[test]
+    fn write_headers_then_serialize() {
+        #[derive(Default, Serialize)]
+        struct Row {
+            foo: i32,
+            bar: f64,
         }
+
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        wtr.write_headers::<Row>().unwrap();
+        wtr.write_headers::<Row>().unwrap();
+        wtr.serialize(Row { foo: 42, bar: 42.5 }).unwrap();
+        wtr.serialize(Row { foo: 1, bar: 2.0 }).unwrap();
+        assert_eq!(wtr_as_string(wtr), "foo,bar\n42,42.5\n1,2.0\n");
+
+        let mut wtr =
+            WriterBuilder::new().has_headers(false).from_writer(vec![]);
+        wtr.write_headers::<Row>().unwrap();
+        wtr.serialize(Row { foo: 42, bar: 42.5 }).unwrap();
+        assert_eq!(wtr_as_string(wtr), "42,42.5\n");
     }
 
-    /// Write a CSV delimiter.
-    fn write_delimiter(&mut self) -> Result<()> {
-        loop {
-            let (res, nout) = self.core.delimiter(self.buf.writable());
-            self.buf.written(nout);
-            match res {
-                WriteResult::InputEmpty => return Ok(()),
-                WriteResult::OutputFull => self.flush_buf()?,
-            }
+    #[test]
+    fn into_string() {
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        wtr.write_record(&["name", "note"]).unwrap();
+        wtr.write_record(&["a", "x,y"]).unwrap();
+        wtr.write_record(&["b", "☃"]).unwrap();
+        assert_eq!(wtr.into_string().unwrap(), "name,note\na,\"x,y\"\nb,☃\n");
+
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        wtr.write_field(b"\xFF").unwrap();
+        assert!(wtr.into_string().is_err());
+    }
+
+    #[test]
+    fn position_matches_record_offsets() {
+        let records = vec![
+            vec!["name", "value"],
+            vec!["a", "x,y"],
+            vec!["b", "quote \"here\""],
+            vec!["c", ""],
+        ];
+        let mut wtr =
+            WriterBuilder::new().from_writer(io::Cursor::new(vec![]));
+        let mut offsets = vec![];
+        for record in &records {
+            offsets.push(wtr.position().unwrap());
+            wtr.write_record(record).unwrap();
+        }
+        let data = wtr.into_inner().unwrap().into_inner();
+
+        let mut rdr =
+            crate::ReaderBuilder::new().has_headers(false).from_reader(&*data);
+        let mut rec = StringRecord::new();
+        for (i, &offset) in offsets.iter().enumerate() {
+            assert!(rdr.read_record(&mut rec).unwrap());
+            assert_eq!(rec.position().unwrap().byte(), offset);
+            assert_eq!(rec, records[i]);
         }
     }
 
-    /// Write a CSV terminator.
-    fn write_terminator(&mut self) -> Result<()> {
-        self.check_field_count()?;
-        loop {
-            let (res, nout) = self.core.terminator(self.buf.writable());
-            self.buf.written(nout);
-            match res {
-                WriteResult::InputEmpty => {
-                    self.state.fields_written = 0;
-                    return Ok(());
-                }
-                WriteResult::OutputFull => self.flush_buf()?,
-            }
+    #[test]
+    fn max_records() {
+        #[derive(Serialize)]
+        struct Row {
+            x: i32,
+        }
+
+        let mut wtr =
+            WriterBuilder::new().max_records(Some(2)).from_writer(vec![]);
+        wtr.serialize(Row { x: 1 }).unwrap();
+        wtr.serialize(Row { x: 2 }).unwrap();
+        match *wtr.serialize(Row { x: 3 }).unwrap_err().kind() {
+            ErrorKind::TooManyRecords { limit } => assert_eq!(limit, 2),
+            ref err => panic!("unexpected error: {:?}", err),
         }
+        assert!(wtr.write_record(&["4"]).is_err());
+        assert!(wtr.write_field("5").is_err());
+        assert_eq!(wtr_as_string(wtr), "x\n1\n2\n");
+
+        let mut wtr = WriterBuilder::new()
+            .has_headers(false)
+            .max_records(Some(1))
+            .from_writer(vec![]);
+        wtr.write_byte_record(&ByteRecord::from(vec!["a"])).unwrap();
+        assert!(wtr.write_byte_record(&ByteRecord::from(vec!["b"])).is_err());
+        assert_eq!(wtr_as_string(wtr), "a\n");
+
+        let mut wtr =
+            WriterBuilder::new().max_records(Some(0)).from_writer(vec![]);
+        assert!(wtr.write_record(&["record"]).is_err());
+        wtr.set_header(&["header"]).unwrap();
+        assert!(wtr.write_record(&["record"]).is_err());
+        assert_eq!(wtr_as_string(wtr), "header\n");
     }
 
-    /// Write a CSV terminator that is guaranteed to fit into the current
-    /// buffer.
-    #[inline(never)]
-    fn write_terminator_into_buffer(&mut self) -> Result<()> {
-        self.check_field_count()?;
-        match self.core.get_terminator() {
-            csv_core::Terminator::CRLF => {
-                self.buf.writable()[0] = b'\r';
-                self.buf.writable()[1] = b'\n';
-                self.buf.written(2);
+    #[test]
+    fn never_quote_strict() {
+        use crate::QuoteStyle;
+
+        let mut wtr = WriterBuilder::new()
+            .quote_style(QuoteStyle::Never)
+            .never_quote_strict(true)
+            .from_writer(vec![]);
+        wtr.write_record(&["a", "b", "c"]).unwrap();
+        wtr.write_byte_record(&ByteRecord::from(vec!["x", "y", "z"])).unwrap();
+        assert_eq!(wtr_as_string(wtr), "a,b,c\nx,y,z\n");
+
+        for &bad in &["x,y", "x\"y", "x\ny"] {
+            let mut wtr = WriterBuilder::new()
+                .quote_style(QuoteStyle::Never)
+                .never_quote_strict(true)
+                .from_writer(vec![]);
+            match *wtr.write_record(&["a", "b", bad]).unwrap_err().kind() {
+                ErrorKind::FieldNeedsQuoting { field_index } => {
+                    assert_eq!(field_index, 2)
+                }
+                ref err => panic!("unexpected error: {:?}", err),
             }
-            csv_core::Terminator::Any(b) => {
-                self.buf.writable()[0] = b;
-                self.buf.written(1);
+        }
+        let mut wtr = WriterBuilder::new()
+            .quote_style(QuoteStyle::Never)
+            .never_quote_strict(true)
+            .from_writer(vec![]);
+        let rec = ByteRecord::from(vec!["a,b", "c"]);
+        match *wtr.write_byte_record(&rec).unwrap_err().kind() {
+            ErrorKind::FieldNeedsQuoting { field_index } => {
+                assert_eq!(field_index, 0)
             }
-            _ => unreachable!(),
+            ref err => panic!("unexpected error: {:?}", err),
         }
-        self.state.fields_written = 0;
-        Ok(())
+
+        // Without `QuoteStyle::Never`, the option has no effect.
+        let mut wtr =
+            WriterBuilder::new().never_quote_strict(true).from_writer(vec![]);
+        wtr.write_record(&["x,y"]).unwrap();
+        assert_eq!(wtr_as_string(wtr), "\"x,y\"\n");
     }
 
-    fn check_field_count(&mut self) -> Result<()> {
-        if !self.state.flexible {
-            match self.state.first_field_count {
-                None => {
-                    self.state.first_field_count =
-                        Some(self.state.fields_written);
-                }
-                Some(expected) if expected != self.state.fields_written => {
-                    return Err(Error::new(ErrorKind::UnequalLengths {
-                        pos: None,
-                        expected_len: expected,
-                        len: self.state.fields_written,
-                    }))
-                }
-                Some(_) => {}
-            }
+    #[test]
+    fn max_field_len() {
+        let mut wtr =
+            WriterBuilder::new().max_field_len(Some(3)).from_writer(vec![]);
+        wtr.write_record(&["abc", "a,\""]).unwrap();
+
+        let record = ByteRecord::from(vec!["a", "abcd"]);
+        let err = wtr.write_byte_record(&record).unwrap_err();
+        match *err.kind() {
+            ErrorKind::FieldTooLong { field_index: 1, len: 4, limit: 3 } => {}
+            ref x => panic!("expected FieldTooLong, got {:?}", x),
         }
-        Ok(())
+        assert!(wtr.write_field("abcd").is_err());
+        assert_eq!(wtr_as_string(wtr), "abc,\"a,\"\"\"\n");
+
+        // A rejected record leaves no partial record behind.
+        let mut wtr =
+            WriterBuilder::new().max_field_len(Some(3)).from_writer(vec![]);
+        let err = wtr.write_record(&["ok", "toolong"]).unwrap_err();
+        match *err.kind() {
+            ErrorKind::FieldTooLong { field_index: 1, len: 7, limit: 3 } => {}
+            ref x => panic!("expected FieldTooLong, got {:?}", x),
+        }
+        wtr.write_record(&["x", "y"]).unwrap();
+        assert_eq!(wtr_as_string(wtr), "x,y\n");
     }
-}
 
-impl Buffer {
-    /// Returns a slice of the buffer's current contents.
-    ///
-    /// The slice returned may be empty.
-    #[inline]
-    fn readable(&self) -> &[u8] {
-        &self.buf[..self.len]
+    #[test]
+    fn skip_columns() {
+        #[derive(Serialize)]
+        struct Row {
+            a: i32,
+            secret: &'static str,
+            b: f64,
+            c: bool,
+        }
+
+        let mut wtr = WriterBuilder::new()
+            .skip_columns(vec!["secret".to_string()])
+            .from_writer(vec![]);
+        wtr.serialize(Row { a: 1, secret: "x", b: 2.5, c: true }).unwrap();
+        wtr.serialize(Row { a: 2, secret: "y", b: 0.5, c: false }).unwrap();
+        assert_eq!(wtr_as_string(wtr), "a,b,c\n1,2.5,true\n2,0.5,false\n");
+    }
+
+    #[test]
+    fn min_field_width() {
+        let rows = vec![
+            vec!["a", "bb", ""],
+            vec!["longer than five", "x,y", "\"q\""],
+            vec!["z", "12345", "y"],
+        ];
+        let mut wtr =
+            WriterBuilder::new().min_field_width(5).from_writer(vec![]);
+        wtr.write_record(&rows[0]).unwrap();
+        wtr.write_record(&rows[1]).unwrap();
+        wtr.write_byte_record(&ByteRecord::from(rows[2].clone())).unwrap();
+        let data = wtr_as_string(wtr);
+        assert_eq!(
+            data,
+            "a    ,bb   ,     \n\
+             longer than five,\"x,y  \",\"\"\"q\"\"  \"\n\
+             z    ,12345,y    \n"
+        );
+
+        let mut rdr = crate::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(data.as_bytes());
+        for result in rdr.byte_records() {
+            assert!(result.unwrap().iter().all(|field| field.len() >= 5));
+        }
+
+        let mut rdr = crate::ReaderBuilder::new()
+            .has_headers(false)
+            .trim(crate::Trim::Fields)
+            .from_reader(data.as_bytes());
+        let records: Vec<StringRecord> =
+            rdr.records().map(|r| r.unwrap()).collect();
+        assert_eq!(records, rows);
+    }
+
+    #[test]
+    fn normalize_field_newlines() {
+        let record = ByteRecord::from(vec!["a\r\nb\nc\rd\n\re", "f\r\n", "g"]);
+
+        let mut wtr = WriterBuilder::new()
+            .normalize_field_newlines(Some(Terminator::Any(b'\n')))
+            .from_writer(vec![]);
+        wtr.write_byte_record(&record).unwrap();
+        wtr.write_record(&record).unwrap();
+        assert_eq!(
+            wtr_as_string(wtr),
+            "\"a\nb\nc\nd\n\ne\",\"f\n\",g\n\"a\nb\nc\nd\n\ne\",\"f\n\",g\n"
+        );
+
+        let mut wtr = WriterBuilder::new()
+            .normalize_field_newlines(Some(Terminator::CRLF))
+            .from_writer(vec![]);
+        wtr.write_byte_record(&record).unwrap();
+        assert_eq!(
+            wtr_as_string(wtr),
+            "\"a\r\nb\r\nc\r\nd\r\n\r\ne\",\"f\r\n\",g\n"
+        );
+    }
+
+    #[test]
+    fn tuple_header_style() {
+        #[derive(Serialize)]
+        struct Row(i32, String, f64);
+
+        #[derive(Serialize)]
+        struct Named {
+            x: i32,
+            y: i32,
+        }
+
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        wtr.serialize(Row(1, "a".to_string(), 1.5)).unwrap();
+        assert_eq!(wtr_as_string(wtr), "1,a,1.5\n");
+
+        let mut wtr = WriterBuilder::new()
+            .tuple_header_style(TupleHeaderStyle::Positional)
+            .from_writer(vec![]);
+        wtr.serialize(Row(1, "a".to_string(), 1.5)).unwrap();
+        wtr.serialize(Row(2, "b".to_string(), 2.5)).unwrap();
+        assert_eq!(
+            wtr_as_string(wtr),
+            "field0,field1,field2\n1,a,1.5\n2,b,2.5\n"
+        );
+
+        let mut wtr = WriterBuilder::new()
+            .tuple_header_style(TupleHeaderStyle::Positional)
+            .from_writer(vec![]);
+        wtr.serialize((1, Named { x: 2, y: 3 }, "z")).unwrap();
+        assert_eq!(wtr_as_string(wtr), "field0,x,y,field3\n1,2,3,z\n");
+
+        let mut wtr = WriterBuilder::new()
+            .has_headers(false)
+            .tuple_header_style(TupleHeaderStyle::Positional)
+            .from_writer(vec![]);
+        wtr.serialize(Row(1, "a".to_string(), 1.5)).unwrap();
+        assert_eq!(wtr_as_string(wtr), "1,a,1.5\n");
+    }
+
+    #[test]
+    fn flush_every() {
+        let mut wtr = WriterBuilder::new()
+            .has_headers(false)
+            .flush_every(Some(100))
+            .from_writer(vec![]);
+        for i in 0..99 {
+            wtr.write_record(&[i.to_string(), "x".to_string()]).unwrap();
+        }
+        assert!(wtr.get_ref().is_empty());
+        wtr.write_byte_record(&ByteRecord::from(vec!["99", "x"])).unwrap();
+        assert_eq!(wtr.get_ref().iter().filter(|&&b| b == b'\n').count(), 100);
+        wtr.write_record(&["100", "x"]).unwrap();
+        assert_eq!(wtr.get_ref().iter().filter(|&&b| b == b'\n').count(), 100);
+
+        // The header row doesn't count as a record.
+        let mut wtr =
+            WriterBuilder::new().flush_every(Some(2)).from_writer(vec![]);
+        wtr.set_header(&["h"]).unwrap();
+        wtr.write_record(&["a"]).unwrap();
+        assert!(wtr.get_ref().is_empty());
+        wtr.write_record(&["b"]).unwrap();
+        assert_eq!(wtr.get_ref(), b"h\na\nb\n");
+    }
+
+    #[test]
+    fn write_header_from_map() {
+        use std::collections::{BTreeMap, HashMap};
+
+        let mut sample = BTreeMap::new();
+        sample.insert("c", 0);
+        sample.insert("a", 0);
+        sample.insert("b", 0);
+
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        wtr.write_header_from_map(&sample).unwrap();
+
+        let mut row = HashMap::new();
+        row.insert("b", "2");
+        row.insert("c", "3");
+        row.insert("a", "1");
+        wtr.serialize(&row).unwrap();
+        // Missing columns are empty.
+        row.remove("b");
+        wtr.serialize(&row).unwrap();
+        wtr.write_record(&["x", "y", "z"]).unwrap();
+        // Keys that aren't columns are an error.
+        row.insert("d", "4");
+        assert!(wtr.serialize(&row).is_err());
+        assert_eq!(wtr_as_string(wtr), "a,b,c\n1,2,3\n1,,3\nx,y,z\n");
+
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        assert!(wtr.write_header_from_map(&("a", "b")).is_err());
+        assert!(wtr.serialize(&sample).is_err());
+    }
+
+    #[test]
+    fn write_record_no_terminator() {
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        wtr.write_record_no_terminator(&["a", "b", "c"]).unwrap();
+        assert_eq!(wtr_as_string(wtr), "a,b,c");
+
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        wtr.write_record_no_terminator(&["a", "b,c"]).unwrap();
+        assert_eq!(wtr_as_string(wtr), "a,\"b,c\"");
+
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        wtr.write_record_no_terminator(&[""]).unwrap();
+        assert_eq!(wtr_as_string(wtr), "\"\"");
+
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        wtr.write_record_no_terminator(&["a", "b"]).unwrap();
+        let err = wtr.write_record_no_terminator(&["c"]).unwrap_err();
+        match *err.kind() {
+            ErrorKind::UnequalLengths { expected_len: 2, len: 1, .. } => {}
+            ref x => panic!("expected UnequalLengths, got {:?}", x),
+        }
     }
 
-    /// Returns a mutable slice of the remaining space in this buffer.
-    ///
-    /// The slice returned may be empty.
-    #[inline]
-    fn writable(&mut self) -> &mut [u8] {
-        &mut self.buf[self.len..]
-    }
+    #[test]
+    fn write_byte_record_preserve_quotes() {
+        use crate::reader::ReaderBuilder;
 
-    /// Indicates that `n` bytes have been written to this buffer.
-    #[inline]
-    fn written(&mut self, n: usize) {
-        self.len += n;
-    }
+        let data = "\"a\",b,\"c\"\"d\"\n1,\"2\",\"x,y\"\n\"\",z,\"\"\n";
+        let quoted: &[&[bool]] = &[
+            &[true, false, true],
+            &[false, true, true],
+            &[true, false, true],
+        ];
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(data.as_bytes());
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        let mut rec = ByteRecord::new();
+        for flags in quoted {
+            assert!(rdr.read_byte_record(&mut rec).unwrap());
+            wtr.write_byte_record_preserve_quotes(&rec, flags).unwrap();
+        }
+        assert_eq!(wtr_as_string(wtr), data);
 
-    /// Clear the buffer.
-    #[inline]
-    fn clear(&mut self) {
-        self.len = 0;
+        // Fields that need quotes are quoted even when they aren't flagged.
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        let rec = ByteRecord::from(vec!["a,b", "c"]);
+        wtr.write_byte_record_preserve_quotes(&rec, &[]).unwrap();
+        assert_eq!(wtr_as_string(wtr), "\"a,b\",c\n");
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use serde::{serde_if_integer128, Serialize};
+    #[test]
+    fn escape_round_trip() {
+        use crate::ReaderBuilder;
 
-    use std::io::{self, Write};
+        let fields = vec!["a\\b", "a\\\\b", "a\"b", "a\\\"b", "\\"];
+        let mut wtr = WriterBuilder::new()
+            .double_quote(false)
+            .escape(b'\\')
+            .from_writer(vec![]);
+        wtr.write_record(&fields).unwrap();
+        let data = wtr_as_string(wtr);
+        assert_eq!(
+            data,
+            "\"a\\\\b\",\"a\\\\\\\\b\",\"a\\\"b\",\"a\\\\\\\"b\",\"\\\\\"\n"
+        );
 
-    use crate::byte_record::ByteRecord;
-    use crate::error::ErrorKind;
-    use crate::string_record::StringRecord;
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .double_quote(false)
+            .escape(Some(b'\\'))
+            .from_reader(data.as_bytes());
+        let mut rec = StringRecord::new();
+        assert!(rdr.read_record(&mut rec).unwrap());
+        assert_eq!(rec, fields);
+    }
 
-    use super::{Writer, WriterBuilder};
+    #[test]
+    fn quote_empty() {
+        let mut wtr = WriterBuilder::new().flexible(true).from_writer(vec![]);
+        wtr.write_record(&[""]).unwrap();
+        wtr.write_byte_record(&ByteRecord::new()).unwrap();
+        wtr.write_record(&["", ""]).unwrap();
+        assert_eq!(wtr_as_string(wtr), "\"\"\n\"\"\n,\n");
 
-    fn wtr_as_string(wtr: Writer<Vec<u8>>) -> String {
-        String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+        let mut wtr = WriterBuilder::new()
+            .flexible(true)
+            .quote_empty(false)
+            .from_writer(vec![]);
+        wtr.write_record(&[""]).unwrap();
+        wtr.write_byte_record(&ByteRecord::new()).unwrap();
+        wtr.write_record(&["", ""]).unwrap();
+        assert_eq!(wtr_as_string(wtr), "\n\n,\n");
     }
 
     #[test]
-    fn one_record() {
-        let mut wtr = WriterBuilder::new().from_writer(vec![]);
-        wtr.write_record(&["a", "b", "c"]).unwrap();
+    fn serialize_field() {
+        let mut wtr = WriterBuilder::new().flexible(true).from_writer(vec![]);
+        wtr.serialize_field(-7i32).unwrap();
+        wtr.serialize_field(2.5f64).unwrap();
+        wtr.serialize_field("x,y".to_string()).unwrap();
+        wtr.serialize_field(true).unwrap();
+        wtr.write_record(None::<&[u8]>).unwrap();
 
-        assert_eq!(wtr_as_string(wtr), "a,b,c\n");
+        let err = wtr.serialize_field((1, 2)).unwrap_err();
+        match *err.kind() {
+            ErrorKind::Serialize(_) => {}
+            ref x => panic!("expected ErrorKind::Serialize but got '{:?}'", x),
+        }
+        wtr.serialize_field(1u8).unwrap();
+        wtr.write_record(None::<&[u8]>).unwrap();
+        assert_eq!(wtr_as_string(wtr), "-7,2.5,\"x,y\",true\n1\n");
     }
 
     #[test]
-    fn one_string_record() {
-        let mut wtr = WriterBuilder::new().from_writer(vec![]);
-        wtr.write_record(&StringRecord::from(vec!["a", "b", "c"])).unwrap();
+    fn reset_record_state() {
+        let mut wtr = WriterBuilder::new()
+            .quote_style(QuoteStyle::Never)
+            .never_quote_strict(true)
+            .from_writer(vec![]);
+        wtr.write_record(&["a", "b"]).unwrap();
+        wtr.write_field("c").unwrap();
+        assert!(wtr.write_field("d,e").is_err());
 
-        assert_eq!(wtr_as_string(wtr), "a,b,c\n");
+        wtr.reset_record_state();
+        wtr.write_record(&["x", "y"]).unwrap();
+        assert_eq!(wtr_as_string(wtr), "a,b\ncx,y\n");
     }
 
+    #[cfg(feature = "encoding")]
     #[test]
-    fn one_byte_record() {
-        let mut wtr = WriterBuilder::new().from_writer(vec![]);
-        wtr.write_record(&ByteRecord::from(vec!["a", "b", "c"])).unwrap();
+    fn encoding_latin1() {
+        use crate::Encoding;
 
-        assert_eq!(wtr_as_string(wtr), "a,b,c\n");
+        let mut wtr = WriterBuilder::new()
+            .encoding(Encoding::Latin1)
+            .from_writer(vec![]);
+        wtr.write_record(&["Zoë", "Ågot"]).unwrap();
+        wtr.write_byte_record(&ByteRecord::from(vec!["é", "\u{20AC}"]))
+            .unwrap();
+        let err = wtr.write_record(&["ok", "Ω"]).unwrap_err();
+        match *err.kind() {
+            ErrorKind::Unencodable { field_index: 1, ch: 'Ω' } => {}
+            ref x => panic!("expected Unencodable, got {:?}", x),
+        }
+        wtr.write_record(&["x", "y"]).unwrap();
+        assert_eq!(
+            wtr.into_inner().unwrap(),
+            b"Zo\xEB,\xC5got\n\xE9,\x80\nx,y\n"
+        );
     }
 
     #[test]
-    fn raw_one_byte_record() {
-        let mut wtr = WriterBuilder::new().from_writer(vec![]);
-        wtr.write_byte_record(&ByteRecord::from(vec!["a", "b", "c"])).unwrap();
+    fn serialize_to_vec() {
+        #[derive(Serialize)]
+        struct Row {
+            name: &'static str,
+            score: f64,
+            note: Option<&'static str>,
+        }
 
-        assert_eq!(wtr_as_string(wtr), "a,b,c\n");
+        let row = Row { name: "a, b", score: 1.5, note: None };
+        for &has_headers in &[true, false] {
+            let mut builder = WriterBuilder::new();
+            builder.has_headers(has_headers).quote_style(QuoteStyle::Always);
+            let mut wtr = builder.from_writer(vec![]);
+            wtr.serialize(&row).unwrap();
+            let expected = wtr.into_inner().unwrap();
+
+            let got = Writer::serialize_to_vec(&row, &builder).unwrap();
+            assert_eq!(got, expected);
+        }
+        let got =
+            Writer::serialize_to_vec(&row, &WriterBuilder::new()).unwrap();
+        assert_eq!(got, b"name,score,note\n\"a, b\",1.5,\n");
     }
 
     #[test]
-    fn one_empty_record() {
-        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+    fn record_prefix_suffix() {
+        let mut wtr = WriterBuilder::new()
+            .flexible(true)
+            .record_prefix("(")
+            .record_suffix(")")
+            .from_writer(vec![]);
+        wtr.write_record(&["a", "b", "c"]).unwrap();
+        wtr.write_byte_record(&ByteRecord::from(vec!["x", "y,z"])).unwrap();
         wtr.write_record(&[""]).unwrap();
+        wtr.write_field("1").unwrap();
+        wtr.write_field("2").unwrap();
+        wtr.write_record(None::<&[u8]>).unwrap();
+        wtr.write_byte_record_preserve_quotes(
+            &ByteRecord::from(vec!["p", "q"]),
+            &[true],
+        )
+        .unwrap();
+        wtr.write_record_no_terminator(&["end"]).unwrap();
+        assert_eq!(
+            wtr_as_string(wtr),
+            "(a,b,c)\n(x,\"y,z\")\n(\"\")\n(1,2)\n(\"p\",q)\n(end)"
+        );
+    }
 
-        assert_eq!(wtr_as_string(wtr), "\"\"\n");
+    #[test]
+    fn multi_delimiter() {
+        use crate::reader::ReaderBuilder;
+
+        let rows = vec![vec!["a", "b|c", ""], vec!["x||y", "|", "\"z\""]];
+        let mut wtr =
+            WriterBuilder::new().multi_delimiter(b"||").from_writer(vec![]);
+        for row in &rows {
+            wtr.write_record(row).unwrap();
+        }
+        let data = wtr_as_string(wtr);
+        assert_eq!(data, "a||\"b|c\"||\n\"x||y\"||\"|\"||\"\"\"z\"\"\"\n");
+
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .multi_delimiter(b"||")
+            .from_reader(data.as_bytes());
+        let got: Vec<StringRecord> =
+            rdr.records().collect::<Result<_, _>>().unwrap();
+        assert_eq!(got, rows);
     }
 
     #[test]
-    fn raw_one_empty_record() {
-        let mut wtr = WriterBuilder::new().from_writer(vec![]);
-        wtr.write_byte_record(&ByteRecord::from(vec![""])).unwrap();
+    fn write_field_raw() {
+        let mut wtr = WriterBuilder::new().flexible(true).from_writer(vec![]);
+        wtr.write_field("a,b").unwrap();
+        wtr.write_field_raw(b"'c'").unwrap();
+        wtr.write_field("d").unwrap();
+        wtr.write_record(None::<&[u8]>).unwrap();
+        wtr.write_field_raw(b"\"x\"\"y\"").unwrap();
+        wtr.write_record(None::<&[u8]>).unwrap();
+        wtr.write_field_raw(b"1").unwrap();
+        wtr.write_field("").unwrap();
+        wtr.write_record(None::<&[u8]>).unwrap();
+        assert_eq!(wtr_as_string(wtr), "\"a,b\",'c',d\n\"x\"\"y\"\n1,\n");
+    }
 
-        assert_eq!(wtr_as_string(wtr), "\"\"\n");
+    #[test]
+    fn row_numbers() {
+        let mut wtr =
+            WriterBuilder::new().with_row_numbers(true).from_writer(vec![]);
+        wtr.set_header(&["city", "pop"]).unwrap();
+        wtr.write_record(&["Boston", "4628910"]).unwrap();
+        wtr.write_byte_record(&ByteRecord::from(vec!["Concord", "42695"]))
+            .unwrap();
+        wtr.write_field("Springfield").unwrap();
+        wtr.write_field("155929").unwrap();
+        wtr.write_record(None::<&[u8]>).unwrap();
+        assert_eq!(
+            wtr_as_string(wtr),
+            "#,city,pop\n1,Boston,4628910\n2,Concord,42695\n\
+             3,Springfield,155929\n"
+        );
+
+        let mut wtr = WriterBuilder::new()
+            .has_headers(false)
+            .with_row_numbers(true)
+            .row_number_header("unused")
+            .from_writer(vec![]);
+        let rec = ByteRecord::from(vec!["a", "b"]);
+        wtr.write_byte_record_preserve_quotes(&rec, &[true]).unwrap();
+        wtr.write_record(&["c", "d"]).unwrap();
+        assert_eq!(wtr_as_string(wtr), "1,\"a\",b\n2,c,d\n");
+
+        let mut wtr = WriterBuilder::new()
+            .with_row_numbers(true)
+            .row_number_header("row, number")
+            .from_writer(vec![]);
+        wtr.serialize(("x", 1)).unwrap();
+        wtr.serialize(("y", 2)).unwrap();
+        assert_eq!(wtr_as_string(wtr), "1,x,1\n2,y,2\n");
     }
 
     #[test]
-    fn two_empty_records() {
-        let mut wtr = WriterBuilder::new().from_writer(vec![]);
-        wtr.write_record(&[""]).unwrap();
-        wtr.write_record(&[""]).unwrap();
+    fn set_header() {
+        let mut wtr = WriterBuilder::new()
+            .has_headers(false)
+            .max_records(Some(2))
+            .from_writer(vec![]);
+        wtr.set_header(&["a", "b"]).unwrap();
+        wtr.write_record(&["1", "2"]).unwrap();
+        assert!(wtr.set_header(&["x", "y"]).is_err());
+        wtr.write_byte_record(&ByteRecord::from(vec!["3", "4"])).unwrap();
+        assert_eq!(wtr_as_string(wtr), "a,b\n1,2\n3,4\n");
 
-        assert_eq!(wtr_as_string(wtr), "\"\"\n\"\"\n");
+        let mut wtr = Writer::from_writer(vec![]);
+        wtr.set_header(&["a", "b"]).unwrap();
+        assert_eq!(wtr_as_string(wtr), "");
     }
 
     #[test]
-    fn raw_two_empty_records() {
-        let mut wtr = WriterBuilder::new().from_writer(vec![]);
-        wtr.write_byte_record(&ByteRecord::from(vec![""])).unwrap();
-        wtr.write_byte_record(&ByteRecord::from(vec![""])).unwrap();
+    fn set_header_field_count() {
+        let mut wtr = WriterBuilder::new().flexible(true).from_writer(vec![]);
+        wtr.set_header(&["a", "b"]).unwrap();
+        let err = wtr.write_record(&["1", "2", "3"]).unwrap_err();
+        match *err.kind() {
+            ErrorKind::UnequalLengths { expected_len: 2, len: 3, .. } => {}
+            ref x => panic!("expected UnequalLengths, got {:?}", x),
+        }
 
-        assert_eq!(wtr_as_string(wtr), "\"\"\n\"\"\n");
+        #[derive(Serialize)]
+        struct Row {
+            x: i32,
+        }
+        let mut wtr = Writer::from_writer(vec![]);
+        wtr.set_header(&["y"]).unwrap();
+        wtr.serialize(Row { x: 1 }).unwrap();
+        wtr.serialize(Row { x: 2 }).unwrap();
+        assert_eq!(wtr_as_string(wtr), "y\n1\n2\n");
     }
 
     #[test]
-    fn unequal_records_bad() {
-        let mut wtr = WriterBuilder::new().from_writer(vec![]);
-        wtr.write_record(&ByteRecord::from(vec!["a", "b", "c"])).unwrap();
-        let err = wtr.write_record(&ByteRecord::from(vec!["a"])).unwrap_err();
+    fn write_empty_record() {
+        let mut wtr = WriterBuilder::new()
+            .terminator(Terminator::CRLF)
+            .max_records(Some(3))
+            .from_writer(vec![]);
+        wtr.write_empty_record().unwrap();
+        wtr.write_record(&["a", "b"]).unwrap();
+        wtr.write_empty_record().unwrap();
+        wtr.write_record(&["c", "d"]).unwrap();
+        wtr.write_field("e").unwrap();
+        wtr.write_field("f").unwrap();
+        wtr.write_empty_record().unwrap();
+        let err = wtr.write_record(&["g", "h"]).unwrap_err();
         match *err.kind() {
-            ErrorKind::UnequalLengths { ref pos, expected_len, len } => {
-                assert!(pos.is_none());
-                assert_eq!(expected_len, 3);
-                assert_eq!(len, 1);
-            }
-            ref x => {
-                panic!("expected UnequalLengths error, but got '{:?}'", x);
-            }
+            ErrorKind::TooManyRecords { limit: 3 } => {}
+            ref x => panic!("expected TooManyRecords, got {:?}", x),
         }
+        assert_eq!(wtr_as_string(wtr), "\r\na,b\r\n\r\nc,d\r\ne,f\r\n\r\n");
     }
 
     #[test]
-    fn raw_unequal_records_bad() {
+    fn collapse_empty_records() {
+        let mut wtr = WriterBuilder::new()
+            .collapse_empty_records(true)
+            .from_writer(vec![]);
+        wtr.write_empty_record().unwrap();
+        wtr.write_empty_record().unwrap();
+        wtr.write_record(&["a", "b"]).unwrap();
+        wtr.write_empty_record().unwrap();
+        wtr.write_empty_record().unwrap();
+        wtr.write_empty_record().unwrap();
+        wtr.write_field("c").unwrap();
+        wtr.write_field("d").unwrap();
+        wtr.write_empty_record().unwrap();
+        wtr.write_empty_record().unwrap();
+        assert_eq!(wtr_as_string(wtr), "\na,b\n\nc,d\n\n");
+
         let mut wtr = WriterBuilder::new().from_writer(vec![]);
-        wtr.write_byte_record(&ByteRecord::from(vec!["a", "b", "c"])).unwrap();
-        let err =
-            wtr.write_byte_record(&ByteRecord::from(vec!["a"])).unwrap_err();
+        wtr.write_empty_record().unwrap();
+        wtr.write_empty_record().unwrap();
+        assert_eq!(wtr_as_string(wtr), "\n\n");
+    }
+
+    #[test]
+    fn validate_utf8() {
+        let rec = ByteRecord::from(vec![&b"a"[..], b"b\xFF"]);
+
+        let mut wtr = Writer::from_writer(vec![]);
+        wtr.write_byte_record(&rec).unwrap();
+        wtr.write_field(b"\xC3").unwrap();
+        wtr.write_field_raw(b"\xC3").unwrap();
+        wtr.write_record(None::<&[u8]>).unwrap();
+        assert_eq!(wtr.into_inner().unwrap(), b"a,b\xFF\n\xC3,\xC3\n");
+
+        let mut wtr =
+            WriterBuilder::new().validate_utf8(true).from_writer(vec![]);
+        wtr.write_record(&["a", "é"]).unwrap();
+        let err = wtr.write_byte_record(&rec).unwrap_err();
         match *err.kind() {
-            ErrorKind::UnequalLengths { ref pos, expected_len, len } => {
-                assert!(pos.is_none());
-                assert_eq!(expected_len, 3);
-                assert_eq!(len, 1);
+            ErrorKind::Utf8 { pos: None, ref err } => {
+                assert_eq!(err.field(), 1);
+                assert_eq!(err.valid_up_to(), 1);
             }
-            ref x => {
-                panic!("expected UnequalLengths error, but got '{:?}'", x);
+            ref x => panic!("expected Utf8, got {:?}", x),
+        }
+        assert!(wtr.write_record(&[&b"\xC3"[..], b"x"]).is_err());
+        assert!(wtr.write_byte_record_preserve_quotes(&rec, &[]).is_err());
+        assert!(wtr.write_field_raw(b"\xC3").is_err());
+        assert!(wtr.write_record(&[&b"ok"[..], b"\xFF"]).is_err());
+        wtr.write_record(&["x", "y"]).unwrap();
+        assert_eq!(wtr.into_inner().unwrap(), "a,é\nx,y\n".as_bytes());
+    }
+
+    #[test]
+    fn write_pairs() {
+        let mut wtr = Writer::from_writer(vec![]);
+        wtr.write_pairs(vec![("a", "1"), ("b", "2"), ("c", "3")]).unwrap();
+        wtr.write_pairs(vec![("c", "6"), ("a", "4"), ("b", "5")]).unwrap();
+        wtr.write_pairs(vec![("b", "8"), ("c", "9"), ("a", "7")]).unwrap();
+        let bad = vec![
+            vec![("a", "1"), ("b", "2"), ("c", "3"), ("d", "4")],
+            vec![("b", "x")],
+            vec![("a", "1"), ("b", "2"), ("a", "3"), ("c", "4")],
+        ];
+        for pairs in bad {
+            let err = wtr.write_pairs(pairs).unwrap_err();
+            match *err.kind() {
+                ErrorKind::Serialize(_) => {}
+                ref x => panic!("expected Serialize, got {:?}", x),
             }
         }
+        assert_eq!(wtr_as_string(wtr), "a,b,c\n1,2,3\n4,5,6\n7,8,9\n");
+
+        let mut wtr = WriterBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_writer(vec![]);
+        wtr.write_pairs(vec![("a", "1"), ("b", "2")]).unwrap();
+        wtr.write_pairs(vec![("d", "0"), ("b", "4"), ("a", "3")]).unwrap();
+        wtr.write_pairs(vec![("b", "5")]).unwrap();
+        wtr.write_pairs(vec![("a", "6"), ("b", "7"), ("a", "8")]).unwrap();
+        assert_eq!(wtr_as_string(wtr), "1,2\n3,4\n,5\n8,7\n");
     }
 
     #[test]
-    fn unequal_records_ok() {
-        let mut wtr = WriterBuilder::new().flexible(true).from_writer(vec![]);
-        wtr.write_record(&ByteRecord::from(vec!["a", "b", "c"])).unwrap();
-        wtr.write_record(&ByteRecord::from(vec!["a"])).unwrap();
-        assert_eq!(wtr_as_string(wtr), "a,b,c\na\n");
+    fn from_buf_writer() {
+        let mut buf = io::BufWriter::with_capacity(8, vec![]);
+        buf.write_all(b"abc").unwrap();
+        let mut wtr = WriterBuilder::new().from_buf_writer(buf);
+        wtr.write_record(&["d", "efghijklmn"]).unwrap();
+        wtr.write_record(&["o", "p"]).unwrap();
+        wtr.flush().unwrap();
+        assert_eq!(wtr.get_ref().len(), 20);
+        assert_eq!(wtr_as_string(wtr), "abcd,efghijklmn\no,p\n");
     }
 
     #[test]
-    fn raw_unequal_records_ok() {
-        let mut wtr = WriterBuilder::new().flexible(true).from_writer(vec![]);
-        wtr.write_byte_record(&ByteRecord::from(vec!["a", "b", "c"])).unwrap();
-        wtr.write_byte_record(&ByteRecord::from(vec!["a"])).unwrap();
-        assert_eq!(wtr_as_string(wtr), "a,b,c\na\n");
+    fn write_kv_table() {
+        use std::collections::BTreeMap;
+
+        use crate::reader::ReaderBuilder;
+
+        let mut map = BTreeMap::new();
+        map.insert("name".to_string(), "a, b".to_string());
+        map.insert("id".to_string(), "1".to_string());
+        let mut wtr = Writer::from_writer(vec![]);
+        wtr.write_kv_table(&map).unwrap();
+        wtr.write_kv_table(vec![("x", "")]).unwrap();
+        let data = wtr_as_string(wtr);
+        assert_eq!(data, "key,value\nid,1\nname,\"a, b\"\nx,\n");
+
+        let mut rdr = ReaderBuilder::new().from_reader(data.as_bytes());
+        let pairs: Vec<(String, String)> =
+            rdr.deserialize().collect::<Result<_, _>>().unwrap();
+        let mut expected: Vec<(String, String)> = map.into_iter().collect();
+        expected.push(("x".to_string(), "".to_string()));
+        assert_eq!(pairs, expected);
+
+        let mut wtr =
+            WriterBuilder::new().has_headers(false).from_writer(vec![]);
+        wtr.write_kv_table(vec![("a", "1")]).unwrap();
+        assert_eq!(wtr_as_string(wtr), "a,1\n");
     }
 
     #[test]
-    fn full_buffer_should_not_flush_underlying() {
-        struct MarkWriteAndFlush(Vec<u8>);
+    fn dialect() {
+        let write = |builder: &mut WriterBuilder| {
+            let mut wtr = builder.from_writer(vec![]);
+            wtr.write_record(&["a", "b\"c", "d,e"]).unwrap();
+            wtr_as_string(wtr)
+        };
+        assert_eq!(
+            write(WriterBuilder::new().dialect(Dialect::Unix)),
+            "\"a\",\"b\"\"c\",\"d,e\"\n"
+        );
+        assert_eq!(
+            write(WriterBuilder::new().dialect(Dialect::Excel)),
+            "a,\"b\"\"c\",\"d,e\"\r\n"
+        );
+        assert_eq!(
+            write(
+                WriterBuilder::new()
+                    .delimiter(b';')
+                    .double_quote(false)
+                    .dialect(Dialect::PostgresCopy)
+            ),
+            "a,\"b\"\"c\",\"d,e\"\n"
+        );
+        assert_eq!(
+            write(
+                WriterBuilder::new()
+                    .dialect(Dialect::Rfc4180)
+                    .delimiter(b';')
+                    .terminator(Terminator::Any(b'\n'))
+            ),
+            "a;\"b\"\"c\";d,e\n"
+        );
+    }
 
-        impl MarkWriteAndFlush {
-            fn to_str(self) -> String {
-                String::from_utf8(self.0).unwrap()
-            }
+    #[test]
+    fn position_columns() {
+        use crate::reader::ReaderBuilder;
+
+        let data = "a,b\nx,1\ny,2\n";
+        let mut rdr = ReaderBuilder::new().from_reader(data.as_bytes());
+        let mut wtr = WriterBuilder::new()
+            .include_position_columns(true)
+            .from_writer(vec![]);
+        wtr.set_header(rdr.byte_headers().unwrap()).unwrap();
+        let mut rec = ByteRecord::new();
+        while rdr.read_byte_record(&mut rec).unwrap() {
+            wtr.write_byte_record(&rec).unwrap();
         }
+        wtr.write_record(&["z", "3"]).unwrap();
+        assert_eq!(
+            wtr_as_string(wtr),
+            "byte,line,a,b\n4,2,x,1\n8,3,y,2\n,,z,3\n"
+        );
 
-        impl Write for MarkWriteAndFlush {
-            fn write(&mut self, data: &[u8]) -> io::Result<usize> {
-                self.0.write(b">")?;
-                let written = self.0.write(data)?;
-                self.0.write(b"<")?;
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(data.as_bytes());
+        let mut wtr = WriterBuilder::new()
+            .has_headers(false)
+            .with_row_numbers(true)
+            .include_position_columns(true)
+            .from_writer(vec![]);
+        while rdr.read_byte_record(&mut rec).unwrap() {
+            let quoted = [true];
+            wtr.write_byte_record_preserve_quotes(&rec, &quoted).unwrap();
+        }
+        assert_eq!(
+            wtr_as_string(wtr),
+            "1,0,1,\"a\",b\n2,4,2,\"x\",1\n3,8,3,\"y\",2\n"
+        );
+    }
 
-                Ok(written)
-            }
+    #[test]
+    fn write_record_with() {
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        wtr.write_record_with(100, |i| Cow::Owned(i.to_string().into_bytes()))
+            .unwrap();
+        wtr.write_record_with(100, |_| Cow::Borrowed(&b"x"[..])).unwrap();
+        let err = wtr
+            .write_record_with(3, |_| Cow::Borrowed(&b"y"[..]))
+            .unwrap_err();
+        match *err.kind() {
+            ErrorKind::UnequalLengths {
+                expected_len: 100, len: 3, ..
+            } => {}
+            ref x => panic!("expected UnequalLengths, got {:?}", x),
+        }
 
-            fn flush(&mut self) -> io::Result<()> {
-                self.0.write(b"!")?;
-                Ok(())
+        let data = wtr_as_string(wtr);
+        let lines: Vec<&str> = data.lines().collect();
+        let expected: Vec<String> = (0..100).map(|i| i.to_string()).collect();
+        assert_eq!(lines[0], expected.join(","));
+        assert_eq!(lines[1], vec!["x"; 100].join(","));
+    }
+
+    #[test]
+    fn write_sparse_record() {
+        let mut wtr = WriterBuilder::new().from_writer(vec![]);
+        wtr.write_sparse_record(5, &[(1, b"b"), (3, b"d,e")]).unwrap();
+        wtr.write_sparse_record(5, &[]).unwrap();
+        wtr.write_sparse_record(5, &[(0, b"a"), (4, b"e")]).unwrap();
+        for fields in
+            &[&[(3, &b"x"[..]), (1, b"y")][..], &[(2, b"x"), (2, b"y")]]
+        {
+            match *wtr.write_sparse_record(5, fields).unwrap_err().kind() {
+                ErrorKind::Serialize(_) => {}
+                ref x => panic!("expected Serialize, got {:?}", x),
             }
         }
+        assert!(wtr.write_sparse_record(5, &[(5, b"x")]).is_err());
+        assert_eq!(wtr_as_string(wtr), ",b,,\"d,e\",\n,,,,\na,,,,e\n");
+    }
 
-        let underlying = MarkWriteAndFlush(vec![]);
+    #[test]
+    fn trim_fields() {
         let mut wtr =
-            WriterBuilder::new().buffer_capacity(4).from_writer(underlying);
+            WriterBuilder::new().trim_fields(Trim::Fields).from_writer(vec![]);
+        wtr.set_header(&[" a ", "b"]).unwrap();
+        wtr.write_record(&["  hi  ", " \t "]).unwrap();
+        wtr.write_record(&[" x, y ", "\"q\" "]).unwrap();
+        assert_eq!(wtr_as_string(wtr), " a ,b\nhi,\n\"x, y\",\"\"\"q\"\"\"\n");
 
-        wtr.write_byte_record(&ByteRecord::from(vec!["a", "b"])).unwrap();
-        wtr.write_byte_record(&ByteRecord::from(vec!["c", "d"])).unwrap();
-        wtr.flush().unwrap();
-        wtr.write_byte_record(&ByteRecord::from(vec!["e", "f"])).unwrap();
+        // Rows written by `write_record` are records, even the first one.
+        let mut wtr =
+            WriterBuilder::new().trim_fields(Trim::Fields).from_writer(vec![]);
+        wtr.write_record(&[" h ", " i "]).unwrap();
+        wtr.write_record(&[" a", "b "]).unwrap();
+        assert_eq!(wtr_as_string(wtr), "h,i\na,b\n");
 
-        let got = wtr.into_inner().unwrap().to_str();
+        let mut wtr = WriterBuilder::new()
+            .has_headers(false)
+            .trim_fields(Trim::Fields)
+            .from_writer(vec![]);
+        wtr.write_record(&["  hi  "]).unwrap();
+        assert_eq!(wtr_as_string(wtr), "hi\n");
+    }
 
-        // As the buffer size is 4 we should write each record separately, and
-        // flush when explicitly called and implictly in into_inner.
-        assert_eq!(got, ">a,b\n<>c,d\n<!>e,f\n<!");
+    #[test]
+    fn write_record_dedup() {
+        let mut wtr = Writer::from_writer(vec![]);
+        let (a, b) =
+            (ByteRecord::from(vec!["A"]), ByteRecord::from(vec!["B"]));
+        let written: Vec<bool> = [&a, &a, &b, &b, &a]
+            .iter()
+            .map(|rec| wtr.write_record_dedup(rec).unwrap())
+            .collect();
+        assert_eq!(written, vec![true, false, true, false, true]);
+        assert_eq!(wtr_as_string(wtr), "A\nB\nA\n");
     }
 
     #[test]
-    fn serialize_with_headers() {
-        #[derive(Serialize)]
-        struct Row {
-            foo: i32,
-            bar: f64,
-            baz: bool,
+    fn trailer_count() {
+        let mut wtr = WriterBuilder::new()
+            .trailer(TrailerKind::Count)
+            .terminator(Terminator::CRLF)
+            .from_writer(vec![]);
+        wtr.set_header(&["a", "b", "c"]).unwrap();
+        for i in 0..5 {
+            wtr.write_record(&[i.to_string(), "x".into(), "y".into()])
+                .unwrap();
         }
+        let data = wtr_as_string(wtr);
+        assert!(data.ends_with("4,x,y\r\n#COUNT,5\r\n"), "{:?}", data);
 
-        let mut wtr = WriterBuilder::new().from_writer(vec![]);
-        wtr.serialize(Row { foo: 42, bar: 42.5, baz: true }).unwrap();
-        assert_eq!(wtr_as_string(wtr), "foo,bar,baz\n42,42.5,true\n");
+        // The trailer is also written when the writer is dropped, and only
+        // data records count.
+        let mut out = vec![];
+        {
+            let mut wtr = WriterBuilder::new()
+                .has_headers(false)
+                .trailer(TrailerKind::Count)
+                .from_writer(&mut out);
+            wtr.write_record(&["a"]).unwrap();
+        }
+        assert_eq!(out, b"a\n#COUNT,1\n");
     }
 
     #[test]
-    fn serialize_no_headers() {
-        #[derive(Serialize)]
-        struct Row {
-            foo: i32,
-            bar: f64,
-            baz: bool,
+    fn trailer_crc32() {
+        let mut wtr = WriterBuilder::new()
+            .buffer_capacity(4)
+            .trailer(TrailerKind::Crc32)
+            .from_writer(vec![]);
+        wtr.write_record(&["city", "pop"]).unwrap();
+        wtr.write_record(&["Boston", "4628910"]).unwrap();
+        let data = wtr_as_string(wtr);
+        let body = "city,pop\nBoston,4628910\n";
+        let crc = crc32_update(0, body.as_bytes());
+        assert_eq!(data, format!("{}#CRC32,{:08x}\n", body, crc));
+    }
+
+    #[test]
+    fn single_quote_round_trip() {
+        use crate::ReaderBuilder;
+
+        let fields = vec!["it's", "a'b'c", "plain", "say \"hi\""];
+        for &double_quote in &[true, false] {
+            let mut wtr = WriterBuilder::new()
+                .quote(b'\'')
+                .double_quote(double_quote)
+                .from_writer(vec![]);
+            wtr.write_record(&fields).unwrap();
+            let data = wtr_as_string(wtr);
+            if double_quote {
+                assert_eq!(data, "'it''s','a''b''c',plain,say \"hi\"\n");
+            } else {
+                assert_eq!(data, "'it\\'s','a\\'b\\'c',plain,say \"hi\"\n");
+            }
+
+            let mut rdr = ReaderBuilder::new()
+                .has_headers(false)
+                .quote(b'\'')
+                .double_quote(double_quote)
+                .escape(if double_quote { None } else { Some(b'\\') })
+                .from_reader(data.as_bytes());
+            let record = rdr.records().next().unwrap().unwrap();
+            assert_eq!(record, fields);
         }
+    }
+
+    #[test]
+    fn decimal_separator() {
+        let mut wtr = WriterBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .delimiter(b';')
+            .decimal_separator(b',')
+            .from_writer(vec![]);
+        wtr.serialize((1.5, 2.0f32, "1.5", 3)).unwrap();
+        wtr.serialize_field(0.25).unwrap();
+        wtr.write_record(None::<&[u8]>).unwrap();
+        assert_eq!(wtr_as_string(wtr), "1,5;2,0;1.5;3\n0,25\n");
+
+        let mut wtr = WriterBuilder::new()
+            .has_headers(false)
+            .decimal_separator(b',')
+            .from_writer(vec![]);
+        wtr.serialize((1.5, 7)).unwrap();
+        assert_eq!(wtr_as_string(wtr), "\"1,5\",7\n");
+    }
 
+    #[test]
+    fn sanitize_formulas() {
         let mut wtr =
-            WriterBuilder::new().has_headers(false).from_writer(vec![]);
-        wtr.serialize(Row { foo: 42, bar: 42.5, baz: true }).unwrap();
-        assert_eq!(wtr_as_string(wtr), "42,42.5,true\n");
+            WriterBuilder::new().sanitize_formulas(true).from_writer(vec![]);
+        wtr.write_record(&["=cmd", "formula"]).unwrap();
+        wtr.write_record(&["=SUM(A1)", "+1+2"]).unwrap();
+        wtr.write_record(&["-3.5", "@x"]).unwrap();
+        wtr.write_record(&["\tx", "a=b"]).unwrap();
+        wtr.write_record(&["", "+"]).unwrap();
+        assert_eq!(
+            wtr_as_string(wtr),
+            "'=cmd,formula\n'=SUM(A1),'+1+2\n-3.5,'@x\n'\tx,a=b\n,'+\n"
+        );
+
+        let mut wtr = Writer::from_writer(vec![]);
+        wtr.write_record(&["=SUM(A1)", "@x"]).unwrap();
+        assert_eq!(wtr_as_string(wtr), "=SUM(A1),@x\n");
     }
 
-    serde_if_integer128! {
-        #[test]
-        fn serialize_no_headers_128() {
-            #[derive(Serialize)]
-            struct Row {
-                foo: i128,
-                bar: f64,
-                baz: bool,
-            }
+    #[test]
+    fn quoting_disabled() {
+        let mut wtr = WriterBuilder::new()
+            .quote_style(QuoteStyle::Always)
+            .quoting(false)
+            .flexible(true)
+            .from_writer(vec![]);
+        wtr.write_record(&["a,b", "c", "d\ne"]).unwrap();
+        wtr.write_record(&[""]).unwrap();
+        let record = ByteRecord::from(vec!["x", "y"]);
+        wtr.write_byte_record_preserve_quotes(&record, &[true, true]).unwrap();
+        let data = wtr_as_string(wtr);
+        assert!(!data.contains('"'));
+        // The comma and newline in the first record make the output
+        // ambiguous: it no longer reads back as the record written.
+        assert_eq!(data, "a,b,c,d\ne\n\nx,y\n");
 
-            let mut wtr =
-                WriterBuilder::new().has_headers(false).from_writer(vec![]);
-            wtr.serialize(Row {
-                foo: 9_223_372_036_854_775_808,
-                bar: 42.5,
-                baz: true,
-            }).unwrap();
-            assert_eq!(wtr_as_string(wtr), "9223372036854775808,42.5,true\n");
+        let mut wtr = WriterBuilder::new().quoting(false).from_writer(vec![]);
+        wtr.write_record(&["\"q\"", "r\"s"]).unwrap();
+        assert_eq!(wtr_as_string(wtr), "\"q\",r\"s\n");
+
+        let mut wtr = WriterBuilder::new()
+            .quoting(false)
+            .never_quote_strict(true)
+            .from_writer(vec![]);
+        wtr.write_record(&["a", "b"]).unwrap();
+        match *wtr.write_record(&["c", "d,e"]).unwrap_err().kind() {
+            ErrorKind::FieldNeedsQuoting { field_index: 1 } => {}
+            ref x => panic!("expected FieldNeedsQuoting, got {:?}", x),
         }
     }
 
     #[test]
-    fn serialize_tuple() {
-        let mut wtr = WriterBuilder::new().from_writer(vec![]);
-        wtr.serialize((true, 1.3, "hi")).unwrap();
-        assert_eq!(wtr_as_string(wtr), "true,1.3,hi\n");
+    fn column_formats() {
+        let formats = vec![
+            ColumnFormat::ZeroPad(4),
+            ColumnFormat::Default,
+            ColumnFormat::Decimals(2),
+        ];
+        let mut wtr = WriterBuilder::new()
+            .has_headers(false)
+            .column_formats(formats.clone())
+            .from_writer(vec![]);
+        wtr.serialize((7, 0.125, 1.2345)).unwrap();
+        wtr.serialize((-12, 1.0f32, 2)).unwrap();
+        wtr.serialize((12345, 2.5, 0.005f32)).unwrap();
+        wtr.serialize(("x", "y", "z")).unwrap();
+        wtr.write_record(&["1", "2.5", "3"]).unwrap();
+        assert_eq!(
+            wtr_as_string(wtr),
+            "0007,0.125,1.23\n-0012,1.0,2.00\n12345,2.5,0.01\nx,y,z\n1,2.5,3\n"
+        );
+
+        let mut wtr = WriterBuilder::new()
+            .has_headers(false)
+            .with_row_numbers(true)
+            .delimiter(b';')
+            .decimal_separator(b',')
+            .column_formats(formats.clone())
+            .from_writer(vec![]);
+        wtr.serialize((1, 2, 0.5)).unwrap();
+        assert_eq!(wtr_as_string(wtr), "1;0001;2;0,50\n");
+
+        let mut wtr = WriterBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .column_formats(formats)
+            .from_writer(vec![]);
+        match *wtr.serialize((1, 2.0)).unwrap_err().kind() {
+            ErrorKind::UnequalLengths { expected_len: 3, len: 2, .. } => {}
+            ref x => panic!("expected UnequalLengths, got {:?}", x),
+        }
     }
 }
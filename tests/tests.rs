@@ -61,6 +61,13 @@ fn cookbook_write_serde() {
     assert_eq!(out.stdout().lines().count(), 3);
 }
 
+#[test]
+fn cookbook_passthrough() {
+    let mut cmd = cmd_for_example("cookbook-passthrough");
+    let out = cmd_output_with(&mut cmd, SMALLPOP.as_bytes());
+    assert_eq!(out.stdout(), SMALLPOP);
+}
+
 #[test]
 fn tutorial_setup_01() {
     let mut cmd = cmd_for_example("tutorial-setup-01");